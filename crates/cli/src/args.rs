@@ -1,6 +1,7 @@
 use clap_cryo::Parser;
 use color_print::cstr;
 use colored::Colorize;
+use cryo_freeze::ReorgBuffer;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{default::Default, path::PathBuf};
@@ -34,26 +35,56 @@ pub struct Args {
     #[arg(
         short,
         long,
+        alias = "tx-hash-file",
         help_heading = "Content Options",
         num_args(1..),
     )]
     pub txs: Option<Vec<String>>,
 
+    /// Collect by transaction over the txs of blocks in parquet files,
+    /// e.g. blocks.parquet[:COLUMN_NAME]
+    #[arg(
+        long,
+        value_name = "FILE",
+        help_heading = "Content Options",
+        verbatim_doc_comment,
+        num_args(1..)
+    )]
+    pub transactions_from_block_file: Option<Vec<String>>,
+
+    /// Collect by block or transaction, ignoring inputs of the other,
+    /// by transaction without --txs collects the txs of the blocks
+    #[arg(long, value_name = "MODE", help_heading = "Content Options", verbatim_doc_comment)]
+    pub collect_by: Option<String>,
+
     /// Align chunk boundaries to regular intervals,
     /// e.g. (1000 2000 3000), not (1106 2106 3106)
     #[arg(short, long, help_heading = "Content Options", verbatim_doc_comment)]
     pub align: bool,
 
     /// Reorg buffer, save blocks only when this old,
-    /// can be a number of blocks
+    /// can be a number of blocks or `finalized`
     #[arg(
         long,
-        default_value_t = 0,
+        default_value = "0",
         value_name = "N_BLOCKS",
         help_heading = "Content Options",
         verbatim_doc_comment
     )]
-    pub reorg_buffer: u64,
+    pub reorg_buffer: ReorgBuffer,
+
+    /// Never collect blocks below this number, applied after all other block selection
+    #[arg(long, value_name = "BLOCK", help_heading = "Content Options")]
+    pub min_block: Option<u64>,
+
+    /// Never collect blocks above this number, applied after all other block selection
+    #[arg(long, value_name = "BLOCK", help_heading = "Content Options")]
+    pub max_block: Option<u64>,
+
+    /// File of blocks or block ranges to never collect, one per line,
+    /// chunks that overlap a skipped range are split around it
+    #[arg(long, value_name = "FILE", help_heading = "Content Options", verbatim_doc_comment)]
+    pub skip_chunks_file: Option<String>,
 
     /// Columns to include alongside the defaults,
     /// use `all` to include all available columns
@@ -64,6 +95,16 @@ pub struct Args {
     #[arg(short, long, value_name="COLS", num_args(0..), help_heading="Content Options")]
     pub exclude_columns: Option<Vec<String>>,
 
+    /// Exclude the input column of transactions, dropping inputs once
+    /// fetched unless needed by other columns or --decode-calls
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub exclude_input: bool,
+
+    /// Columns that must be populated, rpc failures for these columns fail the chunk
+    /// instead of producing nulls
+    #[arg(long, value_name = "COLS", num_args(1..), help_heading = "Content Options")]
+    pub require_columns: Option<Vec<String>>,
+
     /// Columns to use instead of the defaults,
     /// use `all` to use all available columns
     #[arg(long, value_name="COLS", num_args(0..), verbatim_doc_comment, help_heading="Content Options")]
@@ -74,18 +115,109 @@ pub struct Args {
     #[arg(long, num_args(1..), help_heading = "Content Options", verbatim_doc_comment)]
     pub u256_types: Option<Vec<String>>,
 
+    /// Output U256 values as exact decimals,
+    /// shorthand for --u256-types decimal128
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub value_as_decimal: bool,
+
     /// Use hex string encoding for binary columns
     #[arg(long, help_heading = "Content Options")]
     pub hex: bool,
 
-    /// Columns(s) to sort by, `none` for unordered
-    #[arg(short, long, num_args(0..), help_heading="Content Options")]
+    /// Prefix hex strings with 0x [default]
+    #[arg(long, help_heading = "Content Options", overrides_with = "no_hex_prefix")]
+    pub hex_prefix: bool,
+
+    /// Do not prefix hex strings with 0x
+    #[arg(long, help_heading = "Content Options", overrides_with = "hex_prefix")]
+    pub no_hex_prefix: bool,
+
+    /// Encode hex address columns with EIP-55 checksums
+    #[arg(long, help_heading = "Content Options")]
+    pub address_checksum: bool,
+
+    /// Columns(s) to sort by, `none` for unordered,
+    /// add `:desc` to a column to sort it descending
+    #[arg(short, long, num_args(0..), help_heading="Content Options", verbatim_doc_comment)]
     pub sort: Option<Vec<String>>,
 
+    /// Name to use for the block_number column in output files
+    #[arg(long, value_name = "NAME", help_heading = "Content Options")]
+    pub block_number_column_name: Option<String>,
+
+    /// Pad file names so that files in filename order are globally
+    /// sorted, (by default, sorting applies within each file)
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub global_sort: bool,
+
+    /// Keep only rows matching a SQL expression over output columns,
+    /// e.g. "gas_used > 1000000"
+    #[arg(long, value_name = "EXPR", help_heading = "Content Options", verbatim_doc_comment)]
+    pub filter_expr: Option<String>,
+
     /// Exclude items from failed transactions
     #[arg(long, help_heading = "Content Options")]
     pub exclude_failed: bool,
 
+    /// Exclude protocol-issued system transactions of the chain,
+    /// e.g. op stack deposits (transactions, traces)
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub exclude_system_txs: bool,
+
+    /// Keep only txs paying at least this priority fee in gwei,
+    /// excluding legacy txs and blocks without base fee (transactions)
+    #[arg(long, value_name = "GWEI", help_heading = "Content Options", verbatim_doc_comment)]
+    pub min_priority_fee: Option<f64>,
+
+    /// Keep only txs of these types, e.g. 0,2 or legacy,1559,
+    /// txs without a type are legacy (transactions)
+    #[arg(
+        long,
+        value_name = "TYPE",
+        help_heading = "Content Options",
+        verbatim_doc_comment,
+        num_args(1..)
+    )]
+    pub tx_type: Option<Vec<String>>,
+
+    /// Add a <COLUMN>_ens_name column of the reverse ENS names of each
+    /// address column, null without a reverse record, if the name
+    /// does not resolve back to the address, or if lookup fails
+    /// (rate limited)
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub resolve_ens: bool,
+
+    /// Exclude call traces to precompiled contracts
+    #[arg(long, help_heading = "Content Options")]
+    pub no_precompiles: bool,
+
+    /// Precompile address ranges for --no-precompiles,
+    /// e.g. 0x1:0x11 [default: known precompiles of chain]
+    #[arg(
+        long,
+        value_name = "START:END",
+        help_heading = "Content Options",
+        verbatim_doc_comment,
+        num_args(1..)
+    )]
+    pub precompile_range: Option<Vec<String>>,
+
+    /// Drop call traces nested deeper than this depth,
+    /// the top-level call has depth 0 and is always kept
+    #[arg(long, value_name = "N", help_heading = "Content Options", verbatim_doc_comment)]
+    pub max_trace_depth: Option<u32>,
+
+    /// Record failed traces of geth_calls as rows with an error,
+    /// using the error messages of parity traces
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub trace_error_as_column: bool,
+
+    /// Include gas and gas_used columns of call frames (traces),
+    /// matching geth_calls, which fetches the gas limit and receipt of
+    /// each transaction
+    #[arg(long, help_heading = "Content Options", verbatim_doc_comment)]
+    pub include_trace_gas: bool,
+
     /// RPC url [default: 1. MESC 2. ETH_RPC_URL]
     #[arg(short, long, help_heading = "Source Options")]
     pub rpc: Option<String>,
@@ -94,15 +226,28 @@ pub struct Args {
     #[arg(long, help_heading = "Source Options")]
     pub network_name: Option<String>,
 
+    /// Abort unless the node reports this chain id
+    #[arg(long, value_name = "CHAIN_ID", help_heading = "Source Options")]
+    pub assert_chain_id: Option<u64>,
+
+    /// Header to send with each http rpc request, can be repeated
+    /// e.g. "Authorization: Bearer xyz"
+    #[arg(long, value_name = "HEADER", help_heading = "Source Options", verbatim_doc_comment)]
+    pub rpc_header: Vec<String>,
+
     /// Ratelimit on requests per second
     #[arg(short('l'), long, value_name = "limit", help_heading = "Acquisition Options")]
     pub requests_per_second: Option<u32>,
 
-    /// Max retries for provider errors
+    /// Max retries for provider errors, 0 disables retries
     #[arg(long, default_value_t = 5, value_name = "R", help_heading = "Acquisition Options")]
     pub max_retries: u32,
 
-    /// Initial retry backoff time (ms)
+    /// Retries for chunks that fail after provider retries
+    #[arg(long, default_value_t = 0, value_name = "N", help_heading = "Acquisition Options")]
+    pub chunk_retries: u32,
+
+    /// Initial retry backoff time (ms), capped at 60000 like server backoff hints
     #[arg(long, default_value_t = 500, value_name = "B", help_heading = "Acquisition Options")]
     pub initial_backoff: u64,
 
@@ -118,7 +263,38 @@ pub struct Args {
     #[arg(long, value_name = "M", help_heading = "Acquisition Options")]
     pub max_concurrent_chunks: Option<u64>,
 
-    /// Chunk collection order (normal, reverse, random)
+    /// Tune concurrent requests from rate limits and errors,
+    /// from 2 up to --max-concurrent-requests
+    #[arg(long, help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub concurrency_auto: bool,
+
+    /// Json-rpc batch size of per-item requests (receipts, balances,
+    /// codes, nonces, slots), falling back if batches are unsupported
+    #[arg(long, value_name = "N", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub rpc_batch_size: Option<u64>,
+
+    /// Replay state diffs, traces, and vm traces in one parity call,
+    /// block reward traces are fetched with a separate call
+    #[arg(long, help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub trace_replay: bool,
+
+    /// Stop starting new chunks once this many rows are written,
+    /// chunks already in progress are finished
+    #[arg(long, value_name = "N", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub max_rows_total: Option<u64>,
+
+    /// Stop starting new chunks that could make output files exceed
+    /// this many bytes, sized by the largest chunk written so far,
+    /// other chunks wait for the first chunk to be written
+    #[arg(long, value_name = "BYTES", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub max_output_bytes: Option<u64>,
+
+    /// Abort the run and exit with the first chunk error,
+    /// cancelling chunks that are still being collected
+    #[arg(long, help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub fail_fast: bool,
+
+    /// Chunk collection order (ascending, descending, or random)
     #[arg(long, help_heading = "Acquisition Options")]
     pub chunk_order: Option<String>,
 
@@ -126,11 +302,30 @@ pub struct Args {
     #[arg(short, long, help_heading = "Acquisition Options")]
     pub dry: bool,
 
+    /// Print first N rows of each dataset, writing no files,
+    /// collecting only as many chunks as needed
+    #[arg(long, value_name = "N", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub preview: Option<usize>,
+
+    /// Write raw rpc responses to files named by method and block,
+    /// only for explicit ranges of at most 100 blocks or txs
+    #[arg(long, value_name = "DIR", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub dump_raw_responses: Option<String>,
+
+    /// Rpc method for receipts: block (eth_getBlockReceipts) or
+    /// tx (eth_getTransactionReceipt), default tries block then tx
+    #[arg(long, value_name = "SOURCE", help_heading = "Acquisition Options", verbatim_doc_comment)]
+    pub receipts_source: Option<String>,
+
+    /// Skip checking that the node supports rpc methods of datasets
+    #[arg(long, help_heading = "Acquisition Options")]
+    pub no_probe: bool,
+
     /// Remember current command for future use
     #[arg(long)]
     pub remember: bool,
 
-    /// Extra verbosity
+    /// Extra verbosity, including calls of each rpc method
     #[arg(short, long)]
     pub verbose: bool,
 
@@ -138,10 +333,25 @@ pub struct Args {
     #[arg(long)]
     pub no_verbose: bool,
 
-    /// Number of blocks per file
+    /// Number of blocks per file, or per collected chunk with --blocks-per-file
     #[arg(short, long, default_value_t = 1000, help_heading = "Output Options")]
     pub chunk_size: u64,
 
+    /// Number of blocks per file, collected in chunks of --chunk-size
+    #[arg(long, value_name = "N", help_heading = "Output Options", conflicts_with = "n_chunks")]
+    pub blocks_per_file: Option<u64>,
+
+    /// Target bytes per file, resizing chunks after the first chunks
+    /// of --chunk-size blocks by their bytes per block
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help_heading = "Output Options",
+        conflicts_with_all = ["n_chunks", "blocks_per_file"],
+        verbatim_doc_comment
+    )]
+    pub chunk_size_bytes: Option<u64>,
+
     /// Number of files (alternative to --chunk-size)
     #[arg(long, help_heading = "Output Options")]
     pub n_chunks: Option<u64>,
@@ -150,8 +360,9 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub partition_by: Option<Vec<String>>,
 
-    /// Directory for output files
-    #[arg(short, long, default_value = ".", help_heading = "Output Options")]
+    /// Directory for output files,
+    /// can use `{network}` and `{datatype}` placeholders
+    #[arg(short, long, default_value = ".", help_heading = "Output Options", verbatim_doc_comment)]
     pub output_dir: String,
 
     /// Subdirectories for output files
@@ -159,14 +370,37 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options", verbatim_doc_comment, num_args(1..))]
     pub subdirs: Vec<String>,
 
-    /// Label to add to each filename
-    #[arg(long, help_heading = "Output Options")]
+    /// Prefix of each filename [default: network name]
+    /// use an empty string to start filenames with the datatype,
+    /// cannot contain path separators or `..`
+    #[arg(long, value_name = "PREFIX", help_heading = "Output Options", verbatim_doc_comment)]
+    pub output_prefix: Option<String>,
+
+    /// Label to add to each filename and report, characters other than
+    /// ASCII letters, digits, `-` and `.` become `_`, runs of `_` are
+    /// merged, and leading or trailing `_` and `.` are trimmed
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
     pub label: Option<String>,
 
     /// Overwrite existing files instead of skipping
     #[arg(long, help_heading = "Output Options")]
     pub overwrite: bool,
 
+    /// Overwrite existing files that are truncated or unreadable
+    #[arg(long, help_heading = "Output Options")]
+    pub overwrite_partial: bool,
+
+    /// Write files whose columns differ from existing files,
+    /// columns that only differ in order are reordered anyway
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
+    pub force_schema_change: bool,
+
+    /// Skip blocks already in the files or manifest of this dir,
+    /// splitting chunks that it covers partially, files must have
+    /// the names of this run and be partitioned by block only
+    #[arg(long, value_name = "DIR", help_heading = "Output Options", verbatim_doc_comment)]
+    pub skip_existing_blocks_from: Option<String>,
+
     /// Save as csv instead of parquet
     #[arg(long, help_heading = "Output Options")]
     pub csv: bool,
@@ -175,6 +409,19 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub json: bool,
 
+    /// Pretty print json files
+    #[arg(long, help_heading = "Output Options", overrides_with = "json_compact")]
+    pub json_pretty: bool,
+
+    /// Write json files on a single line [default]
+    #[arg(long, help_heading = "Output Options", overrides_with = "json_pretty")]
+    pub json_compact: bool,
+
+    /// Write 64-bit integer columns of json files as numbers,
+    /// which readers may round beyond 2^53, rather than strings
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
+    pub json_integers_as_numbers: bool,
+
     /// Number of rows per row group in parquet file
     #[arg(long, value_name = "GROUP_SIZE", help_heading = "Output Options")]
     pub row_group_size: Option<usize>,
@@ -183,14 +430,32 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub n_row_groups: Option<usize>,
 
+    /// Target in-memory bytes per row group in parquet file, used
+    /// when neither --row-group-size nor --n-row-groups is given
+    #[arg(long, value_name = "BYTES", help_heading = "Output Options", verbatim_doc_comment)]
+    pub row_group_target_bytes: Option<usize>,
+
     /// Do not write statistics to parquet files
     #[arg(long, help_heading = "Output Options")]
     pub no_stats: bool,
 
+    /// Maximum size of data pages in parquet files, in bytes
+    #[arg(long, value_name = "BYTES", help_heading = "Output Options")]
+    pub parquet_page_size: Option<usize>,
+
+    /// Do not dictionary encode columns of parquet files
+    #[arg(long, help_heading = "Output Options")]
+    pub parquet_no_dictionary: bool,
+
     /// Compression algorithm and level
     #[arg(long, help_heading="Output Options", value_name="NAME [#]", num_args(1..=2), default_value = "lz4")]
     pub compression: Vec<String>,
 
+    /// On-disk compression of csv or json files,
+    /// e.g. csv:gzip or json:zstd
+    #[arg(long, value_name = "FORMAT:NAME", help_heading = "Output Options", num_args(1..))]
+    pub compression_for: Option<Vec<String>>,
+
     /// Directory to save summary report
     /// [default: {output_dir}/.cryo/reports]
     #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
@@ -200,6 +465,43 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub no_report: bool,
 
+    /// Keep the report updated while collecting,
+    /// so that aborted runs leave a partial report
+    #[arg(
+        long,
+        help_heading = "Output Options",
+        conflicts_with = "no_report",
+        verbatim_doc_comment
+    )]
+    pub report_on_error: bool,
+
+    /// Write a stats parquet per output file in .cryo/stats,
+    /// with value counts of each dimension and the row count
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
+    pub include_chunk_stats_file: bool,
+
+    /// Write _manifest.json listing each output file to output dir,
+    /// merged with the manifest of earlier runs
+    #[arg(long, help_heading = "Output Options", verbatim_doc_comment)]
+    pub manifest: bool,
+
+    /// Output for chunks with zero rows (write, skip, or touch),
+    /// chunks with zero rows are recorded in .cryo/empty
+    #[arg(long, value_name = "POLICY", help_heading = "Output Options", verbatim_doc_comment)]
+    pub empty_file_policy: Option<String>,
+
+    /// Embed this schema version, the cryo version, args hash, and
+    /// datatype in parquet metadata, or a sidecar in .cryo/metadata
+    #[arg(long, value_name = "VERSION", help_heading = "Output Options", verbatim_doc_comment)]
+    pub output_schema_version: Option<String>,
+
+    /// Append rows to csv or json temp files every SECONDS, between
+    /// the chunks of --blocks-per-file files, files are not
+    /// compressed, a rerun collects unfinished files from their start,
+    /// rows are sorted within each append, not across the whole file
+    #[arg(long, value_name = "SECONDS", help_heading = "Output Options", verbatim_doc_comment)]
+    pub flush_interval: Option<u64>,
+
     /// Address(es)
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub address: Option<Vec<String>>,
@@ -212,7 +514,7 @@ pub struct Args {
     #[arg(long, help_heading = "Dataset-specific Options", value_name="address", num_args(1..))]
     pub from_address: Option<Vec<String>>,
 
-    /// Call data(s) to use for eth_calls
+    /// Call data(s) to use for eth_calls, hex or parquet file column
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub call_data: Option<Vec<String>>,
 
@@ -228,6 +530,27 @@ pub struct Args {
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub slot: Option<Vec<String>>,
 
+    /// Parquet file of contract_address and slot columns,
+    /// collects only these (contract, slot) pairs
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub slot_file: Option<String>,
+
+    /// Parquet file of contract_address, call_data, and optional
+    /// block_number columns, calls for trace_calls and simulations
+    /// [default block: each block of --blocks]
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub trace_calls_input_file: Option<String>,
+
     /// Contract address(es)
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub contract: Option<Vec<String>>,
@@ -236,6 +559,17 @@ pub struct Args {
     #[arg(long, visible_alias = "event", help_heading = "Dataset-specific Options", num_args(1..))]
     pub topic0: Option<Vec<String>>,
 
+    /// Files of topic0s, each matched as one set of events,
+    /// parquet topic0 column or text file of one hash per line
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Dataset-specific Options",
+        num_args(1..),
+        verbatim_doc_comment
+    )]
+    pub topic0_file: Option<Vec<String>>,
+
     /// Topic1(s)
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub topic1: Option<Vec<String>>,
@@ -248,10 +582,140 @@ pub struct Args {
     #[arg(long, help_heading = "Dataset-specific Options", num_args(1..))]
     pub topic3: Option<Vec<String>>,
 
+    /// Drop malformed addresses and topics instead of failing
+    #[arg(long, help_heading = "Dataset-specific Options")]
+    pub lenient_address: bool,
+
     /// Event signature for log decoding
     #[arg(long, value_name = "SIG", help_heading = "Dataset-specific Options", num_args(1..))]
     pub event_signature: Option<String>,
 
+    /// Decode log data of the --topic0 events into data__ columns,
+    /// LAYOUT is comma separated types such as uint256,address
+    #[arg(
+        long,
+        value_name = "LAYOUT",
+        alias = "decode-log-data-types",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub decode_data: Option<String>,
+
+    /// Collapse logs to one row per block and KEY with a count column,
+    /// KEY is `address` or `topic0` (address and topic0)
+    #[arg(
+        long,
+        value_name = "KEY",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub collapse_logs: Option<String>,
+
+    /// Keep only logs of blocks with at least N logs, counting all
+    /// logs of the block, even if excluded by --contract or --topic*
+    #[arg(
+        long,
+        value_name = "N",
+        alias = "min-log-count",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub min_block_logs: Option<u64>,
+
+    /// Keep only logs of blocks with at most N logs, counted like
+    /// --min-block-logs (logs)
+    #[arg(
+        long,
+        value_name = "N",
+        alias = "max-log-count",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub max_block_logs: Option<u64>,
+
+    /// Collect address appearances only from these sources,
+    /// any of traces, logs, miner (default: all)
+    #[arg(
+        long,
+        value_name = "SOURCE",
+        help_heading = "Dataset-specific Options",
+        alias = "address-appearances-sources",
+        verbatim_doc_comment,
+        num_args(1..)
+    )]
+    pub appearance_sources: Option<Vec<String>>,
+
+    /// Keep only the first appearance of each address, at its lowest
+    /// block, in one file for the whole run (address_appearances),
+    /// partitions are collected concurrently into .cryo/partials first
+    #[arg(
+        long,
+        help_heading = "Dataset-specific Options",
+        conflicts_with = "chunk_size_bytes",
+        verbatim_doc_comment
+    )]
+    pub first_seen: bool,
+
+    /// Directory of json abis used to decode call inputs
+    #[arg(long, value_name = "DIR", help_heading = "Dataset-specific Options")]
+    pub abi_dir: Option<String>,
+
+    /// Decode transaction and trace inputs into call__ columns,
+    /// using the functions of --abi-dir
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub decode_calls: bool,
+
+    /// Exclude addresses without code at the requested block (codes)
+    #[arg(long, help_heading = "Dataset-specific Options")]
+    pub contracts_only: bool,
+
+    /// Exclude storage written by the init code of contracts
+    /// created in the same transaction (storage_diffs)
+    #[arg(
+        long,
+        alias = "storage-diffs-exclude-init",
+        help_heading = "Dataset-specific Options",
+        verbatim_doc_comment
+    )]
+    pub exclude_creation_storage: bool,
+
+    /// Include storage written by the init code of contracts
+    /// created in the same transaction, the default (storage_diffs)
+    #[arg(
+        long,
+        alias = "storage-diffs-include-init",
+        help_heading = "Dataset-specific Options",
+        conflicts_with = "exclude_creation_storage",
+        verbatim_doc_comment
+    )]
+    pub include_creation_storage: bool,
+
+    /// Keep only blocks where total supply changed (erc20_supplies)
+    /// or where nonce increased (nonces)
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub changes_only: bool,
+
+    /// With --changes-only, drop the baseline row of the first
+    /// block of each token or address (erc20_supplies, nonces)
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub no_baseline: bool,
+
+    /// Fetch erc20 metadata at every block instead of once per token,
+    /// use to capture name or symbol changes, implied by
+    /// --changes-only (erc20_metadata)
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub refetch_metadata: bool,
+
+    /// Add a value_decimal column scaling values by token decimals,
+    /// null for tokens without decimals() (erc20_transfers)
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub erc20_transfers_decode_value_as_decimal: bool,
+
+    /// Skip block bodies if all selected columns come from receipts,
+    /// fetching only the receipts of each block (transactions)
+    #[arg(long, help_heading = "Dataset-specific Options", verbatim_doc_comment)]
+    pub transactions_receipts_only: bool,
+
     /// Blocks per request (eth_getLogs)
     #[arg(
         long,
@@ -264,6 +728,10 @@ pub struct Args {
     /// Event signature for log decoding
     #[arg(long, value_name = "tracer", help_heading = "Dataset-specific Options")]
     pub js_tracer: Option<String>,
+
+    /// Tracer used for state diff datasets, `parity` or `geth`
+    #[arg(long, default_value = "parity", help_heading = "Dataset-specific Options")]
+    pub tracer: String,
 }
 
 impl Args {
@@ -319,6 +787,7 @@ fn get_after_str() -> String {
       <white><bold>cryo help</bold></white>                      display help message
       <white><bold>cryo help syntax</bold></white>               display block + tx specification syntax
       <white><bold>cryo help datasets</bold></white>             display list of all datasets
+      <white><bold>cryo dims</bold></white> [DATASET(S)]         print partition dims of datasets as json
       <white><bold>cryo help</bold></white>"#
     );
     let post_subcommands = " <DATASET(S)>         display info about a dataset";
@@ -18,4 +18,4 @@ use tokio as _;
 
 pub use args::Args;
 pub use parse::{parse_args, parse_query, parse_str};
-pub use run::run;
+pub use run::{check_rpc_methods, run};
@@ -11,8 +11,9 @@ use super::{execution, file_output, query, source};
 pub async fn parse_args(
     args: &Args,
 ) -> Result<(Query, Source, FileOutput, ExecutionEnv), ParseError> {
-    let source = source::parse_source(args).await?;
+    let mut source = source::parse_source(args).await?;
     let query = query::parse_query(args, Arc::new(source.clone())).await?;
+    source.raw_response_dir = source::parse_raw_response_dir(args, &query);
     let sink = file_output::parse_file_output(args, &source)?;
     let env = execution::parse_execution_env(args, query.n_tasks() as u64)?;
     Ok((query, source, sink, env))
@@ -1,40 +1,34 @@
 use polars::prelude::*;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use cryo_freeze::{BlockChunk, ChunkData, Datatype, ParseError, Source, Subchunk, Table};
+use cryo_freeze::{
+    BlockChunk, ChainProfile, CollectError, Datatype, ParseError, ReorgBuffer, Source, Subchunk,
+    Table,
+};
 
-use crate::args::Args;
+use crate::{
+    args::Args,
+    parse::{
+        parse_utils::{partition_file_inputs, read_integer_file, FileColumnReference},
+        timestamps::parse_date_token,
+    },
+};
 
 pub(crate) async fn parse_blocks(
     args: &Args,
     source: Arc<Source>,
 ) -> Result<(Option<Vec<Option<String>>>, Option<Vec<BlockChunk>>), ParseError> {
-    let (files, explicit_numbers): (Vec<&String>, Vec<&String>) = match &args.blocks {
-        Some(blocks) => blocks.iter().partition(|tx| std::path::Path::new(tx).exists()),
+    let (files, explicit_numbers) = match &args.blocks {
+        Some(blocks) => partition_file_inputs(blocks, "block_number"),
         None => return Ok((None, None)),
     };
 
     let (file_labels, file_chunks) = if !files.is_empty() {
-        let mut file_labels = Vec::new();
-        let mut file_chunks = Vec::new();
-        for path in files {
-            let column = if path.contains(':') {
-                path.split(':')
-                    .last()
-                    .ok_or(ParseError::ParseError("could not parse txs path column".to_string()))?
-            } else {
-                "block_number"
-            };
-            let integers = read_integer_column(path, column)
-                .map_err(|_e| ParseError::ParseError("could not read input".to_string()))?;
-            let chunk = BlockChunk::Numbers(integers);
-            let chunk_label = path
-                .split("__")
-                .last()
-                .and_then(|s| s.strip_suffix(".parquet").map(|s| s.to_string()));
-            file_labels.push(chunk_label);
-            file_chunks.push(chunk);
-        }
+        let skipped = skipped_blocks(args, source.clone()).await?;
+        let (file_labels, file_chunks) = parse_block_files(&files, args, &skipped)?;
         (Some(file_labels), Some(file_chunks))
     } else {
         (None, None)
@@ -67,41 +61,28 @@ pub(crate) async fn parse_blocks(
     Ok((labels, Some(block_chunks)))
 }
 
-fn read_integer_column(path: &str, column: &str) -> Result<Vec<u64>, ParseError> {
-    let file = std::fs::File::open(path)
-        .map_err(|_e| ParseError::ParseError("could not open file path".to_string()))?;
-
-    let df = ParquetReader::new(file)
-        .with_columns(Some(vec![column.to_string()]))
-        .finish()
-        .map_err(|_e| ParseError::ParseError("could not read data from column".to_string()))?;
-
-    let series = df
-        .column(column)
-        .map_err(|_e| ParseError::ParseError("could not get column".to_string()))?
-        .unique()
-        .map_err(|_e| ParseError::ParseError("could not get column".to_string()))?;
-
-    match series.u32() {
-        Ok(ca) => ca
-            .into_iter()
-            .map(|v| {
-                v.ok_or_else(|| ParseError::ParseError("block number missing".to_string()))
-                    .map(|data| data.into())
-            })
-            .collect(),
-        Err(_e) => match series.u64() {
-            Ok(ca) => ca
-                .into_iter()
-                .map(|v| {
-                    v.ok_or_else(|| ParseError::ParseError("block number missing".to_string()))
-                })
-                .collect(),
-            Err(_e) => {
-                Err(ParseError::ParseError("could not convert to integer column".to_string()))
-            }
-        },
+/// read block numbers from parquet files, one chunk per file labeled by the file's block range
+///
+/// skipped blocks are dropped, and a file that loses blocks to them loses its label so that its
+/// chunk is named by its own block range
+pub(crate) fn parse_block_files(
+    references: &[FileColumnReference],
+    args: &Args,
+    skipped: &[(u64, u64)],
+) -> Result<(Vec<Option<String>>, Vec<BlockChunk>), ParseError> {
+    let mut file_labels = Vec::new();
+    let mut file_chunks = Vec::new();
+    for reference in references {
+        let chunk = BlockChunk::Numbers(read_integer_file(reference)?);
+        let Some(chunk) = clamp_block_chunk(chunk, args.min_block, args.max_block) else {
+            continue
+        };
+        let (pieces, unchanged) = subtract_block_chunk(chunk, skipped);
+        let label = if unchanged { reference.to_label() } else { None };
+        file_labels.extend(vec![label; pieces.len()]);
+        file_chunks.extend(pieces);
     }
+    Ok((file_labels, file_chunks))
 }
 
 pub(crate) async fn postprocess_block_chunks(
@@ -110,8 +91,9 @@ pub(crate) async fn postprocess_block_chunks(
     source: Arc<Source>,
 ) -> Result<Vec<BlockChunk>, ParseError> {
     // align
+    let file_size = args.blocks_per_file.unwrap_or(args.chunk_size);
     let block_chunks = if args.align {
-        block_chunks.into_iter().filter_map(|x| x.align(args.chunk_size)).collect()
+        block_chunks.into_iter().filter_map(|x| x.align(file_size)).collect()
     } else {
         block_chunks
     };
@@ -119,32 +101,296 @@ pub(crate) async fn postprocess_block_chunks(
     // split block range into chunks
     let block_chunks = match args.n_chunks {
         Some(n_chunks) => block_chunks.subchunk_by_count(&n_chunks),
-        None => block_chunks.subchunk_by_size(&args.chunk_size),
+        None => block_chunks.subchunk_by_size(&file_size),
     };
 
     // apply reorg buffer
-    let block_chunks = apply_reorg_buffer(block_chunks, args.reorg_buffer, source).await?;
+    let block_chunks = apply_reorg_buffer(block_chunks, args.reorg_buffer, source.clone()).await?;
+
+    // clamp to global block bounds
+    let block_chunks: Vec<BlockChunk> = block_chunks
+        .into_iter()
+        .filter_map(|x| clamp_block_chunk(x, args.min_block, args.max_block))
+        .collect();
+
+    // drop skipped blocks, splitting the chunks that overlap them
+    let skipped = skipped_blocks(args, source).await?;
+    let block_chunks = block_chunks
+        .into_iter()
+        .flat_map(|chunk| subtract_block_chunk(chunk, &skipped).0)
+        .collect();
 
     Ok(block_chunks)
 }
 
+/// merged block ranges of --skip-chunks-file, empty if it is not given
+pub(crate) async fn skipped_blocks(
+    args: &Args,
+    source: Arc<Source>,
+) -> Result<Vec<(u64, u64)>, ParseError> {
+    match &args.skip_chunks_file {
+        Some(path) => read_skip_chunks_file(path, source).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+/// merged block ranges of a file of block numbers or ranges, such as `15M:15.1M`
+///
+/// tokens are separated by whitespace or newlines, and `#` starts a comment
+async fn read_skip_chunks_file(
+    path: &str,
+    source: Arc<Source>,
+) -> Result<Vec<(u64, u64)>, ParseError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| ParseError::ParseError(format!("could not read {}: {}", path, e)))?;
+    let mut ranges = Vec::new();
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        for token in line.split_whitespace() {
+            match parse_block_token(token, true, source.clone()).await? {
+                BlockChunk::Range(start, end) => ranges.push((start, end)),
+                BlockChunk::Numbers(numbers) => ranges.extend(numbers.into_iter().map(|n| (n, n))),
+            }
+        }
+    }
+    Ok(merge_block_ranges(ranges))
+}
+
+/// drop blocks outside of [min_block, max_block], or the whole chunk if none remain
+pub(crate) fn clamp_block_chunk(
+    block_chunk: BlockChunk,
+    min_block: Option<u64>,
+    max_block: Option<u64>,
+) -> Option<BlockChunk> {
+    let min_block = min_block.unwrap_or(0);
+    let max_block = max_block.unwrap_or(u64::MAX);
+    match block_chunk {
+        BlockChunk::Numbers(numbers) => {
+            let numbers: Vec<u64> =
+                numbers.into_iter().filter(|n| (min_block..=max_block).contains(n)).collect();
+            if numbers.is_empty() {
+                None
+            } else {
+                Some(BlockChunk::Numbers(numbers))
+            }
+        }
+        BlockChunk::Range(start, end) => {
+            let (start, end) = (start.max(min_block), end.min(max_block));
+            if start > end {
+                None
+            } else {
+                Some(BlockChunk::Range(start, end))
+            }
+        }
+    }
+}
+
 pub(crate) async fn get_default_block_chunks(
     args: &Args,
     source: Arc<Source>,
     schemas: &HashMap<Datatype, Table>,
+    chain_profile: &ChainProfile,
 ) -> Result<Vec<BlockChunk>, ParseError> {
-    let default_blocks = match schemas
-        .keys()
+    let datatypes: Vec<Datatype> = schemas.keys().cloned().collect();
+    let default_blocks = match datatypes
+        .iter()
         .map(|datatype| datatype.default_blocks())
         .find(|blocks| !blocks.is_none())
     {
         Some(Some(blocks)) => blocks,
-        _ => "0:latest".to_string(),
+        _ => chain_profile.default_blocks(&datatypes).unwrap_or("0:latest".to_string()),
     };
     let block_chunks = parse_block_inputs(&default_blocks, source.clone()).await?;
     postprocess_block_chunks(block_chunks, args, source).await
 }
 
+type BlockLabels = Option<Vec<Option<String>>>;
+
+/// remove blocks already covered by the files of every datatype in a prior output directory
+///
+/// chunks that are partially covered are split so only their uncovered blocks are collected,
+/// split chunks lose their label so that each piece is named by its own block range
+pub(crate) fn skip_existing_blocks(
+    labels: BlockLabels,
+    block_chunks: Option<Vec<BlockChunk>>,
+    dir: &Path,
+    filename_stems: &[String],
+) -> Result<(BlockLabels, Option<Vec<BlockChunk>>), ParseError> {
+    let Some(block_chunks) = block_chunks else { return Ok((labels, None)) };
+    let covered = existing_block_ranges(dir, filename_stems)?;
+    let mut labels_iter = labels.as_ref().map(|labels| labels.iter());
+    let mut new_labels = Vec::new();
+    let mut new_chunks = Vec::new();
+    for chunk in block_chunks.into_iter() {
+        let label = labels_iter.as_mut().and_then(|labels| labels.next()).cloned().flatten();
+        let (pieces, unchanged) = subtract_block_chunk(chunk, &covered);
+        if unchanged {
+            new_labels.push(label);
+        } else {
+            new_labels.extend(vec![None; pieces.len()]);
+        }
+        new_chunks.extend(pieces);
+    }
+    let labels = labels.map(|_| new_labels);
+    Ok((labels, Some(new_chunks)))
+}
+
+/// remove the blocks of merged ranges from a chunk, splitting block ranges around them
+///
+/// returns the remaining pieces of the chunk and whether the chunk is unchanged
+pub(crate) fn subtract_block_chunk(
+    chunk: BlockChunk,
+    covered: &[(u64, u64)],
+) -> (Vec<BlockChunk>, bool) {
+    match chunk {
+        BlockChunk::Range(start, end) => {
+            let ranges = subtract_block_ranges(start, end, covered);
+            let unchanged = ranges == [(start, end)];
+            let pieces: Vec<_> =
+                ranges.into_iter().map(|(start, end)| BlockChunk::Range(start, end)).collect();
+            (pieces, unchanged)
+        }
+        BlockChunk::Numbers(numbers) => {
+            let n_numbers = numbers.len();
+            let numbers: Vec<u64> = numbers
+                .into_iter()
+                .filter(|n| !covered.iter().any(|(start, end)| (*start..=*end).contains(n)))
+                .collect();
+            let unchanged = numbers.len() == n_numbers;
+            match numbers.is_empty() {
+                true => (vec![], unchanged),
+                false => (vec![BlockChunk::Numbers(numbers)], unchanged),
+            }
+        }
+    }
+}
+
+/// merged block ranges covered by the files of every filename stem in dir
+///
+/// ranges are read from file names made of a stem and a `<start>_to_<end>` label, searching
+/// subdirectories, and from the paths of the entries of the manifest of dir
+fn existing_block_ranges(
+    dir: &Path,
+    filename_stems: &[String],
+) -> Result<Vec<(u64, u64)>, ParseError> {
+    if !dir.is_dir() {
+        let message = format!("--skip-existing-blocks-from is not a directory: {}", dir.display());
+        return Err(ParseError::ParseError(message))
+    }
+    let mut paths = list_files(dir)?;
+    if let Ok(contents) = std::fs::read_to_string(dir.join("_manifest.json")) {
+        let manifest: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|_| ParseError::ParseError("could not parse existing manifest".to_string()))?;
+        for file in manifest["files"].as_array().into_iter().flatten() {
+            if let Some(path) = file["path"].as_str() {
+                paths.push(PathBuf::from(path));
+            }
+        }
+    }
+    let filenames: Vec<&str> = paths
+        .iter()
+        .filter_map(|path| path.file_name().and_then(|name| name.to_str()))
+        .filter(|filename| !filename.ends_with(".tmp"))
+        .collect();
+
+    let mut covered: Option<Vec<(u64, u64)>> = None;
+    for stem in filename_stems.iter() {
+        let ranges = filenames.iter().filter_map(|filename| file_block_range(filename, stem));
+        let ranges = merge_block_ranges(ranges.collect());
+        covered = Some(match covered {
+            Some(covered) => intersect_block_ranges(&covered, &ranges),
+            None => ranges,
+        });
+    }
+    Ok(covered.unwrap_or_default())
+}
+
+/// block range of a file named `<stem>__<start>_to_<end>.<extension>`
+fn file_block_range(filename: &str, stem: &str) -> Option<(u64, u64)> {
+    let label = filename.strip_prefix(stem)?.strip_prefix("__")?;
+    parse_range_label(label.split('.').next()?)
+}
+
+/// files in dir and its subdirectories, skipping hidden entries such as `.cryo`
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>, ParseError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| ParseError::ParseError(format!("could not read {}: {}", dir.display(), e)))?;
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue
+        } else if path.is_dir() {
+            files.extend(list_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// parse a `<start>_to_<end>` file label
+fn parse_range_label(label: &str) -> Option<(u64, u64)> {
+    let (start, end) = label.split_once("_to_")?;
+    let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+    (start <= end).then_some((start, end))
+}
+
+/// sort inclusive ranges and merge those that overlap or touch
+fn merge_block_ranges(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges.into_iter() {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// blocks covered by both lists of merged ranges
+fn intersect_block_ranges(a: &[(u64, u64)], b: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut intersection = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start <= end {
+            intersection.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    intersection
+}
+
+/// parts of the inclusive range start..=end that are not in the merged covered ranges
+fn subtract_block_ranges(start: u64, end: u64, covered: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut remaining = Vec::new();
+    let mut next = start;
+    for (covered_start, covered_end) in covered.iter() {
+        if *covered_end < next {
+            continue
+        }
+        if *covered_start > end {
+            break
+        }
+        if *covered_start > next {
+            remaining.push((next, covered_start - 1));
+        }
+        if *covered_end >= end {
+            return remaining
+        }
+        next = covered_end + 1;
+    }
+    remaining.push((next, end));
+    remaining
+}
+
 /// parse block numbers to freeze
 async fn parse_block_inputs(
     inputs: &str,
@@ -181,6 +427,9 @@ async fn parse_block_token(
     source: Arc<Source>,
 ) -> Result<BlockChunk, ParseError> {
     let s = s.replace('_', "");
+    if let Some(chunk) = parse_date_token(&s, as_range, source.clone()).await? {
+        return Ok(chunk)
+    }
 
     let parts: Vec<&str> = s.split(':').collect();
     match parts.as_slice() {
@@ -359,28 +608,59 @@ async fn parse_block_number(
 
 async fn apply_reorg_buffer(
     block_chunks: Vec<BlockChunk>,
-    reorg_filter: u64,
+    reorg_buffer: ReorgBuffer,
     source: Arc<Source>,
 ) -> Result<Vec<BlockChunk>, ParseError> {
-    match reorg_filter {
-        0 => Ok(block_chunks),
-        reorg_filter => {
+    let max_allowed = match reorg_buffer {
+        ReorgBuffer::Blocks(0) => return Ok(block_chunks),
+        ReorgBuffer::Blocks(n_blocks) => {
             let latest_block = match source.get_block_number().await {
                 Ok(result) => result,
                 Err(_e) => {
                     return Err(ParseError::ParseError("reorg buffer parse error".to_string()))
                 }
             };
-            let max_allowed = latest_block - reorg_filter;
-            Ok(block_chunks
-                .into_iter()
-                .filter_map(|x| match x.max_value() {
-                    Some(max_block) if max_block <= max_allowed => Some(x),
-                    _ => None,
-                })
-                .collect())
+            latest_block.saturating_sub(n_blocks)
         }
+        ReorgBuffer::Finalized => match source.get_finalized_block_number().await {
+            Ok(Some(finalized_block)) => finalized_block,
+            Ok(None) => return Err(finalized_tag_unsupported()),
+            Err(e) if is_unsupported_block_tag(&e) => return Err(finalized_tag_unsupported()),
+            Err(e) => {
+                return Err(ParseError::ParseError(format!(
+                    "could not fetch the finalized block: {}",
+                    e
+                )))
+            }
+        },
+    };
+    Ok(limit_block_chunks(block_chunks, max_allowed))
+}
+
+/// clamp chunks to the highest block allowed by the reorg buffer
+fn limit_block_chunks(block_chunks: Vec<BlockChunk>, max_allowed: u64) -> Vec<BlockChunk> {
+    block_chunks.into_iter().filter_map(|x| clamp_block_chunk(x, None, Some(max_allowed))).collect()
+}
+
+fn finalized_tag_unsupported() -> ParseError {
+    ParseError::ParseError(
+        "node does not support the finalized block tag, \
+        use a number of blocks for --reorg-buffer instead"
+            .to_string(),
+    )
+}
+
+/// whether a node rejected a block tag it does not know
+fn is_unsupported_block_tag(e: &CollectError) -> bool {
+    let message = match e {
+        CollectError::ProviderError(e) => e.to_string(),
+        CollectError::RPCError(message) => message.clone(),
+        e => e.to_string(),
     }
+    .to_lowercase();
+    ["finalized", "block tag", "unknown block", "invalid block"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
 }
 
 pub(crate) async fn get_latest_block_number(source: Arc<Source>) -> Result<u64, ParseError> {
@@ -398,6 +678,7 @@ mod tests {
         providers::{IpcConnect, ProviderBuilder},
         transports::ipc::MockIpcServer,
     };
+    use cryo_freeze::ChunkData;
 
     use super::*;
 
@@ -422,6 +703,10 @@ mod tests {
             max_concurrent_chunks: None,
             rpc_url: "".to_string(),
             labels: cryo_freeze::SourceLabels::default(),
+            raw_response_dir: None,
+            receipts_source: cryo_freeze::ReceiptsSource::Auto,
+            rpc_stats: Default::default(),
+            rpc_batcher: None,
         };
         let source = Arc::new(source);
         for (test, res) in tests {
@@ -488,6 +773,10 @@ mod tests {
             max_concurrent_chunks: Some(1),
             rate_limiter: Arc::new(None),
             labels: cryo_freeze::SourceLabels::default(),
+            raw_response_dir: None,
+            receipts_source: cryo_freeze::ReceiptsSource::Auto,
+            rpc_stats: Default::default(),
+            rpc_batcher: None,
         });
         for (test, res) in tests {
             match test {
@@ -565,6 +854,10 @@ mod tests {
             max_concurrent_chunks: Some(1),
             rpc_url: "".to_string(),
             labels: cryo_freeze::SourceLabels::default(),
+            raw_response_dir: None,
+            receipts_source: cryo_freeze::ReceiptsSource::Auto,
+            rpc_stats: Default::default(),
+            rpc_batcher: None,
         };
         let source = Arc::new(source);
         for (test, res) in tests {
@@ -714,4 +1007,111 @@ mod tests {
         mock_server.spawn().await;
         block_number_test_helper(tests, mock_ipc_path).await;
     }
+
+    #[test]
+    fn test_limit_block_chunks() {
+        let chunks = vec![
+            BlockChunk::Range(0, 9),
+            BlockChunk::Range(10, 19),
+            BlockChunk::Range(20, 29),
+            BlockChunk::Numbers(vec![14, 15, 16]),
+        ];
+        let expected = vec![
+            BlockChunk::Range(0, 9),
+            BlockChunk::Range(10, 15),
+            BlockChunk::Numbers(vec![14, 15]),
+        ];
+        assert_eq!(format!("{:?}", limit_block_chunks(chunks, 15)), format!("{:?}", expected));
+    }
+
+    #[test]
+    fn test_parse_reorg_buffer() {
+        assert_eq!("0".parse::<ReorgBuffer>(), Ok(ReorgBuffer::Blocks(0)));
+        assert_eq!("12".parse::<ReorgBuffer>(), Ok(ReorgBuffer::Blocks(12)));
+        assert_eq!("finalized".parse::<ReorgBuffer>(), Ok(ReorgBuffer::Finalized));
+        assert!("safe".parse::<ReorgBuffer>().is_err());
+        assert_eq!(ReorgBuffer::Finalized.to_string(), "finalized");
+    }
+
+    #[test]
+    fn test_is_unsupported_block_tag() {
+        let unsupported = CollectError::RPCError("invalid block tag finalized".to_string());
+        assert!(is_unsupported_block_tag(&unsupported));
+        let timeout = CollectError::RPCError("request timed out".to_string());
+        assert!(!is_unsupported_block_tag(&timeout));
+    }
+
+    #[test]
+    fn test_clamp_block_chunk() {
+        let clamp = |chunk| clamp_block_chunk(chunk, Some(10), Some(20));
+        let Some(BlockChunk::Numbers(numbers)) = clamp(BlockChunk::Numbers(vec![5, 10, 15, 25]))
+        else {
+            panic!("expected numbers")
+        };
+        assert_eq!(numbers, vec![10, 15]);
+        let Some(BlockChunk::Range(start, end)) = clamp(BlockChunk::Range(0, 15)) else {
+            panic!("expected range")
+        };
+        assert_eq!((start, end), (10, 15));
+        assert!(clamp(BlockChunk::Numbers(vec![1, 2, 30])).is_none());
+        assert!(clamp(BlockChunk::Range(21, 30)).is_none());
+    }
+
+    #[test]
+    fn test_subtract_block_ranges() {
+        let covered = merge_block_ranges(vec![(20, 29), (0, 9), (10, 12), (50, 60)]);
+        assert_eq!(covered, vec![(0, 12), (20, 29), (50, 60)]);
+        assert_eq!(subtract_block_ranges(0, 99, &covered), vec![(13, 19), (30, 49), (61, 99)]);
+        assert_eq!(subtract_block_ranges(5, 25, &covered), vec![(13, 19)]);
+        assert_eq!(subtract_block_ranges(20, 29, &covered), vec![]);
+        assert_eq!(subtract_block_ranges(30, 40, &covered), vec![(30, 40)]);
+        let other = vec![(5, 22), (55, 70)];
+        assert_eq!(intersect_block_ranges(&covered, &other), vec![(5, 12), (20, 22), (55, 60)]);
+    }
+
+    #[test]
+    fn test_subtract_block_chunk() {
+        let skipped = merge_block_ranges(vec![(15, 17), (30, 39)]);
+        let (pieces, unchanged) = subtract_block_chunk(BlockChunk::Range(10, 34), &skipped);
+        let pieces: Vec<_> =
+            pieces.iter().map(|piece| (piece.min_value(), piece.max_value())).collect();
+        assert_eq!(pieces, vec![(Some(10), Some(14)), (Some(18), Some(29))]);
+        assert!(!unchanged);
+        let (pieces, unchanged) = subtract_block_chunk(BlockChunk::Range(30, 39), &skipped);
+        assert!(pieces.is_empty() && !unchanged);
+        let (pieces, unchanged) = subtract_block_chunk(BlockChunk::Numbers(vec![1, 16]), &skipped);
+        assert_eq!(pieces[0].values(), vec![1]);
+        assert!(!unchanged);
+        assert!(subtract_block_chunk(BlockChunk::Range(40, 50), &skipped).1);
+    }
+
+    #[test]
+    fn test_existing_block_ranges() {
+        let dir = std::env::temp_dir().join(format!("cryo_existing_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("transactions")).unwrap();
+        for path in [
+            "ethereum__blocks__00000000_to_00000999.parquet",
+            "ethereum__blocks__00001000_to_00001999.parquet.tmp",
+            "ethereum__blocks__v2__00002000_to_00002999.parquet",
+            "goerli__blocks__00003000_to_00003999.parquet",
+            "ethereum__blocks__00004000_to_00004999__0x1234.parquet",
+            "transactions/ethereum__transactions__00000500_to_00001499.parquet",
+        ] {
+            std::fs::write(dir.join(path), "").unwrap();
+        }
+        let manifest = serde_json::json!({
+            "files": [{"path": "/other/ethereum__blocks__00005000_to_00005999.parquet"}]
+        });
+        std::fs::write(dir.join("_manifest.json"), manifest.to_string()).unwrap();
+        let stems = |stems: &[&str]| -> Vec<String> {
+            stems.iter().map(|stem| stem.to_string()).collect()
+        };
+        let blocks = existing_block_ranges(&dir, &stems(&["ethereum__blocks"])).unwrap();
+        assert_eq!(blocks, vec![(0, 999), (5000, 5999)]);
+        let labeled = existing_block_ranges(&dir, &stems(&["ethereum__blocks__v2"])).unwrap();
+        assert_eq!(labeled, vec![(2000, 2999)]);
+        let both = stems(&["ethereum__blocks", "ethereum__transactions"]);
+        assert_eq!(existing_block_ranges(&dir, &both).unwrap(), vec![(500, 999)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
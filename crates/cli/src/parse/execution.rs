@@ -4,6 +4,10 @@ use cryo_freeze::{ExecutionEnv, ExecutionEnvBuilder, ParseError};
 pub(crate) fn parse_execution_env(args: &Args, n_tasks: u64) -> Result<ExecutionEnv, ParseError> {
     let args_str =
         serde_json::to_string(args).map_err(|e| ParseError::ParseError(e.to_string()))?;
+    let args_str = super::source::redact_rpc_headers(&args_str, &args.rpc_header);
+    let cli_command: Vec<String> = std::env::args()
+        .map(|arg| super::source::redact_rpc_headers(&arg, &args.rpc_header))
+        .collect();
 
     let verbose = match (args.no_verbose, args.verbose) {
         (true, true) => return Err(ParseError::ParseError("".to_string())),
@@ -17,6 +21,14 @@ pub(crate) fn parse_execution_env(args: &Args, n_tasks: u64) -> Result<Execution
         .verbose(verbose)
         .report(!args.no_report)
         .report_dir(args.report_dir.clone())
+        .report_on_error(args.report_on_error)
+        .manifest(args.manifest)
+        .chunk_retries(args.chunk_retries)
+        .max_rows_total(args.max_rows_total)
+        .max_output_bytes(args.max_output_bytes)
+        .chunk_size_bytes(args.chunk_size_bytes)
+        .fail_fast(args.fail_fast)
+        .cli_command(cli_command)
         .args(args_str);
 
     let builder = if !args.no_verbose {
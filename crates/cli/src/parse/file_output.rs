@@ -1,13 +1,27 @@
 use crate::args::Args;
-use cryo_freeze::{FileFormat, FileOutput, ParseError, Source, SubDir};
+use cryo_freeze::{
+    Datatype, EmptyFilePolicy, FileCompression, FileFormat, FileOutput, JsonOptions, ParseError,
+    Source, SubDir,
+};
 use polars::prelude::*;
-use std::fs;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutput, ParseError> {
     // process output directory
-    std::fs::create_dir_all(args.output_dir.clone())
+    let network = parse_network_name(args, source.chain_id);
+    let (output_dir, template_subdirs) = parse_output_dir_template(&args.output_dir, &network)?;
+    if output_dir.exists() && !output_dir.is_dir() {
+        return Err(ParseError::ParseError(format!(
+            "output dir is a file, not a directory: {}",
+            output_dir.display()
+        )))
+    }
+    std::fs::create_dir_all(&output_dir)
         .map_err(|_| ParseError::ParseError("could not create dir".to_string()))?;
-    let output_dir = std::fs::canonicalize(args.output_dir.clone()).map_err(|_e| {
+    let output_dir = std::fs::canonicalize(&output_dir).map_err(|_e| {
         ParseError::ParseError("Failed to canonicalize output directory".to_string())
     })?;
     match fs::create_dir_all(&output_dir) {
@@ -15,36 +29,181 @@ pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutp
         Err(e) => return Err(ParseError::ParseError(format!("Error creating directory: {}", e))),
     };
 
-    let label = &args.label;
+    let label = parse_label(args)?;
 
     let parquet_compression = parse_compression(&args.compression)?;
 
     let row_group_size = parse_row_group_size(
         args.row_group_size,
         args.n_row_groups,
-        Some(args.chunk_size as usize),
-    );
+        Some(args.blocks_per_file.unwrap_or(args.chunk_size) as usize),
+    )?;
+    let row_group_target_bytes = match (row_group_size, args.row_group_target_bytes) {
+        (_, Some(0)) => {
+            let message = "--row-group-target-bytes must be positive";
+            return Err(ParseError::ParseError(message.to_string()))
+        }
+        (None, target_bytes) => target_bytes,
+        (Some(_), _) => None,
+    };
+
+    let parquet_page_size = parse_parquet_page_size(args.parquet_page_size)?;
 
     let format = parse_output_format(args)?;
-    let file_prefix = parse_network_name(args, source.chain_id);
+    let file_compression = parse_file_compression(&args.compression_for, &format)?;
+    let file_prefix = match parse_output_prefix(args)? {
+        Some(prefix) => prefix,
+        None => network.clone(),
+    };
 
-    let subdirs = parse_subdirs(args);
+    let subdirs = template_subdirs.into_iter().chain(parse_subdirs(args)).collect();
+    let empty_file_policy = parse_empty_file_policy(args)?;
+    let flush_interval = parse_flush_interval(args, &format, &file_compression)?;
 
     let output = FileOutput {
         output_dir,
         subdirs,
         parquet_statistics: !args.no_stats,
+        parquet_page_size,
+        parquet_dictionary: !args.parquet_no_dictionary,
         overwrite: args.overwrite,
+        overwrite_partial: args.overwrite_partial,
+        force_schema_change: args.force_schema_change,
         prefix: file_prefix,
+        network,
         format,
-        suffix: label.clone(),
+        suffix: label,
         parquet_compression,
+        file_compression,
         row_group_size,
+        row_group_target_bytes,
+        chunk_stats_file: args.include_chunk_stats_file,
+        empty_file_policy,
+        schema_version: args.output_schema_version.clone(),
+        flush_interval,
+        json: JsonOptions {
+            pretty: args.json_pretty,
+            integers_as_numbers: args.json_integers_as_numbers,
+        },
     };
 
     Ok(output)
 }
 
+/// output directory and subdirectories of an `--output-dir` template
+///
+/// `{network}` is replaced by the network name, and path components from the first one containing
+/// `{datatype}` onwards become subdirectories that are resolved for each datatype
+pub(crate) fn parse_output_dir_template(
+    template: &str,
+    network: &str,
+) -> Result<(PathBuf, Vec<SubDir>), ParseError> {
+    let template = template.replace("{network}", network);
+    if template.replace("{datatype}", "").contains(['{', '}']) {
+        return Err(ParseError::ParseError(format!(
+            "invalid placeholder in --output-dir: {}, use {{network}} or {{datatype}}",
+            template
+        )))
+    }
+    let mut output_dir = PathBuf::new();
+    let mut subdirs = Vec::new();
+    for component in Path::new(&template).components() {
+        let component = component.as_os_str().to_string_lossy();
+        if !subdirs.is_empty() || component.contains("{datatype}") {
+            subdirs.push(SubDir::Custom(component.to_string()))
+        } else {
+            output_dir.push(component.as_ref())
+        }
+    }
+    if output_dir.as_os_str().is_empty() {
+        output_dir.push(".")
+    }
+    Ok((output_dir, subdirs))
+}
+
+/// sanitize label so that it occupies a single `__`-delimited segment of each filename
+pub(crate) fn parse_label(args: &Args) -> Result<Option<String>, ParseError> {
+    let raw_label = match &args.label {
+        Some(raw_label) => raw_label,
+        None => return Ok(None),
+    };
+    let replaced: String = raw_label
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    let mut label = String::with_capacity(replaced.len());
+    for c in replaced.chars() {
+        if !(c == '_' && label.ends_with('_')) {
+            label.push(c);
+        }
+    }
+    let label = label.trim_matches(|c| c == '_' || c == '.').to_string();
+    if label.is_empty() {
+        return Err(ParseError::ParseError(format!("invalid label: {:?}", raw_label)))
+    }
+    Ok(Some(label))
+}
+
+/// filename prefix of `--output-prefix`, which cannot point outside of the output directory
+fn parse_output_prefix(args: &Args) -> Result<Option<String>, ParseError> {
+    match &args.output_prefix {
+        Some(prefix) if prefix.contains(['/', '\\']) || prefix.contains("..") => Err(
+            ParseError::ParseError(format!("invalid --output-prefix: {:?}", prefix)),
+        ),
+        prefix => Ok(prefix.clone()),
+    }
+}
+
+/// part of the filename of each datatype that precedes the partition label
+pub(crate) fn parse_filename_stems(
+    args: &Args,
+    chain_id: u64,
+    datatypes: &[Datatype],
+) -> Result<Vec<String>, ParseError> {
+    let prefix = parse_output_prefix(args)?.unwrap_or_else(|| parse_network_name(args, chain_id));
+    let label = parse_label(args)?;
+    let stem = |datatype: &Datatype| {
+        let segments = [prefix.clone(), datatype.name()].into_iter().chain(label.clone());
+        segments.filter(|segment| !segment.is_empty()).collect::<Vec<_>>().join("__")
+    };
+    Ok(datatypes.iter().map(stem).collect())
+}
+
+fn parse_empty_file_policy(args: &Args) -> Result<EmptyFilePolicy, ParseError> {
+    match args.empty_file_policy.as_deref() {
+        None | Some("write") => Ok(EmptyFilePolicy::Write),
+        Some("skip") => Ok(EmptyFilePolicy::Skip),
+        Some("touch") => Ok(EmptyFilePolicy::Touch),
+        Some(_) => Err(ParseError::ParseError(
+            "invalid --empty-file-policy, use write, skip, or touch".to_string(),
+        )),
+    }
+}
+
+/// rows can only be appended to uncompressed csv and json files, parquet files are written at once
+fn parse_flush_interval(
+    args: &Args,
+    format: &FileFormat,
+    file_compression: &Option<FileCompression>,
+) -> Result<Option<u64>, ParseError> {
+    let Some(flush_interval) = args.flush_interval else { return Ok(None) };
+    let message = if flush_interval == 0 {
+        "--flush-interval must be positive"
+    } else if format == &FileFormat::Parquet {
+        "--flush-interval requires --csv or --json output"
+    } else if file_compression.is_some() {
+        "--flush-interval cannot append to compressed files"
+    } else if args.collapse_logs.is_some() {
+        "--flush-interval cannot be used with --collapse-logs, which needs whole chunks"
+    } else if args.first_seen {
+        "--flush-interval cannot be used with --first-seen, which needs whole partitions"
+    } else {
+        return Ok(Some(flush_interval))
+    };
+    Err(ParseError::ParseError(message.to_string()))
+}
+
 pub(crate) fn parse_subdirs(args: &Args) -> Vec<SubDir> {
     let mut subdirs = Vec::new();
     for arg in args.subdirs.iter() {
@@ -135,14 +294,184 @@ fn parse_compression(input: &Vec<String>) -> Result<ParquetCompression, ParseErr
     }
 }
 
+/// select the on-disk compression for the output format from `FORMAT:NAME` entries
+fn parse_file_compression(
+    input: &Option<Vec<String>>,
+    format: &FileFormat,
+) -> Result<Option<FileCompression>, ParseError> {
+    let mut selected = None;
+    let mut seen_formats = Vec::new();
+    for entry in input.iter().flatten() {
+        let (entry_format, algorithm) = entry.split_once(':').ok_or_else(|| {
+            ParseError::ParseError(format!("compression must be FORMAT:NAME, got {}", entry))
+        })?;
+        let entry_format = match entry_format {
+            "csv" => FileFormat::Csv,
+            "json" => FileFormat::Json,
+            "parquet" => {
+                return Err(ParseError::ParseError(
+                    "parquet compression is set with --compression".to_string(),
+                ))
+            }
+            _ => return Err(ParseError::ParseError(format!("invalid format: {}", entry_format))),
+        };
+        let compression = match algorithm {
+            "gzip" | "gz" => FileCompression::Gzip,
+            "zstd" | "zst" => FileCompression::Zstd,
+            _ => {
+                return Err(ParseError::ParseError(format!(
+                    "invalid file compression: {}",
+                    algorithm
+                )))
+            }
+        };
+        if seen_formats.contains(&entry_format) {
+            return Err(ParseError::ParseError(format!(
+                "compression specified more than once for {}",
+                entry_format.as_str()
+            )))
+        }
+        if &entry_format == format {
+            selected = Some(compression);
+        }
+        seen_formats.push(entry_format);
+    }
+    Ok(selected)
+}
+
+/// rows per row group, an explicit row count takes precedence over a number of row groups
 fn parse_row_group_size(
     row_group_size: Option<usize>,
     n_row_groups: Option<usize>,
     chunk_size: Option<usize>,
-) -> Option<usize> {
+) -> Result<Option<usize>, ParseError> {
     match (row_group_size, n_row_groups, chunk_size) {
-        (Some(row_group_size), _, _) => Some(row_group_size),
-        (_, Some(n_row_groups), Some(cs)) => Some(cs.div_ceil(n_row_groups)),
-        _ => None,
+        (Some(0), _, _) => {
+            Err(ParseError::ParseError("--row-group-size must be positive".to_string()))
+        }
+        (Some(row_group_size), _, _) => Ok(Some(row_group_size)),
+        (_, Some(0), _) => {
+            Err(ParseError::ParseError("--n-row-groups must be positive".to_string()))
+        }
+        (_, Some(n_row_groups), Some(cs)) => Ok(Some(cs.div_ceil(n_row_groups).max(1))),
+        _ => Ok(None),
+    }
+}
+
+/// largest data page that the parquet writer will produce
+const MAX_PARQUET_PAGE_SIZE: usize = (1 << 31) - (1 << 25);
+
+fn parse_parquet_page_size(page_size: Option<usize>) -> Result<Option<usize>, ParseError> {
+    match page_size {
+        Some(0) => Err(ParseError::ParseError("--parquet-page-size must be positive".to_string())),
+        Some(page_size) if page_size > MAX_PARQUET_PAGE_SIZE => Err(ParseError::ParseError(
+            format!("--parquet-page-size must be at most {} bytes", MAX_PARQUET_PAGE_SIZE),
+        )),
+        page_size => Ok(page_size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(raw: &str) -> Result<Option<String>, ParseError> {
+        parse_label(&Args { label: Some(raw.to_string()), ..Default::default() })
+    }
+
+    fn prefix(raw: &str) -> Result<Option<String>, ParseError> {
+        parse_output_prefix(&Args { output_prefix: Some(raw.to_string()), ..Default::default() })
+    }
+
+    #[test]
+    fn test_parse_label() {
+        assert_eq!(label("my_experiment").unwrap(), Some("my_experiment".to_string()));
+        assert_eq!(label("runs/2024 v2").unwrap(), Some("runs_2024_v2".to_string()));
+        assert_eq!(label("a__b").unwrap(), Some("a_b".to_string()));
+        assert_eq!(label("../x\\y").unwrap(), Some("x_y".to_string()));
+        assert!(label("//").is_err());
+        assert_eq!(parse_label(&Args::default()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_output_prefix() {
+        assert_eq!(prefix("mainnet_v2").unwrap(), Some("mainnet_v2".to_string()));
+        assert_eq!(prefix("").unwrap(), Some("".to_string()));
+        assert!(prefix("../mainnet").is_err());
+        assert!(prefix("..").is_err());
+        assert!(prefix("a/b").is_err());
+        assert!(prefix("a\\b").is_err());
+        assert_eq!(parse_output_prefix(&Args::default()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_filename_stems() {
+        let datatypes = [Datatype::Blocks, Datatype::Logs];
+        let stems = parse_filename_stems(&Args::default(), 1, &datatypes).unwrap();
+        assert_eq!(stems, vec!["ethereum__blocks", "ethereum__logs"]);
+        let args = Args {
+            output_prefix: Some("".to_string()),
+            label: Some("v2".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(parse_filename_stems(&args, 1, &datatypes[..1]).unwrap(), vec!["blocks__v2"]);
+    }
+
+    #[test]
+    fn test_parse_output_dir_template() {
+        let (output_dir, subdirs) = parse_output_dir_template("./data", "ethereum").unwrap();
+        assert_eq!(output_dir, PathBuf::from("./data"));
+        assert!(subdirs.is_empty());
+
+        let template = "./data/{network}/{datatype}";
+        let (output_dir, subdirs) = parse_output_dir_template(template, "ethereum").unwrap();
+        assert_eq!(output_dir, PathBuf::from("./data/ethereum"));
+        assert_eq!(subdirs, vec![SubDir::Custom("{datatype}".to_string())]);
+
+        let template = "/data/raw_{datatype}/{network}";
+        let (output_dir, subdirs) = parse_output_dir_template(template, "base").unwrap();
+        assert_eq!(output_dir, PathBuf::from("/data"));
+        let expected = ["raw_{datatype}", "base"].map(|subdir| SubDir::Custom(subdir.to_string()));
+        assert_eq!(subdirs, expected);
+
+        let (output_dir, _) = parse_output_dir_template("{datatype}", "ethereum").unwrap();
+        assert_eq!(output_dir, PathBuf::from("."));
+
+        assert!(parse_output_dir_template("./data/{chain}", "ethereum").is_err());
+    }
+
+    #[test]
+    fn test_parse_file_compression() {
+        let input = Some(vec!["csv:gzip".to_string(), "json:zstd".to_string()]);
+        let csv = parse_file_compression(&input, &FileFormat::Csv).unwrap();
+        assert_eq!(csv, Some(FileCompression::Gzip));
+        let json = parse_file_compression(&input, &FileFormat::Json).unwrap();
+        assert_eq!(json, Some(FileCompression::Zstd));
+        let parquet = parse_file_compression(&input, &FileFormat::Parquet).unwrap();
+        assert_eq!(parquet, None);
+
+        let invalid = ["parquet:gzip", "csv:lz4", "csv", "csv:gzip csv:zstd"];
+        for entry in invalid {
+            let input = Some(entry.split(' ').map(|x| x.to_string()).collect());
+            assert!(parse_file_compression(&input, &FileFormat::Csv).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_parquet_page_size() {
+        assert_eq!(parse_parquet_page_size(None).unwrap(), None);
+        assert_eq!(parse_parquet_page_size(Some(1 << 20)).unwrap(), Some(1 << 20));
+        assert!(parse_parquet_page_size(Some(0)).is_err());
+        assert!(parse_parquet_page_size(Some(MAX_PARQUET_PAGE_SIZE + 1)).is_err());
+    }
+
+    #[test]
+    fn test_parse_row_group_size() {
+        assert_eq!(parse_row_group_size(Some(10), Some(3), Some(100)).unwrap(), Some(10));
+        assert_eq!(parse_row_group_size(None, Some(3), Some(100)).unwrap(), Some(34));
+        assert_eq!(parse_row_group_size(None, Some(300), Some(100)).unwrap(), Some(1));
+        assert_eq!(parse_row_group_size(None, None, Some(100)).unwrap(), None);
+        assert!(parse_row_group_size(Some(0), None, Some(100)).is_err());
+        assert!(parse_row_group_size(None, Some(0), Some(100)).is_err());
     }
 }
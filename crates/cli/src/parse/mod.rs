@@ -13,3 +13,4 @@ pub use args::*;
 #[allow(unused_imports)]
 pub use query::*;
 use schemas::*;
+pub(crate) use source::redact_rpc_headers;
@@ -1,26 +1,61 @@
-use cryo_freeze::ParseError;
-use std::collections::HashMap;
+use alloy::primitives::Address;
+use cryo_freeze::{ParseError, StorageSlots, TraceCallInputs};
+use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 pub(crate) fn hex_string_to_binary(hex_string: &str) -> Result<Vec<u8>, ParseError> {
-    let hex_string = hex_string.strip_prefix("0x").unwrap_or(hex_string);
-    hex::decode(hex_string)
-        .map_err(|_| ParseError::ParseError("could not parse data as hex".to_string()))
+    cryo_freeze::decode_hex(hex_string)
 }
 
 pub(crate) fn hex_strings_to_binary(hex_strings: &[String]) -> Result<Vec<Vec<u8>>, ParseError> {
-    hex_strings
-        .iter()
-        .map(|x| {
-            hex::decode(x.strip_prefix("0x").unwrap_or(x))
-                .map_err(|_| ParseError::ParseError("could not parse data as hex".to_string()))
-        })
-        .collect::<Result<Vec<_>, _>>()
+    hex_strings.iter().map(|x| hex_string_to_binary(x)).collect::<Result<Vec<_>, _>>()
+}
+
+/// check explicit addresses or topics, which must be `n_bytes` of hex with optional 0x prefix
+///
+/// mixed-case addresses must match their checksum, file references are passed through unchecked,
+/// and in lenient mode malformed values are dropped instead of raising an error
+pub(crate) fn check_fixed_length_hex(
+    inputs: &Option<Vec<String>>,
+    n_bytes: usize,
+    name: &str,
+    lenient: bool,
+) -> Result<Option<Vec<String>>, ParseError> {
+    let Some(inputs) = inputs else { return Ok(None) };
+    let mut checked = Vec::new();
+    for input in inputs.iter() {
+        let is_file = parse_file_column_reference(input, name)
+            .map(|reference| Path::new(&reference.path).exists())
+            .unwrap_or(false);
+        if is_file || is_valid_fixed_length_hex(input, n_bytes) {
+            checked.push(input.clone());
+        } else if !lenient {
+            return Err(ParseError::ParseError(format!(
+                "invalid {}: {}, use --lenient-address to skip malformed values",
+                name, input
+            )))
+        }
+    }
+    Ok(Some(checked))
+}
+
+fn is_valid_fixed_length_hex(input: &str, n_bytes: usize) -> bool {
+    let raw = input.strip_prefix("0x").unwrap_or(input);
+    match hex::decode(raw) {
+        Ok(bytes) if bytes.len() == n_bytes && n_bytes == 20 => {
+            let is_mixed_case = raw.chars().any(|c| c.is_ascii_lowercase()) &&
+                raw.chars().any(|c| c.is_ascii_uppercase());
+            !is_mixed_case || Address::from_slice(&bytes).to_checksum(None)[2..] == *raw
+        }
+        Ok(bytes) => bytes.len() == n_bytes,
+        Err(_) => false,
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Hash)]
 pub(crate) enum BinaryInputList {
     Explicit,
-    ParquetColumn(String, String),
+    FileColumn(String, String),
 }
 
 use std::path::Path;
@@ -30,20 +65,25 @@ impl BinaryInputList {
     pub(crate) fn to_label(&self) -> Option<String> {
         match self {
             BinaryInputList::Explicit => None,
-            BinaryInputList::ParquetColumn(path, _) => Path::new(&path)
-                .file_stem()
-                .and_then(|stem| stem.to_str())
-                .map(|stem_str| stem_str.split("__").last().unwrap_or(stem_str))
-                .map(|s| s.to_string()),
+            BinaryInputList::FileColumn(path, _) => file_label(path),
         }
     }
 }
 
+/// label of a chunk read from a file, the last `__` piece of the file stem
+fn file_label(path: &str) -> Option<String> {
+    Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem_str| stem_str.split("__").last().unwrap_or(stem_str))
+        .map(|s| s.to_string())
+}
+
 type ParsedBinaryArg = HashMap<BinaryInputList, Vec<Vec<u8>>>;
 
 /// parse binary argument list
-/// each argument can be a hex string or a parquet column reference
-/// each parquet column is loaded into its own list, hex strings loaded into another
+/// each argument can be a hex string or a file column reference
+/// each file column is loaded into its own list, hex strings loaded into another
 pub(crate) fn parse_binary_arg(
     inputs: &[String],
     default_column: &str,
@@ -51,20 +91,12 @@ pub(crate) fn parse_binary_arg(
     let mut parsed = HashMap::new();
 
     // separate into files vs explicit
-    let (files, hex_strings): (Vec<&String>, Vec<&String>) = inputs.iter().partition(|tx| {
-        // strip off column name if present
-        match parse_file_column_reference(tx, default_column) {
-            Ok(reference) => std::path::Path::new(&reference.path).exists(),
-            _ => false,
-        }
-    });
+    let (files, hex_strings) = partition_file_inputs(inputs, default_column);
 
     // files columns
-    for path in files {
-        let reference = parse_file_column_reference(path, default_column)?;
-        let values = cryo_freeze::read_binary_column(&reference.path, &reference.column)
-            .map_err(|_e| ParseError::ParseError("could not read input".to_string()))?;
-        let key = BinaryInputList::ParquetColumn(reference.path, reference.column);
+    for reference in files {
+        let values = read_binary_file(&reference)?;
+        let key = BinaryInputList::FileColumn(reference.path, reference.column);
         parsed.insert(key, values);
     }
 
@@ -78,12 +110,96 @@ pub(crate) fn parse_binary_arg(
     Ok(parsed)
 }
 
-struct FileColumnReference {
-    path: String,
-    column: String,
+/// read unique binary values of a parquet or csv column, or of a text file with one per line
+///
+/// blank lines and `#` comments of text files are skipped
+fn read_binary_file(reference: &FileColumnReference) -> Result<Vec<Vec<u8>>, ParseError> {
+    let path = &reference.path;
+    let read_error = |_| ParseError::ParseError(format!("could not read input file: {}", path));
+    let values = if path.ends_with(".parquet") {
+        cryo_freeze::read_binary_column(path, &reference.column)?
+    } else if path.ends_with(".csv") {
+        let df =
+            CsvReader::from_path(path).and_then(|reader| reader.finish()).map_err(read_error)?;
+        let column = df.column(&reference.column).map_err(|_| {
+            ParseError::ParseError(format!("{} has no column {}", path, reference.column))
+        })?;
+        let column = column.cast(&DataType::String).map_err(read_error)?;
+        let hex_strings: Vec<String> =
+            column.str().map_err(read_error)?.into_iter().flatten().map(str::to_string).collect();
+        hex_strings_to_binary(&hex_strings)?
+    } else {
+        let hex_strings: Vec<String> = std::fs::read_to_string(path)
+            .map_err(|_| ParseError::ParseError(format!("could not read input file: {}", path)))?
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect();
+        hex_strings_to_binary(&hex_strings)?
+    };
+    let mut seen = HashSet::new();
+    Ok(values.into_iter().filter(|value| seen.insert(value.clone())).collect())
+}
+
+/// read the unique integers of a parquet column, coercing any integer type to u64
+pub(crate) fn read_integer_file(reference: &FileColumnReference) -> Result<Vec<u64>, ParseError> {
+    let (path, column) = (&reference.path, &reference.column);
+    let read_error = || ParseError::ParseError(format!("could not read input file: {}", path));
+    let file = std::fs::File::open(path).map_err(|_| read_error())?;
+    let df = ParquetReader::new(file)
+        .with_columns(Some(vec![column.clone()]))
+        .finish()
+        .map_err(|_| ParseError::ParseError(format!("{} has no column {}", path, column)))?;
+    let series = df.column(column).map_err(|_| read_error())?;
+    if !series.dtype().is_integer() {
+        let message = format!("column {} of {} is not an integer column", column, path);
+        return Err(ParseError::ParseError(message))
+    }
+
+    // negative values become null when cast
+    let series = series
+        .unique()
+        .and_then(|series| series.cast(&DataType::UInt64))
+        .map_err(|_| read_error())?;
+    let values: Option<Vec<u64>> = series.u64().map_err(|_| read_error())?.into_iter().collect();
+    let mut values = values.ok_or_else(|| {
+        ParseError::ParseError(format!("column {} of {} has missing values", column, path))
+    })?;
+    values.sort_unstable();
+    Ok(values)
+}
+
+/// split inputs into references to file columns of files that exist, and other inputs
+pub(crate) fn partition_file_inputs<'a>(
+    inputs: &'a [String],
+    default_column: &str,
+) -> (Vec<FileColumnReference>, Vec<&'a String>) {
+    let mut files = Vec::new();
+    let mut others = Vec::new();
+    for input in inputs.iter() {
+        // strip off column name if present
+        match parse_file_column_reference(input, default_column) {
+            Ok(reference) if Path::new(&reference.path).exists() => files.push(reference),
+            _ => others.push(input),
+        }
+    }
+    (files, others)
+}
+
+pub(crate) struct FileColumnReference {
+    pub(crate) path: String,
+    pub(crate) column: String,
 }
 
-fn parse_file_column_reference(
+impl FileColumnReference {
+    /// label of the chunk read from the file
+    pub(crate) fn to_label(&self) -> Option<String> {
+        file_label(&self.path)
+    }
+}
+
+pub(crate) fn parse_file_column_reference(
     path: &str,
     default_column: &str,
 ) -> Result<FileColumnReference, ParseError> {
@@ -102,3 +218,324 @@ fn parse_file_column_reference(
 
     Ok(parsed)
 }
+
+/// read (contract, slot) pairs from parquet file with `contract_address` and `slot` columns
+///
+/// duplicate pairs are dropped, and slots shorter than 32 bytes, such as `0x1`, are left-padded
+pub(crate) fn read_slot_file(path: &str) -> Result<StorageSlots, ParseError> {
+    let file = std::fs::File::open(path)
+        .map_err(|_e| ParseError::ParseError(format!("could not open slot file: {}", path)))?;
+    let df = ParquetReader::new(file)
+        .with_columns(Some(vec!["contract_address".to_string(), "slot".to_string()]))
+        .finish()
+        .map_err(|_e| {
+            ParseError::ParseError("slot file needs contract_address and slot columns".to_string())
+        })?;
+    let contracts = read_padded_binary_column(&df, "contract_address", 20, "slot file")?;
+    let slots = read_padded_binary_column(&df, "slot", 32, "slot file")?;
+    Ok(contracts.into_iter().zip(slots).collect())
+}
+
+/// read calls of parquet file with `contract_address`, `call_data`, and optional `block_number`
+/// columns
+///
+/// rows without a block number are called at every block of `--blocks`
+pub(crate) fn read_trace_calls_input_file(path: &str) -> Result<TraceCallInputs, ParseError> {
+    let kind = "trace calls input file";
+    let file = std::fs::File::open(path)
+        .map_err(|_e| ParseError::ParseError(format!("could not open {}: {}", kind, path)))?;
+    let df = ParquetReader::new(file)
+        .finish()
+        .map_err(|_e| ParseError::ParseError(format!("could not read {}: {}", kind, path)))?;
+    if df.height() == 0 {
+        return Err(ParseError::ParseError(format!("{} has no rows: {}", kind, path)))
+    }
+    let contracts = read_padded_binary_column(&df, "contract_address", 20, kind)?;
+    let call_datas = read_binary_values(&df, "call_data", kind)?;
+    let block_numbers: Vec<Option<u64>> = match df.column("block_number") {
+        Ok(series) => {
+            let invalid = || ParseError::ParseError(format!("{} has invalid block_number", kind));
+            let series = series.strict_cast(&DataType::UInt64).map_err(|_e| invalid())?;
+            series.u64().map_err(|_e| invalid())?.into_iter().collect()
+        }
+        Err(_) => vec![None; df.height()],
+    };
+
+    let mut calls: HashMap<_, HashSet<Option<u64>>> = HashMap::new();
+    let pairs = contracts.into_iter().zip(call_datas);
+    for (pair, block_number) in pairs.zip(block_numbers) {
+        calls.entry(pair).or_default().insert(block_number);
+    }
+    Ok(TraceCallInputs { calls, extra_blocks: HashSet::new() })
+}
+
+/// read binary or hex string column of an input file
+fn read_binary_values(
+    df: &DataFrame,
+    column: &str,
+    kind: &str,
+) -> Result<Vec<Vec<u8>>, ParseError> {
+    let series = df
+        .column(column)
+        .map_err(|_e| ParseError::ParseError(format!("{} needs a {} column", kind, column)))?;
+    let invalid = || ParseError::ParseError(format!("{} has invalid {}", kind, column));
+    match series.dtype() {
+        DataType::Binary => series
+            .binary()
+            .map_err(|_e| invalid())?
+            .into_iter()
+            .map(|value| value.map(|value| value.to_vec()).ok_or_else(invalid))
+            .collect(),
+        DataType::String => series
+            .str()
+            .map_err(|_e| invalid())?
+            .into_iter()
+            .map(|value| {
+                let value = value.ok_or_else(invalid)?;
+                let value = value.strip_prefix("0x").unwrap_or(value);
+                let value = if value.len() % 2 == 1 { format!("0{}", value) } else { value.into() };
+                hex::decode(value).map_err(|_e| invalid())
+            })
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+/// read binary or hex string column of an input file, left-padding each value to `n_bytes`
+fn read_padded_binary_column(
+    df: &DataFrame,
+    column: &str,
+    n_bytes: usize,
+    kind: &str,
+) -> Result<Vec<Vec<u8>>, ParseError> {
+    read_binary_values(df, column, kind)?
+        .into_iter()
+        .map(|value| {
+            if value.len() > n_bytes {
+                return Err(ParseError::ParseError(format!("{} has invalid {}", kind, column)))
+            }
+            let mut padded = vec![0; n_bytes - value.len()];
+            padded.extend(value);
+            Ok(padded)
+        })
+        .collect()
+}
+
+/// read topic0s of a parquet or csv column, `topic0` by default, or of a text file with one per
+/// line
+///
+/// blank lines and `#` comments of text files are skipped, and duplicate topic0s are dropped,
+/// returns the label of the file along with its topic0s
+pub(crate) fn read_topic0_file(
+    path: &str,
+) -> Result<(Option<String>, Vec<Vec<u8>>), ParseError> {
+    let reference = parse_file_column_reference(path, "topic0")?;
+    let topic0s = read_binary_file(&reference)
+        .map_err(|_e| ParseError::ParseError(format!("could not read topic0 file: {}", path)))?;
+    if let Some(topic0) = topic0s.iter().find(|topic0| topic0.len() != 32) {
+        return Err(ParseError::ParseError(format!(
+            "invalid topic0 in {}: 0x{}",
+            path,
+            hex::encode(topic0)
+        )))
+    }
+    let topic0s: BTreeSet<Vec<u8>> = topic0s.into_iter().collect();
+    let label = reference.to_label();
+    Ok((label, topic0s.into_iter().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str, n_bytes: usize, lenient: bool) -> Result<Vec<String>, ParseError> {
+        let inputs = Some(vec![input.to_string()]);
+        check_fixed_length_hex(&inputs, n_bytes, "address", lenient).map(|x| x.unwrap_or_default())
+    }
+
+    #[test]
+    fn test_check_fixed_length_hex() {
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert!(check(checksummed, 20, false).is_ok());
+        assert!(check(&checksummed.to_lowercase(), 20, false).is_ok());
+        assert!(check(&checksummed[2..], 20, false).is_ok());
+
+        // bad checksum, with and without prefix
+        let bad_checksum = "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert!(check(bad_checksum, 20, false).is_err());
+        assert!(check(&bad_checksum[2..], 20, false).is_err());
+
+        // wrong length and non-hex
+        assert!(check("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea", 20, false).is_err());
+        assert!(check("0xnothex", 20, false).is_err());
+        assert!(check(&"ab".repeat(32), 32, false).is_ok());
+        assert!(check(&"ab".repeat(20), 32, false).is_err());
+
+        // lenient mode drops malformed values
+        assert_eq!(check("0xnothex", 20, true).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_read_slot_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_slot_file_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("slots.parquet");
+        let contract = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+        let mut df = df!(
+            "contract_address" => [contract, contract, contract],
+            "slot" => ["0x1", "0x01", "0x2"],
+        )
+        .unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+
+        // duplicate pairs are dropped after padding slots to 32 bytes
+        let pairs = read_slot_file(path.to_str().unwrap()).unwrap();
+        let contract = hex_string_to_binary(contract).unwrap();
+        let mut slot = vec![0; 32];
+        slot[31] = 1;
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.contains(&(contract, slot)));
+
+        // slots longer than 32 bytes are invalid
+        let mut df = df!("contract_address" => ["0x01"], "slot" => ["ab".repeat(33)]).unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        assert!(read_slot_file(path.to_str().unwrap()).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_trace_calls_input_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_trace_calls_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("calls.parquet");
+        let contract = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+        let mut df = df!(
+            "contract_address" => [contract, contract, contract],
+            "call_data" => ["0x18160ddd", "0x70a08231", "0x70a08231"],
+            "block_number" => [None, Some(100u64), Some(200)],
+        )
+        .unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+
+        // rows without a block number are called at each block of --blocks
+        let inputs = read_trace_calls_input_file(path.to_str().unwrap()).unwrap();
+        let contract = hex_string_to_binary(contract).unwrap();
+        let total_supply = hex_string_to_binary("0x18160ddd").unwrap();
+        let balance_of = hex_string_to_binary("0x70a08231").unwrap();
+        assert_eq!(inputs.calls.len(), 2);
+        let blocks = |call_data: &Vec<u8>| {
+            let mut blocks: Vec<_> = inputs.calls[&(contract.clone(), call_data.clone())]
+                .iter()
+                .copied()
+                .collect();
+            blocks.sort();
+            blocks
+        };
+        assert_eq!(blocks(&total_supply), vec![None]);
+        assert_eq!(blocks(&balance_of), vec![Some(100), Some(200)]);
+
+        // negative block numbers are invalid
+        let mut df = df!(
+            "contract_address" => ["0x01"],
+            "call_data" => ["0x18160ddd"],
+            "block_number" => [-1i64],
+        )
+        .unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        assert!(read_trace_calls_input_file(path.to_str().unwrap()).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_integer_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_integer_file_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ethereum__blocks__00000000_to_00000009.parquet");
+        let mut df = df!(
+            "block_number" => [3u32, 1, 3],
+            "timestamp" => [30i64, 10, 30],
+            "negative" => [-1i64, 1, 2],
+            "name" => ["a", "b", "c"],
+        )
+        .unwrap();
+        let file = std::fs::File::create(&path).unwrap();
+        ParquetWriter::new(file).finish(&mut df).unwrap();
+
+        // integer columns of any type are deduplicated and sorted
+        let path_str = path.to_str().unwrap().to_string();
+        let inputs = vec![path_str.clone(), format!("{}:timestamp", path_str), "100".to_string()];
+        let (files, others) = partition_file_inputs(&inputs, "block_number");
+        assert_eq!(others, vec![&"100".to_string()]);
+        assert_eq!(read_integer_file(&files[0]).unwrap(), vec![1, 3]);
+        assert_eq!(read_integer_file(&files[1]).unwrap(), vec![10, 30]);
+        assert_eq!(files[1].to_label(), Some("00000000_to_00000009".to_string()));
+
+        // negative values, non-integer columns, and missing columns are rejected
+        for column in ["negative", "name", "missing"] {
+            let reference = parse_file_column_reference(&format!("{}:{}", path_str, column), "");
+            assert!(read_integer_file(&reference.unwrap()).is_err());
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_topic0_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_topic0_file_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dex__swaps.txt");
+        let swap = "0xd78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822";
+        let sync = "1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1";
+        let contents = format!("# uniswap v2\n{}\n\n{}\n{}\n", swap, sync, swap);
+        std::fs::write(&path, contents).unwrap();
+
+        // comments, blank lines, and duplicates are skipped, label is the last piece of the stem
+        let (label, topic0s) = read_topic0_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(label, Some("swaps".to_string()));
+        assert_eq!(topic0s.len(), 2);
+        assert!(topic0s.contains(&hex_string_to_binary(swap).unwrap()));
+
+        // topic0s must be 32 bytes
+        std::fs::write(&path, "0x1234\n").unwrap();
+        assert!(read_topic0_file(path.to_str().unwrap()).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_binary_arg_files() {
+        let dir = std::env::temp_dir().join(format!("cryo_binary_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tx = format!("0x{}", "ab".repeat(32));
+        let other = "cd".repeat(32);
+
+        // csv columns and text lines are read as hex, dropping duplicates
+        let csv = dir.join("txs.csv");
+        std::fs::write(&csv, format!("block_number,hash\n1,{}\n2,{}\n3,{}\n", tx, other, tx))
+            .unwrap();
+        let text = dir.join("txs.txt");
+        std::fs::write(&text, format!("# txs\n{}\n\n{}\n", tx, other)).unwrap();
+        let inputs = vec![format!("{}:hash", csv.display()), text.display().to_string()];
+        let parsed = parse_binary_arg(&inputs, "transaction_hash").unwrap();
+        assert_eq!(parsed.len(), 2);
+        for values in parsed.values() {
+            assert_eq!(values, &hex_strings_to_binary(&[tx.clone(), other.clone()]).unwrap());
+        }
+
+        // parquet columns drop values that only differ in their hex case
+        let parquet = dir.join("txs.parquet");
+        let mut df = df!("hash" => [tx.to_uppercase().replace("0X", "0x"), tx.clone()]).unwrap();
+        ParquetWriter::new(std::fs::File::create(&parquet).unwrap()).finish(&mut df).unwrap();
+        let inputs = vec![format!("{}:hash", parquet.display())];
+        let parsed = parse_binary_arg(&inputs, "transaction_hash").unwrap();
+        assert_eq!(parsed.values().next().unwrap(), &hex_strings_to_binary(&[tx]).unwrap());
+
+        // missing csv columns are an error
+        let inputs = vec![csv.display().to_string()];
+        assert!(parse_binary_arg(&inputs, "transaction_hash").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,28 +1,65 @@
 use super::{
-    blocks,
-    parse_utils::{hex_string_to_binary, hex_strings_to_binary, parse_binary_arg},
+    blocks, file_output,
+    parse_utils::{
+        self, check_fixed_length_hex, hex_string_to_binary, hex_strings_to_binary, parse_binary_arg,
+    },
     timestamps,
 };
 use crate::args::Args;
 use cryo_freeze::{
-    AddressChunk, CallDataChunk, Datatype, Dim, ParseError, Partition, PartitionLabels, SlotChunk,
-    Source, Table, TimeDimension, TopicChunk, TransactionChunk,
+    AddressChunk, BlockChunk, CallDataChunk, ChainProfile, Datatype, Dim, ParseError, Partition,
+    PartitionLabels, SlotChunk, Source, StorageSlots, Table, TimeDimension, TopicChunk,
+    TraceCallInputs, TransactionChunk,
 };
 use rand::{seq::SliceRandom, thread_rng};
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    str::FromStr,
+    sync::Arc,
+};
 
 type ChunkLabels = Vec<Option<String>>;
+type ChunkValues = Vec<Vec<u8>>;
+type TimeChunks = (TimeDimension, Option<Vec<BlockChunk>>, Option<Vec<TransactionChunk>>);
 
 pub(crate) async fn parse_partitions(
     args: &Args,
     source: Arc<Source>,
     schemas: &HashMap<Datatype, Table>,
+    chain_profile: &ChainProfile,
+    storage_slots: &Option<StorageSlots>,
+    trace_call_inputs: &mut Option<TraceCallInputs>,
 ) -> Result<(Vec<Partition>, Vec<Dim>, TimeDimension), ParseError> {
     // TODO: if wanting to chunk these non-block dimensions, do it in parse_binary_arg()
     // TODO: map from args to dim is not exhaustive
 
     // parse chunk data
-    let (block_number_labels, block_numbers) = blocks::parse_blocks(args, source.clone()).await?;
+    if let (Some(min_block), Some(max_block)) = (args.min_block, args.max_block) {
+        if min_block > max_block {
+            return Err(ParseError::ParseError("--min-block is above --max-block".to_string()))
+        }
+    }
+    if args.transactions_from_block_file.is_some() && (args.blocks.is_some() || args.txs.is_some())
+    {
+        let message = "--transactions-from-block-file cannot be used with --blocks or --txs";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    if args.topic0_file.is_some() && args.topic0.is_some() {
+        let message = "--topic0-file cannot be used with --topic0";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    let (block_number_labels, block_numbers) = match &args.transactions_from_block_file {
+        Some(paths) => {
+            let references = paths
+                .iter()
+                .map(|path| parse_utils::parse_file_column_reference(path, "block_number"))
+                .collect::<Result<Vec<_>, _>>()?;
+            let skipped = blocks::skipped_blocks(args, source.clone()).await?;
+            let (labels, chunks) = blocks::parse_block_files(&references, args, &skipped)?;
+            (Some(labels), Some(chunks))
+        }
+        None => blocks::parse_blocks(args, source.clone()).await?,
+    };
     let (block_number_labels, block_numbers) = if block_numbers.is_none() {
         timestamps::parse_timestamps(args, source.clone()).await?
     } else {
@@ -30,25 +67,79 @@ pub(crate) async fn parse_partitions(
     };
     let (transaction_hash_labels, transactions) =
         parse_transaction_chunks(&args.txs, "transaction_hash")?;
-    let call_datas = parse_call_datas(&args.call_data, &args.function, &args.inputs)?;
+    let trace_call_values = parse_trace_call_values(trace_call_inputs);
+    let call_datas = match &trace_call_values {
+        Some((_, call_datas)) => Some(vec![CallDataChunk::Values(call_datas.clone())]),
+        None => parse_call_datas(&args.call_data, &args.function, &args.inputs)?,
+    };
     let call_data_labels = None;
-    let (address_labels, addresses) = parse_address_chunks(&args.address, "address")?;
-    let (contract_labels, contracts) = parse_address_chunks(&args.contract, "contract_address")?;
+    let lenient = args.lenient_address;
+    let address = check_fixed_length_hex(&args.address, 20, "address", lenient)?;
+    let contract = check_fixed_length_hex(&args.contract, 20, "contract_address", lenient)?;
+    let from_address = check_fixed_length_hex(&args.from_address, 20, "from_address", lenient)?;
+    let to_address = check_fixed_length_hex(&args.to_address, 20, "to_address", lenient)?;
+    let topic0 = check_fixed_length_hex(&args.topic0, 32, "topic0", lenient)?;
+    let topic1 = check_fixed_length_hex(&args.topic1, 32, "topic1", lenient)?;
+    let topic2 = check_fixed_length_hex(&args.topic2, 32, "topic2", lenient)?;
+    let topic3 = check_fixed_length_hex(&args.topic3, 32, "topic3", lenient)?;
+    let slot_file_values = parse_slot_file_values(storage_slots, schemas);
+    let (address_labels, addresses) = match &slot_file_values {
+        Some((contracts, _)) => {
+            (Some(vec![None]), Some(vec![AddressChunk::Values(contracts.clone())]))
+        }
+        None => parse_address_chunks(&address, "address")?,
+    };
+    let (contract_labels, contracts) = match trace_call_values {
+        Some((contracts, _)) => (Some(vec![None]), Some(vec![AddressChunk::Values(contracts)])),
+        None => parse_address_chunks(&contract, "contract_address")?,
+    };
     let (from_address_labels, from_addresses) =
-        parse_address_chunks(&args.from_address, "from_address")?;
-    let (to_address_labels, to_addresses) = parse_address_chunks(&args.to_address, "to_address")?;
-    let (slot_labels, slots) = parse_slot_chunks(&args.slot, "slot")?;
-    let (topic0_labels, topic0s) = parse_topic(&args.topic0, "topic0")?;
-    let (topic1_labels, topic1s) = parse_topic(&args.topic1, "topic1")?;
-    let (topic2_labels, topic2s) = parse_topic(&args.topic2, "topic2")?;
-    let (topic3_labels, topic3s) = parse_topic(&args.topic3, "topic3")?;
-
-    // set default blocks
-    let block_numbers = if block_numbers.is_none() && transactions.is_none() {
-        Some(blocks::get_default_block_chunks(args, source, schemas).await?)
+        parse_address_chunks(&from_address, "from_address")?;
+    let (to_address_labels, to_addresses) = parse_address_chunks(&to_address, "to_address")?;
+    let (slot_labels, slots) = match slot_file_values {
+        Some((_, slots)) => (Some(vec![None]), Some(vec![SlotChunk::Values(slots)])),
+        None => parse_slot_chunks(&args.slot, "slot")?,
+    };
+    let (topic0_labels, topic0s) = match &args.topic0_file {
+        Some(paths) => parse_topic0_files(paths)?,
+        None => parse_topic(&topic0, "topic0")?,
+    };
+    let (topic1_labels, topic1s) = parse_topic(&topic1, "topic1")?;
+    let (topic2_labels, topic2s) = parse_topic(&topic2, "topic2")?;
+    let (topic3_labels, topic3s) = parse_topic(&topic3, "topic3")?;
+
+    // set default blocks, unless every call of a trace calls input file has its own block
+    let needs_default_blocks = match trace_call_inputs {
+        Some(inputs) => inputs.calls.values().any(|blocks| blocks.contains(&None)),
+        None => true,
+    };
+    let chain_id = source.chain_id;
+    let block_numbers = if block_numbers.is_none() && transactions.is_none() && needs_default_blocks
+    {
+        Some(blocks::get_default_block_chunks(args, source, schemas, chain_profile).await?)
     } else {
         block_numbers
     };
+    let (block_number_labels, block_numbers) = match &args.skip_existing_blocks_from {
+        Some(dir) => {
+            let datatypes: Vec<Datatype> = schemas.keys().cloned().collect();
+            blocks::skip_existing_blocks(
+                block_number_labels,
+                block_numbers,
+                std::path::Path::new(dir),
+                &file_output::parse_filename_stems(args, chain_id, &datatypes)?,
+            )?
+        }
+        None => (block_number_labels, block_numbers),
+    };
+    let (block_number_labels, block_numbers) = match trace_call_inputs {
+        Some(inputs) => add_trace_call_blocks(block_number_labels, block_numbers, inputs),
+        None => (block_number_labels, block_numbers),
+    };
+    let (time_dimension, block_numbers, transactions) =
+        parse_time_dimension(args, schemas, block_numbers, transactions)?;
+    let block_number_labels = block_numbers.as_ref().and(block_number_labels);
+    let transaction_hash_labels = transactions.as_ref().and(transaction_hash_labels);
 
     // aggregate chunk data
     let chunk = Partition {
@@ -80,8 +171,6 @@ pub(crate) async fn parse_partitions(
         topic2_labels,
         topic3_labels,
     };
-    let time_dimension = parse_time_dimension(&chunk);
-
     let partition_by = match args.partition_by.clone() {
         Some(dim_names) => {
             let dims: Result<Vec<_>, _> =
@@ -94,7 +183,7 @@ pub(crate) async fn parse_partitions(
                 .filter(|dim| labels.dim_labeled(dim) && chunk.n_chunks(dim) > 1)
                 .cloned()
                 .collect();
-            if args.txs.is_some() {
+            if chunk.transactions.is_some() {
                 vec![Dim::TransactionHash]
             } else if multichunk_dims.is_empty() {
                 vec![Dim::BlockNumber]
@@ -103,21 +192,35 @@ pub(crate) async fn parse_partitions(
             }
         }
     };
+    // skipped blocks are matched against files named by their block range only
+    if args.skip_existing_blocks_from.is_some() && partition_by != [Dim::BlockNumber] {
+        let message = "--skip-existing-blocks-from requires partitioning by block number only";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
     let mut partitions = chunk
         .partition_with_labels(labels, partition_by.clone())
         .map_err(|e| ParseError::ParseError(format!("could not partition labels ({})", e)))?;
 
+    // first appearances of partitions are consolidated into one file named by its block range
+    if args.first_seen &&
+        (partition_by != [Dim::BlockNumber] || !matches!(time_dimension, TimeDimension::Blocks))
+    {
+        let message = "--first-seen requires collecting and partitioning by block number only";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+
+    // only scheduling changes, output file contents and names do not depend on chunk order
     match args.chunk_order.as_deref() {
         None => {}
-        Some("normal") => {}
-        Some("reverse") => partitions.reverse(),
+        Some("normal") | Some("ascending") => {}
+        Some("reverse") | Some("descending") => partitions.reverse(),
         Some("random") => {
             let mut rng = thread_rng();
             partitions.shuffle(&mut rng);
         }
         _ => {
             return Err(ParseError::ParseError(
-                "invalid --chunk-order, use normal, reverse, or random".to_string(),
+                "invalid --chunk-order, use ascending, descending, or random".to_string(),
             ))
         }
     };
@@ -125,11 +228,70 @@ pub(crate) async fn parse_partitions(
     Ok((partitions, partition_by, time_dimension))
 }
 
-fn parse_time_dimension(partition: &Partition) -> TimeDimension {
-    if partition.transactions.is_some() {
-        TimeDimension::Transactions
-    } else {
-        TimeDimension::Blocks
+/// collection mode, from --collect-by or else from which inputs are given
+///
+/// an explicit mode drops the inputs of the other mode, and must be supported by each datatype
+fn parse_time_dimension(
+    args: &Args,
+    schemas: &HashMap<Datatype, Table>,
+    block_numbers: Option<Vec<BlockChunk>>,
+    transactions: Option<Vec<TransactionChunk>>,
+) -> Result<TimeChunks, ParseError> {
+    let collect_by = match args.collect_by.as_deref() {
+        // no datatype collects the cross product of blocks and transactions
+        None if transactions.is_some() && (args.blocks.is_some() || args.timestamps.is_some()) => {
+            let message = "--txs cannot be used with --blocks or --timestamps, \
+                           use --collect-by block or --collect-by transaction to pick one";
+            return Err(ParseError::ParseError(message.to_string()))
+        }
+        // blocks of a block file are only used to find the transactions to collect
+        Some("block") if args.transactions_from_block_file.is_some() => {
+            let message = "--transactions-from-block-file collects by transaction, \
+                           it cannot be used with --collect-by block";
+            return Err(ParseError::ParseError(message.to_string()))
+        }
+        None if transactions.is_some() => {
+            return Ok((TimeDimension::Transactions, block_numbers, transactions))
+        }
+        None if args.transactions_from_block_file.is_some() => TimeDimension::Transactions,
+        None => return Ok((TimeDimension::Blocks, block_numbers, transactions)),
+        Some("block") => TimeDimension::Blocks,
+        Some("transaction") => TimeDimension::Transactions,
+        Some(other) => {
+            let message = format!("invalid --collect-by: {}, use block or transaction", other);
+            return Err(ParseError::ParseError(message))
+        }
+    };
+    for datatype in schemas.keys() {
+        let supported: Vec<&str> = [
+            ("block", datatype.can_collect_by_block()),
+            ("transaction", datatype.can_collect_by_transaction()),
+        ]
+        .into_iter()
+        .filter_map(|(mode, supported)| supported.then_some(mode))
+        .collect();
+        let mode = match collect_by {
+            TimeDimension::Blocks => "block",
+            TimeDimension::Transactions => "transaction",
+        };
+        if !supported.contains(&mode) {
+            return Err(ParseError::ParseError(format!(
+                "{} cannot be collected by {}, it can only be collected by {}",
+                datatype.name(),
+                mode,
+                supported.join(" or ")
+            )))
+        }
+    }
+    match collect_by {
+        TimeDimension::Blocks if block_numbers.is_none() => Err(ParseError::ParseError(
+            "--collect-by block cannot be used with only --txs, use --blocks".to_string(),
+        )),
+        TimeDimension::Blocks => Ok((collect_by, block_numbers, None)),
+        TimeDimension::Transactions if transactions.is_some() => {
+            Ok((collect_by, None, transactions))
+        }
+        TimeDimension::Transactions => Ok((collect_by, block_numbers, None)),
     }
 }
 
@@ -140,7 +302,14 @@ fn parse_call_datas(
 ) -> Result<Option<Vec<CallDataChunk>>, ParseError> {
     let call_datas = match (call_datas, function, inputs) {
         (None, None, None) => return Ok(None),
-        (Some(call_data), None, None) => hex_strings_to_binary(call_data)?,
+        (Some(call_data), None, None) => {
+            let mut call_datas = Vec::new();
+            for arg in call_data.iter() {
+                let parsed = parse_binary_arg(std::slice::from_ref(arg), "call_data")?;
+                call_datas.extend(parsed.into_values().flatten());
+            }
+            call_datas
+        }
         (None, Some(function), None) => hex_strings_to_binary(function)?,
         (None, Some(function), Some(inputs)) => {
             let mut call_datas = Vec::new();
@@ -179,6 +348,10 @@ pub(crate) fn parse_transaction_chunks(
 ) -> Result<(Option<ChunkLabels>, Option<Vec<TransactionChunk>>), ParseError> {
     if let Some(input) = input {
         let parsed = parse_binary_arg(input, default_column)?;
+        if let Some(hash) = parsed.values().flatten().find(|hash| hash.len() != 32) {
+            let message = format!("invalid transaction hash: 0x{}", hex::encode(hash));
+            return Err(ParseError::ParseError(message))
+        }
         let labels: Vec<Option<String>> = parsed.keys().map(|x| x.clone().to_label()).collect();
         let chunks = parsed.values().map(|a| TransactionChunk::Values(a.clone())).collect();
         Ok((Some(labels), Some(chunks)))
@@ -201,6 +374,66 @@ pub(crate) fn parse_address_chunks(
     }
 }
 
+/// contracts and slots of slot file pairs, only used as dimensions by the slots dataset
+///
+/// other contract and slot combinations are skipped during collection
+fn parse_slot_file_values(
+    storage_slots: &Option<StorageSlots>,
+    schemas: &HashMap<Datatype, Table>,
+) -> Option<(ChunkValues, ChunkValues)> {
+    match storage_slots {
+        Some(storage_slots) if schemas.contains_key(&Datatype::Slots) => {
+            let contracts: BTreeSet<_> = storage_slots.iter().map(|(c, _)| c.clone()).collect();
+            let slots: BTreeSet<_> = storage_slots.iter().map(|(_, s)| s.clone()).collect();
+            Some((contracts.into_iter().collect(), slots.into_iter().collect()))
+        }
+        _ => None,
+    }
+}
+
+/// contracts and call datas of trace calls input file calls, which label the partitions
+///
+/// calls are made once per row of the input file rather than once per combination of these
+fn parse_trace_call_values(
+    trace_call_inputs: &Option<TraceCallInputs>,
+) -> Option<(ChunkValues, ChunkValues)> {
+    trace_call_inputs.as_ref().map(|inputs| {
+        let contracts: BTreeSet<_> = inputs.calls.keys().map(|(c, _)| c.clone()).collect();
+        let call_datas: BTreeSet<_> = inputs.calls.keys().map(|(_, d)| d.clone()).collect();
+        (contracts.into_iter().collect(), call_datas.into_iter().collect())
+    })
+}
+
+/// add block numbers of trace calls input file calls that are not blocks of `--blocks`
+///
+/// these are recorded in `extra_blocks` so that calls without a block number skip them
+fn add_trace_call_blocks(
+    labels: Option<ChunkLabels>,
+    chunks: Option<Vec<BlockChunk>>,
+    inputs: &mut TraceCallInputs,
+) -> (Option<ChunkLabels>, Option<Vec<BlockChunk>>) {
+    let mut chunks = chunks.unwrap_or_default();
+    let in_chunks = |block: &u64| {
+        chunks.iter().any(|chunk| match chunk {
+            BlockChunk::Numbers(numbers) => numbers.contains(block),
+            BlockChunk::Range(start, end) => start <= block && block <= end,
+        })
+    };
+    let file_blocks = inputs.calls.values().flatten().flatten();
+    let extra_blocks: BTreeSet<u64> =
+        file_blocks.filter(|block| !in_chunks(block)).copied().collect();
+    inputs.extra_blocks = extra_blocks.iter().copied().collect();
+    if extra_blocks.is_empty() {
+        return (labels, Some(chunks))
+    }
+    chunks.push(BlockChunk::Numbers(extra_blocks.into_iter().collect()));
+    let labels = labels.map(|mut labels| {
+        labels.push(None);
+        labels
+    });
+    (labels, Some(chunks))
+}
+
 pub(crate) fn parse_slot_chunks(
     input: &Option<Vec<String>>,
     default_column: &str,
@@ -215,6 +448,29 @@ pub(crate) fn parse_slot_chunks(
     }
 }
 
+/// parse topic0 files into one chunk per file, each labeled by its file name
+fn parse_topic0_files(
+    paths: &[String],
+) -> Result<(Option<ChunkLabels>, Option<Vec<TopicChunk>>), ParseError> {
+    let mut labels = Vec::new();
+    let mut chunks = Vec::new();
+    for path in paths.iter() {
+        let (label, topic0s) = parse_utils::read_topic0_file(path)?;
+        if topic0s.is_empty() {
+            return Err(ParseError::ParseError(format!("no topic0s in topic0 file: {}", path)))
+        }
+        if labels.contains(&label) {
+            return Err(ParseError::ParseError(format!(
+                "topic0 files need distinct names, {} repeats a name",
+                path
+            )))
+        }
+        labels.push(label);
+        chunks.push(TopicChunk::Values(topic0s));
+    }
+    Ok((Some(labels), Some(chunks)))
+}
+
 fn parse_topic(
     input: &Option<Vec<String>>,
     default_column: &str,
@@ -228,3 +484,113 @@ fn parse_topic(
         Ok((None, None))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    fn time_dimension(
+        datatype: &str,
+        collect_by: &str,
+        txs: bool,
+    ) -> Result<TimeChunks, ParseError> {
+        let args = Args {
+            datatype: vec![datatype.to_string()],
+            collect_by: Some(collect_by.to_string()),
+            ..Default::default()
+        };
+        let (_, schemas) = super::super::parse_schemas(&args)?;
+        let blocks = Some(vec![BlockChunk::Numbers(vec![1, 2])]);
+        let transactions = txs.then(|| vec![TransactionChunk::Values(vec![vec![0; 32]])]);
+        parse_time_dimension(&args, &schemas, blocks, transactions)
+    }
+
+    #[test]
+    fn test_parse_collect_by() {
+        // an explicit mode drops the inputs of the other mode
+        let (dimension, blocks, txs) = time_dimension("transactions", "block", true).unwrap();
+        assert!(matches!(dimension, TimeDimension::Blocks) && blocks.is_some() && txs.is_none());
+        let (dimension, blocks, txs) = time_dimension("transactions", "transaction", true).unwrap();
+        assert!(matches!(dimension, TimeDimension::Transactions));
+        assert!(blocks.is_none() && txs.is_some());
+
+        // modes that a datatype does not support are an error naming the supported modes
+        let message = match time_dimension("balances", "transaction", false) {
+            Err(ParseError::ParseError(message)) => message,
+            _ => panic!("balances cannot be collected by transaction"),
+        };
+        let expected =
+            "balances cannot be collected by transaction, it can only be collected by block";
+        assert_eq!(message, expected);
+        assert!(time_dimension("transactions", "slot", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_mixed_inputs() {
+        let transactions = Some(vec![TransactionChunk::Values(vec![vec![0; 32]])]);
+        let blocks = Some(vec![BlockChunk::Numbers(vec![1, 2])]);
+        let mut args = Args {
+            datatype: vec!["transactions".to_string()],
+            blocks: Some(vec!["1:2".to_string()]),
+            ..Default::default()
+        };
+        let (_, schemas) = super::super::parse_schemas(&args).unwrap();
+
+        // blocks and txs together are an error unless --collect-by picks one of them
+        let mixed = parse_time_dimension(&args, &schemas, blocks.clone(), transactions.clone());
+        assert!(mixed.is_err());
+        args.collect_by = Some("transaction".to_string());
+        let (dimension, blocks, txs) =
+            parse_time_dimension(&args, &schemas, blocks, transactions).unwrap();
+        assert!(matches!(dimension, TimeDimension::Transactions));
+        assert!(blocks.is_none() && txs.is_some());
+
+        // transaction hashes must be 32 bytes
+        assert!(parse_transaction_chunks(&Some(vec!["0x1234".to_string()]), "tx").is_err());
+    }
+
+    #[test]
+    fn test_transactions_from_block_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_tx_block_file_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blocks.parquet");
+        let mut df = df!("block_number" => [5u64, 3, 9]).unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        let path = path.to_str().unwrap().to_string();
+        let args = Args {
+            datatype: vec!["transactions".to_string()],
+            transactions_from_block_file: Some(vec![path.clone()]),
+            min_block: Some(4),
+            ..Default::default()
+        };
+        let (_, schemas) = super::super::parse_schemas(&args).unwrap();
+
+        // blocks of the file are kept as the blocks whose transactions are collected
+        let reference = parse_utils::parse_file_column_reference(&path, "block_number").unwrap();
+        let label = reference.to_label();
+        let references = [reference];
+        let (labels, chunks) = blocks::parse_block_files(&references, &args, &[]).unwrap();
+        assert!(label.is_some() && labels == vec![label]);
+        let (dimension, blocks, txs) =
+            parse_time_dimension(&args, &schemas, Some(chunks), None).unwrap();
+        assert!(matches!(dimension, TimeDimension::Transactions) && txs.is_none());
+        let Some([BlockChunk::Numbers(numbers)]) = blocks.as_deref() else {
+            panic!("expected the block numbers of the file")
+        };
+        assert_eq!(numbers, &vec![5, 9]);
+
+        // skipped blocks are dropped from the file, which then loses its label
+        let (labels, chunks) = blocks::parse_block_files(&references, &args, &[(9, 20)]).unwrap();
+        assert_eq!(labels, vec![None]);
+        assert!(matches!(chunks.as_slice(), [BlockChunk::Numbers(numbers)] if numbers == &[5]));
+
+        // block files cannot be collected by block, or for datatypes without transaction mode
+        let block_args = Args { collect_by: Some("block".to_string()), ..args.clone() };
+        assert!(parse_time_dimension(&block_args, &schemas, Some(chunks.clone()), None).is_err());
+        let balance_args = Args { datatype: vec!["balances".to_string()], ..args };
+        let (_, schemas) = super::super::parse_schemas(&balance_args).unwrap();
+        assert!(parse_time_dimension(&balance_args, &schemas, Some(chunks), None).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
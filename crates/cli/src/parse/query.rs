@@ -1,31 +1,399 @@
-use super::{parse_schemas, partitions};
+use super::{file_output, parse_schemas, parse_utils, partitions};
 use crate::args::Args;
-use cryo_freeze::{Dim, ParseError, Query, QueryLabels, Schemas, Source};
+use cryo_freeze::{
+    pad_global_sort_labels, BlockLogCounts, ChainProfile, ChunkData, Datatype, Dim, EnsResolver,
+    Erc20MetadataCache, MetaDatatype, ParseError, Partition, Query, QueryLabels, Schemas, Source,
+    StateDiffTracer, StorageSlots, TimeDimension, TraceCallInputs, APPEARANCE_SOURCES,
+};
+use polars::prelude::Expr;
 use std::sync::Arc;
 
 /// parse Query struct from cli Args
 pub async fn parse_query(args: &Args, source: Arc<Source>) -> Result<Query, ParseError> {
-    let (datatypes, schemas) = parse_schemas(args)?;
+    let (datatypes, mut schemas) = parse_schemas(args)?;
 
     let arg_aliases = find_arg_aliases(args, &schemas);
     let new_args =
         if !arg_aliases.is_empty() { Some(apply_arg_aliases(args, arg_aliases)?) } else { None };
     let args = new_args.as_ref().unwrap_or(args);
 
-    let (partitions, partitioned_by, time_dimension) =
-        partitions::parse_partitions(args, source, &schemas).await?;
-    let datatypes = cryo_freeze::cluster_datatypes(datatypes);
+    let network_name = file_output::parse_network_name(args, source.chain_id);
+    let chain_profile = ChainProfile::new(source.chain_id, &network_name);
+    let exclude_precompiles = parse_precompiles(args, &chain_profile)?;
+    let required_columns = parse_required_columns(args, &schemas)?;
+    let collapse_logs = parse_collapse_logs(args, &mut schemas)?;
+    let first_seen = parse_first_seen(args, &schemas)?;
+    let (min_block_logs, max_block_logs) = parse_block_log_counts(args)?;
+    let storage_slots = parse_storage_slots(args)?;
+    let mut trace_call_inputs = parse_trace_call_inputs(args, &schemas)?;
+    let ens_resolver = parse_ens_resolver(args, &source).await?;
+    let (partitions, partitioned_by, time_dimension) = partitions::parse_partitions(
+        args,
+        source,
+        &schemas,
+        &chain_profile,
+        &storage_slots,
+        &mut trace_call_inputs,
+    )
+    .await?;
+    warn_unsupported_datatypes(&chain_profile, &schemas, &partitions);
+
+    // collecting by transaction without transaction inputs collects the txs of the blocks
+    let transactions_from_blocks = matches!(time_dimension, TimeDimension::Transactions) &&
+        partitions.iter().all(|partition| partition.transactions.is_none());
+    check_flush_interval(args, &time_dimension, transactions_from_blocks)?;
+    let datatypes = parse_clusters(args, datatypes)?;
     let labels = QueryLabels { align: args.align, reorg_buffer: args.reorg_buffer };
-    Ok(Query {
+    let mut query = Query {
         datatypes,
         schemas,
         time_dimension,
+        transactions_from_blocks,
+        topic0_sets: args.topic0_file.is_some(),
         partitions,
         partitioned_by,
+        collect_chunk_size: args.blocks_per_file.map(|_| args.chunk_size),
         exclude_failed: args.exclude_failed,
+        exclude_precompiles,
+        global_sort: args.global_sort,
+        chain_profile,
+        exclude_system_transactions: args.exclude_system_txs,
+        min_priority_fee: parse_min_priority_fee(args)?,
+        tx_types: parse_tx_types(args)?,
+        contracts_only: args.contracts_only,
+        transactions_receipts_only: args.transactions_receipts_only,
+        exclude_creation_storage: args.exclude_creation_storage,
+        changes_only: args.changes_only,
+        changes_baseline: !args.no_baseline,
+        max_trace_depth: args.max_trace_depth,
+        trace_error_as_column: args.trace_error_as_column,
+        collapse_logs,
+        min_block_logs,
+        max_block_logs,
+        block_log_counts: (min_block_logs.is_some() || max_block_logs.is_some())
+            .then(BlockLogCounts::default),
+        appearance_sources: parse_appearance_sources(args)?,
+        first_seen,
+        storage_slots,
+        trace_call_inputs,
+        required_columns,
+        erc20_metadata_cache: parse_erc20_metadata_cache(args),
+        ens_resolver,
         js_tracer: args.js_tracer.clone(),
+        state_diff_tracer: parse_state_diff_tracer(args)?,
         labels,
-    })
+    };
+
+    // name files so that their filename order matches their block order
+    if args.global_sort {
+        pad_global_sort_labels(&mut query).map_err(|e| ParseError::ParseError(e.to_string()))?;
+    }
+    Ok(query)
+}
+
+/// warn about datatypes that the chain cannot serve for the collected blocks
+fn warn_unsupported_datatypes(
+    chain_profile: &ChainProfile,
+    schemas: &Schemas,
+    partitions: &[Partition],
+) {
+    let min_block = partitions
+        .iter()
+        .filter_map(|partition| partition.block_numbers.as_ref())
+        .flatten()
+        .filter_map(|chunk| chunk.min_value())
+        .min();
+    let datatypes: Vec<Datatype> = schemas.keys().cloned().collect();
+    for reason in chain_profile.unsupported_datatypes(&datatypes, min_block) {
+        eprintln!("warning: {}", reason);
+    }
+}
+
+/// (contract, slot) pairs of slot file, which replace --slot and --contract
+fn parse_storage_slots(args: &Args) -> Result<Option<StorageSlots>, ParseError> {
+    let Some(path) = &args.slot_file else { return Ok(None) };
+    if args.slot.is_some() || args.contract.is_some() || args.address.is_some() {
+        let message = "--slot-file cannot be used with --slot, --contract, or --address";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    parse_utils::read_slot_file(path).map(Some)
+}
+
+/// calls of trace calls input file, which replace --contract and --call-data
+///
+/// blocks of the input file are added to the partitions, so only datatypes that make the calls
+/// can be collected with it
+fn parse_trace_call_inputs(
+    args: &Args,
+    schemas: &Schemas,
+) -> Result<Option<TraceCallInputs>, ParseError> {
+    let Some(path) = &args.trace_calls_input_file else { return Ok(None) };
+    let makes_calls = |datatype: &Datatype| {
+        matches!(datatype, Datatype::TraceCalls | Datatype::Simulations)
+    };
+    if !schemas.keys().all(makes_calls) {
+        let message = "--trace-calls-input-file can only be used with trace_calls and simulations";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    if args.contract.is_some() ||
+        args.call_data.is_some() ||
+        args.function.is_some() ||
+        args.inputs.is_some()
+    {
+        let message = "--trace-calls-input-file cannot be used with --contract, --call-data, \
+                       --function, or --inputs";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    parse_utils::read_trace_calls_input_file(path).map(Some)
+}
+
+/// erc20 metadata rarely changes, so by default it is fetched once per token per run
+///
+/// with --changes-only every block is fetched, so that changes of metadata are not hidden
+fn parse_erc20_metadata_cache(args: &Args) -> Option<Erc20MetadataCache> {
+    if args.refetch_metadata || args.changes_only {
+        None
+    } else {
+        Some(Erc20MetadataCache::default())
+    }
+}
+
+/// names are resolved at the latest block, so that every chunk sees the same names
+async fn parse_ens_resolver(
+    args: &Args,
+    source: &Source,
+) -> Result<Option<EnsResolver>, ParseError> {
+    if !args.resolve_ens {
+        return Ok(None)
+    }
+    let block_number = source
+        .get_block_number()
+        .await
+        .map_err(|e| ParseError::ParseError(format!("could not get latest block: {}", e)))?;
+    Ok(Some(EnsResolver::new(block_number)))
+}
+
+/// parse required columns, which may be comma separated, and check that each is collected
+fn parse_required_columns(args: &Args, schemas: &Schemas) -> Result<Vec<String>, ParseError> {
+    let columns: Vec<String> = args
+        .require_columns
+        .iter()
+        .flatten()
+        .flat_map(|raw| raw.split(','))
+        .map(|column| column.trim().to_string())
+        .filter(|column| !column.is_empty())
+        .collect();
+    let missing: Vec<_> = columns
+        .iter()
+        .filter(|column| !schemas.values().any(|schema| schema.has_column(column)))
+        .collect();
+    if !missing.is_empty() {
+        return Err(ParseError::ParseError(format!(
+            "required columns are not collected: {:?}",
+            missing
+        )))
+    }
+    Ok(columns)
+}
+
+/// key columns that logs are collapsed by, each key is per block
+///
+/// the logs schema is collapsed to the key columns, which are the only columns left to filter by
+fn parse_collapse_logs(
+    args: &Args,
+    schemas: &mut Schemas,
+) -> Result<Option<Vec<String>>, ParseError> {
+    let key = match args.collapse_logs.as_deref() {
+        Some("address") => vec!["block_number", "address"],
+        Some("topic0") => vec!["block_number", "address", "topic0"],
+        Some(other) => {
+            let message = format!("invalid --collapse-logs key: {}, use address or topic0", other);
+            return Err(ParseError::ParseError(message))
+        }
+        None => return Ok(None),
+    };
+    let key: Vec<String> = key.into_iter().map(String::from).collect();
+    if let Some(schema) = schemas.get_mut(&Datatype::Logs) {
+        if let Some(column) = key.iter().find(|column| !schema.has_column(column)) {
+            let message = format!("--collapse-logs needs the {} column of logs", column);
+            return Err(ParseError::ParseError(message))
+        }
+        schema.collapse_columns(&key);
+        // rows are filtered before they are collapsed, so count cannot be filtered by either
+        let output_columns = schema.output_columns();
+        let mut filter_columns = schema.filter_expr.iter().flat_map(|expr| expr.into_iter());
+        if let Some(Expr::Column(column)) = filter_columns.find(|node| match node {
+            Expr::Column(column) => {
+                column.as_ref() == "count" || !output_columns.iter().any(|c| c == column.as_ref())
+            }
+            _ => false,
+        }) {
+            let message =
+                format!("--filter-expr column `{}` is dropped by --collapse-logs", column);
+            return Err(ParseError::ParseError(message))
+        }
+    }
+    Ok(Some(key))
+}
+
+/// cluster datatypes, replaying parity traces in one pass with --trace-replay
+fn parse_clusters(args: &Args, datatypes: Vec<Datatype>) -> Result<Vec<MetaDatatype>, ParseError> {
+    if !args.trace_replay {
+        return Ok(cryo_freeze::cluster_datatypes(datatypes))
+    }
+    if parse_state_diff_tracer(args)? != StateDiffTracer::Parity {
+        return Err(ParseError::ParseError("--trace-replay needs the parity tracer".to_string()))
+    }
+    Ok(cryo_freeze::cluster_trace_replay_datatypes(datatypes))
+}
+
+/// rows are only appended between the sub-chunks of --blocks-per-file files or between the
+/// transaction batches of blocks, other collections would never append before the chunk ends
+fn check_flush_interval(
+    args: &Args,
+    time_dimension: &TimeDimension,
+    transactions_from_blocks: bool,
+) -> Result<(), ParseError> {
+    if args.flush_interval.is_none() || transactions_from_blocks {
+        return Ok(())
+    }
+    let sub_chunked = matches!(time_dimension, TimeDimension::Blocks) &&
+        args.blocks_per_file.is_some_and(|blocks_per_file| blocks_per_file > args.chunk_size);
+    if sub_chunked {
+        return Ok(())
+    }
+    let message = "--flush-interval appends between the chunks of --blocks-per-file files, use \
+                   --blocks-per-file larger than --chunk-size";
+    Err(ParseError::ParseError(message.to_string()))
+}
+
+/// bounds of the number of logs of blocks whose logs are kept
+fn parse_block_log_counts(args: &Args) -> Result<(Option<u64>, Option<u64>), ParseError> {
+    if let (Some(min), Some(max)) = (args.min_block_logs, args.max_block_logs) {
+        if min > max {
+            let message = format!("--min-block-logs {} is above --max-block-logs {}", min, max);
+            return Err(ParseError::ParseError(message))
+        }
+    }
+    Ok((args.min_block_logs, args.max_block_logs))
+}
+
+/// whether to keep only first appearances, which needs the address and block of each row
+fn parse_first_seen(args: &Args, schemas: &Schemas) -> Result<bool, ParseError> {
+    if !args.first_seen {
+        return Ok(false)
+    }
+    let Some(schema) = schemas.get(&Datatype::AddressAppearances) else {
+        let message = "--first-seen requires the address_appearances datatype";
+        return Err(ParseError::ParseError(message.to_string()))
+    };
+    if schemas.len() > 1 {
+        let message = "--first-seen cannot collect other datatypes than address_appearances";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    if let Some(column) = ["address", "block_number"].iter().find(|c| !schema.has_column(c)) {
+        let message = format!("--first-seen needs the {} column of address_appearances", column);
+        return Err(ParseError::ParseError(message))
+    }
+    Ok(true)
+}
+
+/// sources of address appearances, which may be comma separated
+fn parse_appearance_sources(args: &Args) -> Result<Option<Vec<String>>, ParseError> {
+    let Some(raw_sources) = &args.appearance_sources else { return Ok(None) };
+    let mut sources = Vec::new();
+    for raw in raw_sources.iter().flat_map(|raw| raw.split(',')).map(str::trim) {
+        let source = raw.to_lowercase();
+        if source.is_empty() || sources.contains(&source) {
+            continue
+        }
+        if !APPEARANCE_SOURCES.contains(&source.as_str()) {
+            let message = format!(
+                "invalid --appearance-sources: {}, use any of {}",
+                raw,
+                APPEARANCE_SOURCES.join(", ")
+            );
+            return Err(ParseError::ParseError(message))
+        }
+        sources.push(source);
+    }
+    if sources.is_empty() {
+        return Err(ParseError::ParseError("--appearance-sources needs a source".to_string()))
+    }
+    Ok(Some(sources))
+}
+
+/// minimum priority fee in wei, from gwei
+fn parse_min_priority_fee(args: &Args) -> Result<Option<u64>, ParseError> {
+    match args.min_priority_fee {
+        Some(gwei) if gwei.is_finite() && gwei >= 0.0 => Ok(Some((gwei * 1e9).round() as u64)),
+        Some(gwei) => Err(ParseError::ParseError(format!("invalid --min-priority-fee: {}", gwei))),
+        None => Ok(None),
+    }
+}
+
+/// transaction types, by number or by name of their eip, which may be comma separated
+fn parse_tx_types(args: &Args) -> Result<Option<Vec<u8>>, ParseError> {
+    let Some(raw_types) = &args.tx_type else { return Ok(None) };
+    let mut tx_types = Vec::new();
+    for raw in raw_types.iter().flat_map(|raw| raw.split(',')).map(str::trim) {
+        let tx_type = match raw.to_lowercase().trim_start_matches("eip") {
+            "" => continue,
+            "legacy" => 0,
+            "2930" => 1,
+            "1559" => 2,
+            "4844" => 3,
+            "7702" => 4,
+            other => other
+                .parse::<u8>()
+                .map_err(|_| ParseError::ParseError(format!("invalid --tx-type: {}", raw)))?,
+        };
+        tx_types.push(tx_type);
+    }
+    // an empty list would filter out every transaction
+    if tx_types.is_empty() {
+        return Err(ParseError::ParseError("--tx-type needs at least one type".to_string()))
+    }
+    Ok(Some(tx_types))
+}
+
+fn parse_state_diff_tracer(args: &Args) -> Result<StateDiffTracer, ParseError> {
+    match args.tracer.as_str() {
+        "parity" => Ok(StateDiffTracer::Parity),
+        "geth" => Ok(StateDiffTracer::Geth),
+        other => Err(ParseError::ParseError(format!("invalid tracer: {}", other))),
+    }
+}
+
+fn parse_precompiles(
+    args: &Args,
+    chain_profile: &ChainProfile,
+) -> Result<Option<Vec<(u64, u64)>>, ParseError> {
+    if !args.no_precompiles {
+        return Ok(None)
+    }
+    let raw_ranges = match &args.precompile_range {
+        Some(raw_ranges) => raw_ranges,
+        None => return Ok(Some(chain_profile.precompile_ranges.clone())),
+    };
+    let parse_address = |raw: &str| match raw.strip_prefix("0x") {
+        Some(raw) => u64::from_str_radix(raw, 16),
+        None => raw.parse::<u64>(),
+    };
+    let mut ranges = Vec::new();
+    for raw_range in raw_ranges.iter() {
+        let (start, end) = raw_range.split_once(':').unwrap_or((raw_range, raw_range));
+        match (parse_address(start), parse_address(end)) {
+            (Ok(start), Ok(end)) if start <= end => ranges.push((start, end)),
+            _ => {
+                return Err(ParseError::ParseError(format!(
+                    "invalid precompile range: {}",
+                    raw_range
+                )))
+            }
+        }
+    }
+    Ok(Some(ranges))
 }
 
 fn find_arg_aliases(args: &Args, schemas: &Schemas) -> Vec<(Dim, Dim)> {
@@ -106,3 +474,50 @@ fn apply_arg_aliases(args: &Args, arg_aliases: Vec<(Dim, Dim)>) -> Result<Args,
     }
     Ok(args)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tx_types() {
+        let parse = |tx_types: &[&str]| {
+            let tx_type = Some(tx_types.iter().map(|tx_type| tx_type.to_string()).collect());
+            parse_tx_types(&Args { tx_type, ..Default::default() })
+        };
+        assert_eq!(parse_tx_types(&Args::default()).unwrap(), None);
+        assert_eq!(parse(&["legacy,eip1559", "3"]).unwrap(), Some(vec![0, 2, 3]));
+        assert!(parse(&[""]).is_err());
+        assert!(parse(&[" , "]).is_err());
+        assert!(parse(&["eip9999"]).is_err());
+    }
+
+    #[test]
+    fn test_check_flush_interval() {
+        let args = |blocks_per_file: Option<u64>| Args {
+            flush_interval: Some(10),
+            blocks_per_file,
+            chunk_size: 1000,
+            ..Default::default()
+        };
+        let blocks = TimeDimension::Blocks;
+        assert!(check_flush_interval(&args(Some(10000)), &blocks, false).is_ok());
+        assert!(check_flush_interval(&args(Some(1000)), &blocks, false).is_err());
+        assert!(check_flush_interval(&args(None), &blocks, false).is_err());
+
+        // transactions of blocks are appended between batches, explicit transactions never are
+        let transactions = TimeDimension::Transactions;
+        assert!(check_flush_interval(&args(None), &transactions, true).is_ok());
+        assert!(check_flush_interval(&args(Some(10000)), &transactions, false).is_err());
+        assert!(check_flush_interval(&Args::default(), &blocks, false).is_ok());
+    }
+
+    #[test]
+    fn test_parse_erc20_metadata_cache() {
+        assert!(parse_erc20_metadata_cache(&Args::default()).is_some());
+        let args = Args { refetch_metadata: true, ..Default::default() };
+        assert!(parse_erc20_metadata_cache(&args).is_none());
+        let args = Args { changes_only: true, ..Default::default() };
+        assert!(parse_erc20_metadata_cache(&args).is_none());
+    }
+}
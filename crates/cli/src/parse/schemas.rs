@@ -1,15 +1,17 @@
 use std::collections::HashMap;
 
 use cryo_freeze::{
-    ColumnEncoding, Datatype, FileFormat, LogDecoder, MultiDatatype, ParseError, Table,
+    CallDecoder, ColumnEncoding, DataDecoder, Datatype, FileFormat, LogDecoder, MultiDatatype,
+    ParseError, Table,
 };
 
 use super::file_output;
+use polars::prelude::Expr;
 use crate::args::Args;
 use cryo_freeze::U256Type;
 use std::str::FromStr;
 
-fn parse_datatypes(raw_inputs: &Vec<String>) -> Result<Vec<Datatype>, ParseError> {
+pub(crate) fn parse_datatypes(raw_inputs: &Vec<String>) -> Result<Vec<Datatype>, ParseError> {
     let mut datatypes = Vec::new();
 
     'outer: for raw_input in raw_inputs {
@@ -31,13 +33,30 @@ pub(crate) fn parse_schemas(
 ) -> Result<(Vec<Datatype>, HashMap<Datatype, Table>), ParseError> {
     // parse inputs
     let datatypes = parse_datatypes(&args.datatype)?;
-    let sort = parse_sort_columns(&args.sort, &datatypes)?;
+    let include_columns = internal_column_names(&args.include_columns, args);
+    let include_columns = with_column(
+        include_columns,
+        "value_decimal",
+        args.erc20_transfers_decode_value_as_decimal,
+    );
+    let include_columns = with_column(include_columns, "gas", args.include_trace_gas);
+    let include_columns = with_column(include_columns, "gas_used", args.include_trace_gas);
+    let exclude_columns = internal_column_names(&args.exclude_columns, args);
+    // --exclude-input only applies to the input column of transactions
+    let transactions_exclude_columns =
+        with_column(exclude_columns.clone(), "input", args.exclude_input);
+    let columns = internal_column_names(&args.columns, args);
+    let (sort, sort_descending) = parse_sort_directions(&args.sort)?;
+    let sort =
+        parse_sort_columns(&internal_column_names(&sort, args), &datatypes, args.global_sort)?;
     let u256_types = parse_u256_types(args)?;
     let output_format = file_output::parse_output_format(args)?;
-    let binary_column_format = match args.hex | (output_format != FileFormat::Parquet) {
-        true => ColumnEncoding::Hex,
-        false => ColumnEncoding::Binary,
-    };
+    let binary_column_format =
+        match (args.hex | (output_format != FileFormat::Parquet), args.no_hex_prefix) {
+            (true, false) => ColumnEncoding::Hex,
+            (true, true) => ColumnEncoding::HexUnprefixed,
+            (false, _) => ColumnEncoding::Binary,
+        };
 
     let log_decoder = match args.event_signature {
         Some(ref sig) => match LogDecoder::new(sig.clone()) {
@@ -47,6 +66,10 @@ pub(crate) fn parse_schemas(
         None => None,
     };
 
+    let call_decoder = parse_call_decoder(args, &datatypes)?;
+    let data_decoder = parse_data_decoder(args, &datatypes)?;
+    let filter_expr = parse_filter_expr(args)?;
+
     // create schemas
     let schemas: Result<HashMap<Datatype, Table>, ParseError> = datatypes
         .iter()
@@ -55,12 +78,28 @@ pub(crate) fn parse_schemas(
                 .table_schema(
                     &u256_types,
                     &binary_column_format,
-                    &args.include_columns,
-                    &args.exclude_columns,
-                    &args.columns,
+                    &include_columns,
+                    match datatype {
+                        Datatype::Transactions => &transactions_exclude_columns,
+                        _ => &exclude_columns,
+                    },
+                    &columns,
                     sort[datatype].clone(),
                     log_decoder.clone(),
                 )
+                .map(|mut schema| {
+                    schema.block_number_column = args.block_number_column_name.clone();
+                    schema.sort_descending = sort_descending.clone();
+                    schema.checksum_addresses = args.address_checksum;
+                    schema.filter_expr = filter_expr.clone();
+                    if matches!(datatype, Datatype::Transactions | Datatype::Traces) {
+                        schema.call_decoder = call_decoder.clone();
+                    }
+                    if datatype == &Datatype::Logs {
+                        schema.data_decoder = data_decoder.clone();
+                    }
+                    schema
+                })
                 .map(|schema| (*datatype, schema))
                 .map_err(|e| {
                     ParseError::ParseError(format!(
@@ -72,19 +111,168 @@ pub(crate) fn parse_schemas(
         .collect();
 
     // make sure all included columns ended up in at least one schema
-    if let (Ok(schemas), Some(include_columns)) = (&schemas, &args.include_columns) {
+    if let (Ok(schemas), Some(include_columns)) = (&schemas, &include_columns) {
         ensure_included_columns(include_columns, schemas)?
     };
 
     // make sure all excluded columns are excluded from at least one schema
-    if let (Ok(schemas), Some(exclude_columns)) = (&schemas, &args.exclude_columns) {
+    if let (Ok(schemas), Some(exclude_columns)) = (&schemas, &exclude_columns) {
         ensure_excluded_columns(exclude_columns, schemas)?
     };
 
-    Ok((datatypes, schemas?))
+    // make sure renamed block_number column does not collide with another column
+    if let (Ok(schemas), Some(name)) = (&schemas, &args.block_number_column_name) {
+        ensure_block_number_column_name(name, schemas)?
+    };
+
+    // make sure filter expression only references output columns, before any chunk is collected
+    if let (Ok(schemas), Some(expr)) = (&schemas, &filter_expr) {
+        ensure_filter_columns(expr, schemas)?
+    };
+
+    // ens names are resolved after rows are filtered, so they cannot be filtered by
+    let mut schemas = schemas?;
+    if args.resolve_ens {
+        for schema in schemas.values_mut() {
+            schema.add_ens_name_columns()
+        }
+    }
+
+    Ok((datatypes, schemas))
+}
+
+fn parse_call_decoder(
+    args: &Args,
+    datatypes: &[Datatype],
+) -> Result<Option<CallDecoder>, ParseError> {
+    if !args.decode_calls {
+        return Ok(None)
+    }
+    let Some(abi_dir) = &args.abi_dir else {
+        return Err(ParseError::ParseError("--decode-calls requires --abi-dir".to_string()))
+    };
+    let decodes_calls = |datatype: &Datatype| {
+        matches!(datatype, Datatype::Transactions | Datatype::Traces)
+    };
+    if !datatypes.iter().any(decodes_calls) {
+        let message = "--decode-calls can only decode transactions or traces";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    CallDecoder::from_abi_dir(std::path::Path::new(abi_dir))
+        .map(Some)
+        .map_err(ParseError::ParseError)
+}
+
+fn parse_data_decoder(
+    args: &Args,
+    datatypes: &[Datatype],
+) -> Result<Option<DataDecoder>, ParseError> {
+    let Some(layout) = &args.decode_data else { return Ok(None) };
+    if !datatypes.contains(&Datatype::Logs) {
+        return Err(ParseError::ParseError("--decode-data can only decode logs".to_string()))
+    }
+    if args.topic0.is_none() && args.topic0_file.is_none() {
+        return Err(ParseError::ParseError("--decode-data requires --topic0".to_string()))
+    }
+    if args.event_signature.is_some() {
+        let message = "--decode-data cannot be used with --event-signature";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+    DataDecoder::new(layout).map(Some).map_err(ParseError::ParseError)
+}
+
+/// map a renamed block_number column back to its internal name
+fn internal_column_names(columns: &Option<Vec<String>>, args: &Args) -> Option<Vec<String>> {
+    match &args.block_number_column_name {
+        Some(name) => columns.as_ref().map(|columns| {
+            columns
+                .iter()
+                .map(|column| {
+                    if column == name {
+                        "block_number".to_string()
+                    } else {
+                        column.clone()
+                    }
+                })
+                .collect()
+        }),
+        None => columns.clone(),
+    }
+}
+
+/// add a column to columns if toggled by a flag such as --include-input or --exclude-input
+fn with_column(columns: Option<Vec<String>>, column: &str, toggled: bool) -> Option<Vec<String>> {
+    if !toggled {
+        return columns
+    }
+    let mut columns = columns.unwrap_or_default();
+    if !columns.iter().any(|c| c == column) {
+        columns.push(column.to_string());
+    }
+    Some(columns)
+}
+
+fn ensure_block_number_column_name(
+    name: &str,
+    schemas: &cryo_freeze::Schemas,
+) -> Result<(), ParseError> {
+    if name.is_empty() {
+        return Err(ParseError::ParseError("block number column name is empty".to_string()))
+    }
+    for (datatype, schema) in schemas.iter() {
+        if name != "block_number" && schema.has_column(name) {
+            return Err(ParseError::ParseError(format!(
+                "block number column name `{}` conflicts with a column of {}",
+                name,
+                datatype.name()
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn parse_filter_expr(args: &Args) -> Result<Option<Expr>, ParseError> {
+    let Some(raw) = &args.filter_expr else { return Ok(None) };
+    polars::sql::sql_expr(raw)
+        .map(Some)
+        .map_err(|e| ParseError::ParseError(format!("invalid --filter-expr `{}`: {}", raw, e)))
+}
+
+/// filters run after u256 columns are expanded and block_number is renamed, so columns are
+/// checked against output column names
+fn ensure_filter_columns(expr: &Expr, schemas: &cryo_freeze::Schemas) -> Result<(), ParseError> {
+    for (datatype, schema) in schemas.iter() {
+        let output_columns = schema.output_columns();
+        let columns = expr.into_iter().filter_map(|node| match node {
+            Expr::Column(column) => Some(column.as_ref()),
+            _ => None,
+        });
+        for column in columns {
+            if !output_columns.iter().any(|c| c == column) && !schema.decodes_column(column) {
+                return Err(ParseError::ParseError(format!(
+                    "--filter-expr column `{}` is not an output column of {}",
+                    column,
+                    datatype.name()
+                )))
+            }
+        }
+    }
+    Ok(())
 }
 
 fn parse_u256_types(args: &Args) -> Result<Vec<U256Type>, ParseError> {
+    let mut u256_types = parse_raw_u256_types(args)?;
+    if args.value_as_decimal {
+        if args.u256_types.is_none() {
+            u256_types = vec![U256Type::Decimal128];
+        } else if !u256_types.contains(&U256Type::Decimal128) {
+            u256_types.push(U256Type::Decimal128);
+        }
+    }
+    Ok(u256_types)
+}
+
+fn parse_raw_u256_types(args: &Args) -> Result<Vec<U256Type>, ParseError> {
     args.u256_types.as_ref().map_or(
         Ok(vec![U256Type::Binary, U256Type::String, U256Type::F64]),
         |raw_u256_types| {
@@ -164,9 +352,43 @@ fn ensure_excluded_columns(
     Ok(())
 }
 
+/// split sort entries into columns and whether each is descending
+///
+/// entries can be comma-separated, and each column can have an `:asc` or `:desc` suffix
+fn parse_sort_directions(
+    raw_sort: &Option<Vec<String>>,
+) -> Result<(Option<Vec<String>>, Vec<bool>), ParseError> {
+    let Some(raw_sort) = raw_sort else { return Ok((None, Vec::new())) };
+    let mut columns = Vec::new();
+    let mut descending = Vec::new();
+    for entry in raw_sort.iter().flat_map(|x| x.split(',')).filter(|x| !x.is_empty()) {
+        let (column, is_descending) = match entry.split_once(':') {
+            Some(("none", _)) => {
+                return Err(ParseError::ParseError(
+                    "sort `none` does not take a sort direction".to_string(),
+                ))
+            }
+            Some((column, "asc")) => (column, false),
+            Some((column, "desc")) => (column, true),
+            Some((_, direction)) => {
+                return Err(ParseError::ParseError(format!(
+                    "invalid sort direction: {}, use asc or desc",
+                    direction
+                )))
+            }
+            None => (entry, false),
+        };
+        columns.push(column.to_string());
+        descending.push(is_descending);
+    }
+    Ok((Some(columns), descending))
+}
+
+/// sort columns of each datatype, None for datatypes whose sorting is disabled by `none`
 fn parse_sort_columns(
     raw_sort: &Option<Vec<String>>,
     datatypes: &[Datatype],
+    global_sort: bool,
 ) -> Result<HashMap<Datatype, Option<Vec<String>>>, ParseError> {
     match raw_sort {
         None => Ok(HashMap::from_iter(
@@ -174,7 +396,16 @@ fn parse_sort_columns(
         )),
         Some(raw_sort) => {
             if (raw_sort.len() == 1) && (raw_sort[0] == "none") {
+                if global_sort {
+                    return Err(ParseError::ParseError(
+                        "--global-sort cannot be used with --sort none".to_string(),
+                    ))
+                }
                 Ok(HashMap::from_iter(datatypes.iter().map(|datatype| (*datatype, None))))
+            } else if raw_sort.iter().any(|column| column == "none") {
+                Err(ParseError::ParseError(
+                    "sort `none` disables sorting and cannot be combined with columns".to_string(),
+                ))
             } else if raw_sort.is_empty() {
                 Err(ParseError::ParseError(
                     "must specify columns to sort by, use `none` to disable sorting".to_string(),
@@ -192,3 +423,50 @@ fn parse_sort_columns(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sort_none() {
+        let datatypes = [Datatype::Blocks, Datatype::Transactions];
+        let none = parse_sort_directions(&Some(vec!["none".to_string()])).unwrap().0;
+        let sort = parse_sort_columns(&none, &datatypes, false).unwrap();
+        assert!(datatypes.iter().all(|datatype| sort[datatype].is_none()));
+
+        // `none` is contradictory with global sort, directions, and other columns
+        assert!(parse_sort_columns(&none, &datatypes, true).is_err());
+        assert!(parse_sort_directions(&Some(vec!["none:desc".to_string()])).is_err());
+        let mixed = parse_sort_directions(&Some(vec!["none,block_number".to_string()])).unwrap();
+        assert!(parse_sort_columns(&mixed.0, &datatypes[..1], false).is_err());
+    }
+
+    #[test]
+    fn test_exclude_input() {
+        let args = Args {
+            datatype: vec!["transactions".to_string(), "geth_calls".to_string()],
+            exclude_input: true,
+            ..Default::default()
+        };
+        let (_, schemas) = parse_schemas(&args).unwrap();
+        assert!(!schemas[&Datatype::Transactions].has_column("input"));
+        assert!(schemas[&Datatype::GethCalls].has_column("input"));
+    }
+
+    #[test]
+    fn test_ensure_filter_columns() {
+        let cols = Some(vec!["block_number".to_string(), "value".to_string()]);
+        let schema = Datatype::Transactions
+            .table_schema(&[U256Type::F64], &ColumnEncoding::Hex, &None, &None, &cols, None, None)
+            .unwrap();
+        let schemas = cryo_freeze::Schemas::from([(Datatype::Transactions, schema)]);
+        let expr = |raw: &str| polars::sql::sql_expr(raw).unwrap();
+
+        // filters reference expanded u256 columns, not the internal u256 column
+        let valid = expr("value_f64 > 1 AND block_number < 5");
+        assert!(ensure_filter_columns(&valid, &schemas).is_ok());
+        assert!(ensure_filter_columns(&expr("value > 1"), &schemas).is_err());
+        assert!(ensure_filter_columns(&expr("gas_used > 1"), &schemas).is_err());
+    }
+}
@@ -2,32 +2,95 @@ use std::env;
 
 use crate::args::Args;
 use alloy::{
+    eips::BlockNumberOrTag,
     providers::{Provider, ProviderBuilder, RootProvider},
     rpc::client::{BuiltInConnectionString, ClientBuilder, RpcClient},
-    transports::{layers::RetryBackoffLayer, BoxTransport},
+    transports::{
+        http::{
+            reqwest::{
+                header::{HeaderMap, HeaderName, HeaderValue},
+                Client,
+            },
+            Http,
+        },
+        layers::RetryBackoffLayer,
+        BoxTransport,
+    },
+};
+use cryo_freeze::{
+    check_chain_id, AutoConcurrency, AutoConcurrencyLayer, BackoffHintLayer, ParseError, Query,
+    RawResponseLayer, ReceiptsSource, RpcBatcher, RpcStats, RpcStatsLayer, Source, SourceLabels,
 };
-use cryo_freeze::{ParseError, Source, SourceLabels};
 use governor::{Quota, RateLimiter};
 use polars::prelude::*;
-use std::num::NonZeroU32;
+use std::{num::NonZeroU32, path::PathBuf};
+
+/// longest initial retry backoff, longer backoffs and backoffs requested by the server are
+/// capped to avoid multi-minute sleeps
+const MAX_INITIAL_BACKOFF_MS: u64 = 60_000;
+
+/// concurrent requests that --concurrency-auto starts from and never goes below
+const MIN_AUTO_CONCURRENT_REQUESTS: u64 = 2;
 
 pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
     // parse network info
     let rpc_url = parse_rpc_url(args)?;
-    let retry_layer = RetryBackoffLayer::new(
-        args.max_retries,
-        args.initial_backoff,
-        args.compute_units_per_second,
-    );
-    let connect: BuiltInConnectionString = rpc_url.parse().map_err(ParseError::ProviderError)?;
-    let client: RpcClient<BoxTransport> = ClientBuilder::default()
-        .layer(retry_layer)
-        .connect_boxed(connect)
-        .await
-        .map_err(ParseError::ProviderError)?
-        .boxed();
+    // a max of 0 retries fails on the first provider error
+    let initial_backoff = args.initial_backoff.min(MAX_INITIAL_BACKOFF_MS);
+    let retry_layer =
+        RetryBackoffLayer::new(args.max_retries, initial_backoff, args.compute_units_per_second);
+    let backoff_hint_layer = BackoffHintLayer::new(MAX_INITIAL_BACKOFF_MS);
+    let rpc_stats = Arc::new(RpcStats::default());
+    let stats_layer = RpcStatsLayer::new(rpc_stats.clone());
+
+    // process concurrency info
+    let max_concurrent_requests = args.max_concurrent_requests.unwrap_or(100);
+    let auto_concurrency = args.concurrency_auto.then(|| {
+        Arc::new(AutoConcurrency::new(MIN_AUTO_CONCURRENT_REQUESTS, max_concurrent_requests))
+    });
+    let concurrency_layer = AutoConcurrencyLayer::new(auto_concurrency);
+
+    let client: RpcClient<BoxTransport> = if args.rpc_header.is_empty() {
+        let connect: BuiltInConnectionString =
+            rpc_url.parse().map_err(ParseError::ProviderError)?;
+        ClientBuilder::default()
+            .layer(retry_layer)
+            .layer(backoff_hint_layer)
+            .layer(stats_layer)
+            .layer(concurrency_layer)
+            .layer(RawResponseLayer)
+            .connect_boxed(connect)
+            .await
+            .map_err(ParseError::ProviderError)?
+            .boxed()
+    } else {
+        if !rpc_url.starts_with("http") {
+            let message = "--rpc-header is only supported for http rpc urls";
+            return Err(ParseError::ParseError(message.to_string()))
+        }
+        let url = rpc_url
+            .parse()
+            .map_err(|_| ParseError::ParseError("rpc url is not valid".to_string()))?;
+        let http_client = Client::builder()
+            .default_headers(parse_rpc_headers(&args.rpc_header)?)
+            .build()
+            .map_err(|e| ParseError::ParseError(format!("could not build http client: {}", e)))?;
+        let transport = Http::with_client(http_client, url);
+        let is_local = transport.guess_local();
+        ClientBuilder::default()
+            .layer(retry_layer)
+            .layer(backoff_hint_layer)
+            .layer(stats_layer)
+            .layer(concurrency_layer)
+            .layer(RawResponseLayer)
+            .transport(transport, is_local)
+            .boxed()
+    };
     let provider: RootProvider<BoxTransport> = ProviderBuilder::default().on_client(client);
     let chain_id = provider.get_chain_id().await.map_err(ParseError::ProviderError)?;
+    check_chain_id(chain_id, args.assert_chain_id)
+        .map_err(|e| ParseError::ParseError(e.to_string()))?;
+    let receipts_source = parse_receipts_source(args, &provider).await?;
     let rate_limiter = match args.requests_per_second {
         Some(rate_limit) => match (NonZeroU32::new(1), NonZeroU32::new(rate_limit)) {
             (Some(one), Some(value)) => {
@@ -39,8 +102,6 @@ pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
         None => None,
     };
 
-    // process concurrency info
-    let max_concurrent_requests = args.max_concurrent_requests.unwrap_or(100);
     let max_concurrent_chunks = match args.max_concurrent_chunks {
         Some(0) => None,
         Some(max) => Some(max),
@@ -59,16 +120,115 @@ pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
         rpc_url,
         provider,
         labels: SourceLabels {
-            max_concurrent_requests: args.max_concurrent_requests,
+            max_concurrent_requests: match args.concurrency_auto {
+                true => Some(max_concurrent_requests),
+                false => args.max_concurrent_requests,
+            },
+            min_concurrent_requests: args.concurrency_auto.then_some(MIN_AUTO_CONCURRENT_REQUESTS),
             max_requests_per_second: args.requests_per_second.map(|x| x as u64),
             max_retries: Some(args.max_retries),
-            initial_backoff: Some(args.initial_backoff),
+            initial_backoff: Some(initial_backoff),
         },
+        raw_response_dir: None,
+        receipts_source,
+        rpc_stats,
+        rpc_batcher: parse_rpc_batcher(args)?,
     };
 
     Ok(output)
 }
 
+/// batcher of per-item requests, batches of 1 are the same as no batching
+fn parse_rpc_batcher(args: &Args) -> Result<Option<Arc<RpcBatcher>>, ParseError> {
+    match args.rpc_batch_size {
+        None | Some(1) => Ok(None),
+        Some(0) => Err(ParseError::ParseError("--rpc-batch-size must be at least 1".to_string())),
+        Some(size) => Ok(Some(Arc::new(RpcBatcher::new(size)))),
+    }
+}
+
+/// rpc method for receipts, checking that the node supports eth_getBlockReceipts if forced
+async fn parse_receipts_source(
+    args: &Args,
+    provider: &RootProvider<BoxTransport>,
+) -> Result<ReceiptsSource, ParseError> {
+    match args.receipts_source.as_deref() {
+        None => Ok(ReceiptsSource::Auto),
+        Some("tx") => Ok(ReceiptsSource::Transaction),
+        Some("block") => match provider.get_block_receipts(BlockNumberOrTag::Latest.into()).await {
+            Ok(Some(_)) => Ok(ReceiptsSource::Block),
+            _ => Err(ParseError::ParseError(
+                "--receipts-source block requires a node that supports eth_getBlockReceipts"
+                    .to_string(),
+            )),
+        },
+        Some(other) => Err(ParseError::ParseError(format!(
+            "invalid --receipts-source: {}, use block or tx",
+            other
+        ))),
+    }
+}
+
+/// most blocks or transactions for which raw rpc responses are dumped
+const MAX_RAW_RESPONSE_ITEMS: u64 = 100;
+
+/// directory for raw rpc responses, only used for explicit small block or transaction ranges
+pub(crate) fn parse_raw_response_dir(args: &Args, query: &Query) -> Option<PathBuf> {
+    let dir = args.dump_raw_responses.as_ref()?;
+    if args.blocks.is_none() && args.txs.is_none() {
+        eprintln!("not dumping raw responses, --dump-raw-responses requires --blocks or --txs");
+        return None
+    }
+    let n_items: u64 = query
+        .partitions
+        .iter()
+        .map(|partition| {
+            let stats = partition.stats();
+            let n_blocks = stats.block_numbers.map(|stats| stats.total_values).unwrap_or(0);
+            let n_txs = stats.transactions.map(|stats| stats.total_values).unwrap_or(0);
+            n_blocks + n_txs
+        })
+        .sum();
+    if n_items > MAX_RAW_RESPONSE_ITEMS {
+        eprintln!(
+            "not dumping raw responses, range of {} blocks or transactions is larger than {}",
+            n_items, MAX_RAW_RESPONSE_ITEMS
+        );
+        return None
+    }
+    Some(PathBuf::from(dir))
+}
+
+/// parse `Name: value` headers, values are marked sensitive and never included in errors
+fn parse_rpc_headers(raw_headers: &[String]) -> Result<HeaderMap, ParseError> {
+    let mut headers = HeaderMap::new();
+    for raw_header in raw_headers.iter() {
+        let (name, value) = raw_header.split_once(':').ok_or_else(|| {
+            ParseError::ParseError("rpc header must have the form `Name: value`".to_string())
+        })?;
+        let name = name.trim();
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| ParseError::ParseError(format!("invalid rpc header name: {}", name)))?;
+        let mut header_value = HeaderValue::from_str(value.trim()).map_err(|_| {
+            ParseError::ParseError(format!("invalid value for rpc header: {}", name))
+        })?;
+        header_value.set_sensitive(true);
+        headers.append(header_name, header_value);
+    }
+    Ok(headers)
+}
+
+/// replace rpc header values so that they are not written to reports
+pub(crate) fn redact_rpc_headers(text: &str, raw_headers: &[String]) -> String {
+    let mut text = text.to_string();
+    for raw_header in raw_headers.iter() {
+        if let Some((name, _)) = raw_header.split_once(':') {
+            text = text.replace(raw_header.as_str(), &format!("{}: <redacted>", name.trim()));
+        }
+    }
+    text
+}
+
 pub(crate) fn parse_rpc_url(args: &Args) -> Result<String, ParseError> {
     // get MESC url
     let mesc_url = if mesc::is_mesc_enabled() {
@@ -106,3 +266,28 @@ pub(crate) fn parse_rpc_url(args: &Args) -> Result<String, ParseError> {
         Ok(url)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rpc_headers() {
+        let raw = vec!["Authorization: Bearer xyz".to_string(), "x-api-key:abc".to_string()];
+        let headers = parse_rpc_headers(&raw).unwrap();
+        assert_eq!(headers["authorization"], "Bearer xyz");
+        assert_eq!(headers["x-api-key"], "abc");
+        assert!(headers["authorization"].is_sensitive());
+
+        assert!(parse_rpc_headers(&["no colon".to_string()]).is_err());
+        let error = parse_rpc_headers(&["Authorization: bad\nvalue".to_string()]).unwrap_err();
+        assert!(!format!("{:?}", error).contains("bad"));
+    }
+
+    #[test]
+    fn test_redact_rpc_headers() {
+        let raw = vec!["Authorization: Bearer xyz".to_string()];
+        let redacted = redact_rpc_headers("--rpc-header=Authorization: Bearer xyz", &raw);
+        assert_eq!(redacted, "--rpc-header=Authorization: <redacted>");
+    }
+}
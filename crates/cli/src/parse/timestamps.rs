@@ -1,9 +1,17 @@
 use alloy::rpc::types::BlockTransactionsKind;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use cryo_freeze::{BlockChunk, ParseError, Source};
 use polars::prelude::*;
+use std::{collections::HashMap, future::Future};
 
 use crate::{
-    parse::blocks::{block_range_to_block_chunk, postprocess_block_chunks},
+    parse::{
+        blocks::{
+            block_range_to_block_chunk, clamp_block_chunk, postprocess_block_chunks,
+            skipped_blocks, subtract_block_chunk,
+        },
+        parse_utils::{partition_file_inputs, read_integer_file, FileColumnReference},
+    },
     Args,
 };
 
@@ -13,32 +21,14 @@ pub(crate) async fn parse_timestamps(
     args: &Args,
     source: Arc<Source>,
 ) -> Result<(Option<Vec<Option<String>>>, Option<Vec<BlockChunk>>), ParseError> {
-    let (files, explicit_numbers): (Vec<&String>, Vec<&String>) = match &args.timestamps {
-        Some(timestamp) => timestamp.iter().partition(|tx| std::path::Path::new(tx).exists()),
+    let (files, explicit_numbers) = match &args.timestamps {
+        Some(timestamps) => partition_file_inputs(timestamps, "timestamp"),
         None => return Ok((None, None)),
     };
 
     let (file_labels, file_chunks) = if !files.is_empty() {
-        let mut file_labels = Vec::new();
-        let mut file_chunks = Vec::new();
-        for path in files {
-            let column = if path.contains(':') {
-                path.split(':')
-                    .last()
-                    .ok_or(ParseError::ParseError("could not parse txs path column".to_string()))?
-            } else {
-                "timestamp"
-            };
-            let integers = read_integer_column(path, column)
-                .map_err(|_e| ParseError::ParseError("could not read input".to_string()))?;
-            let chunk = BlockChunk::Numbers(integers);
-            let chunk_label = path
-                .split("__")
-                .last()
-                .and_then(|s| s.strip_suffix(".parquet").map(|s| s.to_string()));
-            file_labels.push(chunk_label);
-            file_chunks.push(chunk);
-        }
+        let (file_labels, file_chunks) =
+            parse_timestamp_files(&files, args, source.clone()).await?;
         (Some(file_labels), Some(file_chunks))
     } else {
         (None, None)
@@ -71,39 +61,166 @@ pub(crate) async fn parse_timestamps(
     Ok((labels, Some(block_chunks)))
 }
 
-fn read_integer_column(path: &str, column: &str) -> Result<Vec<u64>, ParseError> {
-    let file = std::fs::File::open(path)
-        .map_err(|_e| ParseError::ParseError("could not open file path".to_string()))?;
-
-    let df = ParquetReader::new(file)
-        .with_columns(Some(vec![column.to_string()]))
-        .finish()
-        .map_err(|_e| ParseError::ParseError("could not read data from column".to_string()))?;
-
-    let series = df
-        .column(column)
-        .map_err(|_e| ParseError::ParseError("could not get column".to_string()))?
-        .unique()
-        .map_err(|_e| ParseError::ParseError("could not get column".to_string()))?;
-
-    match series.u32() {
-        Ok(ca) => ca
-            .into_iter()
-            .map(|v| {
-                v.ok_or_else(|| ParseError::ParseError("timestamp missing".to_string()))
-                    .map(|data| data.into())
-            })
-            .collect(),
-        Err(_e) => match series.u64() {
-            Ok(ca) => ca
-                .into_iter()
-                .map(|v| v.ok_or_else(|| ParseError::ParseError("timestamp missing".to_string())))
-                .collect(),
-            Err(_e) => {
-                Err(ParseError::ParseError("could not convert to integer column".to_string()))
+/// read timestamps from parquet files, one chunk per file of the blocks of its timestamps
+///
+/// the timestamps of all files are resolved together, and files whose timestamps resolve to the
+/// same blocks are collapsed into one chunk, after dropping skipped blocks
+async fn parse_timestamp_files(
+    references: &[FileColumnReference],
+    args: &Args,
+    source: Arc<Source>,
+) -> Result<(Vec<Option<String>>, Vec<BlockChunk>), ParseError> {
+    let file_timestamps: Vec<Vec<u64>> =
+        references.iter().map(read_integer_file).collect::<Result<_, _>>()?;
+    let mut timestamps: Vec<u64> = file_timestamps.iter().flatten().copied().collect();
+    timestamps.sort_unstable();
+    timestamps.dedup();
+    let latest_block_number = get_latest_block_number(source.clone()).await?;
+    let skipped = skipped_blocks(args, source.clone()).await?;
+    let get_timestamp = move |block_number| get_block_timestamp(block_number, source.clone());
+    let resolved =
+        resolve_sorted_timestamps(&timestamps, latest_block_number, get_timestamp).await?;
+    let timestamp_blocks: HashMap<u64, u64> = timestamps.into_iter().zip(resolved).collect();
+
+    let labels = references.iter().map(|reference| reference.to_label()).collect();
+    Ok(timestamp_file_chunks(labels, file_timestamps, &timestamp_blocks, args, &skipped))
+}
+
+/// chunk of each file of the blocks of its timestamps, within the block bounds of args
+///
+/// skipped blocks are dropped, and a file that loses blocks to them loses its label so that its
+/// chunk is named by its own blocks, as with block files
+fn timestamp_file_chunks(
+    labels: Vec<Option<String>>,
+    file_timestamps: Vec<Vec<u64>>,
+    timestamp_blocks: &HashMap<u64, u64>,
+    args: &Args,
+    skipped: &[(u64, u64)],
+) -> (Vec<Option<String>>, Vec<BlockChunk>) {
+    let mut file_labels = Vec::new();
+    let mut file_blocks = Vec::new();
+    for (label, timestamps) in labels.into_iter().zip(file_timestamps) {
+        let mut blocks: Vec<u64> =
+            timestamps.iter().filter_map(|t| timestamp_blocks.get(t).copied()).collect();
+        blocks.sort_unstable();
+        blocks.dedup();
+        let chunk = BlockChunk::Numbers(blocks);
+        let Some(chunk) = clamp_block_chunk(chunk, args.min_block, args.max_block) else {
+            continue
+        };
+        let (pieces, unchanged) = subtract_block_chunk(chunk, skipped);
+        for piece in pieces {
+            if let BlockChunk::Numbers(blocks) = piece {
+                file_labels.push(if unchanged { label.clone() } else { None });
+                file_blocks.push(blocks);
+            }
+        }
+    }
+    collapse_file_blocks(file_labels, file_blocks)
+}
+
+/// blocks of sorted unique timestamps, each the last block whose timestamp is at most it
+///
+/// the middle timestamp of each range of timestamps is resolved first, bounding the searches of
+/// the timestamps before and after it, and the searches of each round run concurrently
+async fn resolve_sorted_timestamps<F, Fut>(
+    timestamps: &[u64],
+    latest_block_number: u64,
+    get_timestamp: F,
+) -> Result<Vec<u64>, ParseError>
+where
+    F: Fn(u64) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<u64, ParseError>> + Send,
+{
+    let mut blocks = vec![0; timestamps.len()];
+    // ranges of timestamp indices to resolve, with the bounds of their blocks
+    let mut ranges = vec![(0, timestamps.len(), 0, latest_block_number)];
+    ranges.retain(|(start, end, _, _)| start < end);
+    while !ranges.is_empty() {
+        let mut searches = tokio::task::JoinSet::new();
+        for (index, (start, end, min_block, max_block)) in ranges.iter().copied().enumerate() {
+            let timestamp = timestamps[(start + end) / 2];
+            let get_timestamp = get_timestamp.clone();
+            searches.spawn(async move {
+                let block = search_block(timestamp, min_block, max_block, get_timestamp).await;
+                (index, block)
+            });
+        }
+        let mut resolved = vec![0; ranges.len()];
+        while let Some(result) = searches.join_next().await {
+            let (index, block) = result.map_err(|e| ParseError::ParseError(e.to_string()))?;
+            resolved[index] = block?;
+        }
+
+        let mut next_ranges = Vec::new();
+        for ((start, end, min_block, max_block), block) in ranges.into_iter().zip(resolved) {
+            let mid = (start + end) / 2;
+            blocks[mid] = block;
+            if start < mid {
+                next_ranges.push((start, mid, min_block, block));
             }
-        },
+            if mid + 1 < end {
+                next_ranges.push((mid + 1, end, block, max_block));
+            }
+        }
+        ranges = next_ranges;
+    }
+    Ok(blocks)
+}
+
+/// last block of [min_block, max_block] whose timestamp is at most timestamp, or min_block
+async fn search_block<F, Fut>(
+    timestamp: u64,
+    mut min_block: u64,
+    mut max_block: u64,
+    get_timestamp: F,
+) -> Result<u64, ParseError>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<u64, ParseError>>,
+{
+    while min_block < max_block {
+        let mid = min_block + (max_block - min_block).div_ceil(2);
+        if get_timestamp(mid).await? <= timestamp {
+            min_block = mid
+        } else {
+            max_block = mid - 1
+        }
+    }
+    Ok(min_block)
+}
+
+async fn get_block_timestamp(block_number: u64, source: Arc<Source>) -> Result<u64, ParseError> {
+    source
+        .get_block(block_number, BlockTransactionsKind::Hashes)
+        .await
+        .map_err(|_e| ParseError::ParseError("Error fetching block for timestamp".to_string()))?
+        .map(|block| block.header.timestamp)
+        .ok_or_else(|| ParseError::ParseError(format!("block {} not found", block_number)))
+}
+
+/// collapse files with the same blocks into one chunk, joining their labels with `_`
+fn collapse_file_blocks(
+    file_labels: Vec<Option<String>>,
+    file_blocks: Vec<Vec<u64>>,
+) -> (Vec<Option<String>>, Vec<BlockChunk>) {
+    let mut labels: Vec<Option<String>> = Vec::new();
+    let mut blocks: Vec<Vec<u64>> = Vec::new();
+    for (label, file_blocks) in file_labels.into_iter().zip(file_blocks) {
+        match blocks.iter().position(|blocks| blocks == &file_blocks) {
+            Some(index) => {
+                labels[index] = match (labels[index].take(), label) {
+                    (Some(previous), Some(label)) => Some(format!("{}_{}", previous, label)),
+                    (previous, label) => previous.or(label),
+                }
+            }
+            None => {
+                labels.push(label);
+                blocks.push(file_blocks);
+            }
+        }
     }
+    (labels, blocks.into_iter().map(BlockChunk::Numbers).collect())
 }
 
 /// parse timestamp numbers to freeze
@@ -269,6 +386,89 @@ fn scale_timestamp_str_by_metric_unit(
         .map_err(|_e| ParseError::ParseError("Error parsing timestamp ref".to_string()))
 }
 
+/// parse an ISO 8601 date or date range into blocks, `None` if input contains no date
+///
+/// a date range starts at the first block of its start and ends at the last block before its end,
+/// and an end without a time includes the whole day
+pub(crate) async fn parse_date_token(
+    s: &str,
+    as_range: bool,
+    source: Arc<Source>,
+) -> Result<Option<BlockChunk>, ParseError> {
+    let (start_ref, end_ref) = if let Some((timestamp, date_only)) = parse_iso_date(s) {
+        if !date_only {
+            let block = timestamp_to_block_number(timestamp, source).await?;
+            return Ok(Some(BlockChunk::Numbers(vec![block])))
+        }
+        (s, s)
+    } else if let Some(refs) = split_date_range(s) {
+        refs
+    } else {
+        return Ok(None)
+    };
+
+    let start_block = match parse_iso_date(start_ref) {
+        Some((timestamp, _)) => first_block_at_or_after(timestamp, source.clone()).await?,
+        None => 0,
+    };
+    let end_block = match parse_iso_date(end_ref) {
+        Some((timestamp, true)) => timestamp_to_block_number(timestamp + 86400 - 1, source).await?,
+        Some((timestamp, false)) => {
+            let timestamp = timestamp.checked_sub(1).ok_or_else(|| {
+                ParseError::ParseError("end date is before the first block".to_string())
+            })?;
+            timestamp_to_block_number(timestamp, source).await?
+        }
+        None => get_latest_block_number(source).await?,
+    };
+    block_range_to_block_chunk(start_block, end_block, as_range, None, None).map(Some)
+}
+
+/// unix timestamp of an ISO 8601 date or datetime, and whether it is a date without a time
+///
+/// dates and datetimes without a timezone are interpreted as UTC
+fn parse_iso_date(s: &str) -> Option<(u64, bool)> {
+    let (timestamp, date_only) = if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        (date.and_hms_opt(0, 0, 0)?.and_utc().timestamp(), true)
+    } else if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        (datetime.timestamp(), false)
+    } else {
+        let datetime = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())?;
+        (datetime.and_utc().timestamp(), false)
+    };
+    u64::try_from(timestamp).ok().map(|timestamp| (timestamp, date_only))
+}
+
+/// split a date range at the `:` between its start and end, which can be empty or `latest`
+///
+/// datetimes contain `:` themselves, so each `:` is tried until both sides are valid
+fn split_date_range(s: &str) -> Option<(&str, &str)> {
+    s.match_indices(':').map(|(i, _)| (&s[..i], &s[i + 1..])).find(|(start_ref, end_ref)| {
+        let start = parse_iso_date(start_ref);
+        let end = parse_iso_date(end_ref);
+        (start.is_some() || start_ref.is_empty()) &&
+            (end.is_some() || end_ref.is_empty() || *end_ref == "latest") &&
+            (start.is_some() || end.is_some())
+    })
+}
+
+/// first block with a timestamp greater than or equal to a given timestamp
+async fn first_block_at_or_after(timestamp: u64, source: Arc<Source>) -> Result<u64, ParseError> {
+    let block_number = timestamp_to_block_number(timestamp, source.clone()).await?;
+    let block = source
+        .get_block(block_number, BlockTransactionsKind::Hashes)
+        .await
+        .map_err(|_e| ParseError::ParseError("Error fetching block for timestamp".to_string()))?
+        .ok_or_else(|| ParseError::ParseError("block not found for timestamp".to_string()))?;
+    if block.header.timestamp < timestamp {
+        Ok(block_number + 1)
+    } else {
+        Ok(block_number)
+    }
+}
+
 // perform binary search to determine the closest block number smaller than or equal to a given
 // timestamp
 async fn timestamp_to_block_number(timestamp: u64, source: Arc<Source>) -> Result<u64, ParseError> {
@@ -332,7 +532,7 @@ mod tests {
     use governor::{Quota, RateLimiter};
 
     use super::*;
-    use cryo_freeze::SourceLabels;
+    use cryo_freeze::{ChunkData, SourceLabels};
 
     async fn setup_source() -> Source {
         let rpc_url = match crate::parse::source::parse_rpc_url(&Args::default()) {
@@ -369,6 +569,10 @@ mod tests {
             max_concurrent_chunks: None,
             rpc_url: "".to_string(),
             labels: SourceLabels::default(),
+            raw_response_dir: None,
+            receipts_source: cryo_freeze::ReceiptsSource::Auto,
+            rpc_stats: Default::default(),
+            rpc_batcher: None,
         }
     }
 
@@ -512,4 +716,82 @@ mod tests {
             (18573050, 18573051)
         );
     }
+
+    #[test]
+    fn test_parse_iso_date() {
+        assert_eq!(parse_iso_date("2024-01-01"), Some((1704067200, true)));
+        assert_eq!(parse_iso_date("2024-01-01T12:00"), Some((1704110400, false)));
+        assert_eq!(parse_iso_date("2024-01-01T12:00:00"), Some((1704110400, false)));
+        assert_eq!(parse_iso_date("2024-01-01T12:00:00Z"), Some((1704110400, false)));
+        assert_eq!(parse_iso_date("2024-01-01T14:00:00+02:00"), Some((1704110400, false)));
+        assert_eq!(parse_iso_date("2024-13-01"), None);
+        assert_eq!(parse_iso_date("15M"), None);
+        assert_eq!(parse_iso_date("1704067200"), None);
+    }
+
+    #[test]
+    fn test_split_date_range() {
+        assert_eq!(split_date_range("2024-01-01:2024-02-01"), Some(("2024-01-01", "2024-02-01")));
+        assert_eq!(split_date_range("2024-01-01:"), Some(("2024-01-01", "")));
+        assert_eq!(split_date_range(":2024-02-01"), Some(("", "2024-02-01")));
+        assert_eq!(split_date_range("2024-01-01:latest"), Some(("2024-01-01", "latest")));
+        assert_eq!(
+            split_date_range("2024-01-01T06:00:00Z:2024-01-01T18:00"),
+            Some(("2024-01-01T06:00:00Z", "2024-01-01T18:00"))
+        );
+        assert_eq!(split_date_range("15M:16M"), None);
+        assert_eq!(split_date_range("2024-01-01:16M"), None);
+        assert_eq!(split_date_range(":"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_sorted_timestamps() {
+        // blocks 0..=100 are 12 seconds apart, starting at timestamp 1000
+        let calls = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let get_timestamp = {
+            let calls = calls.clone();
+            move |block_number: u64| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async move { Ok(1000 + 12 * block_number) }
+            }
+        };
+        let timestamps = [0, 999, 1000, 1011, 1012, 1013, 1500, 2199, 2200, 9999];
+        let blocks = resolve_sorted_timestamps(&timestamps, 100, get_timestamp).await.unwrap();
+        assert_eq!(blocks, vec![0, 0, 0, 0, 1, 1, 41, 99, 100, 100]);
+
+        // searches are narrowed by the timestamps already resolved
+        let n_calls = calls.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(n_calls < 7 * timestamps.len() as u64, "{} calls", n_calls);
+
+        let no_timestamps = resolve_sorted_timestamps(&[], 100, |_| async { Ok(0) }).await;
+        assert_eq!(no_timestamps.unwrap(), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn test_collapse_file_blocks() {
+        let labels =
+            vec![Some("a".to_string()), Some("b".to_string()), None, Some("d".to_string())];
+        let blocks = vec![vec![1, 2], vec![3], vec![1, 2], vec![3]];
+        let (labels, chunks) = collapse_file_blocks(labels, blocks);
+        assert_eq!(labels, vec![Some("a".to_string()), Some("b_d".to_string())]);
+        let chunks: Vec<_> = chunks.iter().map(|chunk| chunk.values()).collect();
+        assert_eq!(chunks, vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn test_timestamp_file_chunks_skipped_blocks() {
+        let labels = vec![Some("a".to_string()), Some("b".to_string()), Some("c".to_string())];
+        let file_timestamps = vec![vec![100, 200], vec![300, 400], vec![500]];
+        let timestamp_blocks: HashMap<u64, u64> =
+            [(100, 10), (200, 20), (300, 30), (400, 40), (500, 50)].into_iter().collect();
+        let args = Args::default();
+
+        // skipped blocks are dropped after timestamps are resolved to blocks
+        let skipped = vec![(25, 30), (50, 50)];
+        let (labels, chunks) =
+            timestamp_file_chunks(labels, file_timestamps, &timestamp_blocks, &args, &skipped);
+        assert_eq!(labels, vec![Some("a".to_string()), None]);
+        let chunks: Vec<_> = chunks.iter().map(|chunk| chunk.values()).collect();
+        assert_eq!(chunks, vec![vec![10, 20], vec![40]]);
+    }
 }
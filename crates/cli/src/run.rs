@@ -2,7 +2,9 @@ use crate::{args, parse, remember};
 use clap_cryo::Parser;
 use color_print::cstr;
 use colored::Colorize;
-use cryo_freeze::{err, CollectError, ExecutionEnv, FreezeSummary};
+use cryo_freeze::{
+    err, CollectError, ExecutionEnv, FreezeSummary, Query, RpcMethod, Source, UnsupportedRpcMethod,
+};
 use std::{sync::Arc, time::SystemTime};
 
 /// Entry point to run the CLI application.
@@ -10,6 +12,9 @@ pub async fn run(args: args::Args) -> Result<Option<FreezeSummary>, CollectError
     if is_help_command(&args) {
         return handle_help_subcommands(args);
     }
+    if is_dims_command(&args) {
+        return handle_dims_command(args);
+    }
 
     let cryo_dir = build_cryo_directory(std::path::Path::new(&args.output_dir));
 
@@ -30,8 +35,28 @@ fn is_help_command(args: &args::Args) -> bool {
     args.datatype.first() == Some(&"help".to_string())
 }
 
-/// Build the cryo directory path.
+/// Check if the command is a dims command.
+fn is_dims_command(args: &args::Args) -> bool {
+    args.datatype.first() == Some(&"dims".to_string())
+}
+
+/// Print partition dims of the given datasets, or of all datasets if none are given.
+fn handle_dims_command(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
+    let datatypes = if args.datatype.len() > 1 {
+        parse::schemas::parse_datatypes(&args.datatype[1..].to_vec())?
+    } else {
+        cryo_freeze::Datatype::all()
+    };
+    cryo_freeze::print_dataset_dims(&datatypes);
+    Ok(None)
+}
+
+/// Build the cryo directory path, in the part of the output dir that precedes any placeholder.
 fn build_cryo_directory(output_dir: &std::path::Path) -> std::path::PathBuf {
+    let output_dir: std::path::PathBuf = output_dir
+        .components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains('{'))
+        .collect();
     output_dir.join(".cryo")
 }
 
@@ -67,11 +92,68 @@ async fn run_freeze_process(args: args::Args) -> Result<Option<FreezeSummary>, C
     let (query, source, sink, env) = parse::parse_args(&args).await?;
 
     let source = Arc::new(source);
+
+    // print preview rows instead of writing files
+    if let Some(n_rows) = args.preview {
+        let previews = cryo_freeze::collect_preview(Arc::new(query), source, n_rows).await?;
+        cryo_freeze::print_preview(previews, n_rows)?;
+        return Ok(None)
+    }
+
+    // fail before collecting if node does not support rpc methods of datasets, dry runs collect
+    // nothing so they are not probed
+    if !args.no_probe && !env.dry {
+        check_rpc_methods(&query, &source).await?;
+    }
+
     let env = ExecutionEnv { t_start_parse, ..env }.set_start_time();
 
     cryo_freeze::freeze(&query, &source, &sink, &env).await
 }
 
+/// Fail if the node does not support rpc methods needed by datasets of query.
+pub async fn check_rpc_methods(query: &Query, source: &Source) -> Result<(), CollectError> {
+    let unsupported = cryo_freeze::probe_rpc_methods(query, source).await?;
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(err(&unsupported_rpc_methods_message(&unsupported)))
+    }
+}
+
+/// Describe rpc methods that failed their probe, with guidance for each.
+fn unsupported_rpc_methods_message(unsupported: &[UnsupportedRpcMethod]) -> String {
+    let mut lines = vec!["node does not support rpc methods needed by datasets:".to_string()];
+    for method in unsupported.iter() {
+        let guidance = if method.historical {
+            "requires an archive node for historical state"
+        } else {
+            match method.method {
+                RpcMethod::GetBlockReceipts => "use --receipts-source tx instead",
+                RpcMethod::TraceReplayBlockTransactions | RpcMethod::TraceReplayTransaction => {
+                    "requires the trace namespace (e.g. reth, erigon), or --tracer geth for diffs"
+                }
+                RpcMethod::TraceBlock | RpcMethod::TraceTransaction | RpcMethod::TraceCall => {
+                    "requires the trace namespace (e.g. reth, erigon)"
+                }
+                RpcMethod::DebugTraceBlockByNumber | RpcMethod::DebugTraceTransaction => {
+                    "requires the debug namespace (e.g. geth, reth)"
+                }
+                _ => "required by the selected datasets",
+            }
+        };
+        lines.push(format!(
+            "- {} at block {}: {}\n  {}",
+            method.method.name(),
+            method.block_number,
+            method.error,
+            guidance
+        ));
+    }
+    lines.push("use --no-probe to collect anyway".to_string());
+    lines.join("\n")
+}
+
 /// Handle help-related subcommands.
 fn handle_help_subcommands(args: args::Args) -> Result<Option<FreezeSummary>, CollectError> {
     match args.datatype.len() {
@@ -107,12 +189,34 @@ fn print_syntax_help() {
 - plus sign on end means plus start  <white><bold>15M:+1000</bold></white> == <white><bold>15M:15.001K</bold></white>
 - can use every nth value            <white><bold>2000:5000:1000</bold></white> == <white><bold>2000 3000 4000</bold></white>
 - can use n values total             <white><bold>100:200/5</bold></white> == <white><bold>100 124 149 174 199</bold></white>
+- can use ISO 8601 dates (UTC)       <white><bold>--blocks 2024-01-01:2024-01-31</bold></white>
+- range end date includes whole day  <white><bold>2024-01-01:2024-01-01</bold></white> == <white><bold>2024-01-01</bold></white>
+- omitting blocks means all blocks   <white><bold>0:latest</bold></white>, traces of optimism and arbitrum start at
+                                     their first traceable block
+
+<white><bold>Timestamp specification syntax</bold></white>
+- can use numbers                    <white><bold>--timestamps 5000 6000 7000</bold></white>
+- can use ranges                     <white><bold>--timestamps 12M:13M 15M:16M</bold></white>
+- can use a parquet file             <white><bold>--timestamps ./path/to/file.parquet[:COLUMN_NAME]</bold></white>
+                                     (default column name is <white><bold>timestamp</bold></white>)
+- can use multiple parquet files     <white><bold>--timestamps ./path/to/files/*.parquet[:COLUMN_NAME]</bold></white>
+- files of the same blocks combine   <white><bold>a__x.parquet b__y.parquet</bold></white> -> label <white><bold>x_y</bold></white>
+- can contain { _ . m h d w M y }    <white><bold>31_536_000 525600m 8760h 365d 52.143w 12.17M 1y</bold></white>
+- omitting range end means latest    <white><bold>15.5M:</bold></white> == <white><bold>15.5M:latest</bold></white>
+- omitting range start means 0       <white><bold>:700</bold></white> == <white><bold>0:700</bold></white>
+- minus on start means minus end     <white><bold>-1000:7000</bold></white> == <white><bold>6000:7000</bold></white>
+- plus sign on end means plus start  <white><bold>15M:+1000</bold></white> == <white><bold>15M:15.001M</bold></white>
+- can use n values total             <white><bold>100:200/5</bold></white> == <white><bold>100 124 149 174 199</bold></white>
 
 <white><bold>Transaction specification syntax</bold></white>
 - can use transaction hashes         <white><bold>--txs TX_HASH1 TX_HASH2 TX_HASH3</bold></white>
 - can use a parquet file             <white><bold>--txs ./path/to/file.parquet[:COLUMN_NAME]</bold></white>
                                      (default column name is <white><bold>transaction_hash</bold></white>)
-- can use multiple parquet files     <white><bold>--txs ./path/to/ethereum__logs*.parquet</bold></white>"#
+- can use multiple parquet files     <white><bold>--txs ./path/to/ethereum__logs*.parquet</bold></white>
+- can use a csv file                 <white><bold>--txs ./path/to/file.csv[:COLUMN_NAME]</bold></white>
+- can use a text file                <white><bold>--txs ./path/to/txs.txt</bold></white>
+                                     (one hash per line, <white><bold>#</bold></white> starts a comment)
+- cannot be used with --blocks       unless <white><bold>--collect-by</bold></white> picks block or transaction"#
     );
     println!("{}", content);
 }
@@ -160,6 +264,17 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_handle_dims_command() {
+        let args =
+            Args { datatype: vec!["dims".to_string(), "logs".to_string()], ..Default::default() };
+        assert!(handle_dims_command(args).is_ok());
+
+        let args =
+            Args { datatype: vec!["dims".to_string(), "nope".to_string()], ..Default::default() };
+        assert!(handle_dims_command(args).is_err());
+    }
+
     #[test]
     fn test_handle_help_subcommands_datasets_help() {
         let args = Args {
@@ -1,5 +1,6 @@
-use crate::{collect_partition, CollectError, Query, Source};
+use crate::{collect_partition, CollectError, Datatype, Query, Source};
 use polars::prelude::*;
+use std::collections::HashMap;
 
 /// collect single dataframe
 pub async fn collect(query: Arc<Query>, source: Arc<Source>) -> Result<DataFrame, CollectError> {
@@ -28,3 +29,44 @@ pub async fn collect(query: Arc<Query>, source: Arc<Source>) -> Result<DataFrame
         }
     }
 }
+
+/// collect the first `n_rows` rows of each datatype of query
+///
+/// partitions are collected one at a time in query order, stopping once every datatype has
+/// `n_rows` rows, so no more chunks are collected than are needed to fill the preview
+pub async fn collect_preview(
+    query: Arc<Query>,
+    source: Arc<Source>,
+    n_rows: usize,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    query.is_valid()?;
+    let mut previews: HashMap<Datatype, DataFrame> = HashMap::new();
+    for meta_datatype in query.datatypes.iter() {
+        for partition in query.partitions.iter() {
+            let filled = meta_datatype.datatypes().iter().all(|datatype| {
+                previews.get(datatype).is_some_and(|df| df.height() >= n_rows)
+            });
+            if filled {
+                break
+            }
+            let dfs = collect_partition(
+                meta_datatype.clone(),
+                partition.clone(),
+                query.clone(),
+                source.clone(),
+            )
+            .await?;
+            for (datatype, df) in dfs.into_iter() {
+                match previews.get_mut(&datatype) {
+                    Some(preview) => {
+                        preview.vstack_mut(&df).map_err(CollectError::PolarsError)?;
+                    }
+                    None => {
+                        previews.insert(datatype, df);
+                    }
+                }
+            }
+        }
+    }
+    Ok(previews.into_iter().map(|(datatype, df)| (datatype, df.head(Some(n_rows)))).collect())
+}
@@ -42,17 +42,48 @@ impl Dataset for AddressAppearances {
     }
 }
 
-type BlockLogsTraces = (Block, Vec<Log>, Vec<LocalizedTransactionTrace>);
+/// sources of address appearances, each can be disabled for nodes that lack it
+pub const APPEARANCE_SOURCES: [&str; 3] = ["traces", "logs", "miner"];
+
+/// whether appearances of source are collected, every source is collected by default
+pub(crate) fn uses_appearance_source(query: &Query, source: &str) -> bool {
+    match &query.appearance_sources {
+        Some(sources) => sources.iter().any(|s| s == source),
+        None => true,
+    }
+}
+
+/// block, hashes of the transactions to process, and logs and traces if their source is used
+type BlockLogsTraces =
+    (Block, Vec<TxHash>, Option<Vec<Log>>, Option<Vec<LocalizedTransactionTrace>>);
+
+/// fetch a source only if it is used, naming it in errors so that it can be disabled
+async fn fetch_source<T, F>(query: &Query, name: &str, fetch: F) -> R<Option<T>>
+where
+    F: std::future::Future<Output = R<T>>,
+{
+    if !uses_appearance_source(query, name) {
+        return Ok(None)
+    }
+    fetch.await.map(Some).map_err(|e| {
+        CollectError::CollectError(format!(
+            "could not collect {} of address_appearances ({}), use --appearance-sources to \
+             collect without them",
+            name, e
+        ))
+    })
+}
 
 #[async_trait::async_trait]
 impl CollectByBlock for AddressAppearances {
     type Response = BlockLogsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let block_number = request.ethers_block_number()?;
         let block =
             source.get_block(request.block_number()?, BlockTransactionsKind::Hashes).await?;
         let block = block.ok_or(CollectError::CollectError("block not found".to_string()))?;
+        let tx_hashes = block.transactions.hashes().collect();
         let filter = Filter {
             block_option: FilterBlockOption::Range {
                 from_block: Some(block_number.into()),
@@ -60,14 +91,15 @@ impl CollectByBlock for AddressAppearances {
             },
             ..Default::default()
         };
-        let logs = source.get_logs(&filter).await?;
-        let traces = source.trace_block(request.block_number()?).await?;
-        Ok((block, logs, traces))
+        let logs = fetch_source(&query, "logs", source.get_logs(&filter)).await?;
+        let traces =
+            fetch_source(&query, "traces", source.trace_block(request.block_number()?)).await?;
+        Ok((block, tx_hashes, logs, traces))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::AddressAppearances)?;
-        process_appearances(response, columns, schema)
+        process_appearances(response, columns, schema, query)
     }
 }
 
@@ -75,7 +107,7 @@ impl CollectByBlock for AddressAppearances {
 impl CollectByTransaction for AddressAppearances {
     type Response = BlockLogsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let tx_hash = request.ethers_transaction_hash()?;
 
         let tx_data = source.get_transaction_by_hash(tx_hash).await?.ok_or_else(|| {
@@ -91,30 +123,33 @@ impl CollectByTransaction for AddressAppearances {
             .ok_or(CollectError::CollectError("could not get block".to_string()))?;
 
         // logs
-        let logs = source
-            .get_transaction_receipt(tx_hash)
-            .await?
-            .ok_or(CollectError::CollectError("could not get tx receipt".to_string()))?
-            .inner
-            .logs()
-            .to_vec();
+        let logs = fetch_source(&query, "logs", async {
+            Ok(source
+                .get_transaction_receipt(tx_hash)
+                .await?
+                .ok_or(CollectError::CollectError("could not get tx receipt".to_string()))?
+                .inner
+                .logs()
+                .to_vec())
+        })
+        .await?;
 
         // traces
-        let traces = source.trace_transaction(request.ethers_transaction_hash()?).await?;
+        let traces = fetch_source(&query, "traces", source.trace_transaction(tx_hash)).await?;
 
-        Ok((block, logs, traces))
+        Ok((block, vec![tx_hash], logs, traces))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::AddressAppearances)?;
-        process_appearances(response, columns, schema)
+        process_appearances(response, columns, schema, query)
     }
 }
 
 fn name(log: &Log) -> Option<&'static str> {
     let event = log.topic0().unwrap();
     if event == *ERC20::Transfer::SIGNATURE_HASH {
-        if log.data().data.len() > 0 {
+        if !log.data().data.is_empty() {
             Some("erc20_transfer")
         } else if log.topics().len() == 4 {
             Some("erc721_transfer")
@@ -127,51 +162,51 @@ fn name(log: &Log) -> Option<&'static str> {
 }
 
 impl AddressAppearances {
-    fn process_first_transaction(
+    fn process_logs(
         &mut self,
-        block_author: Address,
-        trace: &LocalizedTransactionTrace,
-        schema: &Table,
+        logs: &[Log],
+        block_number: u32,
+        block_hash: &[u8],
         tx_hash: TxHash,
-        logs_by_tx: &HashMap<TxHash, Vec<Log>>,
+        schema: &Table,
     ) {
-        let block_number = trace.block_number.unwrap() as u32;
-        let block_hash = trace.block_hash.unwrap().to_vec();
-        self.process_address(block_author, "miner_fee", block_number, &block_hash, tx_hash, schema);
+        for log in logs.iter() {
+            if log.topics().len() >= 3 {
+                if let Some(name) = name(log) {
+                    let from = Address::from_word(log.topics()[1]);
+                    let relationship = name.to_string() + "_from";
+                    self.process_address(
+                        from,
+                        &relationship,
+                        block_number,
+                        block_hash,
+                        tx_hash,
+                        schema,
+                    );
 
-        if let Some(logs) = logs_by_tx.get(&tx_hash) {
-            for log in logs.iter() {
-                if log.topics().len() >= 3 {
-                    if let Some(name) = name(log) {
-                        let mut from: [u8; 20] = [0; 20];
-                        from.copy_from_slice(&log.topics()[1][12..32]);
-
-                        let name = &(name.to_string() + "_from");
-                        self.process_address(
-                            Address::from_slice(&from),
-                            name,
-                            block_number,
-                            &block_hash,
-                            tx_hash,
-                            schema,
-                        );
-
-                        let mut to: [u8; 20] = [0; 20];
-                        to.copy_from_slice(&log.topics()[1][12..32]);
-                        let name = &(name.to_string() + "_to");
-                        self.process_address(
-                            Address::from_slice(&to),
-                            name,
-                            block_number,
-                            &block_hash,
-                            tx_hash,
-                            schema,
-                        );
-                    }
+                    let to = Address::from_word(log.topics()[2]);
+                    let relationship = name.to_string() + "_to";
+                    self.process_address(
+                        to,
+                        &relationship,
+                        block_number,
+                        block_hash,
+                        tx_hash,
+                        schema,
+                    );
                 }
             }
         }
+    }
 
+    fn process_first_trace(
+        &mut self,
+        trace: &LocalizedTransactionTrace,
+        schema: &Table,
+        tx_hash: TxHash,
+    ) {
+        let block_number = trace.block_number.unwrap() as u32;
+        let block_hash = trace.block_hash.unwrap().to_vec();
         match &trace.trace.action {
             Action::Call(action) => {
                 self.process_address(
@@ -313,32 +348,124 @@ impl AddressAppearances {
     }
 }
 
+/// keep the first appearance of each address, the first row of its lowest block
+///
+/// appearances reduced earlier can be stacked with new appearances and reduced again, so the
+/// first appearances of a range can be found chunk by chunk
+pub(crate) fn first_seen(df: DataFrame, schema: &Table) -> R<DataFrame> {
+    let block_column = schema.block_number_column.as_deref().unwrap_or("block_number");
+    let options = SortOptions { maintain_order: true, ..Default::default() };
+    df.lazy()
+        .sort(block_column, options)
+        .unique_stable(Some(vec!["address".to_string()]), UniqueKeepStrategy::First)
+        .collect()
+        .map_err(CollectError::PolarsError)
+}
+
+/// process the appearances of each transaction once, from whichever sources are used
 fn process_appearances(
-    traces: BlockLogsTraces,
+    response: BlockLogsTraces,
     columns: &mut AddressAppearances,
     schema: &Table,
+    query: &Query,
 ) -> R<()> {
-    let (block, logs, traces) = traces;
+    let (block, tx_hashes, logs, traces) = response;
     let mut logs_by_tx: HashMap<TxHash, Vec<Log>> = HashMap::new();
-    for log in logs.into_iter() {
+    for log in logs.into_iter().flatten() {
         if let Some(tx_hash) = log.transaction_hash {
             logs_by_tx.entry(tx_hash).or_default().push(log);
         }
     }
-
-    let (_block_number, block_author) = (block.header.number, block.header.beneficiary);
-
-    let mut current_tx_hash = TxHash::ZERO;
-    for trace in traces.iter() {
+    let mut traces_by_tx: HashMap<TxHash, Vec<LocalizedTransactionTrace>> = HashMap::new();
+    for trace in traces.into_iter().flatten() {
         if let (Some(tx_hash), Some(_tx_pos)) = (trace.transaction_hash, trace.transaction_position)
         {
-            if tx_hash != current_tx_hash {
-                columns.process_first_transaction(block_author, trace, schema, tx_hash, &logs_by_tx)
+            traces_by_tx.entry(tx_hash).or_default().push(trace);
+        }
+    }
+
+    let block_number = block.header.number as u32;
+    let block_hash = block.header.hash.to_vec();
+    let block_author = block.header.beneficiary;
+    let use_miner = uses_appearance_source(query, "miner");
+    for tx_hash in tx_hashes.into_iter() {
+        if use_miner {
+            columns.process_address(
+                block_author,
+                "miner_fee",
+                block_number,
+                &block_hash,
+                tx_hash,
+                schema,
+            );
+        }
+        if let Some(logs) = logs_by_tx.get(&tx_hash) {
+            columns.process_logs(logs, block_number, &block_hash, tx_hash, schema);
+        }
+        if let Some(traces) = traces_by_tx.get(&tx_hash) {
+            if let Some(first) = traces.first() {
+                columns.process_first_trace(first, schema, tx_hash);
+            }
+            for trace in traces.iter() {
+                columns.process_trace(trace, schema, tx_hash);
             }
-            columns.process_trace(trace, schema, tx_hash);
-            current_tx_hash = tx_hash;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, LogData, B256};
+
+    #[test]
+    fn test_process_logs() {
+        let columns = Some(vec!["address".to_string(), "relationship".to_string()]);
+        let schema = Datatype::AddressAppearances
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &columns, None, None)
+            .unwrap();
+        let (from, to) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let topics = vec![ERC20::Transfer::SIGNATURE_HASH, from.into_word(), to.into_word()];
+        let data = LogData::new_unchecked(topics, Bytes::from(B256::with_last_byte(5).to_vec()));
+        let inner = alloy::primitives::Log { address: Address::ZERO, data };
+        let log = Log { inner, ..Default::default() };
+
+        let mut appearances = AddressAppearances::default();
+        appearances.process_logs(&[log], 1, &[0; 32], TxHash::ZERO, &schema);
+        assert_eq!(appearances.address, vec![from.to_vec(), to.to_vec()]);
+        assert_eq!(appearances.relationship, vec!["erc20_transfer_from", "erc20_transfer_to"]);
+    }
+
+    #[test]
+    fn test_first_seen() {
+        let columns = Some(vec!["block_number".to_string(), "address".to_string()]);
+        let schema = Datatype::AddressAppearances
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &columns, None, None)
+            .unwrap();
+        let (a, b, c) = (vec![1u8; 20], vec![2u8; 20], vec![3u8; 20]);
+        let chunk = |block_numbers: &[u32], addresses: &[&Vec<u8>], relationships: &[&str]| {
+            df!(
+                "block_number" => block_numbers,
+                "address" => addresses.iter().map(|address| address.to_vec()).collect::<Vec<_>>(),
+                "relationship" => relationships,
+            )
+            .unwrap()
+        };
+
+        // the first row of the lowest block of each address is kept
+        let mut df = chunk(&[5, 5, 6, 6], &[&a, &b, &b, &a], &["tx_from", "tx_to", "tx_from", "x"]);
+        df = first_seen(df, &schema).unwrap();
+        assert_eq!(df.height(), 2);
+
+        // reductions of later chunks stack with earlier ones, whatever order chunks finish in
+        let earlier = chunk(&[2, 3], &[&b, &c], &["miner_fee", "call_to"]);
+        df.vstack_mut(&earlier).unwrap();
+        df = first_seen(df, &schema).unwrap();
+        let block_numbers: Vec<_> = df.column("block_number").unwrap().u32().unwrap().into();
+        assert_eq!(block_numbers, vec![Some(2), Some(3), Some(5)]);
+        let relationships: Vec<_> = df.column("relationship").unwrap().str().unwrap().into();
+        assert_eq!(relationships, vec![Some("miner_fee"), Some("call_to"), Some("tx_from")]);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::*;
+use alloy::{
+    consensus::Transaction as ConsensusTransaction,
+    primitives::U256,
+    rpc::types::{BlockTransactionsKind, Transaction},
+};
+use polars::prelude::*;
+
+/// columns for blobs, one row per blob of each blob-carrying transaction
+///
+/// only the versioned hashes of blobs are on the execution layer, blob contents, commitments, and
+/// proofs are only served by the blob sidecars endpoint of a beacon node
+#[cryo_to_df::to_df(Datatype::Blobs)]
+#[derive(Default)]
+pub struct Blobs {
+    n_rows: u64,
+    block_number: Vec<Option<u32>>,
+    transaction_index: Vec<Option<u64>>,
+    transaction_hash: Vec<Vec<u8>>,
+    blob_index: Vec<u32>,
+    versioned_hash: Vec<Vec<u8>>,
+    max_fee_per_blob_gas: Vec<Option<U256>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Blobs {
+    fn aliases() -> Vec<&'static str> {
+        vec!["blob_sidecars"]
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index", "blob_index"])
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for Blobs {
+    type Response = Vec<Transaction>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block = source
+            .get_block(request.block_number()?, BlockTransactionsKind::Full)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        let transactions = block
+            .transactions
+            .as_transactions()
+            .ok_or(CollectError::CollectError("block transactions not full".to_string()))?;
+        Ok(transactions.to_vec())
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Blobs)?;
+        for transaction in response.into_iter() {
+            process_blobs(transaction, columns, schema)
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for Blobs {
+    type Response = Transaction;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        source
+            .get_transaction_by_hash(request.ethers_transaction_hash()?)
+            .await?
+            .ok_or(CollectError::CollectError("transaction not found".to_string()))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Blobs)?;
+        process_blobs(response, columns, schema);
+        Ok(())
+    }
+}
+
+/// explode versioned hashes of transaction into rows, transactions without blobs produce no rows
+fn process_blobs(tx: Transaction, columns: &mut Blobs, schema: &Table) {
+    let versioned_hashes = match tx.inner.blob_versioned_hashes() {
+        Some(versioned_hashes) => versioned_hashes.to_vec(),
+        None => return,
+    };
+    let max_fee_per_blob_gas = tx.inner.max_fee_per_blob_gas().map(U256::from);
+    for (blob_index, versioned_hash) in versioned_hashes.into_iter().enumerate() {
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, tx.block_number.map(|x| x as u32));
+        store!(schema, columns, transaction_index, tx.transaction_index);
+        store!(schema, columns, transaction_hash, tx.inner.tx_hash().to_vec());
+        store!(schema, columns, blob_index, blob_index as u32);
+        store!(schema, columns, versioned_hash, versioned_hash.to_vec());
+        store!(schema, columns, max_fee_per_blob_gas, max_fee_per_blob_gas);
+    }
+}
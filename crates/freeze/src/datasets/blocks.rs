@@ -30,6 +30,8 @@ pub struct Blocks {
     nonce: Vec<Option<Vec<u8>>>,
     base_fee_per_gas: Vec<Option<u64>>,
     withdrawals_root: Vec<Option<Vec<u8>>>,
+    blob_gas_used: Vec<Option<u64>>,
+    excess_blob_gas: Vec<Option<u64>>,
     chain_id: Vec<u64>,
 }
 
@@ -121,5 +123,47 @@ pub(crate) fn process_block<TX>(block: Block<TX>, columns: &mut Blocks, schema:
     store!(schema, columns, mix_hash, Some(block.header.mix_hash.to_vec()));
     store!(schema, columns, nonce, Some(block.header.nonce.0.to_vec()));
     store!(schema, columns, withdrawals_root, block.header.withdrawals_root.map(|x| x.0.to_vec()));
+    store!(schema, columns, blob_gas_used, block.header.blob_gas_used);
+    store!(schema, columns, excess_blob_gas, block.header.excess_blob_gas);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bloom, B256};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_header_roots() {
+        let columns = ["state_root", "receipts_root", "transactions_root", "logs_bloom"];
+        let columns = Some(columns.iter().map(|column| column.to_string()).collect());
+        let mut block: Block = Block::default();
+        block.header.inner.state_root = B256::repeat_byte(1);
+        block.header.inner.receipts_root = B256::repeat_byte(2);
+        block.header.inner.transactions_root = B256::repeat_byte(3);
+        block.header.inner.logs_bloom = Bloom::repeat_byte(0xab);
+
+        // the 256 byte logs bloom is kept whole under both binary and hex encodings
+        for encoding in [ColumnEncoding::Binary, ColumnEncoding::Hex] {
+            let schema = Datatype::Blocks
+                .table_schema(&[], &encoding, &None, &None, &columns, None, None)
+                .unwrap();
+            let mut blocks = Blocks::default();
+            process_block(block.clone(), &mut blocks, &schema).unwrap();
+            let schemas = HashMap::from([(Datatype::Blocks, schema)]);
+            let df = blocks.create_dfs(&schemas, 1).unwrap().remove(&Datatype::Blocks).unwrap();
+            let (bloom, state_root) =
+                (df.column("logs_bloom").unwrap(), df.column("state_root").unwrap());
+            if encoding == ColumnEncoding::Binary {
+                assert_eq!(bloom.binary().unwrap().get(0).unwrap(), [0xab; 256]);
+                assert_eq!(state_root.binary().unwrap().get(0).unwrap(), [1; 32]);
+            } else {
+                let expected = format!("0x{}", "ab".repeat(256));
+                assert_eq!(bloom.str().unwrap().get(0).unwrap(), expected);
+                let expected = format!("0x{}", "01".repeat(32));
+                assert_eq!(state_root.str().unwrap().get(0).unwrap(), expected);
+            }
+        }
+    }
+}
@@ -31,10 +31,8 @@ impl CollectByBlock for CodeDiffs {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get(&Datatype::CodeDiffs).ok_or(err("schema not provided"))?;
         let include_txs = schema.has_column("transaction_hash");
-        let (bn, txs, traces) =
-            source.trace_block_state_diffs(request.block_number()? as u32, include_txs).await?;
-        let trace_results = traces.into_iter().map(|t| t.full_trace).collect();
-        Ok((bn, txs, trace_results))
+        let block_number = request.block_number()? as u32;
+        state_diffs::extract_block_state_diffs(block_number, include_txs, &source, &query).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -46,8 +44,9 @@ impl CollectByBlock for CodeDiffs {
 impl CollectByTransaction for CodeDiffs {
     type Response = BlockTxTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.trace_transaction_state_diffs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let transaction_hash = request.transaction_hash()?;
+        state_diffs::extract_transaction_state_diffs(transaction_hash, &source, &query).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -47,6 +47,10 @@ impl CollectByBlock for Codes {
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Codes)?;
+        // code is fetched at the requested block, so emptiness is specific to that block
+        if query.contracts_only && response.3.is_empty() {
+            return Ok(())
+        }
         process_code(columns, response, schema)
     }
 }
@@ -5,6 +5,7 @@ use alloy::{
     rpc::types::trace::parity::{Action, LocalizedTransactionTrace, TraceOutput},
 };
 use polars::prelude::*;
+use std::collections::HashSet;
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Contracts)]
@@ -23,7 +24,7 @@ pub struct Contracts {
     init_code_hash: Vec<Vec<u8>>,
     n_init_code_bytes: Vec<u32>,
     n_code_bytes: Vec<u32>,
-    code_hash: Vec<Vec<u8>>,
+    code_hash: Vec<Option<Vec<u8>>>,
     chain_id: Vec<u64>,
 }
 
@@ -73,6 +74,16 @@ pub(crate) fn process_contracts(
     let schema = schemas.get(&Datatype::Contracts).ok_or(err("schema not provided"))?;
     let mut deployer = Address::ZERO;
     let mut create_index = 0;
+
+    // contracts destroyed in their creation transaction are left without runtime code
+    let destroyed: HashSet<_> = traces
+        .iter()
+        .filter_map(|trace| match &trace.trace.action {
+            Action::Selfdestruct(action) => Some((trace.transaction_hash, action.address)),
+            _ => None,
+        })
+        .collect();
+
     for trace in traces.iter() {
         if trace.trace.trace_address.is_empty() {
             deployer = match &trace.trace.action {
@@ -98,11 +109,91 @@ pub(crate) fn process_contracts(
             store!(schema, columns, factory, create.from.to_vec());
             store!(schema, columns, init_code, create.init.to_vec());
             store!(schema, columns, code, result.code.to_vec());
-            store!(schema, columns, init_code_hash, keccak256(result.code.clone()).to_vec());
-            store!(schema, columns, code_hash, keccak256(create.init.clone()).to_vec());
+            store!(schema, columns, init_code_hash, keccak256(&create.init).to_vec());
+            let has_code = !result.code.is_empty() &&
+                !destroyed.contains(&(trace.transaction_hash, result.address));
+            let code_hash = if has_code { Some(keccak256(&result.code).to_vec()) } else { None };
+            store!(schema, columns, code_hash, code_hash);
             store!(schema, columns, n_init_code_bytes, create.init.len() as u32);
             store!(schema, columns, n_code_bytes, result.code.len() as u32);
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        primitives::{Bytes, B256, U256},
+        rpc::types::trace::parity::{
+            CreateAction, CreateOutput, CreationMethod, SelfdestructAction, TransactionTrace,
+        },
+    };
+
+    fn trace(
+        action: Action,
+        result: Option<TraceOutput>,
+        trace_address: Vec<usize>,
+        tx: u8,
+    ) -> LocalizedTransactionTrace {
+        LocalizedTransactionTrace {
+            trace: TransactionTrace { action, error: None, result, subtraces: 0, trace_address },
+            block_hash: Some(B256::ZERO),
+            block_number: Some(1),
+            transaction_hash: Some(B256::repeat_byte(tx)),
+            transaction_position: Some(tx as u64),
+        }
+    }
+
+    fn create(
+        method: CreationMethod,
+        address: Address,
+        init: Vec<u8>,
+        code: Vec<u8>,
+        tx: u8,
+    ) -> LocalizedTransactionTrace {
+        let action = Action::Create(CreateAction {
+            creation_method: method,
+            init: Bytes::from(init),
+            ..Default::default()
+        });
+        let output = CreateOutput { address, code: Bytes::from(code), gas_used: 0 };
+        trace(action, Some(TraceOutput::Create(output)), vec![], tx)
+    }
+
+    #[test]
+    fn test_process_contracts_code_hashes() {
+        let cols = Some(vec!["all".to_string()]);
+        let u256_types = [U256Type::Binary];
+        let schema = Datatype::Contracts
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &cols, None, None)
+            .unwrap();
+        let schemas: Schemas = [(Datatype::Contracts, schema)].into_iter().collect();
+
+        let a = Address::repeat_byte(1);
+        let b = Address::repeat_byte(2);
+        let c = Address::repeat_byte(3);
+        let selfdestruct = Action::Selfdestruct(SelfdestructAction {
+            address: c,
+            balance: U256::ZERO,
+            refund_address: a,
+        });
+        let traces = vec![
+            create(CreationMethod::Create, a, vec![1], vec![0xaa], 1),
+            create(CreationMethod::Create2, b, vec![2], vec![0xbb], 2),
+            create(CreationMethod::Create, c, vec![3], vec![0xcc], 3),
+            trace(selfdestruct, None, vec![0], 3),
+        ];
+        let mut columns = Contracts::default();
+        process_contracts(&traces, &mut columns, &schemas).unwrap();
+
+        assert_eq!(columns.n_rows, 3);
+        let init_hashes: Vec<_> = [1, 2, 3].iter().map(|x| keccak256([*x]).to_vec()).collect();
+        assert_eq!(columns.init_code_hash, init_hashes);
+        assert_eq!(
+            columns.code_hash,
+            vec![Some(keccak256([0xaa]).to_vec()), Some(keccak256([0xbb]).to_vec()), None]
+        );
+    }
+}
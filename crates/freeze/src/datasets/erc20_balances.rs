@@ -29,14 +29,15 @@ impl Dataset for Erc20Balances {
 impl CollectByBlock for Erc20Balances {
     type Response = (u32, Vec<u8>, Vec<u8>, Option<U256>);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let signature = ERC20::balanceOfCall::SELECTOR;
         let mut call_data = signature.clone().to_vec();
         call_data.extend(vec![0; 12]);
         call_data.extend(request.address()?);
         let block_number = request.ethers_block_number()?;
         let contract = request.ethers_contract()?;
-        let balance = source.call2(contract, call_data, block_number).await.ok();
+        let balance = source.call2(contract, call_data, block_number).await;
+        let balance = query.column_result(&["balance"], balance)?;
         let balance = balance.map(|x| U256::from_be_slice(x.as_ref()));
         Ok((request.block_number()? as u32, request.contract()?, request.address()?, balance))
     }
@@ -1,6 +1,10 @@
 use crate::*;
 use alloy::sol_types::SolCall;
 use polars::prelude::*;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Erc20Metadata)]
@@ -34,6 +38,46 @@ impl Dataset for Erc20Metadata {
     }
 }
 
+type TokenMetadata = (Option<String>, Option<String>, Option<u32>);
+
+/// name, symbol, and decimals of each erc20, shared across the chunks of a run
+///
+/// only complete metadata is cached, so failed or missing values are retried at later blocks
+#[derive(Clone, Default)]
+pub struct Erc20MetadataCache {
+    metadata: Arc<Mutex<HashMap<Vec<u8>, TokenMetadata>>>,
+    decimals: Arc<Mutex<HashMap<Vec<u8>, Option<u32>>>>,
+}
+
+impl Erc20MetadataCache {
+    fn get(&self, address: &[u8]) -> Option<TokenMetadata> {
+        self.metadata.lock().ok().and_then(|cache| cache.get(address).cloned())
+    }
+
+    fn insert(&self, address: Vec<u8>, metadata: TokenMetadata) {
+        if let (Some(_), Some(_), Some(_)) = &metadata {
+            if let Ok(mut cache) = self.metadata.lock() {
+                cache.insert(address, metadata);
+            }
+        }
+    }
+
+    /// decimals of erc20, None if not cached, Some(None) if the token has no decimals
+    pub(crate) fn get_decimals(&self, address: &[u8]) -> Option<Option<u32>> {
+        if let Some(decimals) = self.decimals.lock().ok()?.get(address) {
+            return Some(*decimals)
+        }
+        self.get(address).map(|(_, _, decimals)| decimals)
+    }
+
+    /// cache the result of a decimals() call, which is None if it reverted or was not decodable
+    pub(crate) fn insert_decimals(&self, address: Vec<u8>, decimals: Option<u32>) {
+        if let Ok(mut cache) = self.decimals.lock() {
+            cache.insert(address, decimals);
+        }
+    }
+}
+
 pub(crate) fn remove_control_characters(s: &str) -> String {
     let re = regex::Regex::new(r"[ \x00-\x1F\x7F]").unwrap();
     re.replace_all(s, "").to_string()
@@ -43,35 +87,37 @@ pub(crate) fn remove_control_characters(s: &str) -> String {
 impl CollectByBlock for Erc20Metadata {
     type Response = (u32, Vec<u8>, Option<String>, Option<String>, Option<u32>);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let block_number = request.ethers_block_number()?;
         let address = request.ethers_address()?;
+        let cache = query.erc20_metadata_cache.as_ref();
+        if let Some((name, symbol, decimals)) = cache.and_then(|c| c.get(address.as_slice())) {
+            return Ok((request.block_number()? as u32, request.address()?, name, symbol, decimals))
+        }
 
         // name
         let call_data = ERC20::nameCall::SELECTOR.to_vec();
-        let name = match source.call2(address, call_data, block_number).await {
-            Ok(output) => {
-                String::from_utf8(output.to_vec()).ok().map(|s| remove_control_characters(&s))
-            }
-            Err(_) => None,
-        };
+        let name = query
+            .column_result(&["name"], source.call2(address, call_data, block_number).await)?
+            .and_then(|output| String::from_utf8(output.to_vec()).ok())
+            .map(|s| remove_control_characters(&s));
 
         // symbol
         let call_data = ERC20::symbolCall::SELECTOR.to_vec();
-        let symbol = match source.call2(address, call_data, block_number).await {
-            Ok(output) => {
-                String::from_utf8(output.to_vec()).ok().map(|s| remove_control_characters(&s))
-            }
-            Err(_) => None,
-        };
+        let symbol = query
+            .column_result(&["symbol"], source.call2(address, call_data, block_number).await)?
+            .and_then(|output| String::from_utf8(output.to_vec()).ok())
+            .map(|s| remove_control_characters(&s));
 
         // decimals
         let call_data = ERC20::decimalsCall::SELECTOR.to_vec();
-        let decimals = match source.call2(address, call_data, block_number).await {
-            Ok(output) => bytes_to_u32(output).ok(),
-            Err(_) => None,
-        };
+        let decimals = query
+            .column_result(&["decimals"], source.call2(address, call_data, block_number).await)?
+            .and_then(|output| bytes_to_u32(output).ok());
 
+        if let Some(cache) = cache {
+            cache.insert(request.address()?, (name.clone(), symbol.clone(), decimals));
+        }
         Ok((request.block_number()? as u32, request.address()?, name, symbol, decimals))
     }
 
@@ -91,3 +137,31 @@ impl CollectByBlock for Erc20Metadata {
 impl CollectByTransaction for Erc20Metadata {
     type Response = ();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_erc20_metadata_cache() {
+        let cache = Erc20MetadataCache::default();
+        let (token, other) = (vec![1u8; 20], vec![2u8; 20]);
+        assert_eq!(cache.get(&token), None);
+
+        // incomplete metadata is retried at later blocks
+        cache.insert(token.clone(), (Some("Token".to_string()), None, Some(18)));
+        assert_eq!(cache.get(&token), None);
+        assert_eq!(cache.get_decimals(&token), None);
+
+        let metadata = (Some("Token".to_string()), Some("TKN".to_string()), Some(18));
+        cache.insert(token.clone(), metadata.clone());
+        assert_eq!(cache.get(&token), Some(metadata));
+        assert_eq!(cache.get_decimals(&token), Some(Some(18)));
+
+        // clones share the cache across chunks
+        let shared = cache.clone();
+        shared.insert_decimals(other.clone(), None);
+        assert_eq!(cache.get_decimals(&other), Some(None));
+        assert_eq!(cache.get(&other), None);
+    }
+}
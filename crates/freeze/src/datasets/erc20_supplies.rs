@@ -1,6 +1,11 @@
-use crate::*;
-use alloy::{primitives::U256, sol_types::SolCall};
+use crate::{collection::collect_generic::fetch_partition_responses, *};
+use alloy::{
+    primitives::{Address, BlockNumber, U256},
+    rpc::types::{Filter, Log},
+    sol_types::{SolCall, SolEvent},
+};
 use polars::prelude::*;
+use std::collections::HashMap;
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Erc20Supplies)]
@@ -10,11 +15,16 @@ pub struct Erc20Supplies {
     block_number: Vec<u32>,
     erc20: Vec<Vec<u8>>,
     total_supply: Vec<Option<U256>>,
+    holder_count: Vec<Option<u64>>,
     chain_id: Vec<u64>,
 }
 
 #[async_trait::async_trait]
 impl Dataset for Erc20Supplies {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "erc20", "total_supply", "chain_id"])
+    }
+
     fn default_sort() -> Option<Vec<&'static str>> {
         Some(vec!["erc20", "block_number"])
     }
@@ -32,32 +42,336 @@ impl Dataset for Erc20Supplies {
     }
 }
 
+/// block number, erc20, total supply, and transfer logs of block if holder counts are collected
+type SupplyResponse = (u32, Vec<u8>, Option<U256>, Option<Vec<Log>>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for Erc20Supplies {
-    type Response = (u32, Vec<u8>, Option<U256>);
+    type Response = SupplyResponse;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let signature: Vec<u8> = ERC20::totalSupplyCall::SELECTOR.to_vec();
-        let mut call_data = signature.clone();
-        call_data.extend(request.address()?);
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let block_number = request.ethers_block_number()?;
         let contract = request.ethers_address()?;
-        let output = source.call2(contract, call_data, block_number).await.ok();
-        let output = output.map(|x| U256::from_be_slice(x.as_ref()));
-        Ok((request.block_number()? as u32, request.address()?, output))
+        let output = fetch_total_supply(contract, block_number, &source, &query).await?;
+
+        let schema = query.schemas.get_schema(&Datatype::Erc20Supplies)?;
+        let transfers = if schema.has_column("holder_count") {
+            let filter = Filter::new()
+                .address(contract)
+                .event_signature(ERC20::Transfer::SIGNATURE_HASH)
+                .from_block(block_number)
+                .to_block(block_number);
+            let logs = source.get_logs(&filter).await;
+            query.column_result(&["holder_count"], logs)?.map(|logs| {
+                logs.into_iter()
+                    .filter(|log| log.topics().len() == 3 && log.data().data.len() == 32)
+                    .collect()
+            })
+        } else {
+            None
+        };
+        Ok((request.block_number()? as u32, request.address()?, output, transfers))
     }
 
-    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+    /// holder counts and supply changes depend on previous blocks of each token, so responses
+    /// are buffered and processed in block order
+    ///
+    /// with changes_only, chunks after the first compare against the supplies of the block
+    /// preceding the chunk, whereas holder counts cannot continue across chunks
+    async fn collect_by_block(
+        partition: Partition,
+        source: Arc<Source>,
+        query: Arc<Query>,
+        inner_request_size: Option<u64>,
+    ) -> R<HashMap<Datatype, DataFrame>> {
         let schema = query.schemas.get_schema(&Datatype::Erc20Supplies)?;
-        let (block, erc20, total_supply) = response;
-        columns.n_rows += 1;
-        store!(schema, columns, block_number, block);
-        store!(schema, columns, erc20, erc20);
-        store!(schema, columns, total_supply, total_supply);
-        Ok(())
+        let previous_block = query.previous_chunk_block(&partition);
+        if previous_block.is_some() && schema.has_column("holder_count") {
+            return Err(CollectError::CollectError(
+                "holder_count must be collected in a single chunk of blocks, use a larger \
+                 --chunk-size"
+                    .to_string(),
+            ))
+        }
+        let previous_supplies = match previous_block {
+            Some(block) if query.changes_only => {
+                let tokens = partition.addresses.iter().flatten().flat_map(|chunk| chunk.values());
+                let supplies = tokens.map(|token| {
+                    let (source, query) = (source.clone(), query.clone());
+                    async move {
+                        let contract = Address::from_slice(&token);
+                        let supply =
+                            fetch_total_supply(contract, block, &source, &query).await?;
+                        Ok::<_, CollectError>((token, supply))
+                    }
+                });
+                futures::future::try_join_all(supplies).await?.into_iter().collect()
+            }
+            _ => HashMap::new(),
+        };
+
+        let chain_id = source.chain_id;
+        let responses = fetch_partition_responses(
+            <Self as CollectByBlock>::extract,
+            partition,
+            source,
+            inner_request_size,
+            query.clone(),
+        )
+        .await?;
+        let columns = process_erc20_supplies(responses, &previous_supplies, &query)?;
+        columns.create_dfs(&query.schemas, chain_id)
+    }
+}
+
+/// total supply of token at block, empty outputs of tokens without totalSupply() become null
+async fn fetch_total_supply(
+    contract: Address,
+    block_number: BlockNumber,
+    source: &Source,
+    query: &Query,
+) -> R<Option<U256>> {
+    let call_data = ERC20::totalSupplyCall::SELECTOR.to_vec();
+    let output = source.call2(contract, call_data, block_number).await;
+    let output = query.column_result(&["total_supply"], output)?;
+    Ok(output.filter(|x| x.len() == 32).map(|x| U256::from_be_slice(x.as_ref())))
+}
+
+/// process responses of each token in block order
+///
+/// with changes_only, the first block of a token is compared to its previous supply if known,
+/// and is otherwise kept as a baseline if changes_baseline is set
+fn process_erc20_supplies(
+    mut responses: Vec<SupplyResponse>,
+    previous_supplies: &HashMap<Vec<u8>, Option<U256>>,
+    query: &Arc<Query>,
+) -> R<Erc20Supplies> {
+    responses.sort_by(|a, b| (&a.1, a.0).cmp(&(&b.1, b.0)));
+    let mut columns = Erc20Supplies::default();
+    for token_responses in responses.chunk_by(|a, b| a.1 == b.1) {
+        let mut balances = Some(HashMap::new());
+        let mut previous_block: Option<u32> = None;
+        let mut previous_supply = previous_supplies.get(&token_responses[0].1).copied();
+        for (block, erc20, total_supply, transfers) in token_responses.iter() {
+            // transfers of skipped blocks are not observed
+            if previous_block.is_some_and(|previous_block| previous_block + 1 != *block) {
+                balances = None;
+            }
+            previous_block = Some(*block);
+            let holder_count = update_holders(&mut balances, transfers.as_deref(), *total_supply);
+            let unchanged = match previous_supply.replace(*total_supply) {
+                _ if !query.changes_only => false,
+                Some(previous_supply) => previous_supply == *total_supply,
+                None => !query.changes_baseline,
+            };
+            if unchanged {
+                continue
+            }
+            let erc20 = erc20.clone();
+            let supply = *total_supply;
+            process_erc20_supply(*block, erc20, supply, holder_count, &mut columns, query)?;
+        }
+    }
+    Ok(columns)
+}
+
+fn process_erc20_supply(
+    block: u32,
+    erc20: Vec<u8>,
+    total_supply: Option<U256>,
+    holder_count: Option<u64>,
+    columns: &mut Erc20Supplies,
+    query: &Arc<Query>,
+) -> R<()> {
+    let schema = query.schemas.get_schema(&Datatype::Erc20Supplies)?;
+    columns.n_rows += 1;
+    store!(schema, columns, block_number, block);
+    store!(schema, columns, erc20, erc20);
+    store!(schema, columns, total_supply, total_supply);
+    store!(schema, columns, holder_count, holder_count);
+    Ok(())
+}
+
+/// apply transfers of the next block to balances, returning the number of holders if derivable
+///
+/// holders are derivable when the transfers of every block are replayed from a block where the
+/// total supply was zero, and balances keep adding up to the total supply, which is not the case
+/// for rebasing tokens
+fn update_holders(
+    balances: &mut Option<HashMap<Vec<u8>, U256>>,
+    transfers: Option<&[Log]>,
+    total_supply: Option<U256>,
+) -> Option<u64> {
+    let (Some(current), Some(transfers), Some(total_supply)) =
+        (balances.as_mut(), transfers, total_supply)
+    else {
+        *balances = None;
+        return None
+    };
+    for log in transfers.iter() {
+        let from = log.topics()[1][12..].to_vec();
+        let to = log.topics()[2][12..].to_vec();
+        let value = U256::from_be_slice(&log.data().data);
+        if !apply_transfer(current, from, to, value) {
+            *balances = None;
+            return None
+        }
+    }
+    let sum = current.values().try_fold(U256::ZERO, |sum, balance| sum.checked_add(*balance));
+    if sum != Some(total_supply) {
+        *balances = None;
+        return None
+    }
+    Some(current.len() as u64)
+}
+
+/// move value between balances, returning false if the sender balance would be negative
+fn apply_transfer(
+    balances: &mut HashMap<Vec<u8>, U256>,
+    from: Vec<u8>,
+    to: Vec<u8>,
+    value: U256,
+) -> bool {
+    let zero_address = vec![0u8; 20];
+    if from != zero_address {
+        let balance = balances.get(&from).copied().unwrap_or_default();
+        match balance.checked_sub(value) {
+            Some(remaining) if remaining.is_zero() => {
+                balances.remove(&from);
+            }
+            Some(remaining) => {
+                balances.insert(from, remaining);
+            }
+            None => return false,
+        }
     }
+    if to != zero_address && !value.is_zero() {
+        *balances.entry(to).or_default() += value;
+    }
+    true
 }
 
 impl CollectByTransaction for Erc20Supplies {
     type Response = ();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, Bytes, LogData, B256};
+
+    fn transfer(from: u8, to: u8, value: u64) -> Log {
+        let topics = vec![
+            ERC20::Transfer::SIGNATURE_HASH,
+            Address::repeat_byte(from).into_word(),
+            Address::repeat_byte(to).into_word(),
+        ];
+        let data = Bytes::from(B256::from(U256::from(value)).to_vec());
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(topics, data),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_update_holders() {
+        let mut balances = Some(HashMap::new());
+        let transfers = [transfer(0, 1, 100), transfer(1, 2, 40)];
+        let supply = Some(U256::from(100));
+        assert_eq!(update_holders(&mut balances, Some(&transfers), supply), Some(2));
+
+        // sending the full balance removes the holder
+        let transfers = [transfer(1, 2, 60)];
+        assert_eq!(update_holders(&mut balances, Some(&transfers), supply), Some(1));
+
+        // burns reduce the total supply
+        let transfers = [transfer(2, 0, 30)];
+        let supply = Some(U256::from(70));
+        assert_eq!(update_holders(&mut balances, Some(&transfers), supply), Some(1));
+
+        // supply changing without transfers, as for rebasing tokens, is not derivable
+        let supply = Some(U256::from(77));
+        assert_eq!(update_holders(&mut balances, Some(&[]), supply), None);
+        assert_eq!(update_holders(&mut balances, Some(&[]), supply), None);
+
+        // tokens with existing supply before the first block are not derivable
+        let mut balances = Some(HashMap::new());
+        let transfers = [transfer(3, 4, 10)];
+        assert_eq!(update_holders(&mut balances, Some(&transfers), Some(U256::from(500))), None);
+
+        // missing total supply or transfers are not derivable
+        let mut balances = Some(HashMap::new());
+        assert_eq!(update_holders(&mut balances, Some(&[]), None), None);
+        let mut balances = Some(HashMap::new());
+        assert_eq!(update_holders(&mut balances, None, Some(U256::ZERO)), None);
+    }
+
+    #[test]
+    fn test_process_erc20_supplies() {
+        let u256_types = [U256Type::Binary];
+        let schema = Datatype::Erc20Supplies
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &None, None, None)
+            .unwrap();
+        let mut query = Query::for_datatype(Datatype::Erc20Supplies, schema, vec![]);
+        query.changes_only = true;
+        let (a, b) = (vec![1u8; 20], vec![2u8; 20]);
+        let supply = |value: u64| Some(U256::from(value));
+        let responses = vec![
+            (11, a.clone(), supply(5), None),
+            (10, a.clone(), supply(5), None),
+            (12, a.clone(), supply(6), None),
+            (10, b.clone(), supply(7), None),
+            (11, b.clone(), supply(7), None),
+        ];
+        let blocks = |columns: Erc20Supplies| {
+            columns.block_number.into_iter().zip(columns.erc20).collect::<Vec<_>>()
+        };
+
+        // first blocks of tokens are baselines
+        let query = Arc::new(query);
+        let columns = process_erc20_supplies(responses.clone(), &HashMap::new(), &query).unwrap();
+        let expected = vec![(10, a.clone()), (12, a.clone()), (10, b.clone())];
+        assert_eq!(blocks(columns), expected);
+
+        // later chunks continue from the supplies preceding the chunk
+        let previous = [(a.clone(), supply(4)), (b.clone(), supply(7))].into_iter().collect();
+        let columns = process_erc20_supplies(responses, &previous, &query).unwrap();
+        assert_eq!(blocks(columns), vec![(10, a.clone()), (12, a)]);
+    }
+
+    #[test]
+    fn test_changes_only_stepped_blocks() {
+        let u256_types = [U256Type::Binary];
+        let schema = Datatype::Erc20Supplies
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &None, None, None)
+            .unwrap();
+        let a = vec![1u8; 20];
+        let partition = |blocks: Vec<u64>| Partition {
+            block_numbers: Some(vec![BlockChunk::Numbers(blocks)]),
+            addresses: Some(vec![AddressChunk::Values(vec![a.clone()])]),
+            ..Default::default()
+        };
+
+        // blocks 0:60:10 in chunks of 3 blocks
+        let partitions = vec![partition(vec![0, 10, 20]), partition(vec![30, 40, 50])];
+        let mut query = Query::for_datatype(Datatype::Erc20Supplies, schema, partitions.clone());
+        query.changes_only = true;
+        let query = Arc::new(query);
+
+        // the second chunk compares against the last sampled block, not the block before it
+        assert_eq!(query.previous_chunk_block(&partitions[1]), Some(20));
+        let supply = |value: u64| Some(U256::from(value));
+        let previous = [(a.clone(), supply(5))].into_iter().collect();
+        let responses = vec![
+            (30, a.clone(), supply(5), None),
+            (40, a.clone(), supply(5), None),
+            (50, a.clone(), supply(8), None),
+        ];
+        let columns = process_erc20_supplies(responses, &previous, &query).unwrap();
+        assert_eq!(columns.block_number, vec![50]);
+        assert_eq!(columns.total_supply, vec![supply(8)]);
+    }
+}
@@ -1,10 +1,12 @@
 use crate::*;
 use alloy::{
-    primitives::{B256, U256},
+    primitives::{utils::format_units, Address, B256, U256},
     rpc::types::{Filter, Log, Topic},
-    sol_types::SolEvent,
+    sol_types::{SolCall, SolEvent},
+    transports::RpcError,
 };
 use polars::prelude::*;
+use std::collections::HashMap;
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Erc20Transfers)]
@@ -20,6 +22,7 @@ pub struct Erc20Transfers {
     from_address: Vec<Vec<u8>>,
     to_address: Vec<Vec<u8>>,
     value: Vec<U256>,
+    value_decimal: Vec<Option<String>>,
     chain_id: Vec<u64>,
 }
 
@@ -53,11 +56,14 @@ impl Dataset for Erc20Transfers {
     }
 }
 
+/// transfer logs, and the decimals of their tokens if value_decimal is collected
+type TransferLogs = (Vec<Log>, HashMap<Address, Option<u32>>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for Erc20Transfers {
-    type Response = Vec<Log>;
+    type Response = TransferLogs;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let mut topics: [Topic; 4] = Default::default();
         topics[0] = ERC20::Transfer::SIGNATURE_HASH.into();
         if let Some(from_address) = &request.from_address {
@@ -69,12 +75,14 @@ impl CollectByBlock for Erc20Transfers {
             topics[2] = v.into();
         }
         let filter = Filter { topics, ..request.ethers_log_filter()? };
-        let logs = source.get_logs(&filter).await?;
-
-        Ok(logs
+        let logs: Vec<Log> = source
+            .get_logs(&filter)
+            .await?
             .into_iter()
             .filter(|x| x.topics().len() == 3 && x.data().data.len() == 32)
-            .collect())
+            .collect();
+        let decimals = get_token_decimals(&logs, &source, &query).await?;
+        Ok((logs, decimals))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -85,11 +93,13 @@ impl CollectByBlock for Erc20Transfers {
 
 #[async_trait::async_trait]
 impl CollectByTransaction for Erc20Transfers {
-    type Response = Vec<Log>;
+    type Response = TransferLogs;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let logs = source.get_transaction_logs(request.transaction_hash()?).await?;
-        Ok(logs.into_iter().filter(is_erc20_transfer).collect())
+        let logs: Vec<Log> = logs.into_iter().filter(is_erc20_transfer).collect();
+        let decimals = get_token_decimals(&logs, &source, &query).await?;
+        Ok((logs, decimals))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -104,8 +114,79 @@ fn is_erc20_transfer(log: &Log) -> bool {
         log.topics()[0] == ERC20::Approval::SIGNATURE_HASH
 }
 
+/// decimals of the token of each log, fetched once per token
+///
+/// decimals() calls that succeed or revert are cached across chunks, so tokens without decimals
+/// are not called again, while calls that fail for other reasons are retried by later chunks
+async fn get_token_decimals(
+    logs: &[Log],
+    source: &Source,
+    query: &Query,
+) -> R<HashMap<Address, Option<u32>>> {
+    let mut decimals = HashMap::new();
+    let schema = query.schemas.get_schema(&Datatype::Erc20Transfers)?;
+    if !schema.has_column("value_decimal") {
+        return Ok(decimals)
+    }
+    let cache = query.erc20_metadata_cache.as_ref();
+    let mut uncached = HashMap::new();
+    for log in logs.iter() {
+        let (address, Some(block_number)) = (log.address(), log.block_number) else { continue };
+        if decimals.contains_key(&address) || uncached.contains_key(&address) {
+            continue
+        }
+        match cache.and_then(|cache| cache.get_decimals(address.as_slice())) {
+            Some(cached) => {
+                decimals.insert(address, cached);
+            }
+            None => {
+                uncached.insert(address, block_number);
+            }
+        }
+    }
+
+    let calls = uncached.into_iter().map(|(address, block_number)| async move {
+        let call_data = ERC20::decimalsCall::SELECTOR.to_vec();
+        (address, source.call2(address, call_data, block_number).await)
+    });
+    for (address, output) in futures::future::join_all(calls).await {
+        let cacheable = output.as_ref().map_or_else(is_revert, |_| true);
+        let token_decimals = query
+            .column_result(&["value_decimal"], output)?
+            .and_then(|output| bytes_to_u32(output).ok());
+        if let (Some(cache), true) = (cache, cacheable) {
+            cache.insert_decimals(address.to_vec(), token_decimals);
+        }
+        decimals.insert(address, token_decimals);
+    }
+    Ok(decimals)
+}
+
+/// whether a call failed because the contract reverted, such as a token without decimals()
+fn is_revert(error: &CollectError) -> bool {
+    match error {
+        CollectError::ProviderError(RpcError::ErrorResp(payload)) => {
+            payload.message.to_lowercase().contains("revert")
+        }
+        _ => false,
+    }
+}
+
+/// value scaled by decimals, None if decimals are unknown or out of range
+fn value_to_decimal(value: U256, decimals: Option<u32>) -> Option<String> {
+    match u8::try_from(decimals?).ok()? {
+        0 => Some(value.to_string()),
+        decimals => format_units(value, decimals).ok(),
+    }
+}
+
 /// process block into columns
-fn process_erc20_transfers(logs: Vec<Log>, columns: &mut Erc20Transfers, schema: &Table) -> R<()> {
+fn process_erc20_transfers(
+    response: TransferLogs,
+    columns: &mut Erc20Transfers,
+    schema: &Table,
+) -> R<()> {
+    let (logs, decimals) = response;
     for log in logs.iter() {
         if let (Some(bn), Some(tx), Some(ti), Some(li)) =
             (log.block_number, log.transaction_hash, log.transaction_index, log.log_index)
@@ -119,13 +200,41 @@ fn process_erc20_transfers(logs: Vec<Log>, columns: &mut Erc20Transfers, schema:
             store!(schema, columns, erc20, log.address().to_vec());
             store!(schema, columns, from_address, log.topics()[1][12..].to_vec());
             store!(schema, columns, to_address, log.topics()[2][12..].to_vec());
-            store!(
-                schema,
-                columns,
-                value,
-                U256::from_be_slice(log.data().data.to_vec().as_slice())
-            );
+            let value = U256::from_be_slice(log.data().data.to_vec().as_slice());
+            store!(schema, columns, value, value);
+            if schema.has_column("value_decimal") {
+                let token_decimals = decimals.get(&log.address()).copied().flatten();
+                columns.value_decimal.push(value_to_decimal(value, token_decimals));
+            }
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_to_decimal() {
+        let value = U256::from(1_234_500_000_000_000_000u64);
+        assert_eq!(value_to_decimal(value, Some(18)), Some("1.234500000000000000".to_string()));
+        assert_eq!(value_to_decimal(value, Some(0)), Some(value.to_string()));
+        assert_eq!(value_to_decimal(value, None), None);
+        assert_eq!(value_to_decimal(value, Some(300)), None);
+    }
+
+    #[test]
+    fn test_is_revert() {
+        let error = |message: &str| {
+            CollectError::ProviderError(RpcError::ErrorResp(alloy::rpc::json_rpc::ErrorPayload {
+                code: 3,
+                message: message.to_string().into(),
+                data: None,
+            }))
+        };
+        assert!(is_revert(&error("execution reverted")));
+        assert!(!is_revert(&error("header not found")));
+        assert!(!is_revert(&CollectError::CollectError("execution reverted".to_string())));
+    }
+}
@@ -33,27 +33,23 @@ impl Dataset for Erc721Metadata {
 impl CollectByBlock for Erc721Metadata {
     type Response = (u32, Vec<u8>, Option<String>, Option<String>);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let block_number = request.ethers_block_number()?;
         let address = request.ethers_address()?;
 
         // name
         let call_data = ERC721::nameCall::SELECTOR.to_vec();
-        let name = match source.call2(address, call_data, block_number).await {
-            Ok(output) => {
-                String::from_utf8(output.to_vec()).ok().map(|s| remove_control_characters(&s))
-            }
-            Err(_) => None,
-        };
+        let name = query
+            .column_result(&["name"], source.call2(address, call_data, block_number).await)?
+            .and_then(|output| String::from_utf8(output.to_vec()).ok())
+            .map(|s| remove_control_characters(&s));
 
         // symbol
         let call_data = ERC721::symbolCall::SELECTOR.to_vec();
-        let symbol = match source.call2(address, call_data, block_number).await {
-            Ok(output) => {
-                String::from_utf8(output.to_vec()).ok().map(|s| remove_control_characters(&s))
-            }
-            Err(_) => None,
-        };
+        let symbol = query
+            .column_result(&["symbol"], source.call2(address, call_data, block_number).await)?
+            .and_then(|output| String::from_utf8(output.to_vec()).ok())
+            .map(|s| remove_control_characters(&s));
 
         Ok((request.block_number()? as u32, request.address()?, name, symbol))
     }
@@ -48,14 +48,17 @@ type EthCallsResponse = (u32, Vec<u8>, Vec<u8>, Option<Vec<u8>>);
 impl CollectByBlock for EthCalls {
     type Response = EthCallsResponse;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let transaction = TransactionRequest {
             to: Some(TxKind::Call(request.ethers_contract()?)),
             input: TransactionInput::new(request.call_data()?.into()),
             ..Default::default()
         };
         let number = request.block_number()?;
-        let output = source.call(transaction, number).await.ok().map(|x| x.to_vec());
+        let output = source.call(transaction, number).await;
+        let output = query
+            .column_result(&["output_data", "output_data_hash"], output)?
+            .map(|x| x.to_vec());
         Ok((number as u32, request.contract()?, request.call_data()?, output))
     }
 
@@ -0,0 +1,220 @@
+use crate::{
+    types::collection::collect_generic::{fetch_partition, join_partition_handles},
+    *,
+};
+use alloy::{
+    hex,
+    primitives::{Address, B256, U256},
+};
+use polars::prelude::*;
+use std::collections::HashMap;
+use tokio::{sync::mpsc, task};
+
+/// columns for genesis alloc
+#[cryo_to_df::to_df(Datatype::GenesisAlloc)]
+#[derive(Default)]
+pub struct GenesisAlloc {
+    n_rows: usize,
+    block_number: Vec<u32>,
+    address: Vec<Option<Vec<u8>>>,
+    balance: Vec<Option<U256>>,
+    nonce: Vec<Option<u64>>,
+    code: Vec<Option<Vec<u8>>>,
+    slot: Vec<Option<Vec<u8>>>,
+    value: Vec<Option<Vec<u8>>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for GenesisAlloc {
+    fn aliases() -> Vec<&'static str> {
+        vec!["genesis"]
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "address", "slot"])
+    }
+
+    fn optional_parameters() -> Vec<Dim> {
+        vec![Dim::Address]
+    }
+
+    fn default_blocks() -> Option<String> {
+        Some("0".to_string())
+    }
+}
+
+/// number of accounts requested per `debug_accountRange` page
+const GENESIS_PAGE_SIZE: u64 = 256;
+
+/// (address, balance, nonce, code, storage slots and values)
+type GenesisAccount = (Option<Vec<u8>>, U256, u64, Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>);
+
+type BlockAccounts = (u32, Vec<GenesisAccount>);
+
+#[async_trait::async_trait]
+impl CollectByBlock for GenesisAlloc {
+    type Response = BlockAccounts;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let address = request.address()?;
+        let block_number = request.block_number()?;
+        let account = Address::from_slice(&address);
+        let balance = source.get_balance(account, block_number).await?;
+        let nonce = source.get_transaction_count(account, block_number).await?;
+        let code = source.get_code(account, block_number).await?;
+        Ok((block_number as u32, vec![(Some(address), balance, nonce, code.to_vec(), vec![])]))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema =
+            query.schemas.get(&Datatype::GenesisAlloc).ok_or(err("schema not provided"))?;
+        process_accounts(columns, response, schema);
+        Ok(())
+    }
+
+    /// without addresses, the whole state is paged through `debug_accountRange`, transforming
+    /// each page as it arrives so that large allocs are not buffered
+    async fn collect_by_block(
+        partition: Partition,
+        source: Arc<Source>,
+        query: Arc<Query>,
+        inner_request_size: Option<u64>,
+    ) -> R<HashMap<Datatype, DataFrame>> {
+        let (sender, receiver) = mpsc::channel(1);
+        let chain_id = source.chain_id;
+        let handles = if partition.addresses.is_some() {
+            fetch_partition(
+                <Self as CollectByBlock>::extract,
+                partition,
+                source,
+                inner_request_size,
+                query.clone(),
+                sender,
+            )
+            .await?
+        } else {
+            let mut block_numbers = Vec::new();
+            for params in partition.param_sets(None, query.topic0_sets)?.into_iter() {
+                block_numbers.push(params.block_number()?);
+            }
+            vec![task::spawn(send_account_pages(block_numbers, source, sender))]
+        };
+        let columns = <Self as CollectByBlock>::transform_channel(receiver, &query).await?;
+        join_partition_handles(handles).await?;
+        columns.create_dfs(&query.schemas, chain_id)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for GenesisAlloc {
+    type Response = ();
+}
+
+/// page through the accounts of each block, sending one message per page
+async fn send_account_pages(
+    block_numbers: Vec<u64>,
+    source: Arc<Source>,
+    sender: mpsc::Sender<R<BlockAccounts>>,
+) -> R<()> {
+    let send_failure = |_| CollectError::CollectError("tokio mpsc send failure".to_string());
+    for block_number in block_numbers.into_iter() {
+        let mut start = Some(B256::ZERO);
+        while let Some(page_start) = start {
+            let page = source
+                .debug_account_range(block_number, page_start, GENESIS_PAGE_SIZE)
+                .await
+                .and_then(|page| {
+                    start = page.next_start();
+                    parse_account_range(page)
+                });
+            let failed = page.is_err();
+            let message = page.map(|accounts| (block_number as u32, accounts));
+            sender.send(message).await.map_err(send_failure)?;
+            if failed {
+                return Ok(())
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_account_range(page: AccountRange) -> R<Vec<GenesisAccount>> {
+    let mut accounts = Vec::with_capacity(page.accounts.len());
+    for account in page.accounts.into_values() {
+        let balance = U256::from_str_radix(&account.balance, 10)
+            .map_err(|_| err(&format!("invalid balance: {}", account.balance)))?;
+        let mut storage = Vec::new();
+        for (slot, value) in account.storage.unwrap_or_default().into_iter() {
+            storage.push((slot.to_vec(), parse_storage_value(&value)?));
+        }
+        let code = account.code.map(|code| code.to_vec()).unwrap_or_default();
+        let address = account.address.map(|address| address.to_vec());
+        accounts.push((address, balance, account.nonce, code, storage));
+    }
+    Ok(accounts)
+}
+
+/// storage values are dumped as hex without leading zeros, so left-pad them to a full word
+fn parse_storage_value(value: &str) -> R<Vec<u8>> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    let digits = if digits.len() % 2 == 1 { format!("0{}", digits) } else { digits.to_string() };
+    let bytes =
+        hex::decode(&digits).map_err(|_| err(&format!("invalid storage value: {}", value)))?;
+    if bytes.len() > 32 {
+        return Err(err(&format!("invalid storage value: {}", value)))
+    }
+    let mut word = vec![0u8; 32 - bytes.len()];
+    word.extend(bytes);
+    Ok(word)
+}
+
+fn process_accounts(columns: &mut GenesisAlloc, response: BlockAccounts, schema: &Table) {
+    let (block_number, accounts) = response;
+    for (address, balance, nonce, code, storage) in accounts.into_iter() {
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, block_number);
+        store!(schema, columns, address, address.clone());
+        store!(schema, columns, balance, Some(balance));
+        store!(schema, columns, nonce, Some(nonce));
+        store!(schema, columns, code, Some(code));
+        store!(schema, columns, slot, None);
+        store!(schema, columns, value, None);
+        for (slot, value) in storage.into_iter() {
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, block_number);
+            store!(schema, columns, address, address.clone());
+            store!(schema, columns, balance, None);
+            store!(schema, columns, nonce, None);
+            store!(schema, columns, code, None);
+            store!(schema, columns, slot, Some(slot));
+            store!(schema, columns, value, Some(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_storage_value() {
+        let mut expected = vec![0u8; 30];
+        expected.extend([0x01, 0x23]);
+        assert_eq!(parse_storage_value("123").unwrap(), expected);
+        assert_eq!(parse_storage_value("0x0123").unwrap(), expected);
+        assert!(parse_storage_value(&"ff".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_next_start() {
+        let account =
+            |key: u8| DumpAccount { key: Some(B256::with_last_byte(key)), ..Default::default() };
+        let mut page = AccountRange::default();
+        page.accounts.insert("a".to_string(), account(3));
+        page.accounts.insert("b".to_string(), account(7));
+        assert_eq!(page.next_start(), None);
+        page.next = Some(serde_json::Value::String("next".to_string()));
+        assert_eq!(page.next_start(), Some(B256::with_last_byte(8)));
+    }
+}
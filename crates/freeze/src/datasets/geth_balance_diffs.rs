@@ -43,6 +43,13 @@ impl CollectByTransaction for GethBalanceDiffs {
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schemas = &query.schemas;
-        geth_state_diffs::process_geth_diffs(&response, Some(columns), None, None, None, schemas)
+        geth_state_diffs::process_geth_transaction_diffs(
+            &response,
+            Some(columns),
+            None,
+            None,
+            None,
+            schemas,
+        )
     }
 }
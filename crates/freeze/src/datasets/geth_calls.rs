@@ -7,13 +7,13 @@ use polars::prelude::*;
 #[derive(Default)]
 pub struct GethCalls {
     n_rows: u64,
-    typ: Vec<String>,
-    from_address: Vec<Vec<u8>>,
+    typ: Vec<Option<String>>,
+    from_address: Vec<Option<Vec<u8>>>,
     to_address: Vec<Option<Vec<u8>>>,
     value: Vec<Option<U256>>,
-    gas: Vec<U256>,
-    gas_used: Vec<U256>,
-    input: Vec<Vec<u8>>,
+    gas: Vec<Option<U256>>,
+    gas_used: Vec<Option<U256>>,
+    input: Vec<Option<Vec<u8>>>,
     output: Vec<Option<Vec<u8>>>,
     error: Vec<Option<String>>,
     block_number: Vec<Option<u32>>,
@@ -28,50 +28,140 @@ impl Dataset for GethCalls {}
 
 #[async_trait::async_trait]
 impl CollectByBlock for GethCalls {
-    type Response = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>);
+    type Response = GethCallsResponse;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::GethCalls)?;
         let include_transaction = schema.has_column("block_number");
         let block_number = request.block_number()? as u32;
-        source.geth_debug_trace_block_calls(block_number, include_transaction).await
+        source
+            .geth_debug_trace_block_calls(
+                block_number,
+                include_transaction,
+                query.trace_error_as_column,
+            )
+            .await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_geth_traces(response, columns, &query.schemas)
+        process_geth_traces(response, columns, query)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for GethCalls {
-    type Response = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>);
+    type Response = GethCallsResponse;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::GethCalls)?;
         let include_block_number = schema.has_column("block_number");
         source
-            .geth_debug_trace_transaction_calls(request.transaction_hash()?, include_block_number)
+            .geth_debug_trace_transaction_calls(
+                request.transaction_hash()?,
+                include_block_number,
+                query.trace_error_as_column,
+            )
             .await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_geth_traces(response, columns, &query.schemas)
+        process_geth_traces(response, columns, query)
     }
 }
 
-fn process_geth_traces(
-    traces: (Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrame>),
+/// block number, transaction hashes, and call traces or tracing errors of transactions
+type GethCallsResponse = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<CallFrameResult>);
+
+pub(crate) fn process_geth_traces(
+    traces: GethCallsResponse,
     columns: &mut GethCalls,
-    schemas: &Schemas,
+    query: &Query,
 ) -> R<()> {
     let (block_number, txs, traces) = traces;
-    let schema = schemas.get(&Datatype::GethCalls).ok_or(err("schema for geth_traces missing"))?;
+    let schema =
+        query.schemas.get(&Datatype::GethCalls).ok_or(err("schema for geth_traces missing"))?;
+    let max_depth = query.max_trace_depth;
     for (tx_index, (tx, trace)) in txs.into_iter().zip(traces).enumerate() {
-        process_trace(trace, columns, schema, &block_number, &tx, tx_index as u32, vec![])?
+        let tx_index = tx_index as u32;
+        match trace {
+            Ok(mut trace) => {
+                if query.trace_error_as_column {
+                    use_parity_errors(&mut trace);
+                }
+                process_trace(
+                    trace,
+                    columns,
+                    schema,
+                    &block_number,
+                    &tx,
+                    tx_index,
+                    vec![],
+                    max_depth,
+                )?
+            }
+            Err(error) => process_trace_error(error, columns, schema, &block_number, &tx, tx_index),
+        }
     }
     Ok(())
 }
 
+/// row of a transaction that failed to trace, with nulls for the fields of its unknown call
+fn process_trace_error(
+    error: String,
+    columns: &mut GethCalls,
+    schema: &Table,
+    block_number: &Option<u32>,
+    tx: &Option<Vec<u8>>,
+    tx_index: u32,
+) {
+    columns.n_rows += 1;
+    store!(schema, columns, typ, None);
+    store!(schema, columns, from_address, None);
+    store!(schema, columns, to_address, None);
+    store!(schema, columns, value, None);
+    store!(schema, columns, gas, None);
+    store!(schema, columns, gas_used, None);
+    store!(schema, columns, input, None);
+    store!(schema, columns, output, None);
+    store!(schema, columns, error, Some(parity_error_message(&error)));
+    store!(schema, columns, block_number, *block_number);
+    store!(schema, columns, transaction_hash, tx.clone());
+    store!(schema, columns, transaction_index, tx_index);
+    store!(schema, columns, trace_address, String::new());
+}
+
+/// replace geth error messages of a call and its subcalls with those of parity traces
+fn use_parity_errors(frame: &mut CallFrame) {
+    if let Some(error) = &frame.error {
+        frame.error = Some(parity_error_message(error));
+    }
+    for subcall in frame.calls.iter_mut() {
+        use_parity_errors(subcall);
+    }
+}
+
+/// parity trace error message of a geth error message, unknown messages are kept as is
+pub(crate) fn parity_error_message(error: &str) -> String {
+    let message = match error {
+        "execution reverted" => "Reverted",
+        "out of gas" | "contract creation code storage out of gas" | "gas uint64 overflow" => {
+            "Out of gas"
+        }
+        "invalid jump destination" => "Bad jump destination",
+        "write protection" => "Mutable Call In Static Context",
+        "max call depth exceeded" => "Out of stack",
+        _ if error.starts_with("invalid opcode") || error.starts_with("undefined opcode") => {
+            "Bad instruction"
+        }
+        _ if error.starts_with("stack underflow") => "Stack underflow",
+        _ if error.starts_with("stack limit reached") => "Out of stack",
+        _ => error,
+    };
+    message.to_string()
+}
+
+/// depth of a call is the length of its trace address, matching parity traces
+#[allow(clippy::too_many_arguments)]
 fn process_trace(
     trace: CallFrame,
     columns: &mut GethCalls,
@@ -80,15 +170,16 @@ fn process_trace(
     tx: &Option<Vec<u8>>,
     tx_index: u32,
     trace_address: Vec<u32>,
+    max_depth: Option<u32>,
 ) -> R<()> {
     columns.n_rows += 1;
-    store!(schema, columns, typ, trace.typ);
-    store!(schema, columns, from_address, trace.from.to_vec());
+    store!(schema, columns, typ, Some(trace.typ));
+    store!(schema, columns, from_address, Some(trace.from.to_vec()));
     store!(schema, columns, to_address, trace.to.map(|x| x.to_vec()));
     store!(schema, columns, value, trace.value);
-    store!(schema, columns, gas, trace.gas);
-    store!(schema, columns, gas_used, trace.gas_used);
-    store!(schema, columns, input, trace.input.0.to_vec());
+    store!(schema, columns, gas, Some(trace.gas));
+    store!(schema, columns, gas_used, Some(trace.gas_used));
+    store!(schema, columns, input, Some(trace.input.0.to_vec()));
     store!(schema, columns, output, trace.output.map(|x| x.0.to_vec()));
     store!(schema, columns, error, trace.error);
     store!(schema, columns, block_number, *block_number);
@@ -101,11 +192,103 @@ fn process_trace(
         trace_address.iter().map(|&n| n.to_string()).collect::<Vec<_>>().join(" ")
     );
 
+    if max_depth.is_some_and(|max_depth| trace_address.len() >= max_depth as usize) {
+        return Ok(())
+    }
     for (s, subcall) in trace.calls.into_iter().enumerate() {
         let mut sub_trace_address = trace_address.clone();
         sub_trace_address.push(s as u32);
-        process_trace(subcall, columns, schema, block_number, tx, tx_index, sub_trace_address)?
+        process_trace(
+            subcall,
+            columns,
+            schema,
+            block_number,
+            tx,
+            tx_index,
+            sub_trace_address,
+            max_depth,
+        )?
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parity_error_message() {
+        assert_eq!(parity_error_message("execution reverted"), "Reverted");
+        assert_eq!(parity_error_message("out of gas"), "Out of gas");
+        assert_eq!(parity_error_message("invalid opcode: INVALID"), "Bad instruction");
+        assert_eq!(parity_error_message("stack underflow (0 <=> 2)"), "Stack underflow");
+        let unknown = "insufficient balance for transfer";
+        assert_eq!(parity_error_message(unknown), unknown);
+    }
+
+    #[test]
+    fn test_use_parity_errors() {
+        let subcall = CallFrame { error: Some("out of gas".to_string()), ..Default::default() };
+        let mut frame = CallFrame {
+            error: Some("execution reverted".to_string()),
+            calls: vec![subcall, CallFrame::default()],
+            ..Default::default()
+        };
+        use_parity_errors(&mut frame);
+        assert_eq!(frame.error.as_deref(), Some("Reverted"));
+        assert_eq!(frame.calls[0].error.as_deref(), Some("Out of gas"));
+        assert_eq!(frame.calls[1].error, None);
+    }
+
+    fn error_query() -> Query {
+        let u256_types = [U256Type::Binary];
+        let columns = Some(vec!["all".to_string()]);
+        let schema = Datatype::GethCalls
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &columns, None, None)
+            .unwrap();
+        let mut query = Query::for_datatype(Datatype::GethCalls, schema, vec![]);
+        query.trace_error_as_column = true;
+        query
+    }
+
+    #[test]
+    fn test_process_geth_traces_errors_by_block() {
+        let query = error_query();
+        let frame = CallFrame {
+            typ: "CALL".to_string(),
+            gas: U256::from(21000),
+            error: Some("execution reverted".to_string()),
+            ..Default::default()
+        };
+        let txs = vec![Some(vec![1]), Some(vec![2])];
+        let traces = vec![Err("execution timeout".to_string()), Ok(frame)];
+        let mut columns = GethCalls::default();
+        process_geth_traces((Some(5), txs, traces), &mut columns, &query).unwrap();
+
+        // failed traces have nulls rather than zeros for the fields of their unknown call
+        assert_eq!(columns.n_rows, 2);
+        assert_eq!(columns.typ, vec![None, Some("CALL".to_string())]);
+        assert_eq!(columns.from_address, vec![None, Some(vec![0; 20])]);
+        assert_eq!(columns.gas, vec![None, Some(U256::from(21000))]);
+        assert_eq!(columns.gas_used, vec![None, Some(U256::ZERO)]);
+        assert_eq!(columns.input, vec![None, Some(vec![])]);
+        let errors = vec![Some("execution timeout".to_string()), Some("Reverted".to_string())];
+        assert_eq!(columns.error, errors);
+        assert_eq!(columns.transaction_index, vec![0, 1]);
+        assert_eq!(columns.block_number, vec![Some(5), Some(5)]);
+    }
+
+    #[test]
+    fn test_process_geth_traces_errors_by_transaction() {
+        let query = error_query();
+        let traces = vec![Err("out of gas".to_string())];
+        let mut columns = GethCalls::default();
+        process_geth_traces((Some(7), vec![Some(vec![3])], traces), &mut columns, &query).unwrap();
+        assert_eq!(columns.n_rows, 1);
+        assert_eq!(columns.error, vec![Some("Out of gas".to_string())]);
+        assert_eq!(columns.transaction_hash, vec![Some(vec![3])]);
+        assert_eq!(columns.trace_address, vec!["".to_string()]);
+        assert_eq!(columns.gas, vec![None]);
+    }
+}
@@ -42,6 +42,13 @@ impl CollectByTransaction for GethCodeDiffs {
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schemas = &query.schemas;
-        geth_state_diffs::process_geth_diffs(&response, None, Some(columns), None, None, schemas)
+        geth_state_diffs::process_geth_transaction_diffs(
+            &response,
+            None,
+            Some(columns),
+            None,
+            None,
+            schemas,
+        )
     }
 }
@@ -42,6 +42,13 @@ impl CollectByTransaction for GethNonceDiffs {
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schemas = &query.schemas;
-        geth_state_diffs::process_geth_diffs(&response, None, None, Some(columns), None, schemas)
+        geth_state_diffs::process_geth_transaction_diffs(
+            &response,
+            None,
+            None,
+            Some(columns),
+            None,
+            schemas,
+        )
     }
 }
@@ -1,9 +1,15 @@
 use crate::*;
 use alloy::{
     dyn_abi::{DynSolType, DynSolValue, EventExt},
-    rpc::types::Log,
+    rpc::types::{Filter, FilterBlockOption, Log},
 };
 use polars::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Mutex,
+};
+use tokio::sync::{mpsc, OnceCell};
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Logs)]
@@ -23,6 +29,7 @@ pub struct Logs {
     data: Vec<Vec<u8>>,
     n_data_bytes: Vec<u32>,
     event_cols: indexmap::IndexMap<String, Vec<DynSolValue>>,
+    data_cols: DataColumns,
     chain_id: Vec<u64>,
 }
 
@@ -68,22 +75,71 @@ impl Dataset for Logs {
 impl CollectByBlock for Logs {
     type Response = Vec<Log>;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.get_logs(&request.ethers_log_filter()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let filter = request.ethers_log_filter()?;
+        let logs = source.get_logs(&filter).await?;
+        if !filters_block_log_counts(&query) {
+            return Ok(logs)
+        }
+        let unfiltered = filter.address.is_empty() && filter.topics.iter().all(|t| t.is_empty());
+        let counts = if unfiltered {
+            count_block_logs(&logs)
+        } else {
+            let (from_block, to_block) = request.block_range()?;
+            block_log_counts(from_block, to_block, None, &source, &query).await?
+        };
+        Ok(filter_block_log_counts(logs, &counts, query.min_block_logs, query.max_block_logs))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Logs)?;
         process_logs(response, columns, schema)
     }
+
+    /// keep each (block_number, log_index) once, even if requests of the chunk overlap
+    async fn transform_channel(
+        mut receiver: mpsc::Receiver<R<Self::Response>>,
+        query: &Arc<Query>,
+    ) -> R<Self> {
+        let schema = query.schemas.get_schema(&Datatype::Logs)?;
+        let mut columns = Self::default();
+        let mut seen = HashSet::new();
+        while let Some(message) = receiver.recv().await {
+            let logs = dedup_logs(message?, &mut seen);
+            process_logs(logs, &mut columns, schema)?;
+        }
+        Ok(columns)
+    }
+}
+
+/// drop logs whose (block_number, log_index) has already been seen
+fn dedup_logs(logs: Vec<Log>, seen: &mut HashSet<(u64, u64)>) -> Vec<Log> {
+    logs.into_iter()
+        .filter(|log| match (log.block_number, log.log_index) {
+            (Some(block_number), Some(log_index)) => seen.insert((block_number, log_index)),
+            _ => true,
+        })
+        .collect()
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for Logs {
     type Response = Vec<Log>;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.get_transaction_logs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let logs = source.get_transaction_logs(request.transaction_hash()?).await?;
+        let logs = filter_transaction_logs(logs, &request);
+        if !filters_block_log_counts(&query) {
+            return Ok(logs)
+        }
+        let Some(block_number) = logs.first().and_then(|log| log.block_number) else {
+            return Ok(logs)
+        };
+        let transaction = request.transaction_hash()?;
+        let counts =
+            block_log_counts(block_number, block_number, Some(&transaction), &source, &query)
+                .await?;
+        Ok(filter_block_log_counts(logs, &counts, query.min_block_logs, query.max_block_logs))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
@@ -92,6 +148,171 @@ impl CollectByTransaction for Logs {
     }
 }
 
+fn filters_block_log_counts(query: &Query) -> bool {
+    query.min_block_logs.is_some() || query.max_block_logs.is_some()
+}
+
+/// number of logs of each block
+fn count_block_logs(logs: &[Log]) -> HashMap<u64, u64> {
+    let mut counts = HashMap::new();
+    for block_number in logs.iter().filter_map(|log| log.block_number) {
+        *counts.entry(block_number).or_default() += 1;
+    }
+    counts
+}
+
+/// number of logs of each block of a block range, shared across the requests of the chunks in
+/// flight
+///
+/// requests of the same range, such as those of each address or of each transaction of a block,
+/// share a single unfiltered `eth_getLogs`. counts are evicted once the chunk of their blocks or
+/// transactions finishes, so a run does not keep the counts of every range it has collected
+#[derive(Clone, Default)]
+pub struct BlockLogCounts {
+    cached: Arc<Mutex<CachedLogCounts>>,
+}
+
+#[derive(Default)]
+struct CachedLogCounts {
+    ranges: HashMap<(u64, u64), RangeLogCounts>,
+    /// range of each transaction whose counts were requested, evicted with its chunk
+    transactions: HashMap<Vec<u8>, (u64, u64)>,
+}
+
+/// counts of a block range, set once by the first request of the range
+type RangeLogCounts = Arc<OnceCell<HashMap<u64, u64>>>;
+
+impl BlockLogCounts {
+    /// counts of range, fetched by the first request of the range while later requests wait
+    async fn get_or_fetch<F>(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        transaction: Option<&[u8]>,
+        fetch: F,
+    ) -> R<HashMap<u64, u64>>
+    where
+        F: Future<Output = R<HashMap<u64, u64>>>,
+    {
+        let cell = match self.cached.lock() {
+            Ok(mut cached) => {
+                if let Some(transaction) = transaction {
+                    cached.transactions.insert(transaction.to_vec(), (from_block, to_block));
+                }
+                cached.ranges.entry((from_block, to_block)).or_default().clone()
+            }
+            Err(_) => Arc::new(OnceCell::new()),
+        };
+        cell.get_or_try_init(|| fetch).await.cloned()
+    }
+
+    /// drop the counts of the blocks and transactions of a finished chunk
+    pub(crate) fn evict(&self, partition: &Partition) {
+        let Ok(mut cached) = self.cached.lock() else { return };
+        let CachedLogCounts { ranges, transactions } = &mut *cached;
+        for chunk in partition.transactions.iter().flatten() {
+            for transaction in chunk.values().iter() {
+                if let Some(range) = transactions.remove(transaction) {
+                    ranges.remove(&range);
+                }
+            }
+        }
+        for chunk in partition.block_numbers.iter().flatten() {
+            if let (Some(min), Some(max)) = (chunk.min_value(), chunk.max_value()) {
+                ranges.retain(|(from_block, to_block), _| *to_block < min || *from_block > max);
+            }
+        }
+        transactions.retain(|_, range| ranges.contains_key(range));
+    }
+}
+
+/// number of logs of each block of range, through the cache of the query if it has one
+async fn block_log_counts(
+    from_block: u64,
+    to_block: u64,
+    transaction: Option<&[u8]>,
+    source: &Source,
+    query: &Query,
+) -> R<HashMap<u64, u64>> {
+    let fetch = fetch_block_log_counts(from_block, to_block, source);
+    match &query.block_log_counts {
+        Some(cache) => cache.get_or_fetch(from_block, to_block, transaction, fetch).await,
+        None => fetch.await,
+    }
+}
+
+/// number of logs of each block, counting every log of the blocks regardless of address or topic
+async fn fetch_block_log_counts(
+    from_block: u64,
+    to_block: u64,
+    source: &Source,
+) -> R<HashMap<u64, u64>> {
+    let block_option = FilterBlockOption::Range {
+        from_block: Some(from_block.into()),
+        to_block: Some(to_block.into()),
+    };
+    let filter = Filter { block_option, ..Default::default() };
+    Ok(count_block_logs(&source.get_logs(&filter).await?))
+}
+
+/// keep logs of blocks whose number of logs is within the inclusive min and max bounds
+fn filter_block_log_counts(
+    logs: Vec<Log>,
+    counts: &HashMap<u64, u64>,
+    min: Option<u64>,
+    max: Option<u64>,
+) -> Vec<Log> {
+    logs.into_iter()
+        .filter(|log| {
+            let count = log.block_number.and_then(|n| counts.get(&n)).copied().unwrap_or_default();
+            min.is_none_or(|min| count >= min) && max.is_none_or(|max| count <= max)
+        })
+        .collect()
+}
+
+/// keep logs that match the optional address and topic parameters of request
+fn filter_transaction_logs(logs: Vec<Log>, request: &Params) -> Vec<Log> {
+    let topics = [&request.topic0, &request.topic1, &request.topic2, &request.topic3];
+    logs.into_iter()
+        .filter(|log| match &request.address {
+            Some(address) => log.address().as_slice() == address.as_slice(),
+            None => true,
+        })
+        .filter(|log| match &request.topic0s {
+            Some(topic0s) => {
+                log.topic0().is_some_and(|t| topic0s.iter().any(|x| t.as_slice() == x))
+            }
+            None => true,
+        })
+        .filter(|log| {
+            topics.iter().enumerate().all(|(i, topic)| match topic {
+                Some(topic) => log.topics().get(i).is_some_and(|t| t.as_slice() == topic.as_slice()),
+                None => true,
+            })
+        })
+        .collect()
+}
+
+/// collapse logs into one row per key, with the number of logs of each key in a count column
+///
+/// rows are grouped by the key columns alone, other columns such as decoded event columns differ
+/// across the logs of a key and are dropped, see `Table::collapse_columns`
+pub(crate) fn collapse_logs(df: DataFrame, key: &[String], schema: &Table) -> R<DataFrame> {
+    let block_column = schema.block_number_column.as_deref().unwrap_or("block_number");
+    let mut keys: Vec<Expr> = key
+        .iter()
+        .map(|column| if column == "block_number" { col(block_column) } else { col(column) })
+        .collect();
+    if schema.has_column("chain_id") {
+        keys.push(col("chain_id"));
+    }
+    df.lazy()
+        .group_by_stable(keys)
+        .agg([len().cast(DataType::UInt64).alias("count")])
+        .collect()
+        .map_err(CollectError::PolarsError)
+}
+
 /// process block into columns
 fn process_logs(logs: Vec<Log>, columns: &mut Logs, schema: &Table) -> R<()> {
     // let decode_keys = match &schema.log_decoder {
@@ -161,6 +382,9 @@ fn process_logs(logs: Vec<Log>, columns: &mut Logs, schema: &Table) -> R<()> {
                     Err(_) => continue,
                 }
             };
+            if let Some(decoder) = &schema.data_decoder {
+                columns.data_cols.push(decoder, &log.data().data);
+            }
 
             columns.n_rows += 1;
             store!(schema, columns, block_number, bn as u32);
@@ -189,3 +413,121 @@ fn process_logs(logs: Vec<Log>, columns: &mut Logs, schema: &Table) -> R<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_logs() {
+        let log = |block_number, log_index| Log {
+            block_number: Some(block_number),
+            log_index: Some(log_index),
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+        let first = dedup_logs(vec![log(9, 0), log(10, 0), log(10, 1)], &mut seen);
+        assert_eq!(first.len(), 3);
+
+        // logs of a block returned by two overlapping requests are kept once
+        let second = dedup_logs(vec![log(10, 1), log(10, 2), log(11, 0)], &mut seen);
+        let kept: Vec<_> = second.iter().map(|log| (log.block_number, log.log_index)).collect();
+        assert_eq!(kept, vec![(Some(10), Some(2)), (Some(11), Some(0))]);
+    }
+
+    #[test]
+    fn test_filter_block_log_counts() {
+        let log = |block_number| Log { block_number: Some(block_number), ..Default::default() };
+        let logs = vec![log(1), log(2), log(2), log(3)];
+        // blocks are counted by all of their logs, not only by the logs that were kept
+        let counts = HashMap::from([(1, 5), (2, 2), (3, 1)]);
+        let kept = filter_block_log_counts(logs.clone(), &counts, Some(2), Some(4));
+        assert_eq!(kept.iter().map(|log| log.block_number).collect::<Vec<_>>(), [Some(2); 2]);
+        assert_eq!(filter_block_log_counts(logs.clone(), &counts, Some(2), None).len(), 3);
+        assert_eq!(count_block_logs(&logs), HashMap::from([(1, 1), (2, 2), (3, 1)]));
+    }
+
+    #[test]
+    fn test_collapse_logs() {
+        let columns = ["block_number", "log_index", "address", "topic0", "chain_id"];
+        let columns = Some(columns.iter().map(|column| column.to_string()).collect());
+        let mut schema = Datatype::Logs
+            .table_schema(&[], &ColumnEncoding::Hex, &None, &None, &columns, None, None)
+            .unwrap();
+        let df = df!(
+            "block_number" => [1u32, 1, 1, 2],
+            "log_index" => [0u32, 1, 2, 0],
+            "address" => ["0xaa", "0xaa", "0xbb", "0xaa"],
+            "topic0" => ["0x01", "0x02", "0x01", "0x01"],
+            "chain_id" => [1u64, 1, 1, 1],
+        )
+        .unwrap();
+        let key = vec!["block_number".to_string(), "address".to_string()];
+        let collapsed = collapse_logs(df, &key, &schema).unwrap();
+        assert_eq!(
+            collapsed.get_column_names(),
+            vec!["block_number", "address", "chain_id", "count"]
+        );
+        let counts: Vec<_> =
+            collapsed.column("count").unwrap().u64().unwrap().into_no_null_iter().collect();
+        assert_eq!(counts, vec![2, 1, 1]);
+
+        // the collapsed schema lists the collapsed columns
+        schema.collapse_columns(&key);
+        assert_eq!(schema.output_columns(), collapsed.get_column_names());
+        assert_eq!(schema.column_type("count"), Some(ColumnType::UInt64));
+        assert_eq!(schema.sort_columns, Some(key));
+    }
+
+    #[tokio::test]
+    async fn test_block_log_counts() {
+        let cache = BlockLogCounts::default();
+        let fetches = std::sync::atomic::AtomicU64::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(HashMap::from([(1, 3)]))
+        };
+
+        // concurrent requests of a range share one fetch, other ranges are fetched separately
+        let counts = futures::future::join_all([
+            cache.get_or_fetch(1, 2, None, fetch()),
+            cache.get_or_fetch(1, 2, None, fetch()),
+            cache.get_or_fetch(1, 1, None, fetch()),
+        ])
+        .await;
+        assert!(counts.iter().all(|counts| counts.as_ref().unwrap() == &HashMap::from([(1, 3)])));
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 2);
+        cache.get_or_fetch(1, 2, None, fetch()).await.unwrap();
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_evict_block_log_counts() {
+        let cache = BlockLogCounts::default();
+        let fetch = || async { Ok(HashMap::from([(1, 3)])) };
+        cache.get_or_fetch(0, 9, None, fetch()).await.unwrap();
+        cache.get_or_fetch(10, 19, None, fetch()).await.unwrap();
+        cache.get_or_fetch(25, 25, Some(&[1u8; 32]), fetch()).await.unwrap();
+        cache.get_or_fetch(26, 26, Some(&[2u8; 32]), fetch()).await.unwrap();
+        let ranges = |cache: &BlockLogCounts| {
+            let mut ranges: Vec<_> = cache.cached.lock().unwrap().ranges.keys().copied().collect();
+            ranges.sort();
+            ranges
+        };
+
+        // finished chunks drop the counts of their blocks or transactions
+        let blocks = Partition {
+            block_numbers: Some(vec![BlockChunk::Range(0, 9)]),
+            ..Default::default()
+        };
+        cache.evict(&blocks);
+        assert_eq!(ranges(&cache), vec![(10, 19), (25, 25), (26, 26)]);
+        let transactions = Partition {
+            transactions: Some(vec![TransactionChunk::Values(vec![vec![1u8; 32]])]),
+            ..Default::default()
+        };
+        cache.evict(&transactions);
+        assert_eq!(ranges(&cache), vec![(10, 19), (26, 26)]);
+        assert_eq!(cache.cached.lock().unwrap().transactions.len(), 1);
+    }
+}
@@ -6,6 +6,8 @@ pub mod balance_diffs;
 pub mod balance_reads;
 /// balances
 pub mod balances;
+/// blobs
+pub mod blobs;
 /// blocks
 pub mod blocks;
 /// code diffs
@@ -34,6 +36,8 @@ pub mod erc721_transfers;
 pub mod eth_calls;
 /// four byte counts
 pub mod four_byte_counts;
+/// genesis alloc
+pub mod genesis_alloc;
 /// geth balance diffs
 pub mod geth_balance_diffs;
 /// geth calls
@@ -58,6 +62,8 @@ pub mod nonce_diffs;
 pub mod nonce_reads;
 /// nonces
 pub mod nonces;
+/// simulations
+pub mod simulations;
 /// slots
 pub mod slots;
 /// storage diffs
@@ -68,8 +74,12 @@ pub mod storage_reads;
 pub mod trace_calls;
 /// traces
 pub mod traces;
+/// transaction access lists
+pub mod transaction_access_lists;
 /// transactions
 pub mod transactions;
+/// uncles
+pub mod uncles;
 /// vm traces
 pub mod vm_traces;
 
@@ -77,6 +87,7 @@ pub use address_appearances::*;
 pub use balance_diffs::*;
 pub use balance_reads::*;
 pub use balances::*;
+pub use blobs::*;
 pub use blocks::*;
 pub use code_diffs::*;
 pub use code_reads::*;
@@ -91,6 +102,7 @@ pub use erc721_metadata::*;
 pub use erc721_transfers::*;
 pub use eth_calls::*;
 pub use four_byte_counts::*;
+pub use genesis_alloc::*;
 pub use geth_balance_diffs::*;
 pub use geth_calls::*;
 pub use geth_code_diffs::*;
@@ -103,10 +115,13 @@ pub use native_transfers::*;
 pub use nonce_diffs::*;
 pub use nonce_reads::*;
 pub use nonces::*;
+pub use simulations::*;
 pub use slots::*;
 pub use storage_diffs::*;
 pub use storage_reads::*;
 pub use trace_calls::*;
 pub use traces::*;
+pub use transaction_access_lists::*;
 pub use transactions::*;
+pub use uncles::*;
 pub use vm_traces::*;
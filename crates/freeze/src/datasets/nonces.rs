@@ -1,6 +1,7 @@
-use crate::*;
+use crate::{collection::collect_generic::fetch_partition_responses, *};
 use alloy::primitives::Address;
 use polars::prelude::*;
+use std::collections::HashMap;
 
 /// columns for balances
 #[cryo_to_df::to_df(Datatype::Nonces)]
@@ -10,6 +11,7 @@ pub struct Nonces {
     block_number: Vec<u32>,
     address: Vec<Vec<u8>>,
     nonce: Vec<u64>,
+    delta: Vec<Option<u64>>,
     chain_id: Vec<u64>,
 }
 
@@ -45,20 +47,152 @@ impl CollectByBlock for Nonces {
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Nonces)?;
-        process_nonce(columns, response, schema)
+        process_nonce(columns, response, None, schema)
+    }
+
+    /// deltas and nonce changes depend on the previous sampled block of each address, so
+    /// responses are buffered and processed in block order
+    ///
+    /// chunks after the first continue from the nonces of the block preceding the chunk, whereas
+    /// the first sampled block of each address in the first chunk is its baseline, with a null
+    /// delta
+    async fn collect_by_block(
+        partition: Partition,
+        source: Arc<Source>,
+        query: Arc<Query>,
+        inner_request_size: Option<u64>,
+    ) -> R<HashMap<Datatype, DataFrame>> {
+        let schema = query.schemas.get_schema(&Datatype::Nonces)?;
+        let previous_nonces = match query.previous_chunk_block(&partition) {
+            Some(block) if query.changes_only || schema.has_column("delta") => {
+                let addresses = partition.addresses.iter().flatten().flat_map(|c| c.values());
+                let nonces = addresses.map(|address| {
+                    let source = source.clone();
+                    async move {
+                        let account = Address::from_slice(&address);
+                        let nonce = source.get_transaction_count(account, block).await?;
+                        Ok::<_, CollectError>((address, nonce))
+                    }
+                });
+                futures::future::try_join_all(nonces).await?.into_iter().collect()
+            }
+            _ => HashMap::new(),
+        };
+
+        let chain_id = source.chain_id;
+        let responses = fetch_partition_responses(
+            <Self as CollectByBlock>::extract,
+            partition,
+            source,
+            inner_request_size,
+            query.clone(),
+        )
+        .await?;
+        let mut columns = Self::default();
+        let deltas =
+            nonce_deltas(responses, &previous_nonces, query.changes_only, query.changes_baseline);
+        for (response, delta) in deltas {
+            process_nonce(&mut columns, response, delta, schema)?;
+        }
+        columns.create_dfs(&query.schemas, chain_id)
     }
 }
 
+/// sort responses by address and block, pairing each with the nonce increase since the
+/// previous sampled block of its address, or since its previous nonce if known
+///
+/// with changes_only, responses whose nonce did not increase are dropped, as are baselines
+/// unless baseline is set
+fn nonce_deltas(
+    mut responses: Vec<BlockTxAddressOutput>,
+    previous_nonces: &HashMap<Vec<u8>, u64>,
+    changes_only: bool,
+    baseline: bool,
+) -> Vec<(BlockTxAddressOutput, Option<u64>)> {
+    responses.sort_by(|a, b| (&a.2, a.0).cmp(&(&b.2, b.0)));
+    let mut output = Vec::new();
+    let mut previous: Option<(Vec<u8>, u64)> = None;
+    for response in responses.into_iter() {
+        let delta = match &previous {
+            Some((address, nonce)) if *address == response.2 => {
+                Some(response.3.saturating_sub(*nonce))
+            }
+            _ => previous_nonces.get(&response.2).map(|nonce| response.3.saturating_sub(*nonce)),
+        };
+        previous = Some((response.2.clone(), response.3));
+        let keep = match delta {
+            _ if !changes_only => true,
+            None => baseline,
+            Some(delta) => delta > 0,
+        };
+        if keep {
+            output.push((response, delta));
+        }
+    }
+    output
+}
+
 #[async_trait::async_trait]
 impl CollectByTransaction for Nonces {
     type Response = ();
 }
 
-fn process_nonce(columns: &mut Nonces, data: BlockTxAddressOutput, schema: &Table) -> R<()> {
+fn process_nonce(
+    columns: &mut Nonces,
+    data: BlockTxAddressOutput,
+    delta: Option<u64>,
+    schema: &Table,
+) -> R<()> {
     let (block, _tx, address, output) = data;
     columns.n_rows += 1;
     store!(schema, columns, block_number, block);
     store!(schema, columns, address, address);
     store!(schema, columns, nonce, output);
+    store!(schema, columns, delta, delta);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_deltas() {
+        let (a, b) = (vec![1u8], vec![2u8]);
+        let responses = vec![
+            (12, None, a.clone(), 5),
+            (10, None, a.clone(), 3),
+            (11, None, a.clone(), 3),
+            (10, None, b.clone(), 7),
+            (11, None, b.clone(), 7),
+        ];
+        let summarize = |output: Vec<(BlockTxAddressOutput, Option<u64>)>| {
+            output.into_iter().map(|(r, delta)| (r.0, r.2, delta)).collect::<Vec<_>>()
+        };
+
+        let all = summarize(nonce_deltas(responses.clone(), &HashMap::new(), false, true));
+        let expected = vec![
+            (10, a.clone(), None),
+            (11, a.clone(), Some(0)),
+            (12, a.clone(), Some(2)),
+            (10, b.clone(), None),
+            (11, b.clone(), Some(0)),
+        ];
+        assert_eq!(all, expected);
+
+        let changes = summarize(nonce_deltas(responses.clone(), &HashMap::new(), true, true));
+        let expected = vec![(10, a.clone(), None), (12, a.clone(), Some(2)), (10, b.clone(), None)];
+        assert_eq!(changes, expected);
+
+        let changes = summarize(nonce_deltas(responses.clone(), &HashMap::new(), true, false));
+        assert_eq!(changes, vec![(12, a.clone(), Some(2))]);
+
+        // chunks after the first continue from the nonces preceding the chunk
+        let previous = [(a.clone(), 1), (b.clone(), 7)].into_iter().collect();
+        let all = summarize(nonce_deltas(responses.clone(), &previous, false, true));
+        assert_eq!(all[0], (10, a.clone(), Some(2)));
+        assert_eq!(all[3], (10, b, Some(0)));
+        let changes = summarize(nonce_deltas(responses, &previous, true, true));
+        assert_eq!(changes, vec![(10, a.clone(), Some(2)), (12, a, Some(2))]);
+    }
+}
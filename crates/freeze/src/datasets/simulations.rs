@@ -0,0 +1,181 @@
+use crate::*;
+use alloy::{
+    primitives::keccak256,
+    rpc::types::trace::parity::{TraceOutput, TraceResults, TraceType},
+    sol_types::decode_revert_reason,
+};
+use polars::prelude::*;
+
+/// columns for simulations
+///
+/// each call is simulated on its own against the state of its block, so bundles of calls that
+/// depend on each other's state changes are not supported
+#[cryo_to_df::to_df(Datatype::Simulations)]
+#[derive(Default)]
+pub struct Simulations {
+    n_rows: u64,
+    block_number: Vec<u32>,
+    contract_address: Vec<Vec<u8>>,
+    call_data: Vec<Vec<u8>>,
+    call_data_hash: Vec<Vec<u8>>,
+    success: Vec<bool>,
+    gas_used: Vec<Option<u64>>,
+    output_data: Vec<Option<Vec<u8>>>,
+    error: Vec<Option<String>>,
+    revert_reason: Vec<Option<String>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Simulations {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "block_number",
+            "contract_address",
+            "call_data",
+            "success",
+            "gas_used",
+            "output_data",
+            "revert_reason",
+            "chain_id",
+        ])
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "contract_address"])
+    }
+
+    fn default_blocks() -> Option<String> {
+        Some("latest".to_string())
+    }
+
+    fn arg_aliases() -> Option<std::collections::HashMap<Dim, Dim>> {
+        Some([(Dim::Address, Dim::Contract), (Dim::ToAddress, Dim::Contract)].into_iter().collect())
+    }
+
+    fn required_parameters() -> Vec<Dim> {
+        vec![Dim::Contract, Dim::CallData]
+    }
+}
+
+type SimulationsResponse = (u32, Vec<u8>, Vec<u8>, TraceResults);
+
+#[async_trait::async_trait]
+impl CollectByBlock for Simulations {
+    type Response = SimulationsResponse;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let contract = request.contract()?;
+        let call_data = request.call_data()?;
+        let results = source
+            .trace_call2(
+                request.ethers_contract()?,
+                call_data.clone(),
+                vec![TraceType::Trace],
+                Some(request.ethers_block_number()?),
+            )
+            .await?;
+        Ok((block_number as u32, contract, call_data, results))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Simulations)?;
+        process_simulation(response, columns, schema);
+        Ok(())
+    }
+}
+
+impl CollectByTransaction for Simulations {
+    type Response = ();
+}
+
+/// a reverted simulation still produces a row, with its error and decoded revert reason
+fn process_simulation(response: SimulationsResponse, columns: &mut Simulations, schema: &Table) {
+    let (block_number, contract_address, call_data, results) = response;
+    let root = results.trace.iter().find(|trace| trace.trace_address.is_empty());
+    let error = root.and_then(|trace| trace.error.clone());
+    let gas_used = root.and_then(|trace| match &trace.result {
+        Some(TraceOutput::Call(output)) => Some(output.gas_used),
+        Some(TraceOutput::Create(output)) => Some(output.gas_used),
+        None => None,
+    });
+    let output_data = results.output.to_vec();
+    let revert_reason = match error {
+        Some(_) => decode_revert_reason(&output_data),
+        None => None,
+    };
+
+    columns.n_rows += 1;
+    store!(schema, columns, block_number, block_number);
+    store!(schema, columns, contract_address, contract_address);
+    store!(schema, columns, call_data, call_data.clone());
+    store!(schema, columns, call_data_hash, keccak256(call_data).to_vec());
+    store!(schema, columns, success, error.is_none());
+    store!(schema, columns, gas_used, gas_used);
+    store!(schema, columns, output_data, Some(output_data));
+    store!(schema, columns, error, error);
+    store!(schema, columns, revert_reason, revert_reason);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        primitives::{Address, Bytes},
+        rpc::types::trace::parity::{Action, CallAction, CallOutput, TransactionTrace},
+        sol_types::{Revert, SolError},
+    };
+
+    fn call_trace(result: Option<TraceOutput>, error: Option<String>) -> TransactionTrace {
+        TransactionTrace {
+            action: Action::Call(CallAction::default()),
+            error,
+            result,
+            subtraces: 0,
+            trace_address: vec![],
+        }
+    }
+
+    fn simulate(output: Bytes, trace: TransactionTrace) -> Simulations {
+        let cols = Some(vec!["all".to_string()]);
+        let u256_types = [U256Type::Binary];
+        let schema = Datatype::Simulations
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &cols, None, None)
+            .unwrap();
+        let results = TraceResults {
+            output,
+            state_diff: None,
+            trace: vec![trace],
+            vm_trace: None,
+        };
+        let mut columns = Simulations::default();
+        let response = (1, Address::ZERO.to_vec(), vec![0x12, 0x34], results);
+        process_simulation(response, &mut columns, &schema);
+        columns
+    }
+
+    #[test]
+    fn test_process_simulation_success() {
+        let output = CallOutput { gas_used: 21000, output: Bytes::from(vec![1]) };
+        let columns =
+            simulate(Bytes::from(vec![1]), call_trace(Some(TraceOutput::Call(output)), None));
+        assert_eq!(columns.n_rows, 1);
+        assert_eq!(columns.success, vec![true]);
+        assert_eq!(columns.gas_used, vec![Some(21000)]);
+        assert_eq!(columns.output_data, vec![Some(vec![1])]);
+        assert_eq!(columns.revert_reason, vec![None]);
+    }
+
+    #[test]
+    fn test_process_simulation_revert() {
+        let output = Revert::from("insufficient balance").abi_encode();
+        let columns =
+            simulate(Bytes::from(output), call_trace(None, Some("Reverted".to_string())));
+        assert_eq!(columns.n_rows, 1);
+        assert_eq!(columns.success, vec![false]);
+        assert_eq!(columns.gas_used, vec![None]);
+        assert_eq!(columns.error, vec![Some("Reverted".to_string())]);
+        assert_eq!(columns.revert_reason, vec![Some("revert: insufficient balance".to_string())]);
+    }
+}
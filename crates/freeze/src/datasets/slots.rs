@@ -41,12 +41,19 @@ type BlockTxAddressOutput = (u32, Option<Vec<u8>>, Vec<u8>, Vec<u8>, Vec<u8>);
 
 #[async_trait::async_trait]
 impl CollectByBlock for Slots {
-    type Response = BlockTxAddressOutput;
+    type Response = Option<BlockTxAddressOutput>;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let address = request.address()?;
         let block_number = request.block_number()? as u32;
         let slot = request.slot()?;
+
+        // slot files pair each slot with a contract, skip the other address and slot combinations
+        if let Some(storage_slots) = &query.storage_slots {
+            if !storage_slots.contains(&(address.clone(), slot.clone())) {
+                return Ok(None)
+            }
+        }
         let output = source
             .get_storage_at(
                 Address::from_slice(&address),
@@ -54,12 +61,15 @@ impl CollectByBlock for Slots {
                 block_number.into(),
             )
             .await?;
-        Ok((block_number, None, address, slot, output.to_vec_u8()))
+        Ok(Some((block_number, None, address, slot, output.to_vec_u8())))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Slots)?;
-        process_slot(columns, response, schema)
+        match response {
+            Some(response) => process_slot(columns, response, schema),
+            None => Ok(()),
+        }
     }
 }
 
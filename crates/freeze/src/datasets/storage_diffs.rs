@@ -1,7 +1,7 @@
 use crate::*;
 use alloy::{
     primitives::{Address, B256},
-    rpc::types::trace::parity::{ChangedType, Delta, TraceResults},
+    rpc::types::trace::parity::{AccountDiff, ChangedType, Delta, TraceResults},
 };
 use polars::prelude::*;
 
@@ -36,14 +36,12 @@ impl CollectByBlock for StorageDiffs {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let schema = query.schemas.get_schema(&Datatype::StorageDiffs)?;
         let include_txs = schema.has_column("transaction_hash");
-        let (bn, txs, traces) =
-            source.trace_block_state_diffs(request.block_number()? as u32, include_txs).await?;
-        let trace_results: Vec<TraceResults> = traces.into_iter().map(|t| t.full_trace).collect();
-        Ok((bn, txs, trace_results))
+        let block_number = request.block_number()? as u32;
+        state_diffs::extract_block_state_diffs(block_number, include_txs, &source, &query).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_diffs(&response, columns, &query.schemas)
+        process_storage_diffs(&response, columns, query)
     }
 }
 
@@ -51,25 +49,32 @@ impl CollectByBlock for StorageDiffs {
 impl CollectByTransaction for StorageDiffs {
     type Response = BlockTxsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.trace_transaction_state_diffs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let transaction_hash = request.transaction_hash()?;
+        state_diffs::extract_transaction_state_diffs(transaction_hash, &source, &query).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_diffs(&response, columns, &query.schemas)
+        process_storage_diffs(&response, columns, query)
     }
 }
 
+/// process storage diffs of each transaction
+///
+/// storage of contracts created in a transaction is kept unless `exclude_creation_storage` is set
 pub(crate) fn process_storage_diffs(
     response: &BlockTxsTraces,
     columns: &mut StorageDiffs,
-    schemas: &Schemas,
+    query: &Query,
 ) -> R<()> {
-    let schema = schemas.get(&Datatype::StorageDiffs).ok_or(err("schema not provided"))?;
+    let schema = query.schemas.get(&Datatype::StorageDiffs).ok_or(err("schema not provided"))?;
     let (block_number, txs, traces) = response;
     for (index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
         if let Some(state_diffs) = &trace.state_diff {
             for (addr, diff) in state_diffs.iter() {
+                if query.exclude_creation_storage && is_created(diff) {
+                    continue
+                }
                 process_storage_diff(addr, &diff.storage, block_number, tx, index, columns, schema);
             }
         }
@@ -77,6 +82,15 @@ pub(crate) fn process_storage_diffs(
     Ok(())
 }
 
+/// whether account was created in the transaction, gaining code where it had none
+fn is_created(diff: &AccountDiff) -> bool {
+    match &diff.code {
+        Delta::Added(code) => !code.is_empty(),
+        Delta::Changed(ChangedType { from, to }) => from.is_empty() && !to.is_empty(),
+        Delta::Unchanged | Delta::Removed(_) => false,
+    }
+}
+
 pub(crate) fn process_storage_diff(
     addr: &Address,
     diff: &std::collections::BTreeMap<B256, Delta<B256>>,
@@ -93,6 +107,11 @@ pub(crate) fn process_storage_diff(
             Delta::Removed(value) => (*value, B256::ZERO),
             Delta::Changed(ChangedType { from, to }) => (*from, *to),
         };
+        // slots written and reset within the transaction, such as storage of contracts that are
+        // created and self-destructed in the same transaction, have no net diff
+        if from == to {
+            continue
+        }
         columns.n_rows += 1;
         store!(schema, columns, block_number, *block_number);
         store!(schema, columns, transaction_index, Some(transaction_index as u32));
@@ -103,3 +122,69 @@ pub(crate) fn process_storage_diff(
         store!(schema, columns, to_value, to.to_vec());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{primitives::Bytes, rpc::types::trace::parity::StateDiff};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_process_storage_diff() {
+        let columns = Some(vec!["slot".to_string(), "to_value".to_string()]);
+        let schema = Datatype::StorageDiffs
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &columns, None, None)
+            .unwrap();
+        let mut diff = AccountDiff {
+            balance: Delta::Unchanged,
+            code: Delta::Added(Bytes::from_static(&[0x60])),
+            nonce: Delta::Unchanged,
+            storage: BTreeMap::new(),
+        };
+        diff.storage.insert(B256::with_last_byte(1), Delta::Added(B256::with_last_byte(9)));
+        // created and self-destructed in the same transaction
+        diff.storage.insert(B256::with_last_byte(2), Delta::Added(B256::ZERO));
+        assert!(is_created(&diff));
+
+        let mut storage_diffs = StorageDiffs::default();
+        let storage = &diff.storage;
+        process_storage_diff(&Address::ZERO, storage, &None, &None, 0, &mut storage_diffs, &schema);
+        assert_eq!(storage_diffs.n_rows, 1);
+        assert_eq!(storage_diffs.slot, vec![B256::with_last_byte(1).to_vec()]);
+
+        diff.code = Delta::Changed(ChangedType { from: Bytes::new(), to: Bytes::new() });
+        assert!(!is_created(&diff));
+    }
+
+    #[test]
+    fn test_process_storage_diffs_of_created_contracts() {
+        let schema = Datatype::StorageDiffs
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &None, None, None)
+            .unwrap();
+        let mut diff = AccountDiff {
+            balance: Delta::Unchanged,
+            code: Delta::Added(Bytes::from_static(&[0x60])),
+            nonce: Delta::Unchanged,
+            storage: BTreeMap::new(),
+        };
+        diff.storage.insert(B256::with_last_byte(1), Delta::Added(B256::with_last_byte(9)));
+        let trace = TraceResults {
+            output: Bytes::new(),
+            state_diff: Some(StateDiff(BTreeMap::from([(Address::ZERO, diff)]))),
+            trace: vec![],
+            vm_trace: None,
+        };
+        let response = (Some(1), vec![Some(vec![0; 32])], vec![trace]);
+
+        // storage written by init code is kept by default
+        let mut query = Query::for_datatype(Datatype::StorageDiffs, schema, vec![]);
+        let mut storage_diffs = StorageDiffs::default();
+        process_storage_diffs(&response, &mut storage_diffs, &query).unwrap();
+        assert_eq!(storage_diffs.n_rows, 1);
+
+        query.exclude_creation_storage = true;
+        let mut storage_diffs = StorageDiffs::default();
+        process_storage_diffs(&response, &mut storage_diffs, &query).unwrap();
+        assert_eq!(storage_diffs.n_rows, 0);
+    }
+}
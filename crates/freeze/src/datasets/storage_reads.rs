@@ -1,7 +1,7 @@
 use crate::*;
 use alloy::{primitives::Address, rpc::types::trace::geth::AccountState};
 use polars::prelude::*;
-use std::collections::BTreeMap;
+use std::{borrow::Cow, collections::BTreeMap};
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::StorageReads)]
@@ -38,7 +38,7 @@ impl CollectByBlock for StorageReads {
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_reads(&response, columns, &query.schemas)
+        process_storage_reads(&response, columns, &query.schemas, &query.storage_slots)
     }
 }
 
@@ -55,7 +55,7 @@ impl CollectByTransaction for StorageReads {
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_storage_reads(&response, columns, &query.schemas)
+        process_storage_reads(&response, columns, &query.schemas, &query.storage_slots)
     }
 }
 
@@ -63,12 +63,23 @@ pub(crate) fn process_storage_reads(
     response: &BlockTxsTraces,
     columns: &mut StorageReads,
     schemas: &Schemas,
+    storage_slots: &Option<StorageSlots>,
 ) -> R<()> {
     let schema = schemas.get(&Datatype::StorageReads).ok_or(err("schema not provided"))?;
     let (block_number, txs, traces) = response;
     for (index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
         for (addr, account_state) in trace.iter() {
-            process_storage_read(addr, account_state, block_number, tx, index, columns, schema);
+            let account_state = match storage_slots {
+                Some(storage_slots) => {
+                    let mut account_state = account_state.clone();
+                    account_state.storage.retain(|slot, _| {
+                        storage_slots.contains(&(addr.to_vec(), slot.to_vec()))
+                    });
+                    Cow::Owned(account_state)
+                }
+                None => Cow::Borrowed(account_state),
+            };
+            process_storage_read(addr, &account_state, block_number, tx, index, columns, schema);
         }
     }
     Ok(())
@@ -50,21 +50,24 @@ impl CollectByBlock for TraceCalls {
     type Response = (u32, Vec<u8>, Vec<u8>, Vec<TransactionTrace>);
 
     async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let contract = request.contract()?;
+        let call_data = request.call_data()?;
         let traces: Vec<TransactionTrace> = source
             .trace_call2(
                 request.ethers_contract()?,
-                request.call_data()?,
+                call_data.clone(),
                 vec![TraceType::Trace],
                 Some(request.ethers_block_number()?),
             )
             .await?
             .trace;
-        Ok((request.block_number()? as u32, request.contract()?, request.call_data()?, traces))
+        Ok((block_number as u32, contract, call_data, traces))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::TraceCalls)?;
-        process_transaction_traces(response, columns, schema);
+        process_transaction_traces(response, columns, schema, query.max_trace_depth);
         Ok(())
     }
 }
@@ -77,9 +80,13 @@ fn process_transaction_traces(
     response: (u32, Vec<u8>, Vec<u8>, Vec<TransactionTrace>),
     columns: &mut TraceCalls,
     schema: &Table,
+    max_depth: Option<u32>,
 ) {
     let (block_number, contract, call_data, traces) = response;
     for (transaction_index, trace) in traces.iter().enumerate() {
+        if max_depth.is_some_and(|max_depth| trace.trace_address.len() > max_depth as usize) {
+            continue
+        }
         columns.n_rows += 1;
 
         process_action(&trace.action, columns, schema);
@@ -1,11 +1,16 @@
 use crate::*;
 use alloy::{
-    primitives::Address,
-    rpc::types::trace::parity::{
-        Action, ActionType, CallType, LocalizedTransactionTrace, RewardType, TraceOutput,
+    consensus::Transaction,
+    primitives::{Address, TxHash, U256},
+    rpc::types::{
+        trace::parity::{
+            Action, ActionType, CallType, LocalizedTransactionTrace, RewardType, TraceOutput,
+        },
+        BlockTransactionsKind,
     },
 };
 use polars::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Traces)]
@@ -25,6 +30,12 @@ pub struct Traces {
     result_output: Vec<Option<Vec<u8>>>,
     result_code: Vec<Option<Vec<u8>>>,
     result_address: Vec<Option<Vec<u8>>>,
+    /// gas supplied to the frame, the gas limit of the transaction for top-level frames like geth
+    /// call frames. null for rewards and selfdestructs
+    gas: Vec<Option<U256>>,
+    /// gas used by the frame and its subcalls, the gas used of the receipt for top-level frames
+    /// like geth call frames. null for failed subcalls
+    gas_used: Vec<Option<U256>>,
     trace_address: Vec<String>,
     subtraces: Vec<u32>,
     transaction_index: Vec<Option<u32>>,
@@ -33,10 +44,37 @@ pub struct Traces {
     block_hash: Vec<Vec<u8>>,
     error: Vec<Option<String>>,
     chain_id: Vec<u64>,
+    call_cols: CallColumns,
 }
 
 #[async_trait::async_trait]
 impl Dataset for Traces {
+    fn default_columns() -> Option<Vec<&'static str>> {
+        Some(vec![
+            "action_from",
+            "action_to",
+            "action_value",
+            "action_gas",
+            "action_input",
+            "action_call_type",
+            "action_init",
+            "action_reward_type",
+            "action_type",
+            "result_gas_used",
+            "result_output",
+            "result_code",
+            "result_address",
+            "trace_address",
+            "subtraces",
+            "transaction_index",
+            "transaction_hash",
+            "block_number",
+            "block_hash",
+            "error",
+            "chain_id",
+        ])
+    }
+
     fn optional_parameters() -> Vec<Dim> {
         vec![Dim::FromAddress, Dim::ToAddress]
     }
@@ -44,34 +82,220 @@ impl Dataset for Traces {
 
 #[async_trait::async_trait]
 impl CollectByBlock for Traces {
-    type Response = Vec<LocalizedTransactionTrace>;
+    type Response = (Vec<LocalizedTransactionTrace>, TransactionGas, HashSet<TxHash>);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let traces = source.trace_block(request.block_number()?).await?;
-        Ok(filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address))
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let traces = source.trace_block(block_number).await?;
+        let traces =
+            filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address);
+        let system_txs = system_tx_type_hashes(&traces, &source, &query).await?;
+        Ok((traces, block_transaction_gas(block_number, &source, &query).await?, system_txs))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_traces(&traces, columns, &query.schemas)
+        let (traces, tx_gas, system_txs) = response;
+        let traces = filter_query_traces(traces, query, &system_txs);
+        process_traces(&traces, &tx_gas, columns, &query.schemas)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for Traces {
-    type Response = Vec<LocalizedTransactionTrace>;
+    type Response = (Vec<LocalizedTransactionTrace>, TransactionGas, HashSet<TxHash>);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        let traces = source.trace_transaction(request.ethers_transaction_hash()?).await?;
-        Ok(filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address))
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let tx_hash = request.ethers_transaction_hash()?;
+        let traces = source.trace_transaction(tx_hash).await?;
+        let traces =
+            filter_traces_by_from_to_addresses(traces, &request.from_address, &request.to_address);
+        let system_txs = system_tx_type_hashes(&traces, &source, &query).await?;
+        Ok((traces, transaction_gas(tx_hash, &source, &query).await?, system_txs))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_traces(&traces, columns, &query.schemas)
+        let (traces, tx_gas, system_txs) = response;
+        let traces = filter_query_traces(traces, query, &system_txs);
+        process_traces(&traces, &tx_gas, columns, &query.schemas)
+    }
+}
+
+/// gas limit and gas used of the receipt of transactions, by transaction hash
+///
+/// parity traces exclude intrinsic gas and refunds from their top-level frames, so the gas columns
+/// of top-level frames are taken from their transaction to match geth call frames
+pub type TransactionGas = HashMap<TxHash, (U256, U256)>;
+
+/// the gas of transactions is only fetched if the gas columns of traces are collected
+fn needs_transaction_gas(query: &Query) -> bool {
+    query
+        .schemas
+        .get(&Datatype::Traces)
+        .is_some_and(|schema| schema.has_column("gas") || schema.has_column("gas_used"))
+}
+
+/// fetch the gas of the transactions of a block
+pub(crate) async fn block_transaction_gas(
+    block_number: u64,
+    source: &Source,
+    query: &Query,
+) -> R<TransactionGas> {
+    if !needs_transaction_gas(query) {
+        return Ok(TransactionGas::new())
+    }
+    let block = source
+        .get_block(block_number, BlockTransactionsKind::Full)
+        .await?
+        .ok_or(CollectError::CollectError("could not find block".to_string()))?;
+    let receipts = source.get_tx_receipts_in_block(&block).await?;
+    let gas_limits: HashMap<TxHash, u64> =
+        block.transactions.txns().map(|tx| (*tx.inner.tx_hash(), tx.inner.gas_limit())).collect();
+    receipts
+        .into_iter()
+        .map(|receipt| {
+            let gas_limit = gas_limits.get(&receipt.transaction_hash).ok_or_else(|| {
+                CollectError::CollectError("receipt of transaction not in block".to_string())
+            })?;
+            Ok((receipt.transaction_hash, (U256::from(*gas_limit), U256::from(receipt.gas_used))))
+        })
+        .collect()
+}
+
+/// fetch the gas of a transaction
+pub(crate) async fn transaction_gas(
+    tx_hash: TxHash,
+    source: &Source,
+    query: &Query,
+) -> R<TransactionGas> {
+    if !needs_transaction_gas(query) {
+        return Ok(TransactionGas::new())
+    }
+    let (tx, receipt) = futures::try_join!(
+        source.get_transaction_by_hash(tx_hash),
+        source.get_transaction_receipt(tx_hash)
+    )?;
+    match (tx, receipt) {
+        (Some(tx), Some(receipt)) => {
+            let gas = (U256::from(tx.inner.gas_limit()), U256::from(receipt.gas_used));
+            Ok(TransactionGas::from([(tx_hash, gas)]))
+        }
+        _ => Err(CollectError::CollectError("could not find transaction data".to_string())),
+    }
+}
+
+/// fetch the hashes of the traced transactions whose type is a system transaction type
+///
+/// traces do not include the type of their transaction, so the blocks of the traces are fetched
+pub(crate) async fn system_tx_type_hashes(
+    traces: &[LocalizedTransactionTrace],
+    source: &Source,
+    query: &Query,
+) -> R<HashSet<TxHash>> {
+    let tx_types = &query.chain_profile.system_tx_types;
+    if !query.exclude_system_transactions || tx_types.is_empty() {
+        return Ok(HashSet::new())
+    }
+    let block_numbers: BTreeSet<u64> =
+        traces.iter().filter_map(|trace| trace.block_number).collect();
+    let mut system_txs = HashSet::new();
+    for block_number in block_numbers {
+        system_txs.extend(source.get_block_tx_hashes_of_types(block_number, tx_types).await?);
+    }
+    Ok(system_txs)
+}
+
+/// remove the traces excluded by the options of query
+///
+/// `system_txs` are the transactions identified as system transactions by their type
+pub(crate) fn filter_query_traces(
+    traces: Vec<LocalizedTransactionTrace>,
+    query: &Query,
+    system_txs: &HashSet<TxHash>,
+) -> Vec<LocalizedTransactionTrace> {
+    let traces = if query.exclude_failed { filter_failed_traces(traces) } else { traces };
+    let traces = match &query.exclude_precompiles {
+        Some(ranges) => filter_precompile_traces(traces, ranges),
+        None => traces,
+    };
+    let traces = match query.max_trace_depth {
+        Some(max_depth) => filter_deep_traces(traces, max_depth),
+        None => traces,
+    };
+    if query.exclude_system_transactions {
+        filter_system_traces(traces, &query.chain_profile, system_txs)
+    } else {
+        traces
+    }
+}
+
+/// address ranges of precompiled contracts for a chain
+pub fn default_precompile_ranges(chain_id: u64) -> Vec<(u64, u64)> {
+    ChainProfile::new(chain_id, "").precompile_ranges
+}
+
+/// remove traces of system transactions, identified by the sender of their top-level call or
+/// by their type
+pub(crate) fn filter_system_traces(
+    traces: Vec<LocalizedTransactionTrace>,
+    profile: &ChainProfile,
+    system_txs: &HashSet<TxHash>,
+) -> Vec<LocalizedTransactionTrace> {
+    let mut system_transactions: HashSet<_> = traces
+        .iter()
+        .filter(|trace| trace.trace.trace_address.is_empty())
+        .filter(|trace| match &trace.trace.action {
+            Action::Call(action) => profile.is_system_sender(&action.from),
+            Action::Create(action) => profile.is_system_sender(&action.from),
+            _ => false,
+        })
+        .filter_map(|trace| trace.transaction_hash)
+        .collect();
+    system_transactions.extend(system_txs);
+    if system_transactions.is_empty() {
+        return traces
+    }
+    traces
+        .into_iter()
+        .filter(|trace| {
+            trace.transaction_hash.is_none_or(|hash| !system_transactions.contains(&hash))
+        })
+        .collect()
+}
+
+/// remove call traces whose target is a precompiled contract
+pub(crate) fn filter_precompile_traces(
+    traces: Vec<LocalizedTransactionTrace>,
+    ranges: &[(u64, u64)],
+) -> Vec<LocalizedTransactionTrace> {
+    traces
+        .into_iter()
+        .filter(|trace| match &trace.trace.action {
+            Action::Call(action) => !is_precompile(&action.to, ranges),
+            _ => true,
+        })
+        .collect()
+}
+
+/// remove traces nested deeper than max_depth, where depth is the length of the trace address
+pub(crate) fn filter_deep_traces(
+    traces: Vec<LocalizedTransactionTrace>,
+    max_depth: u32,
+) -> Vec<LocalizedTransactionTrace> {
+    traces
+        .into_iter()
+        .filter(|trace| trace.trace.trace_address.len() <= max_depth as usize)
+        .collect()
+}
+
+fn is_precompile(address: &Address, ranges: &[(u64, u64)]) -> bool {
+    let bytes = address.as_slice();
+    if bytes[..12].iter().any(|b| *b != 0) {
+        return false
     }
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&bytes[12..]);
+    let value = u64::from_be_bytes(low);
+    ranges.iter().any(|(start, end)| *start <= value && value <= *end)
 }
 
 pub(crate) fn filter_traces_by_from_to_addresses(
@@ -115,6 +339,7 @@ pub(crate) fn filter_traces_by_from_to_addresses(
 /// process block into columns
 pub(crate) fn process_traces(
     traces: &[LocalizedTransactionTrace],
+    tx_gas: &TransactionGas,
     columns: &mut Traces,
     schemas: &Schemas,
 ) -> R<()> {
@@ -122,7 +347,19 @@ pub(crate) fn process_traces(
     for trace in traces.iter() {
         columns.n_rows += 1;
         process_action(&trace.trace.action, columns, schema);
+        if let Some(decoder) = &schema.call_decoder {
+            let input = match &trace.trace.action {
+                Action::Call(action) => Some(action.input.as_ref()),
+                _ => None,
+            };
+            columns.call_cols.push(decoder, input);
+        }
         process_result(&trace.trace.result, columns, schema);
+        if schema.has_column("gas") || schema.has_column("gas_used") {
+            let (gas, gas_used) = frame_gas(trace, tx_gas)?;
+            store!(schema, columns, gas, gas);
+            store!(schema, columns, gas_used, gas_used);
+        }
         store!(schema, columns, action_type, action_type_to_string(&trace.trace.action.kind()));
         store!(
             schema,
@@ -224,6 +461,30 @@ fn process_result(result: &Option<TraceOutput>, columns: &mut Traces, schema: &T
     }
 }
 
+/// gas and gas used of a frame, as geth call frames report them
+fn frame_gas(
+    trace: &LocalizedTransactionTrace,
+    tx_gas: &TransactionGas,
+) -> R<(Option<U256>, Option<U256>)> {
+    if let (true, Some(tx_hash)) = (trace.trace.trace_address.is_empty(), trace.transaction_hash) {
+        let (gas, gas_used) = tx_gas.get(&tx_hash).ok_or_else(|| {
+            CollectError::CollectError(format!("gas of transaction {} not fetched", tx_hash))
+        })?;
+        return Ok((Some(*gas), Some(*gas_used)))
+    }
+    let gas = match &trace.trace.action {
+        Action::Call(action) => Some(U256::from(action.gas)),
+        Action::Create(action) => Some(U256::from(action.gas)),
+        Action::Selfdestruct(_) | Action::Reward(_) => None,
+    };
+    let gas_used = match &trace.trace.result {
+        Some(TraceOutput::Call(result)) => Some(U256::from(result.gas_used)),
+        Some(TraceOutput::Create(result)) => Some(U256::from(result.gas_used)),
+        None => None,
+    };
+    Ok((gas, gas_used))
+}
+
 pub(crate) fn reward_type_to_string(reward_type: &RewardType) -> String {
     match reward_type {
         RewardType::Block => "reward".to_string(),
@@ -287,3 +548,230 @@ pub(crate) fn filter_failed_traces(
 
     filtered
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::rpc::types::trace::{
+        geth::CallFrame,
+        parity::{CallAction, CallOutput, RewardAction, TransactionTrace},
+    };
+
+    fn trace(trace_address: Vec<usize>) -> LocalizedTransactionTrace {
+        LocalizedTransactionTrace {
+            trace: TransactionTrace {
+                action: Action::Call(CallAction::default()),
+                error: None,
+                result: None,
+                subtraces: 0,
+                trace_address,
+            },
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_position: None,
+        }
+    }
+
+    #[test]
+    fn test_is_precompile() {
+        let ranges = ChainProfile::new(42161, "arbitrum").precompile_ranges;
+        let at = |value: u64| Address::left_padding_from(&value.to_be_bytes());
+
+        // both ends of each range are precompiles, the addresses around them are not
+        assert!(!is_precompile(&at(0x0), &ranges));
+        assert!(is_precompile(&at(0x1), &ranges));
+        assert!(is_precompile(&at(0x11), &ranges));
+        assert!(!is_precompile(&at(0x12), &ranges));
+        assert!(!is_precompile(&at(0x63), &ranges));
+        assert!(is_precompile(&at(0x64), &ranges));
+        assert!(is_precompile(&at(0x100), &ranges));
+        assert!(!is_precompile(&at(0x101), &ranges));
+
+        // addresses with high bytes set never match, whatever their low bytes
+        let mut bytes = [0u8; 20];
+        bytes[0] = 1;
+        bytes[19] = 1;
+        assert!(!is_precompile(&Address::from(bytes), &ranges));
+    }
+
+    #[test]
+    fn test_filter_precompile_traces() {
+        let call = |to: u64| {
+            let action = CallAction {
+                to: Address::left_padding_from(&to.to_be_bytes()),
+                ..Default::default()
+            };
+            let mut call = trace(vec![]);
+            call.trace.action = Action::Call(action);
+            call
+        };
+        let mut create = trace(vec![]);
+        create.trace.action = Action::Create(Default::default());
+        let traces = vec![call(0x1), call(0x11), call(0x12), call(0x100), create];
+        let ranges = ChainProfile::new(1, "ethereum").precompile_ranges;
+        let kept: Vec<_> = filter_precompile_traces(traces, &ranges)
+            .into_iter()
+            .map(|trace| match trace.trace.action {
+                Action::Call(action) => Some(action.to),
+                _ => None,
+            })
+            .collect();
+        let at = |value: u64| Some(Address::left_padding_from(&value.to_be_bytes()));
+        assert_eq!(kept, vec![at(0x12), at(0x100), None]);
+    }
+
+    #[test]
+    fn test_filter_deep_traces() {
+        let traces = vec![trace(vec![]), trace(vec![0]), trace(vec![0, 0]), trace(vec![1])];
+        let depths = |traces: Vec<LocalizedTransactionTrace>| {
+            traces.iter().map(|t| t.trace.trace_address.len()).collect::<Vec<_>>()
+        };
+        assert_eq!(depths(filter_deep_traces(traces.clone(), 0)), vec![0]);
+        assert_eq!(depths(filter_deep_traces(traces.clone(), 1)), vec![0, 1, 1]);
+        assert_eq!(depths(filter_deep_traces(traces, 2)), vec![0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_filter_system_traces() {
+        let profile = ChainProfile::new(10, "optimism");
+        let localized = |tx: u8, from: Address, trace_address: Vec<usize>| {
+            let mut trace = trace(trace_address);
+            trace.trace.action = Action::Call(CallAction { from, ..Default::default() });
+            trace.transaction_hash = Some(TxHash::repeat_byte(tx));
+            trace
+        };
+        let depositor = profile.system_senders[0];
+        let traces = vec![
+            localized(1, depositor, vec![]),
+            localized(1, Address::ZERO, vec![0]),
+            localized(2, Address::repeat_byte(2), vec![]),
+            localized(3, Address::repeat_byte(3), vec![]),
+        ];
+        let kept = |system_txs: &HashSet<TxHash>| {
+            filter_system_traces(traces.clone(), &profile, system_txs)
+                .iter()
+                .filter_map(|trace| trace.transaction_hash)
+                .collect::<Vec<_>>()
+        };
+
+        // system transactions are identified by sender, and by type when their type is known
+        let expected = vec![TxHash::repeat_byte(2), TxHash::repeat_byte(3)];
+        assert_eq!(kept(&HashSet::new()), expected);
+        let deposits = HashSet::from([TxHash::repeat_byte(2)]);
+        assert_eq!(kept(&deposits), vec![TxHash::repeat_byte(3)]);
+    }
+
+    #[test]
+    fn test_trace_gas_columns() {
+        let columns = Some(vec!["gas".to_string(), "gas_used".to_string()]);
+        let schema = Datatype::Traces
+            .table_schema(
+                &[U256Type::Binary],
+                &ColumnEncoding::Binary,
+                &None,
+                &None,
+                &columns,
+                None,
+                None,
+            )
+            .unwrap();
+        assert!(!Datatype::Traces.default_columns().contains(&"gas"));
+
+        // top-level frames take the gas of their transaction, even when they fail
+        let tx_hash = TxHash::repeat_byte(1);
+        let mut call = trace(vec![]);
+        call.trace.action = Action::Call(CallAction { gas: 79_000, ..Default::default() });
+        let mut failed = trace(vec![0]);
+        failed.trace.error = Some("Reverted".to_string());
+        let mut top_level_failed = trace(vec![]);
+        top_level_failed.trace.error = Some("Reverted".to_string());
+        let mut reward = trace(vec![]);
+        let reward_type = RewardType::Block;
+        let action = RewardAction { author: Address::ZERO, reward_type, value: U256::ZERO };
+        reward.trace.action = Action::Reward(action);
+        let mut traces = vec![call, failed, top_level_failed, reward];
+        for (i, trace) in traces.iter_mut().enumerate() {
+            trace.block_number = Some(1);
+            trace.block_hash = Some(Default::default());
+            trace.transaction_hash = if i < 3 { Some(tx_hash) } else { None };
+        }
+        let tx_gas = TransactionGas::from([(tx_hash, (U256::from(100_000), U256::from(21_000)))]);
+
+        let mut columns = Traces::default();
+        let schemas = Schemas::from([(Datatype::Traces, schema)]);
+        process_traces(&traces, &tx_gas, &mut columns, &schemas).unwrap();
+        let tx_gas = Some(U256::from(100_000));
+        assert_eq!(columns.gas, vec![tx_gas, Some(U256::ZERO), tx_gas, None]);
+        let tx_gas_used = Some(U256::from(21_000));
+        assert_eq!(columns.gas_used, vec![tx_gas_used, None, tx_gas_used, None]);
+
+        // top-level frames of transactions without fetched gas are an error
+        let mut columns = Traces::default();
+        assert!(process_traces(&traces, &TransactionGas::new(), &mut columns, &schemas).is_err());
+    }
+
+    #[test]
+    fn test_trace_gas_columns_match_geth_calls() {
+        // a transaction with a gas limit of 100k, 21k intrinsic gas, and one subcall
+        let (gas_limit, gas_used) = (100_000, 26_000);
+        let subcall_gas = (50_000, 3_000);
+        let tx_hash = TxHash::repeat_byte(1);
+
+        let columns = Some(vec!["gas".to_string(), "gas_used".to_string()]);
+        let u256_types = [U256Type::U64];
+        let schema = |datatype: Datatype| {
+            datatype
+                .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &columns, None, None)
+                .unwrap()
+        };
+
+        let mut call = trace(vec![]);
+        call.trace.action = Action::Call(CallAction { gas: 79_000, ..Default::default() });
+        let output = CallOutput { gas_used: 5_000, output: Default::default() };
+        call.trace.result = Some(TraceOutput::Call(output));
+        call.trace.subtraces = 1;
+        let mut subcall = trace(vec![0]);
+        subcall.trace.action =
+            Action::Call(CallAction { gas: subcall_gas.0, ..Default::default() });
+        let output = CallOutput { gas_used: subcall_gas.1, output: Default::default() };
+        subcall.trace.result = Some(TraceOutput::Call(output));
+        let mut traces = vec![call, subcall];
+        for trace in traces.iter_mut() {
+            trace.block_number = Some(1);
+            trace.block_hash = Some(Default::default());
+            trace.transaction_hash = Some(tx_hash);
+        }
+        let gas = (U256::from(gas_limit), U256::from(gas_used));
+        let tx_gas = TransactionGas::from([(tx_hash, gas)]);
+        let schemas = Schemas::from([(Datatype::Traces, schema(Datatype::Traces))]);
+        let mut columns = Traces::default();
+        process_traces(&traces, &tx_gas, &mut columns, &schemas).unwrap();
+        let traces_df = columns.create_dfs(&schemas, 1).unwrap().remove(&Datatype::Traces).unwrap();
+
+        let frame = CallFrame {
+            gas: U256::from(gas_limit),
+            gas_used: U256::from(gas_used),
+            calls: vec![CallFrame {
+                gas: U256::from(subcall_gas.0),
+                gas_used: U256::from(subcall_gas.1),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let query = Query::for_datatype(Datatype::GethCalls, schema(Datatype::GethCalls), vec![]);
+        let mut columns = GethCalls::default();
+        let response = (Some(1), vec![Some(tx_hash.to_vec())], vec![Ok(frame)]);
+        geth_calls::process_geth_traces(response, &mut columns, &query).unwrap();
+        let geth_df =
+            columns.create_dfs(&query.schemas, 1).unwrap().remove(&Datatype::GethCalls).unwrap();
+
+        for column in ["gas_u64", "gas_used_u64"] {
+            let traces_column = traces_df.column(column).unwrap();
+            assert_eq!(traces_column, geth_df.column(column).unwrap());
+            assert_eq!(traces_column.dtype(), &DataType::UInt64);
+        }
+        let gas: Vec<_> = traces_df.column("gas_u64").unwrap().u64().unwrap().into_iter().collect();
+        assert_eq!(gas, vec![Some(gas_limit), Some(subcall_gas.0)]);
+    }
+}
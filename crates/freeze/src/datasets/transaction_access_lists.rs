@@ -0,0 +1,97 @@
+use crate::*;
+use alloy::{
+    consensus::Transaction as ConsensusTransaction,
+    rpc::types::{BlockTransactionsKind, Transaction},
+};
+use polars::prelude::*;
+
+/// columns for transaction access lists, one row per storage key
+#[cryo_to_df::to_df(Datatype::TransactionAccessLists)]
+#[derive(Default)]
+pub struct TransactionAccessLists {
+    n_rows: u64,
+    block_number: Vec<Option<u32>>,
+    transaction_index: Vec<Option<u64>>,
+    transaction_hash: Vec<Vec<u8>>,
+    address: Vec<Vec<u8>>,
+    storage_key: Vec<Option<Vec<u8>>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for TransactionAccessLists {
+    fn aliases() -> Vec<&'static str> {
+        vec!["access_lists"]
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "transaction_index"])
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for TransactionAccessLists {
+    type Response = Vec<Transaction>;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        let block = source
+            .get_block(request.block_number()?, BlockTransactionsKind::Full)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        let transactions = block
+            .transactions
+            .as_transactions()
+            .ok_or(CollectError::CollectError("block transactions not full".to_string()))?;
+        Ok(transactions.to_vec())
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::TransactionAccessLists)?;
+        for transaction in response.into_iter() {
+            process_access_list(transaction, columns, schema)
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for TransactionAccessLists {
+    type Response = Transaction;
+
+    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
+        source
+            .get_transaction_by_hash(request.ethers_transaction_hash()?)
+            .await?
+            .ok_or(CollectError::CollectError("transaction not found".to_string()))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::TransactionAccessLists)?;
+        process_access_list(response, columns, schema);
+        Ok(())
+    }
+}
+
+/// explode access list of transaction into rows, legacy transactions produce no rows
+fn process_access_list(tx: Transaction, columns: &mut TransactionAccessLists, schema: &Table) {
+    let access_list = match tx.inner.access_list() {
+        Some(access_list) => access_list.clone(),
+        None => return,
+    };
+    for item in access_list.0.into_iter() {
+        // addresses without storage keys are kept as a single row with a null storage key
+        let storage_keys: Vec<Option<Vec<u8>>> = if item.storage_keys.is_empty() {
+            vec![None]
+        } else {
+            item.storage_keys.iter().map(|key| Some(key.to_vec())).collect()
+        };
+        for storage_key in storage_keys.into_iter() {
+            columns.n_rows += 1;
+            store!(schema, columns, block_number, tx.block_number.map(|x| x as u32));
+            store!(schema, columns, transaction_index, tx.transaction_index);
+            store!(schema, columns, transaction_hash, tx.inner.tx_hash().to_vec());
+            store!(schema, columns, address, item.address.to_vec());
+            store!(schema, columns, storage_key, storage_key);
+        }
+    }
+}
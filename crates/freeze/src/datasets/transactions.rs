@@ -1,12 +1,13 @@
 use crate::*;
 use alloy::{
-    consensus::Transaction as ConsensusTransaction,
-    primitives::{Address, TxKind, U256},
+    consensus::{Transaction as ConsensusTransaction, TxEip4844Variant, TxEnvelope, TxType},
+    primitives::{Address, Bytes, TxKind, U256},
     rpc::types::{
         Block, BlockTransactions, BlockTransactionsKind, Transaction, TransactionReceipt,
     },
 };
 use polars::prelude::*;
+use std::collections::HashSet;
 
 /// columns for transactions
 #[cryo_to_df::to_df(Datatype::Transactions)]
@@ -24,6 +25,8 @@ pub struct Transactions {
     gas_limit: Vec<u64>,
     gas_used: Vec<Option<u64>>,
     gas_price: Vec<Option<u64>>,
+    cumulative_gas_used: Vec<Option<u64>>,
+    effective_gas_price: Vec<Option<u64>>,
     transaction_type: Vec<u32>,
     max_priority_fee_per_gas: Vec<Option<u64>>,
     max_fee_per_gas: Vec<Option<u64>>,
@@ -38,6 +41,7 @@ pub struct Transactions {
     r: Vec<Vec<u8>>,
     s: Vec<Vec<u8>>,
     v: Vec<bool>,
+    call_cols: CallColumns,
 }
 
 #[async_trait::async_trait]
@@ -78,17 +82,49 @@ impl Dataset for Transactions {
 /// tuple representing transaction and optional receipt
 pub type TransactionAndReceipt = (Transaction, Option<TransactionReceipt>);
 
+/// transactions of a block, or only its receipts if the block body was skipped
+#[derive(Clone)]
+pub enum BlockTransactionsResponse {
+    /// block with its transactions and their optional receipts
+    Block(Box<Block>, Vec<TransactionAndReceipt>),
+    /// receipts of a block whose body was not fetched
+    Receipts(Vec<TransactionReceipt>),
+}
+
 #[async_trait::async_trait]
 impl CollectByBlock for Transactions {
-    type Response = (Block, Vec<TransactionAndReceipt>, bool);
+    type Response = (BlockTransactionsResponse, bool);
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
-        let block = source
-            .get_block(request.block_number()?, BlockTransactionsKind::Full)
-            .await?
-            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+        // receipts are enough if no column or filter reads the block body, falling back to the
+        // block body if the node does not serve block receipts
+        if skips_block_body(&query, source.receipts_source) {
+            if let Ok(Some(receipts)) = source.get_block_receipts(request.block_number()?).await {
+                let response = BlockTransactionsResponse::Receipts(receipts);
+                return Ok((response, query.exclude_failed))
+            }
+        }
+
+        // system transactions of types unknown to ethereum are dropped before decoding
+        let system_tx_types = match query.exclude_system_transactions {
+            true => query.chain_profile.system_tx_types.as_slice(),
+            false => &[],
+        };
+        let block = if system_tx_types.is_empty() {
+            source.get_block(request.block_number()?, BlockTransactionsKind::Full).await?
+        } else {
+            source.get_block_without_tx_types(request.block_number()?, system_tx_types).await?
+        };
+        let mut block = block.ok_or(CollectError::CollectError("block not found".to_string()))?;
         let schema = query.schemas.get_schema(&Datatype::Transactions)?;
 
+        // 0. drop inputs of transactions if no column or decoder reads them
+        if !needs_input(schema) {
+            if let BlockTransactions::Full(transactions) = &mut block.transactions {
+                transactions.iter_mut().for_each(clear_input);
+            }
+        }
+
         // 1. collect transactions and filter them if optional parameters are supplied
         // filter by from_address
         let from_filter: Box<dyn Fn(&Transaction) -> bool + Send> =
@@ -122,10 +158,22 @@ impl CollectByBlock for Transactions {
         // if transactions are filtered fetch by set of transaction hashes, else fetch all receipts
         // in block
         let receipts: Vec<Option<_>> =
-            if schema.has_column("gas_used") | schema.has_column("success") {
+            if needs_receipts(schema) |
+                schema.has_column("success") |
+                query.min_priority_fee.is_some()
+            {
                 // receipts required
-                let receipts = if request.from_address.is_some() || request.to_address.is_some() {
+                let filtered = request.from_address.is_some() ||
+                    request.to_address.is_some() ||
+                    !system_tx_types.is_empty();
+                let receipts = if filtered && source.receipts_source != ReceiptsSource::Block {
                     source.get_tx_receipts(BlockTransactions::Full(transactions.clone())).await?
+                } else if filtered {
+                    let tx_hashes: HashSet<_> =
+                        transactions.iter().map(|tx| *tx.inner.tx_hash()).collect();
+                    let mut receipts = source.get_tx_receipts_in_block(&block).await?;
+                    receipts.retain(|receipt| tx_hashes.contains(&receipt.transaction_hash));
+                    receipts
                 } else {
                     source.get_tx_receipts_in_block(&block).await?
                 };
@@ -135,13 +183,31 @@ impl CollectByBlock for Transactions {
             };
 
         let transactions_with_receips = transactions.into_iter().zip(receipts).collect();
-        Ok((block, transactions_with_receips, query.exclude_failed))
+        let response = BlockTransactionsResponse::Block(Box::new(block), transactions_with_receips);
+        Ok((response, query.exclude_failed))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Transactions)?;
-        let (block, transactions_with_receipts, exclude_failed) = response;
+        let (response, exclude_failed) = response;
+        let (block, transactions_with_receipts) = match response {
+            BlockTransactionsResponse::Block(block, transactions) => (*block, transactions),
+            BlockTransactionsResponse::Receipts(receipts) => {
+                for receipt in receipts.into_iter() {
+                    process_receipt(receipt, columns, schema, exclude_failed);
+                }
+                return Ok(())
+            }
+        };
         for (tx, receipt) in transactions_with_receipts.into_iter() {
+            if is_excluded_system_transaction(&tx, query) || !matches_tx_type(&tx, query) {
+                continue
+            }
+            let base_fee = block.header.base_fee_per_gas;
+            let effective_gas_price = receipt.as_ref().map(|receipt| receipt.effective_gas_price);
+            if !meets_min_priority_fee(&tx, effective_gas_price, base_fee, query) {
+                continue
+            }
             let gas_price = get_gas_price(&block, &tx);
             process_transaction(
                 tx,
@@ -164,11 +230,14 @@ impl CollectByTransaction for Transactions {
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let tx_hash = request.ethers_transaction_hash()?;
         let schema = query.schemas.get_schema(&Datatype::Transactions)?;
-        let transaction = source
+        let mut transaction = source
             .get_transaction_by_hash(tx_hash)
             .await?
             .ok_or(CollectError::CollectError("transaction not found".to_string()))?;
-        let receipt = if schema.has_column("gas_used") {
+        if !needs_input(schema) {
+            clear_input(&mut transaction);
+        }
+        let receipt = if needs_receipts(schema) || query.min_priority_fee.is_some() {
             source.get_transaction_receipt(tx_hash).await?
         } else {
             None
@@ -191,6 +260,16 @@ impl CollectByTransaction for Transactions {
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let schema = query.schemas.get_schema(&Datatype::Transactions)?;
         let ((transaction, receipt), block, exclude_failed, timestamp) = response;
+        if is_excluded_system_transaction(&transaction, query) ||
+            !matches_tx_type(&transaction, query)
+        {
+            return Ok(())
+        }
+        let base_fee = block.header.base_fee_per_gas;
+        let effective_gas_price = receipt.as_ref().map(|receipt| receipt.effective_gas_price);
+        if !meets_min_priority_fee(&transaction, effective_gas_price, base_fee, query) {
+            return Ok(())
+        }
         let gas_price = get_gas_price(&block, &transaction);
         process_transaction(
             transaction,
@@ -205,6 +284,43 @@ impl CollectByTransaction for Transactions {
     }
 }
 
+fn is_excluded_system_transaction(tx: &Transaction, query: &Query) -> bool {
+    query.exclude_system_transactions && query.chain_profile.is_system_sender(&tx.from)
+}
+
+/// whether transaction is of one of the types of query, if any
+///
+/// transactions reported without a type are deserialized as legacy, so they match type 0
+fn matches_tx_type(tx: &Transaction, query: &Query) -> bool {
+    match &query.tx_types {
+        Some(tx_types) => tx_types.contains(&(tx.inner.tx_type() as u8)),
+        None => true,
+    }
+}
+
+/// whether transaction pays at least the minimum priority fee of query, if any
+///
+/// legacy transactions, transactions without a receipt, and transactions of blocks without a
+/// base fee are excluded
+fn meets_min_priority_fee(
+    tx: &Transaction,
+    effective_gas_price: Option<u128>,
+    base_fee: Option<u64>,
+    query: &Query,
+) -> bool {
+    let Some(min_priority_fee) = query.min_priority_fee else { return true };
+    if tx.inner.tx_type() == TxType::Legacy {
+        return false
+    }
+    match (effective_gas_price, base_fee) {
+        (Some(effective_gas_price), Some(base_fee)) => {
+            let priority_fee = effective_gas_price.saturating_sub(base_fee as u128);
+            priority_fee >= min_priority_fee as u128
+        }
+        _ => false,
+    }
+}
+
 pub(crate) fn process_transaction(
     tx: Transaction,
     receipt: Option<TransactionReceipt>,
@@ -241,6 +357,13 @@ pub(crate) fn process_transaction(
     store!(schema, columns, nonce, tx.inner.nonce());
     store!(schema, columns, value, tx.inner.value());
     store!(schema, columns, input, tx.inner.input().to_vec());
+    if let Some(decoder) = &schema.call_decoder {
+        let input = match tx.inner.kind() {
+            TxKind::Create => None,
+            TxKind::Call(_) => Some(tx.inner.input().as_ref()),
+        };
+        columns.call_cols.push(decoder, input);
+    }
     store!(schema, columns, gas_limit, tx.inner.gas_limit());
     store!(schema, columns, success, success);
     if schema.has_column("n_input_bytes") |
@@ -258,6 +381,19 @@ pub(crate) fn process_transaction(
     store!(schema, columns, gas_used, receipt.as_ref().map(|r| r.gas_used as u64));
     // store!(schema, columns, gas_price, Some(receipt.unwrap().effective_gas_price as u64));
     store!(schema, columns, gas_price, gas_price);
+    store!(
+        schema,
+        columns,
+        cumulative_gas_used,
+        receipt.as_ref().map(|r| r.inner.cumulative_gas_used() as u64)
+    );
+    // clients already normalize effective gas price for legacy transactions
+    store!(
+        schema,
+        columns,
+        effective_gas_price,
+        receipt.as_ref().map(|r| r.effective_gas_price as u64)
+    );
     store!(schema, columns, transaction_type, tx.inner.tx_type() as u32);
     store!(schema, columns, max_fee_per_gas, get_max_fee_per_gas(&tx));
     store!(
@@ -276,6 +412,95 @@ pub(crate) fn process_transaction(
     Ok(())
 }
 
+/// store the columns of a transaction that are read from its receipt, without its block body
+fn process_receipt(
+    receipt: TransactionReceipt,
+    columns: &mut Transactions,
+    schema: &Table,
+    exclude_failed: bool,
+) {
+    let success = receipt.status();
+    if exclude_failed & !success {
+        return
+    }
+
+    columns.n_rows += 1;
+    store!(schema, columns, block_number, receipt.block_number.map(|x| x as u32));
+    store!(schema, columns, transaction_hash, receipt.transaction_hash.to_vec());
+    store!(schema, columns, gas_used, Some(receipt.gas_used as u64));
+    store!(schema, columns, cumulative_gas_used, Some(receipt.inner.cumulative_gas_used() as u64));
+    store!(schema, columns, effective_gas_price, Some(receipt.effective_gas_price as u64));
+    store!(schema, columns, success, success);
+    store!(schema, columns, block_hash, receipt.block_hash.unwrap_or_default().to_vec());
+}
+
+/// columns that are read from receipts alone, columns such as transaction_index and
+/// from_address are read from the block body
+const RECEIPT_COLUMNS: [&str; 8] = [
+    "block_number",
+    "block_hash",
+    "transaction_hash",
+    "gas_used",
+    "cumulative_gas_used",
+    "effective_gas_price",
+    "success",
+    "chain_id",
+];
+
+/// whether every selected column is read from receipts, with no decoder reading inputs
+fn reads_only_receipts(schema: &Table) -> bool {
+    schema.call_decoder.is_none() &&
+        schema.columns().iter().all(|column| RECEIPT_COLUMNS.contains(column))
+}
+
+/// whether transactions are collected from block receipts alone, skipping block bodies
+///
+/// requires --transactions-receipts-only, and that no selected column, filter, or dataset
+/// collected alongside transactions reads the block body
+pub(crate) fn skips_block_body(query: &Query, receipts_source: ReceiptsSource) -> bool {
+    let Some(schema) = query.schemas.get(&Datatype::Transactions) else { return false };
+    query.transactions_receipts_only &&
+        receipts_source != ReceiptsSource::Transaction &&
+        reads_only_receipts(schema) &&
+        !query.schemas.contains_key(&Datatype::Blocks) &&
+        !query.exclude_system_transactions &&
+        query.tx_types.is_none() &&
+        query.min_priority_fee.is_none() &&
+        query.partitions.iter().all(|partition| {
+            partition.from_addresses.is_none() && partition.to_addresses.is_none()
+        })
+}
+
+/// whether any selected column is read from the receipt
+pub(crate) fn needs_receipts(schema: &Table) -> bool {
+    ["gas_used", "cumulative_gas_used", "effective_gas_price"]
+        .iter()
+        .any(|column| schema.has_column(column))
+}
+
+/// whether any selected column or the call decoder reads transaction inputs
+pub(crate) fn needs_input(schema: &Table) -> bool {
+    schema.call_decoder.is_some() ||
+        ["input", "n_input_bytes", "n_input_zero_bytes", "n_input_nonzero_bytes", "n_rlp_bytes"]
+            .iter()
+            .any(|column| schema.has_column(column))
+}
+
+/// drop the input of a transaction, keeping its hash
+fn clear_input(tx: &mut Transaction) {
+    match &mut tx.inner {
+        TxEnvelope::Legacy(signed) => signed.tx_mut().input = Bytes::new(),
+        TxEnvelope::Eip2930(signed) => signed.tx_mut().input = Bytes::new(),
+        TxEnvelope::Eip1559(signed) => signed.tx_mut().input = Bytes::new(),
+        TxEnvelope::Eip4844(signed) => match signed.tx_mut() {
+            TxEip4844Variant::TxEip4844(tx) => tx.input = Bytes::new(),
+            TxEip4844Variant::TxEip4844WithSidecar(tx) => tx.tx.input = Bytes::new(),
+        },
+        TxEnvelope::Eip7702(signed) => signed.tx_mut().input = Bytes::new(),
+        _ => {}
+    }
+}
+
 fn get_max_fee_per_gas(tx: &Transaction) -> Option<u64> {
     match &tx.inner {
         alloy::consensus::TxEnvelope::Legacy(_) => None,
@@ -310,9 +535,98 @@ fn tx_success(tx: &Transaction, receipt: &Option<TransactionReceipt>) -> R<bool>
         if let Some(r) = receipt {
             Ok(r.gas_used == 0)
         } else {
-            return Err(err("could not determine status of transaction"))
+            Err(err("could not determine status of transaction"))
         }
     } else {
-        return Err(err("could not determine status of transaction"))
+        Err(err("could not determine status of transaction"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        consensus::{Signed, TxEip1559, TxLegacy},
+        primitives::{PrimitiveSignature, B256},
+    };
+
+    #[test]
+    fn test_clear_input() {
+        let tx = TxEip1559 { input: Bytes::from(vec![1; 100]), ..Default::default() };
+        let signature = PrimitiveSignature::test_signature();
+        let signed = Signed::new_unchecked(tx, signature, B256::repeat_byte(1));
+        let mut tx = Transaction {
+            inner: TxEnvelope::Eip1559(signed),
+            block_hash: None,
+            block_number: Some(1),
+            transaction_index: Some(0),
+            effective_gas_price: None,
+            from: Address::ZERO,
+        };
+        clear_input(&mut tx);
+        assert!(tx.inner.input().is_empty());
+        assert_eq!(*tx.inner.tx_hash(), B256::repeat_byte(1));
+
+        // inputs are kept for columns and decoders that read them
+        let schema = |columns: &[&str]| {
+            let columns = Some(columns.iter().map(|column| column.to_string()).collect());
+            Datatype::Transactions
+                .table_schema(&[], &ColumnEncoding::Hex, &None, &None, &columns, None, None)
+                .unwrap()
+        };
+        assert!(!needs_input(&schema(&["transaction_hash"])));
+        assert!(needs_input(&schema(&["transaction_hash", "n_rlp_bytes"])));
+        let mut decoded = schema(&["transaction_hash"]);
+        decoded.call_decoder = Some(CallDecoder::new(vec![]));
+        assert!(needs_input(&decoded));
+
+        // receipts alone are read only if every column comes from them
+        assert!(reads_only_receipts(&schema(&["transaction_hash", "gas_used", "success"])));
+        assert!(!reads_only_receipts(&schema(&["transaction_hash", "transaction_index"])));
+        assert!(!reads_only_receipts(&schema(&["gas_used", "from_address"])));
+        assert!(!reads_only_receipts(&decoded));
+    }
+
+    #[test]
+    fn test_meets_min_priority_fee() {
+        let signature = PrimitiveSignature::test_signature();
+        let transaction = |inner: TxEnvelope| Transaction {
+            inner,
+            block_hash: None,
+            block_number: Some(1),
+            transaction_index: Some(0),
+            effective_gas_price: None,
+            from: Address::ZERO,
+        };
+        let hash = B256::repeat_byte(1);
+        let tx = transaction(TxEnvelope::Eip1559(Signed::new_unchecked(
+            TxEip1559::default(),
+            signature,
+            hash,
+        )));
+        let legacy = transaction(TxEnvelope::Legacy(Signed::new_unchecked(
+            TxLegacy::default(),
+            signature,
+            hash,
+        )));
+        let u256_types = [U256Type::Binary];
+        let schema = Datatype::Transactions
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &None, None, None)
+            .unwrap();
+        let mut query = Query::for_datatype(Datatype::Transactions, schema, vec![]);
+
+        // every transaction is kept without a minimum
+        assert!(meets_min_priority_fee(&legacy, None, None, &query));
+
+        // priority fee is the effective gas price minus the base fee
+        query.min_priority_fee = Some(2);
+        assert!(meets_min_priority_fee(&tx, Some(12), Some(10), &query));
+        assert!(meets_min_priority_fee(&tx, Some(15), Some(10), &query));
+        assert!(!meets_min_priority_fee(&tx, Some(11), Some(10), &query));
+
+        // legacy transactions and unknown fees are excluded
+        assert!(!meets_min_priority_fee(&legacy, Some(15), Some(10), &query));
+        assert!(!meets_min_priority_fee(&tx, None, Some(10), &query));
+        assert!(!meets_min_priority_fee(&tx, Some(15), None, &query));
     }
 }
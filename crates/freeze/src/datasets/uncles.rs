@@ -0,0 +1,113 @@
+use crate::*;
+use alloy::{
+    primitives::B256,
+    rpc::types::{Block, BlockTransactionsKind},
+};
+use polars::prelude::*;
+
+/// columns for uncles, one row per ommer of a block
+#[cryo_to_df::to_df(Datatype::Uncles)]
+#[derive(Default)]
+pub struct Uncles {
+    n_rows: u64,
+    block_number: Vec<u32>,
+    uncle_index: Vec<u32>,
+    uncle_hash: Vec<Vec<u8>>,
+    uncle_number: Vec<Option<u32>>,
+    uncle_miner: Vec<Option<Vec<u8>>>,
+    chain_id: Vec<u64>,
+}
+
+#[async_trait::async_trait]
+impl Dataset for Uncles {
+    fn aliases() -> Vec<&'static str> {
+        vec!["ommers"]
+    }
+
+    fn default_sort() -> Option<Vec<&'static str>> {
+        Some(vec!["block_number", "uncle_index"])
+    }
+}
+
+type UncleResponse = (u32, Vec<B256>, Vec<Option<Block>>);
+
+#[async_trait::async_trait]
+impl CollectByBlock for Uncles {
+    type Response = UncleResponse;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let block = source
+            .get_block(block_number, BlockTransactionsKind::Hashes)
+            .await?
+            .ok_or(CollectError::CollectError("block not found".to_string()))?;
+
+        // uncle headers are only fetched when their columns are collected
+        let schema = query.schemas.get_schema(&Datatype::Uncles)?;
+        let mut headers = Vec::new();
+        if schema.has_column("uncle_number") || schema.has_column("uncle_miner") {
+            for index in 0..block.uncles.len() {
+                headers.push(source.get_uncle(block_number, index as u64).await?);
+            }
+        }
+        Ok((block_number as u32, block.uncles, headers))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        let schema = query.schemas.get_schema(&Datatype::Uncles)?;
+        process_uncles(response, columns, schema);
+        Ok(())
+    }
+}
+
+impl CollectByTransaction for Uncles {
+    type Response = ();
+}
+
+/// explode ommers of block into rows, post-merge blocks have no ommers and produce no rows
+fn process_uncles(response: UncleResponse, columns: &mut Uncles, schema: &Table) {
+    let (block_number, uncles, headers) = response;
+    for (index, uncle_hash) in uncles.into_iter().enumerate() {
+        let header = headers.get(index).and_then(|header| header.as_ref());
+        columns.n_rows += 1;
+        store!(schema, columns, block_number, block_number);
+        store!(schema, columns, uncle_index, index as u32);
+        store!(schema, columns, uncle_hash, uncle_hash.to_vec());
+        store!(schema, columns, uncle_number, header.map(|h| h.header.number as u32));
+        store!(schema, columns, uncle_miner, header.map(|h| h.header.beneficiary.to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_process_uncles() {
+        let schema = Datatype::Uncles
+            .table_schema(&[], &ColumnEncoding::Binary, &None, &None, &None, None, None)
+            .unwrap();
+        let mut header: Block = Block::default();
+        header.header.inner.number = 99;
+        header.header.inner.beneficiary = Address::repeat_byte(7);
+
+        // each ommer is a row, with header columns null where no header was fetched
+        let mut uncles = Uncles::default();
+        let hashes = vec![B256::repeat_byte(1), B256::repeat_byte(2)];
+        process_uncles((100, hashes, vec![Some(header)]), &mut uncles, &schema);
+        process_uncles((101, vec![], vec![]), &mut uncles, &schema);
+        let schemas = HashMap::from([(Datatype::Uncles, schema)]);
+        let df = uncles.create_dfs(&schemas, 1).unwrap().remove(&Datatype::Uncles).unwrap();
+        assert_eq!(df.height(), 2);
+        let column = |name: &str| df.column(name).unwrap().clone();
+        assert_eq!(column("block_number").u32().unwrap().to_vec(), vec![Some(100), Some(100)]);
+        assert_eq!(column("uncle_index").u32().unwrap().to_vec(), vec![Some(0), Some(1)]);
+        assert_eq!(column("uncle_hash").binary().unwrap().get(1).unwrap(), [2; 32]);
+        assert_eq!(column("uncle_number").u32().unwrap().to_vec(), vec![Some(99), None]);
+        let miners = column("uncle_miner");
+        assert_eq!(miners.binary().unwrap().get(0).unwrap(), [7; 20]);
+        assert_eq!(miners.binary().unwrap().get(1), None);
+    }
+}
@@ -66,7 +66,7 @@ impl CollectByTransaction for VmTraces {
     }
 }
 
-fn process_vm_traces(
+pub(crate) fn process_vm_traces(
     response: (Option<u32>, Option<Vec<u8>>, Vec<TraceResults>),
     columns: &mut VmTraces,
     schemas: &Schemas,
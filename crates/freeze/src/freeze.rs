@@ -1,15 +1,27 @@
 use crate::{
-    collect_partition, dataframes, err, reports, summaries, CollectError, Datatype, ExecutionEnv,
-    FileOutput, FreezeSummary, MetaDatatype, Partition, Query, Source,
+    address_appearances, collect_partition_appending, dataframes, err, reports, summaries,
+    BlockChunk, CollectError, Datatype, Dim, ExecutionEnv, ExistingColumns, FileOutput,
+    FreezeSummary, MetaDatatype, OutputFile, Partition, Query, SchemaFunctions, Source,
 };
 use chrono::{DateTime, Local};
+use dataframes::SortableDataFrame;
 use futures::{stream::FuturesUnordered, StreamExt};
+use polars::prelude::{DataFrame, ParquetReader, SerReader};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     path::PathBuf,
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use tokio::sync::Semaphore;
+use tokio::{
+    sync::{Notify, Semaphore},
+    task::JoinHandle,
+};
+
+const CHUNK_RETRY_BACKOFF_MS: u64 = 1000;
+
+/// growth limit of an adaptive chunk, relative to the largest chunk collected so far
+const ADAPTIVE_CHUNK_MAX_GROWTH: u64 = 4;
 
 type PartitionPayload = (
     Partition,
@@ -20,8 +32,174 @@ type PartitionPayload = (
     FileOutput,
     ExecutionEnv,
     Option<std::sync::Arc<Semaphore>>,
+    Arc<HashMap<Datatype, ExistingColumns>>,
 );
 
+type ChunkOutput = Option<(HashMap<PathBuf, OutputFile>, u32)>;
+
+/// whether a chunk can start under a budget
+#[derive(Debug, PartialEq)]
+enum Admission {
+    Admitted,
+    Exhausted,
+    Wait,
+}
+
+/// output counted against a budget, and the chunks in flight
+///
+/// no chunk is measured until a chunk finishes, so the first chunk runs alone as a probe. after
+/// that each chunk in flight is expected to produce as much as the largest chunk so far
+#[derive(Default)]
+struct BudgetState {
+    written: u64,
+    n_in_flight: u64,
+    n_measured: u64,
+    max_chunk: u64,
+}
+
+impl BudgetState {
+    /// admit a chunk unless the chunks in flight are expected to reach max
+    ///
+    /// the budget is only exhausted by output actually written, chunks in flight can produce less
+    /// than expected so a chunk waits for them rather than giving up
+    fn admit(&mut self, max: u64) -> Admission {
+        if self.written >= max {
+            return Admission::Exhausted
+        }
+        if self.n_measured == 0 && self.n_in_flight > 0 {
+            return Admission::Wait
+        }
+        let expected = self.written.saturating_add(self.n_in_flight * self.max_chunk);
+        if expected >= max {
+            return Admission::Wait
+        }
+        self.n_in_flight += 1;
+        Admission::Admitted
+    }
+
+    /// release a chunk, measuring it if it produced output or was skipped empty
+    fn release(&mut self, n: Option<u64>) {
+        self.n_in_flight = self.n_in_flight.saturating_sub(1);
+        if let Some(n) = n {
+            self.n_measured += 1;
+            self.written += n;
+            self.max_chunk = self.max_chunk.max(n);
+        }
+    }
+}
+
+/// budget of a run, which chunks are admitted against whether or not chunks share a semaphore
+struct Budget {
+    max: u64,
+    state: Mutex<BudgetState>,
+    released: Notify,
+}
+
+impl Budget {
+    fn new(max: u64) -> Arc<Self> {
+        Arc::new(Budget { max, state: Mutex::new(BudgetState::default()), released: Notify::new() })
+    }
+
+    fn admit(&self) -> Result<Admission, CollectError> {
+        Ok(self.state.lock().map_err(|_| err("could not lock budget"))?.admit(self.max))
+    }
+
+    /// wait until a chunk is admitted, None if the budget is exhausted
+    async fn reserve(self: &Arc<Self>) -> Result<Option<BudgetReservation>, CollectError> {
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            match self.admit()? {
+                Admission::Admitted => {
+                    return Ok(Some(BudgetReservation { budget: self.clone(), n: None }))
+                }
+                Admission::Exhausted => return Ok(None),
+                Admission::Wait => released.await,
+            }
+        }
+    }
+}
+
+/// admission of a chunk against a budget, released when dropped
+///
+/// chunks that fail are released without a measurement, so that a failed probe is replaced
+struct BudgetReservation {
+    budget: Arc<Budget>,
+    n: Option<u64>,
+}
+
+impl Drop for BudgetReservation {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.budget.state.lock() {
+            state.release(self.n);
+        }
+        self.budget.released.notify_waiters();
+    }
+}
+
+/// block chunks of a datatype, sized to target a number of bytes per output file
+///
+/// chunks are cut in order from segments of uncollected blocks, so they never overlap or reorder
+/// blocks. chunks have the initial size until a chunk is collected, and afterwards are sized by
+/// the bytes per block of the collected chunks
+#[derive(Clone, Debug)]
+struct AdaptiveChunks {
+    segments: VecDeque<(u64, u64)>,
+    target_bytes: u64,
+    initial_size: u64,
+    max_size: u64,
+    n_blocks: u64,
+    n_bytes: u64,
+}
+
+impl AdaptiveChunks {
+    fn new(segments: VecDeque<(u64, u64)>, initial_size: u64, target_bytes: u64) -> Self {
+        AdaptiveChunks {
+            segments,
+            target_bytes,
+            initial_size,
+            max_size: 0,
+            n_blocks: 0,
+            n_bytes: 0,
+        }
+    }
+
+    /// block range of the next chunk, None once all blocks are planned
+    fn next_range(&mut self) -> Option<(u64, u64)> {
+        let (start, end) = self.segments.pop_front()?;
+        let chunk_end = start.saturating_add(self.chunk_size() - 1).min(end);
+        if chunk_end < end {
+            self.segments.push_front((chunk_end + 1, end));
+        }
+        Some((start, chunk_end))
+    }
+
+    /// number of blocks expected to produce target_bytes, at least 1
+    fn chunk_size(&self) -> u64 {
+        if self.n_blocks == 0 {
+            return self.initial_size.max(1)
+        }
+        let max_size = self.max_size.saturating_mul(ADAPTIVE_CHUNK_MAX_GROWTH) as u128;
+        let estimate = (self.target_bytes as u128 * self.n_blocks as u128)
+            .checked_div(self.n_bytes as u128)
+            .unwrap_or(max_size);
+        estimate.clamp(1, max_size.max(1)) as u64
+    }
+
+    /// record the number of blocks and largest output file of a collected chunk
+    fn record(&mut self, n_blocks: u64, n_bytes: u64) {
+        self.n_blocks += n_blocks;
+        self.n_bytes += n_bytes;
+        self.max_size = self.max_size.max(n_blocks);
+    }
+
+    /// stop planning chunks
+    fn stop(&mut self) {
+        self.segments.clear()
+    }
+}
+
 /// collect data and output as files
 pub async fn freeze(
     query: &Query,
@@ -32,6 +210,12 @@ pub async fn freeze(
     // check validity of query
     query.is_valid()?;
 
+    // plan adaptive chunks of each datatype
+    let adaptive_chunks = match env.chunk_size_bytes {
+        Some(chunk_size_bytes) => Some(get_adaptive_chunks(query, source, sink, chunk_size_bytes)?),
+        None => None,
+    };
+
     // get partitions
     let (payloads, skipping) = get_payloads(query, source, sink, env)?;
 
@@ -47,24 +231,42 @@ pub async fn freeze(
 
     // check if empty
     if payloads.is_empty() {
-        let results = FreezeSummary { skipped: skipping, ..Default::default() };
+        let mut results = FreezeSummary { skipped: skipping, ..Default::default() };
+        if query.first_seen {
+            consolidate_first_seen(query, sink, env, &mut results)?;
+        }
         if env.verbose >= 1 {
-            summaries::print_cryo_conclusion(&results, query, env)
+            summaries::print_cryo_conclusion(&results, query, source, env)
         }
         return Ok(Some(results))
     }
 
+    // remove temp files of chunks whose writes were interrupted by a crash
+    let paths = payloads.iter().flat_map(|payload| payload.2.values());
+    dataframes::remove_tmp_files(paths)
+        .map_err(|_| CollectError::CollectError("could not remove temp files".to_string()))?;
+
     // create initial report
     if env.report {
         reports::write_report(env, query, sink, None)?;
     };
 
     // perform collection
-    let results = freeze_partitions(env, payloads, skipping).await;
+    let report = reports::PartialReport::new(env, query, sink);
+    let mut results = match adaptive_chunks {
+        Some(adaptive_chunks) => {
+            freeze_adaptive_partitions(query, source, sink, env, adaptive_chunks, skipping, report)
+                .await?
+        }
+        None => freeze_partitions(env, payloads, skipping, report).await?,
+    };
+    if query.first_seen && results.errored.is_empty() {
+        consolidate_first_seen(query, sink, env, &mut results)?;
+    }
 
     // create summary
     if env.verbose >= 1 {
-        summaries::print_cryo_conclusion(&results, query, env)
+        summaries::print_cryo_conclusion(&results, query, source, env)
     }
 
     // create final report
@@ -72,6 +274,17 @@ pub async fn freeze(
         reports::write_report(env, query, sink, Some(&results))?;
     };
 
+    // exit with the first chunk error
+    if env.fail_fast && !results.errored.is_empty() {
+        let (_, e) = results.errored.swap_remove(0);
+        return Err(e)
+    }
+
+    // create manifest of output files
+    if env.manifest && results.errored.is_empty() {
+        reports::write_manifest(sink, &results)?;
+    };
+
     // return
     Ok(Some(results))
 }
@@ -87,13 +300,29 @@ fn get_payloads(
         .map(|x| std::sync::Arc::new(tokio::sync::Semaphore::new(x as usize)));
     let source: Arc<Source> = Arc::new(source.clone());
     let arc_query = Arc::new(query.clone());
+    let existing_columns = sink.get_existing_columns(query)?;
+    dataframes::check_existing_columns(query, &existing_columns, sink.force_schema_change)?;
+    let existing_columns = Arc::new(existing_columns);
+
+    // first appearances of each partition are written to partial files, which are consolidated
+    // into one file of the whole run, so no partition is collected once that file exists
+    let sink = match query.first_seen {
+        true => {
+            let path = sink.get_path(query, &whole_partition(query), Datatype::AddressAppearances)?;
+            if !sink.overwrite && sink.is_complete(&path) {
+                return Ok((Vec::new(), query.partitions.clone()))
+            }
+            &sink.partials_output()
+        }
+        false => sink,
+    };
     let mut payloads = Vec::new();
     let mut skipping = Vec::new();
     let mut all_paths = HashSet::new();
     for datatype in query.datatypes.clone().into_iter() {
         for partition in query.partitions.clone().into_iter() {
             let paths = sink.get_paths(query, &partition, Some(vec![datatype.clone()]))?;
-            if !sink.overwrite && paths.values().all(|path| path.exists()) {
+            if !sink.overwrite && paths.values().all(|path| sink.is_complete(path)) {
                 skipping.push(partition);
                 continue
             }
@@ -117,6 +346,7 @@ fn get_payloads(
                 sink.clone(),
                 env.clone(),
                 semaphore.clone(),
+                existing_columns.clone(),
             );
             payloads.push(payload);
         }
@@ -124,40 +354,319 @@ fn get_payloads(
     Ok((payloads, skipping))
 }
 
+/// partition spanning the blocks of every partition of query
+fn whole_partition(query: &Query) -> Partition {
+    let block_numbers = query
+        .partitions
+        .iter()
+        .flat_map(|partition| partition.block_numbers.clone().unwrap_or_default())
+        .collect();
+    let partition = query.partitions.first().cloned().unwrap_or_default();
+    Partition { label: None, block_numbers: Some(block_numbers), ..partition }
+}
+
+/// reduce the partial first appearances of every partition into one file of the whole run, and
+/// remove the partial files once it is written
+///
+/// nothing is consolidated until every partition has its partial file, so that a rerun only
+/// collects the partitions that are missing
+fn consolidate_first_seen(
+    query: &Query,
+    sink: &FileOutput,
+    env: &ExecutionEnv,
+    summary: &mut FreezeSummary,
+) -> Result<(), CollectError> {
+    let datatype = Datatype::AddressAppearances;
+    let partials = sink.partials_output();
+    let partial_paths = query
+        .partitions
+        .iter()
+        .map(|partition| partials.get_path(query, partition, datatype))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !partial_paths.iter().all(|path| partials.is_complete(path)) {
+        return Ok(())
+    }
+
+    // partitions cover disjoint blocks, so the order in which they are stacked does not matter
+    let mut df: Option<DataFrame> = None;
+    for path in partial_paths.iter() {
+        let file = std::fs::File::open(path)
+            .map_err(|_| CollectError::CollectError("could not open partial file".to_string()))?;
+        let partial = ParquetReader::new(file).finish().map_err(CollectError::PolarsError)?;
+        match df.as_mut() {
+            Some(df) => {
+                df.vstack_mut(&partial).map_err(CollectError::PolarsError)?;
+            }
+            None => df = Some(partial),
+        }
+    }
+    let Some(df) = df else { return Ok(()) };
+    let schema = query.schemas.get_schema(&datatype)?;
+    let df = address_appearances::first_seen(df, schema)?;
+    let mut df = Ok(df).sort_by_schema(schema)?;
+
+    let path = sink.get_path(query, &whole_partition(query), datatype)?;
+    let metadata = sink.schema_version.as_ref().map(|schema_version| dataframes::FileMetadata {
+        cryo_version: reports::CRYO_VERSION.to_string(),
+        args_hash: reports::args_hash(env),
+        datatype: datatype.name(),
+        schema_version: schema_version.clone(),
+    });
+    let n_bytes = dataframes::chunk_to_file(&mut df, &path, sink, metadata.as_ref())
+        .map_err(|_| CollectError::CollectError("error writing file".to_string()))?;
+    for partial_path in partial_paths.iter() {
+        summary.files.remove(partial_path);
+        std::fs::remove_file(partial_path).map_err(|_| {
+            CollectError::CollectError("could not remove partial file".to_string())
+        })?;
+    }
+
+    // the consolidated file replaces the partial files in stats and in the manifest
+    if n_bytes.is_some() {
+        let partition = whole_partition(query);
+        let n_rows = df.height() as u64;
+        if sink.chunk_stats_file {
+            dataframes::chunk_stats_to_file(&partition, n_rows, &path, sink)
+                .map_err(|_| CollectError::CollectError("error writing stats file".to_string()))?;
+        }
+        summary.files.insert(path, OutputFile::new(datatype, &partition, n_rows));
+    }
+    summary.n_rows = summary.files.values().map(|file| file.n_rows).sum();
+    Ok(())
+}
+
+/// plan adaptive chunks of each datatype over the block ranges of query
+///
+/// blocks of complete output files already on disk are excluded, whatever their chunk size
+fn get_adaptive_chunks(
+    query: &Query,
+    source: &Source,
+    sink: &FileOutput,
+    chunk_size_bytes: u64,
+) -> Result<Vec<(MetaDatatype, AdaptiveChunks)>, CollectError> {
+    if query.partitioned_by != [Dim::BlockNumber] {
+        return Err(err("--chunk-size-bytes requires partitioning by block number only"))
+    }
+    if source.max_concurrent_chunks.is_none() {
+        return Err(err("--chunk-size-bytes requires a limit on concurrent chunks"))
+    }
+    let mut ranges = Vec::new();
+    for chunk in query.partitions.iter().flat_map(|partition| partition.block_numbers.iter()) {
+        for chunk in chunk.iter() {
+            match chunk {
+                BlockChunk::Range(start, end) => ranges.push((*start, *end)),
+                BlockChunk::Numbers(_) => {
+                    return Err(err(
+                        "--chunk-size-bytes requires block ranges, not lists of blocks",
+                    ))
+                }
+            }
+        }
+    }
+    let initial_size = ranges.first().map(|(start, end)| end - start + 1).unwrap_or(1);
+    let segments = merge_ranges(ranges);
+
+    let mut adaptive_chunks = Vec::new();
+    for datatype in query.datatypes.iter() {
+        // blocks count as collected if every datatype of meta datatype has a complete file
+        let mut existing: Option<HashSet<(u64, u64)>> = None;
+        if !sink.overwrite {
+            for datatype in datatype.datatypes().into_iter() {
+                if query.schemas.contains_key(&datatype) {
+                    let ranges = sink.get_existing_block_ranges(query, datatype)?;
+                    let ranges = HashSet::from_iter(ranges);
+                    existing = Some(match existing {
+                        Some(existing) => existing.intersection(&ranges).copied().collect(),
+                        None => ranges,
+                    });
+                }
+            }
+        }
+        let existing = merge_ranges(existing.unwrap_or_default().into_iter().collect());
+        let segments = subtract_ranges(&segments, &existing);
+        let chunks = AdaptiveChunks::new(segments, initial_size, chunk_size_bytes);
+        adaptive_chunks.push((datatype.clone(), chunks));
+    }
+    Ok(adaptive_chunks)
+}
+
+/// sort inclusive ranges, merging ranges that overlap or are adjacent
+fn merge_ranges(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges.into_iter() {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// parts of sorted disjoint inclusive ranges not covered by sorted disjoint removed ranges
+fn subtract_ranges(ranges: &[(u64, u64)], removed: &[(u64, u64)]) -> VecDeque<(u64, u64)> {
+    let mut output = VecDeque::new();
+    for (start, end) in ranges.iter().copied() {
+        let mut start = Some(start);
+        for (removed_start, removed_end) in removed.iter().copied() {
+            let Some(current) = start else { break };
+            if removed_end < current || removed_start > end {
+                continue
+            }
+            if removed_start > current {
+                output.push_back((current, removed_start - 1));
+            }
+            start = removed_end.checked_add(1).filter(|next| *next <= end);
+        }
+        if let Some(start) = start {
+            output.push_back((start, end));
+        }
+    }
+    output
+}
+
 async fn freeze_partitions(
     env: &ExecutionEnv,
     payloads: Vec<PartitionPayload>,
     skipped: Vec<Partition>,
-) -> FreezeSummary {
+    mut report: Option<reports::PartialReport<'_>>,
+) -> Result<FreezeSummary, CollectError> {
     if let Some(bar) = &env.bar {
         bar.set_length(payloads.len() as u64);
         if let Some(payload) = &payloads.first() {
-            let (_, _, _, _, _, _, env, _) = payload;
+            let (_, _, _, _, _, _, env, _, _) = payload;
             let dt_start: DateTime<Local> = env.t_start.into();
             bar.set_message(format!("started at {}", dt_start.format("%Y-%m-%d %H:%M:%S%.3f")));
         }
     }
 
     // spawn task for each partition
+    let row_budget = env.max_rows_total.map(Budget::new);
+    let byte_budget = env.max_output_bytes.map(Budget::new);
     let mut futures = FuturesUnordered::new();
     for payload in payloads.into_iter() {
-        futures.push(tokio::spawn(
-            async move { (payload.0.clone(), freeze_partition(payload).await) },
-        ));
+        let row_budget = row_budget.clone();
+        let byte_budget = byte_budget.clone();
+        futures.push(tokio::spawn(async move {
+            (payload.0.clone(), freeze_partition(payload, row_budget, byte_budget).await)
+        }));
     }
 
     // aggregate results
-    let mut completed = Vec::new();
-    let mut errored = Vec::new();
-    let mut n_rows = 0;
+    let mut summary = FreezeSummary { skipped, ..Default::default() };
     while let Some(result) = futures.next().await {
-        match result {
-            Ok((partition, Ok(chunk_n_rows))) => {
-                n_rows += chunk_n_rows;
-                completed.push(partition)
+        add_chunk_result(&mut summary, result);
+        if let Some(report) = &mut report {
+            report.update(&summary)?;
+        }
+        if env.fail_fast && !summary.errored.is_empty() {
+            cancel_chunks(&mut futures).await;
+            break
+        }
+    }
+
+    if let Some(bar) = &env.bar {
+        bar.finish_and_clear();
+    }
+
+    Ok(summary)
+}
+
+/// collect adaptive chunks of each datatype in order, sizing each chunk when it is started
+async fn freeze_adaptive_partitions(
+    query: &Query,
+    source: &Source,
+    sink: &FileOutput,
+    env: &ExecutionEnv,
+    mut adaptive_chunks: Vec<(MetaDatatype, AdaptiveChunks)>,
+    skipped: Vec<Partition>,
+    mut report: Option<reports::PartialReport<'_>>,
+) -> Result<FreezeSummary, CollectError> {
+    if let Some(bar) = &env.bar {
+        bar.set_length(0);
+        let dt_start: DateTime<Local> = env.t_start.into();
+        bar.set_message(format!("started at {}", dt_start.format("%Y-%m-%d %H:%M:%S%.3f")));
+    }
+
+    let template = query.partitions.first().cloned().unwrap_or_default();
+    let max_in_flight = source.max_concurrent_chunks.unwrap_or(1).max(1) as usize;
+    let arc_query = Arc::new(query.clone());
+    let arc_source = Arc::new(source.clone());
+    let existing_columns = sink.get_existing_columns(query)?;
+    dataframes::check_existing_columns(query, &existing_columns, sink.force_schema_change)?;
+    let existing_columns = Arc::new(existing_columns);
+    let row_budget = env.max_rows_total.map(Budget::new);
+    let byte_budget = env.max_output_bytes.map(Budget::new);
+    let mut summary = FreezeSummary { skipped, ..Default::default() };
+    let mut futures = FuturesUnordered::new();
+    let mut index = 0;
+    loop {
+        // start chunks until the concurrency limit, so that each is sized by the latest results
+        while futures.len() < max_in_flight && index < adaptive_chunks.len() {
+            let (datatype, chunks) = &mut adaptive_chunks[index];
+            let Some((start, end)) = chunks.next_range() else {
+                index += 1;
+                continue
+            };
+            let partition = Partition {
+                label: None,
+                block_numbers: Some(vec![BlockChunk::Range(start, end)]),
+                ..template.clone()
+            };
+            let paths = sink.get_paths(query, &partition, Some(vec![datatype.clone()]))?;
+            dataframes::remove_tmp_files(paths.values()).map_err(|_| {
+                CollectError::CollectError("could not remove temp files".to_string())
+            })?;
+            if let Some(bar) = &env.bar {
+                bar.inc_length(1);
+            }
+            let payload = (
+                partition,
+                datatype.clone(),
+                paths,
+                arc_query.clone(),
+                arc_source.clone(),
+                sink.clone(),
+                env.clone(),
+                None,
+                existing_columns.clone(),
+            );
+            let row_budget = row_budget.clone();
+            let byte_budget = byte_budget.clone();
+            let n_blocks = end - start + 1;
+            let chunk_index = index;
+            futures.push(tokio::spawn(async move {
+                let partition = payload.0.clone();
+                let result = freeze_partition(payload, row_budget, byte_budget).await;
+                (chunk_index, n_blocks, partition, result)
+            }));
+        }
+        let Some(result) = futures.next().await else { break };
+
+        // size later chunks by the largest file of each collected chunk
+        match &result {
+            Ok((chunk_index, n_blocks, _, Ok(Some((files, _))))) => {
+                let n_bytes = files
+                    .keys()
+                    .filter_map(|path| std::fs::metadata(path).ok())
+                    .map(|metadata| metadata.len())
+                    .max()
+                    .unwrap_or(0);
+                adaptive_chunks[*chunk_index].1.record(*n_blocks, n_bytes);
             }
-            Ok((partition, Err(e))) => errored.push((Some(partition), e)),
-            Err(e) => errored.push((None, err(format!("error joining chunks: {:?}", e).as_str()))),
+            Ok((_, _, _, Ok(None))) => {
+                adaptive_chunks.iter_mut().for_each(|(_, chunks)| chunks.stop())
+            }
+            _ => {}
+        }
+        let result = result.map(|(_, _, partition, result)| (partition, result));
+        add_chunk_result(&mut summary, result);
+        if let Some(report) = &mut report {
+            report.update(&summary)?;
+        }
+        if env.fail_fast && !summary.errored.is_empty() {
+            cancel_chunks(&mut futures).await;
+            break
         }
     }
 
@@ -165,11 +674,46 @@ async fn freeze_partitions(
         bar.finish_and_clear();
     }
 
-    FreezeSummary { completed, errored, skipped, n_rows }
+    Ok(summary)
 }
 
-async fn freeze_partition(payload: PartitionPayload) -> Result<u64, CollectError> {
-    let (partition, datatype, paths, query, source, sink, env, semaphore) = payload;
+/// add the result of a chunk task to summary
+fn add_chunk_result(
+    summary: &mut FreezeSummary,
+    result: Result<(Partition, Result<ChunkOutput, CollectError>), tokio::task::JoinError>,
+) {
+    match result {
+        Ok((partition, Ok(Some((chunk_files, chunk_n_retries))))) => {
+            summary.n_rows += chunk_files.values().map(|file| file.n_rows).sum::<u64>();
+            summary.files.extend(chunk_files);
+            summary.completed.push(partition);
+            summary.n_retries.push(chunk_n_retries)
+        }
+        Ok((partition, Ok(None))) => summary.skipped.push(partition),
+        Ok((partition, Err(e))) => summary.errored.push((Some(partition), e)),
+        Err(e) => {
+            summary.errored.push((None, err(format!("error joining chunks: {:?}", e).as_str())))
+        }
+    }
+}
+
+/// cancel outstanding chunks, waiting for them to stop so that no file is written after
+/// returning, and files are written atomically so cancellation leaves no partial files
+async fn cancel_chunks<T>(futures: &mut FuturesUnordered<JoinHandle<T>>) {
+    for handle in futures.iter() {
+        handle.abort();
+    }
+    while futures.next().await.is_some() {}
+}
+
+/// collect and write partition, returning None if skipped because row or byte budget was reached
+async fn freeze_partition(
+    payload: PartitionPayload,
+    row_budget: Option<Arc<Budget>>,
+    byte_budget: Option<Arc<Budget>>,
+) -> Result<ChunkOutput, CollectError> {
+    let (partition, datatype, paths, query, source, sink, env, semaphore, existing_columns) =
+        payload;
 
     // acquire chunk semaphore
     let _permit = match &semaphore {
@@ -177,18 +721,126 @@ async fn freeze_partition(payload: PartitionPayload) -> Result<u64, CollectError
         None => None,
     };
 
-    // collect data
-    let dfs = collect_partition(datatype, partition, query, source).await?;
+    // skip chunks once the chunks in flight are expected to reach the row budget, in-flight
+    // chunks are finished
+    let mut row_reservation = None;
+    if let Some(row_budget) = &row_budget {
+        match row_budget.reserve().await? {
+            Some(reservation) => row_reservation = Some(reservation),
+            None => {
+                if let Some(bar) = env.bar {
+                    bar.inc(1);
+                }
+                return Ok(None)
+            }
+        }
+    }
+
+    // skip chunks once the chunks in flight are expected to reach the byte budget, the first
+    // chunk is written alone so that the size of a chunk is known before others start
+    let mut byte_reservation = None;
+    if let Some(byte_budget) = &byte_budget {
+        match byte_budget.reserve().await? {
+            Some(reservation) => byte_reservation = Some(reservation),
+            None => {
+                if let Some(bar) = env.bar {
+                    bar.inc(1);
+                }
+                return Ok(None)
+            }
+        }
+    }
+
+    // collect data, retrying transient errors
+    let mut appender = sink.flush_interval.map(|seconds| {
+        let interval = Duration::from_secs(seconds);
+        dataframes::ChunkAppender::new(
+            paths.clone(),
+            sink.clone(),
+            existing_columns.clone(),
+            interval,
+        )
+    });
+    let mut n_retries = 0;
+    let dfs = loop {
+        let result = collect_partition_appending(
+            datatype.clone(),
+            partition.clone(),
+            query.clone(),
+            source.clone(),
+            appender.as_mut(),
+        )
+        .await;
+
+        // rows appended by a failed attempt are removed, so that a retry starts over
+        if let (Err(_), Some(appender)) = (&result, appender.as_mut()) {
+            appender.reset().map_err(|_| {
+                CollectError::CollectError("could not remove temp files".to_string())
+            })?;
+        }
+        match result {
+            Ok(dfs) => break dfs,
+            Err(e) if e.is_retryable() && n_retries < env.chunk_retries => {
+                let backoff = CHUNK_RETRY_BACKOFF_MS << n_retries.min(6);
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+                n_retries += 1;
+            }
+            Err(e) if n_retries > 0 => {
+                return Err(CollectError::RetriesExhausted(n_retries, Box::new(e)))
+            }
+            Err(e) => return Err(e),
+        }
+    };
 
-    // write dataframes to disk
+    // write dataframes to disk, after any rows appended while collecting
     let mut n_rows = 0;
+    let mut files = HashMap::new();
     for (datatype, mut df) in dfs {
-        n_rows += df.height() as u64;
+        let n_appended = appender.as_ref().and_then(|appender| appender.n_rows(&datatype));
+        let n_file_rows = df.height() as u64 + n_appended.unwrap_or(0);
+        n_rows += n_file_rows;
         let path = paths.get(&datatype).ok_or_else(|| {
             CollectError::CollectError("could not get path for datatype".to_string())
         })?;
-        let result = dataframes::df_to_file(&mut df, path, &sink);
-        result.map_err(|_| CollectError::CollectError("error writing file".to_string()))?
+        if let Some(existing) = existing_columns.get(&datatype) {
+            df = dataframes::match_existing_columns(
+                df,
+                datatype,
+                existing,
+                sink.force_schema_change,
+            )?;
+        }
+        let metadata = sink.schema_version.as_ref().map(|schema_version| {
+            dataframes::FileMetadata {
+                cryo_version: reports::CRYO_VERSION.to_string(),
+                args_hash: reports::args_hash(&env),
+                datatype: datatype.name(),
+                schema_version: schema_version.clone(),
+            }
+        });
+        let n_bytes = match appender.as_mut() {
+            Some(appender) if n_appended.is_some() => {
+                appender.finish(datatype, &mut df, metadata.as_ref()).map(Some)
+            }
+            _ => dataframes::chunk_to_file(&mut df, path, &sink, metadata.as_ref()),
+        }
+        .map_err(|_| CollectError::CollectError("error writing file".to_string()))?;
+        let Some(n_bytes) = n_bytes else { continue };
+        if let Some(reservation) = byte_reservation.as_mut() {
+            reservation.n = Some(reservation.n.unwrap_or(0) + n_bytes);
+        }
+        if sink.chunk_stats_file {
+            dataframes::chunk_stats_to_file(&partition, n_file_rows, path, &sink)
+                .map_err(|_| CollectError::CollectError("error writing stats file".to_string()))?;
+        }
+        files.insert(path.clone(), OutputFile::new(datatype, &partition, n_file_rows));
+    }
+
+    if let Some(reservation) = row_reservation.as_mut() {
+        reservation.n = Some(n_rows);
+    }
+    if let Some(reservation) = byte_reservation.as_mut() {
+        reservation.n.get_or_insert(0);
     }
 
     // update progress bar
@@ -196,5 +848,372 @@ async fn freeze_partition(payload: PartitionPayload) -> Result<u64, CollectError
         bar.inc(1);
     }
 
-    Ok(n_rows)
+    Ok(Some((files, n_retries)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ColumnEncoding, EmptyFilePolicy, ExecutionEnvBuilder, FileFormat, JsonOptions, U256Type,
+    };
+    use alloy::{
+        providers::ProviderBuilder,
+        rpc::{client::RpcClient, json_rpc::RequestPacket},
+        transports::{BoxTransport, TransportErrorKind, TransportFut},
+    };
+    use polars::prelude::ParquetCompression;
+
+    #[test]
+    fn test_budget_admit() {
+        let mut state = BudgetState::default();
+
+        // the first chunk is a probe, a failed probe is replaced by the next chunk
+        assert_eq!(state.admit(35), Admission::Admitted);
+        assert_eq!(state.admit(35), Admission::Wait);
+        state.release(None);
+        assert_eq!(state.admit(35), Admission::Admitted);
+        state.release(Some(10));
+
+        // chunks are admitted until the chunks in flight are expected to reach the budget
+        assert_eq!(state.admit(35), Admission::Admitted);
+        assert_eq!(state.admit(35), Admission::Admitted);
+        assert_eq!(state.admit(35), Admission::Admitted);
+        assert_eq!(state.admit(35), Admission::Wait);
+        state.release(Some(10));
+        state.release(Some(10));
+        assert_eq!(state.admit(35), Admission::Wait);
+        state.release(Some(10));
+        assert_eq!(state.written, 40);
+        assert_eq!(state.admit(35), Admission::Exhausted);
+    }
+
+    #[test]
+    fn test_row_budget_empty_chunks_in_flight() {
+        let mut state = BudgetState::default();
+        assert_eq!(state.admit(35), Admission::Admitted);
+        state.release(Some(10));
+        for _ in 0..3 {
+            assert_eq!(state.admit(35), Admission::Admitted);
+        }
+        assert_eq!(state.admit(35), Admission::Wait);
+
+        // chunks in flight that produce no rows leave the budget open for later chunks
+        for _ in 0..3 {
+            state.release(Some(0));
+        }
+        assert_eq!(state.written, 10);
+        assert_eq!(state.admit(35), Admission::Admitted);
+    }
+
+    /// output of chunks of n each, started together with an optional chunk semaphore
+    async fn run_budget(n_chunks: usize, n: u64, max: u64, permits: Option<usize>) -> u64 {
+        let budget = Budget::new(max);
+        let semaphore = permits.map(|permits| Arc::new(Semaphore::new(permits)));
+        let written = Arc::new(Mutex::new(0));
+        let handles: Vec<_> = (0..n_chunks)
+            .map(|_| {
+                let (budget, semaphore, written) =
+                    (budget.clone(), semaphore.clone(), written.clone());
+                tokio::spawn(async move {
+                    let _permit = match &semaphore {
+                        Some(semaphore) => Some(semaphore.acquire().await),
+                        None => None,
+                    };
+                    let Some(mut reservation) = budget.reserve().await.unwrap() else { return };
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    *written.lock().unwrap() += n;
+                    reservation.n = Some(n);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        let written = *written.lock().unwrap();
+        written
+    }
+
+    #[tokio::test]
+    async fn test_row_budget_with_and_without_semaphore() {
+        // chunks started at once still stop near the budget, with or without a chunk semaphore
+        assert_eq!(run_budget(100, 10, 35, None).await, 40);
+        assert_eq!(run_budget(100, 10, 35, Some(2)).await, 40);
+        assert_eq!(run_budget(100, 10, 35, Some(100)).await, 40);
+    }
+
+    #[tokio::test]
+    async fn test_byte_budget_smaller_than_first_wave() {
+        // a budget below a single chunk writes only the probe chunk, not the whole first wave
+        assert_eq!(run_budget(100, 500, 300, None).await, 500);
+        assert_eq!(run_budget(100, 500, 300, Some(16)).await, 500);
+    }
+
+    #[tokio::test]
+    async fn test_byte_budget_empty_chunks_in_flight() {
+        let budget = Budget::new(1000);
+        let mut probe = budget.reserve().await.unwrap().unwrap();
+        probe.n = Some(400);
+        drop(probe);
+        let mut first = budget.reserve().await.unwrap().unwrap();
+        let mut second = budget.reserve().await.unwrap().unwrap();
+
+        // a chunk waits while the chunks in flight are expected to reach the budget
+        let waiting = tokio::spawn({
+            let budget = budget.clone();
+            async move { budget.reserve().await.unwrap().map(|reservation| reservation.n) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        // and is admitted once they finish without writing any bytes
+        first.n = Some(0);
+        second.n = Some(0);
+        drop((first, second));
+        assert_eq!(waiting.await.unwrap(), Some(None));
+        assert_eq!(budget.state.lock().unwrap().written, 400);
+    }
+
+    /// source whose requests all fail
+    fn failing_source() -> Source {
+        let service = tower::service_fn(|_request: RequestPacket| {
+            let future: TransportFut<'static> =
+                Box::pin(async { Err(TransportErrorKind::http_error(400, "".to_string())) });
+            future
+        });
+        Source {
+            provider: ProviderBuilder::default()
+                .on_client(RpcClient::new(BoxTransport::new(service), true)),
+            chain_id: 1,
+            inner_request_size: 1,
+            max_concurrent_chunks: None,
+            rpc_url: "".to_string(),
+            semaphore: Arc::new(None),
+            rate_limiter: Arc::new(None),
+            labels: Default::default(),
+            raw_response_dir: None,
+            receipts_source: Default::default(),
+            rpc_stats: Default::default(),
+            rpc_batcher: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_cancels_chunks() {
+        let dir = std::env::temp_dir().join(format!("cryo_fail_fast_{}", std::process::id()));
+        let sink = FileOutput {
+            output_dir: dir.clone(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: true,
+            force_schema_change: false,
+            format: FileFormat::Parquet,
+            row_group_size: None,
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            json: JsonOptions::default(),
+        };
+        let env = ExecutionEnvBuilder::new().fail_fast(true).build();
+        let schema = Datatype::Blocks
+            .table_schema(
+                &[U256Type::Binary],
+                &ColumnEncoding::Hex,
+                &None,
+                &None,
+                &None,
+                None,
+                None,
+            )
+            .unwrap();
+        let query = Arc::new(Query::for_datatype(Datatype::Blocks, schema, vec![]));
+        let source = Arc::new(failing_source());
+        let payload = |block: u64, semaphore: Option<Arc<Semaphore>>| {
+            let partition = Partition {
+                block_numbers: Some(vec![BlockChunk::Numbers(vec![block])]),
+                ..Default::default()
+            };
+            let datatype = MetaDatatype::Scalar(Datatype::Blocks);
+            let path = dir.join(format!("ethereum__blocks__{}.parquet", block));
+            let paths = HashMap::from([(Datatype::Blocks, path)]);
+            let env = env.clone();
+            let (query, source, sink) = (query.clone(), source.clone(), sink.clone());
+            (partition, datatype, paths, query, source, sink, env, semaphore, Arc::default())
+        };
+
+        // chunks waiting for a permit that is never released are cancelled by the failed chunk
+        let blocked = Arc::new(Semaphore::new(0));
+        let mut payloads = vec![payload(0, None)];
+        payloads.extend((1..=10).map(|block| payload(block, Some(blocked.clone()))));
+        let freeze = freeze_partitions(&env, payloads, vec![], None);
+        let summary = tokio::time::timeout(Duration::from_secs(10), freeze).await.unwrap().unwrap();
+        assert_eq!(summary.errored.len(), 1);
+        assert!(summary.completed.is_empty() && summary.skipped.is_empty());
+        assert_eq!(blocked.available_permits(), 0);
+    }
+
+    #[test]
+    fn test_adaptive_chunks() {
+        let segments = subtract_ranges(&merge_ranges(vec![(10, 19), (0, 9), (30, 99)]), &[]);
+        assert_eq!(segments, vec![(0, 19), (30, 99)]);
+        let mut chunks = AdaptiveChunks::new(segments, 5, 100);
+
+        // chunks are cut in order, not crossing segments, and never overlap
+        assert_eq!(chunks.next_range(), Some((0, 4)));
+        assert_eq!(chunks.next_range(), Some((5, 9)));
+
+        // growth is limited relative to the largest collected chunk
+        chunks.record(5, 10);
+        assert_eq!(chunks.chunk_size(), 20);
+        assert_eq!(chunks.next_range(), Some((10, 19)));
+        chunks.record(10, 0);
+        assert_eq!(chunks.chunk_size(), 40);
+        assert_eq!(chunks.next_range(), Some((30, 69)));
+        chunks.record(40, 3940);
+        assert_eq!(chunks.chunk_size(), 1);
+        assert_eq!(chunks.next_range(), Some((70, 70)));
+        chunks.stop();
+        assert_eq!(chunks.next_range(), None);
+    }
+
+    #[test]
+    fn test_subtract_ranges() {
+        let ranges = merge_ranges(vec![(0, 99), (100, 199), (300, u64::MAX)]);
+        assert_eq!(ranges, vec![(0, 199), (300, u64::MAX)]);
+        let removed = vec![(0, 9), (50, 59), (150, 400), (500, u64::MAX)];
+        let expected = vec![(10, 49), (60, 149), (401, 499)];
+        assert_eq!(subtract_ranges(&ranges, &removed), expected);
+        assert_eq!(subtract_ranges(&ranges, &[]), ranges);
+    }
+
+    /// sink and first_seen query of address appearances over two partitions
+    fn first_seen_fixture(dir: &std::path::Path) -> (FileOutput, Query) {
+        let sink = FileOutput {
+            output_dir: dir.to_path_buf(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: false,
+            force_schema_change: false,
+            format: crate::FileFormat::Csv,
+            row_group_size: None,
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: polars::prelude::ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: crate::EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            json: Default::default(),
+        };
+        let columns = Some(vec!["block_number".to_string(), "address".to_string()]);
+        let schema = Datatype::AddressAppearances
+            .table_schema(&[], &crate::ColumnEncoding::Hex, &None, &None, &columns, None, None)
+            .unwrap();
+        let partition = |start: u64, end: u64| Partition {
+            block_numbers: Some(vec![BlockChunk::Range(start, end)]),
+            ..Default::default()
+        };
+        let mut query = Query::for_datatype(
+            Datatype::AddressAppearances,
+            schema,
+            vec![partition(10, 19), partition(0, 9)],
+        );
+        query.first_seen = true;
+        (sink, query)
+    }
+
+    #[test]
+    fn test_consolidate_first_seen() {
+        let dir = std::env::temp_dir().join(format!("cryo_first_seen_{}", std::process::id()));
+        let (sink, query) = first_seen_fixture(&dir);
+        let env = ExecutionEnv::default();
+        let partials = sink.partials_output();
+        let write_partial = |partition: &Partition, blocks: &[u32], addresses: &[&str]| {
+            let path = partials.get_path(&query, partition, Datatype::AddressAppearances).unwrap();
+            let mut df = polars::df!("block_number" => blocks, "address" => addresses).unwrap();
+            dataframes::chunk_to_file(&mut df, &path, &partials, None).unwrap();
+            path
+        };
+
+        // nothing is consolidated until every partition has its partial file
+        let later = write_partial(&query.partitions[0], &[12, 15], &["0xaa", "0xbb"]);
+        let mut summary = FreezeSummary::default();
+        let file = OutputFile::new(Datatype::AddressAppearances, &query.partitions[0], 2);
+        summary.files.insert(later.clone(), file);
+        consolidate_first_seen(&query, &sink, &env, &mut summary).unwrap();
+        assert!(later.exists() && summary.files.contains_key(&later));
+
+        // partials of partitions finished in any order reduce to the first appearances of the run
+        write_partial(&query.partitions[1], &[3, 7], &["0xbb", "0xcc"]);
+        consolidate_first_seen(&query, &sink, &env, &mut summary).unwrap();
+        let path = sink
+            .get_path(&query, &whole_partition(&query), Datatype::AddressAppearances)
+            .unwrap();
+        let filename = path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(filename, "ethereum__address_appearances__00000000_to_00000019.csv");
+        let file = OutputFile {
+            datatype: Datatype::AddressAppearances,
+            start_block: Some(0),
+            end_block: Some(19),
+            n_rows: 3,
+        };
+        assert_eq!(summary.files, HashMap::from([(path.clone(), file)]));
+        assert_eq!(summary.n_rows, 3);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "block_number,address\n3,0xbb\n7,0xcc\n12,0xaa\n");
+        assert!(std::fs::read_dir(dir.join(".cryo/partials")).unwrap().next().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_consolidate_first_seen_manifest() {
+        let dir = std::env::temp_dir().join(format!("cryo_manifest_{}", std::process::id()));
+        let (sink, query) = first_seen_fixture(&dir);
+        let sink = FileOutput { chunk_stats_file: true, ..sink };
+        let env = ExecutionEnv::default();
+        let partials = sink.partials_output();
+        let mut summary = FreezeSummary::default();
+        for (partition, block) in query.partitions.iter().zip([12u32, 3]) {
+            let path = partials.get_path(&query, partition, Datatype::AddressAppearances).unwrap();
+            let mut df = polars::df!("block_number" => [block], "address" => ["0xaa"]).unwrap();
+            dataframes::chunk_to_file(&mut df, &path, &partials, None).unwrap();
+            let file = OutputFile::new(Datatype::AddressAppearances, partition, 1);
+            summary.files.insert(path, file);
+        }
+
+        // the manifest and stats describe the consolidated file instead of the partial files
+        consolidate_first_seen(&query, &sink, &env, &mut summary).unwrap();
+        let manifest = reports::write_manifest(&sink, &summary).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(manifest).unwrap()).unwrap();
+        let path = sink
+            .get_path(&query, &whole_partition(&query), Datatype::AddressAppearances)
+            .unwrap();
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0]["path"], path.to_str().unwrap());
+        assert_eq!(files[0]["start_block"], 0);
+        assert_eq!(files[0]["end_block"], 19);
+        assert_eq!(files[0]["n_rows"], 1);
+        assert!(sink.get_stats_path(&path).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -13,7 +13,7 @@ mod freeze;
 mod multi_datasets;
 mod types;
 
-pub use collect::collect;
+pub use collect::{collect, collect_preview};
 pub use datasets::*;
 pub use freeze::freeze;
 pub use multi_datasets::*;
@@ -1,4 +1,8 @@
-use crate::{datasets::transactions, types::collection::*, Datatype, *};
+use crate::{
+    datasets::transactions::{self, BlockTransactionsResponse},
+    types::collection::*,
+    Datatype, *,
+};
 use polars::prelude::*;
 use std::collections::HashMap;
 
@@ -30,9 +34,11 @@ impl CollectByBlock for BlocksAndTransactions {
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let BlocksAndTransactions(blocks, transactions) = columns;
-        let (block, _, _) = response.clone();
+        let (BlockTransactionsResponse::Block(block, _), _) = &response else {
+            return Err(err("block body of transactions was not collected"))
+        };
         let schema = query.schemas.get_schema(&Datatype::Blocks)?;
-        blocks::process_block(block, blocks, schema)?;
+        blocks::process_block(block.as_ref().clone(), blocks, schema)?;
         <Transactions as CollectByBlock>::transform(response, transactions, query)?;
         Ok(())
     }
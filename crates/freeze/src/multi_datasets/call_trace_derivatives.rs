@@ -34,39 +34,43 @@ impl ToDataFrames for CallTraceDerivatives {
 
 #[async_trait::async_trait]
 impl CollectByBlock for CallTraceDerivatives {
-    type Response = Vec<LocalizedTransactionTrace>;
+    type Response = (Vec<LocalizedTransactionTrace>, traces::TransactionGas);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.trace_block(request.block_number()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let traces = source.trace_block(block_number).await?;
+        Ok((traces, traces::block_transaction_gas(block_number, &source, &query).await?))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_call_trace_derivatives(traces, columns, &query.schemas)
+        process_call_trace_derivatives(response, columns, query)
     }
 }
 
 #[async_trait::async_trait]
 impl CollectByTransaction for CallTraceDerivatives {
-    type Response = Vec<LocalizedTransactionTrace>;
+    type Response = (Vec<LocalizedTransactionTrace>, traces::TransactionGas);
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.trace_transaction(request.ethers_transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let tx_hash = request.ethers_transaction_hash()?;
+        let traces = source.trace_transaction(tx_hash).await?;
+        Ok((traces, traces::transaction_gas(tx_hash, &source, &query).await?))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        let traces =
-            if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
-        process_call_trace_derivatives(traces, columns, &query.schemas)
+        process_call_trace_derivatives(response, columns, query)
     }
 }
 
 fn process_call_trace_derivatives(
-    response: Vec<LocalizedTransactionTrace>,
+    response: (Vec<LocalizedTransactionTrace>, traces::TransactionGas),
     columns: &mut CallTraceDerivatives,
-    schemas: &HashMap<Datatype, Table>,
+    query: &Query,
 ) -> R<()> {
+    let (response, tx_gas) = response;
+    let response =
+        if query.exclude_failed { traces::filter_failed_traces(response) } else { response };
+    let schemas = &query.schemas;
     let CallTraceDerivatives(contracts, native_transfers, traces) = columns;
     if schemas.contains_key(&Datatype::Contracts) {
         contracts::process_contracts(&response, contracts, schemas)?;
@@ -75,7 +79,7 @@ fn process_call_trace_derivatives(
         native_transfers::process_native_transfers(&response, native_transfers, schemas)?;
     }
     if schemas.contains_key(&Datatype::Traces) {
-        traces::process_traces(&response, traces, schemas)?;
+        traces::process_traces(&response, &tx_gas, traces, schemas)?;
     }
     Ok(())
 }
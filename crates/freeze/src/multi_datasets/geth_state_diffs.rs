@@ -51,6 +51,9 @@ impl ToDataFrames for GethStateDiffs {
 
 type BlockTxsTraces = (Option<u32>, Vec<Option<Vec<u8>>>, Vec<DiffMode>);
 
+/// diffs of a single transaction, along with the index of the transaction in its block
+type TransactionTraces = (BlockTxsTraces, Option<u32>);
+
 #[async_trait::async_trait]
 impl CollectByBlock for GethStateDiffs {
     type Response = BlockTxsTraces;
@@ -77,19 +80,33 @@ impl CollectByBlock for GethStateDiffs {
 
 #[async_trait::async_trait]
 impl CollectByTransaction for GethStateDiffs {
-    type Response = BlockTxsTraces;
+    type Response = TransactionTraces;
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
-        let include_block_number = query.schemas.values().any(|x| x.has_column("transaction_hash"));
-        source
-            .geth_debug_trace_transaction_diffs(request.transaction_hash()?, include_block_number)
-            .await
+        let transaction_hash = request.transaction_hash()?;
+        let (_, txs, diffs) =
+            source.geth_debug_trace_transaction_diffs(transaction_hash.clone(), false).await?;
+
+        // block number and index are not part of the trace, resolve them from the transaction
+        let schemas = query.schemas.values();
+        let include_block_number = schemas.clone().any(|x| x.has_column("block_number"));
+        let include_transaction_index = schemas.clone().any(|x| x.has_column("transaction_index"));
+        if !include_block_number && !include_transaction_index {
+            return Ok(((None, txs, diffs), None))
+        }
+        let tx = source
+            .get_transaction_by_hash(B256::from_slice(&transaction_hash))
+            .await?
+            .ok_or(err("could not get block for txs"))?;
+        let block_number = tx.block_number.map(|x| x as u32);
+        let transaction_index = tx.transaction_index.map(|x| x as u32);
+        Ok(((block_number, txs, diffs), transaction_index))
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
         let GethStateDiffs(ref mut balances, ref mut codes, ref mut nonces, ref mut storages) =
             columns;
-        process_geth_diffs(
+        process_geth_transaction_diffs(
             &response,
             balances.as_mut(),
             codes.as_mut(),
@@ -102,6 +119,31 @@ impl CollectByTransaction for GethStateDiffs {
 
 pub(crate) fn process_geth_diffs(
     response: &BlockTxsTraces,
+    balances: Option<&mut GethBalanceDiffs>,
+    codes: Option<&mut GethCodeDiffs>,
+    nonces: Option<&mut GethNonceDiffs>,
+    storages: Option<&mut GethStorageDiffs>,
+    schemas: &Schemas,
+) -> R<()> {
+    process_diffs(response, None, balances, codes, nonces, storages, schemas)
+}
+
+pub(crate) fn process_geth_transaction_diffs(
+    response: &TransactionTraces,
+    balances: Option<&mut GethBalanceDiffs>,
+    codes: Option<&mut GethCodeDiffs>,
+    nonces: Option<&mut GethNonceDiffs>,
+    storages: Option<&mut GethStorageDiffs>,
+    schemas: &Schemas,
+) -> R<()> {
+    let (response, transaction_index) = response;
+    process_diffs(response, *transaction_index, balances, codes, nonces, storages, schemas)
+}
+
+/// transactions are indexed by their order in response unless `transaction_index` is given
+fn process_diffs(
+    response: &BlockTxsTraces,
+    transaction_index: Option<u32>,
     mut balances: Option<&mut GethBalanceDiffs>,
     mut codes: Option<&mut GethCodeDiffs>,
     mut nonces: Option<&mut GethNonceDiffs>,
@@ -116,7 +158,8 @@ pub(crate) fn process_geth_diffs(
 
     let blank = &AccountState::default();
     for (tx_index, (trace, tx)) in traces.iter().zip(txs).enumerate() {
-        let index = &(*block_number, tx_index as u32, tx.clone());
+        let tx_index = transaction_index.unwrap_or(tx_index as u32);
+        let index = &(*block_number, tx_index, tx.clone());
         let addresses: Vec<_> = trace
             .pre
             .keys()
@@ -261,3 +304,37 @@ where
         (None, None) => (new, new),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_geth_transaction_diffs() {
+        let encoding = ColumnEncoding::Binary;
+        let schema = Datatype::GethNonceDiffs
+            .table_schema(&[U256Type::Binary], &encoding, &None, &None, &None, None, None)
+            .unwrap();
+        let schemas: Schemas = [(Datatype::GethNonceDiffs, schema)].into_iter().collect();
+        let pre = AccountState { nonce: Some(1), ..Default::default() };
+        let post = AccountState { nonce: Some(2), ..Default::default() };
+        let diff = DiffMode {
+            pre: BTreeMap::from([(Address::repeat_byte(1), pre)]),
+            post: BTreeMap::from([(Address::repeat_byte(1), post)]),
+        };
+        let tx = Some(vec![1; 32]);
+
+        // collected by transaction, rows use the index of the transaction in its block
+        let response = ((Some(100), vec![tx], vec![diff]), Some(7));
+        let mut nonces = GethNonceDiffs::default();
+        process_geth_transaction_diffs(&response, None, None, Some(&mut nonces), None, &schemas)
+            .unwrap();
+        assert_eq!(nonces.block_number, vec![Some(100)]);
+        assert_eq!(nonces.transaction_index, vec![Some(7)]);
+
+        // collected by block, rows use their order in the block
+        let mut nonces = GethNonceDiffs::default();
+        process_geth_diffs(&response.0, None, None, Some(&mut nonces), None, &schemas).unwrap();
+        assert_eq!(nonces.transaction_index, vec![Some(0)]);
+    }
+}
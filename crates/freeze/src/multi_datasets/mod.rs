@@ -2,11 +2,15 @@ mod blocks_and_transactions;
 mod call_trace_derivatives;
 /// geth state diffs
 pub mod geth_state_diffs;
-mod state_diffs;
+/// state diffs
+pub mod state_diffs;
 mod state_reads;
+/// trace replay
+pub mod trace_replay;
 
 pub use blocks_and_transactions::*;
 pub use call_trace_derivatives::*;
 pub use geth_state_diffs::*;
 pub use state_diffs::*;
 pub use state_reads::*;
+pub use trace_replay::*;
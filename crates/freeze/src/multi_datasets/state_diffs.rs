@@ -1,7 +1,13 @@
 use crate::*;
-use alloy::rpc::types::trace::parity::TraceResults;
+use alloy::{
+    primitives::{Address, Bytes, B256, U64},
+    rpc::types::trace::{
+        geth::{AccountState, DiffMode},
+        parity::{AccountDiff, ChangedType, Delta, StateDiff, TraceResults},
+    },
+};
 use polars::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 /// StateDiffs
 #[derive(Default)]
@@ -36,14 +42,12 @@ impl CollectByBlock for StateDiffs {
 
     async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
         let include_txs = query.schemas.values().any(|x| x.has_column("transaction_hash"));
-        let (bn, txs, traces) =
-            source.trace_block_state_diffs(request.block_number()? as u32, include_txs).await?;
-        let trace_results = traces.into_iter().map(|t| t.full_trace).collect();
-        Ok((bn, txs, trace_results))
+        let block_number = request.block_number()? as u32;
+        extract_block_state_diffs(block_number, include_txs, &source, &query).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_diffs(response, columns, &query.schemas)
+        process_state_diffs(response, columns, query)
     }
 }
 
@@ -51,24 +55,193 @@ impl CollectByBlock for StateDiffs {
 impl CollectByTransaction for StateDiffs {
     type Response = BlockTxsTraces;
 
-    async fn extract(request: Params, source: Arc<Source>, _: Arc<Query>) -> R<Self::Response> {
-        source.trace_transaction_state_diffs(request.transaction_hash()?).await
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        extract_transaction_state_diffs(request.transaction_hash()?, &source, &query).await
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_diffs(response, columns, &query.schemas)
+        process_state_diffs(response, columns, query)
     }
 }
 
-fn process_state_diffs(
-    response: BlockTxsTraces,
-    columns: &mut StateDiffs,
-    schemas: &HashMap<Datatype, Table>,
-) -> R<()> {
+fn process_state_diffs(response: BlockTxsTraces, columns: &mut StateDiffs, query: &Query) -> R<()> {
+    let schemas = &query.schemas;
     let StateDiffs(balances, codes, nonces, storages) = columns;
     balance_diffs::process_balance_diffs(&response, balances, schemas)?;
     code_diffs::process_code_diffs(&response, codes, schemas)?;
     nonce_diffs::process_nonce_diffs(&response, nonces, schemas)?;
-    storage_diffs::process_storage_diffs(&response, storages, schemas)?;
+    storage_diffs::process_storage_diffs(&response, storages, query)?;
     Ok(())
 }
+
+/// get state diffs of block from the tracer configured in query
+pub(crate) async fn extract_block_state_diffs(
+    block_number: u32,
+    include_txs: bool,
+    source: &Source,
+    query: &Query,
+) -> R<BlockTxsTraces> {
+    match query.state_diff_tracer {
+        StateDiffTracer::Parity => {
+            let (bn, txs, traces) =
+                source.trace_block_state_diffs(block_number, include_txs).await?;
+            Ok((bn, txs, traces.into_iter().map(|t| t.full_trace).collect()))
+        }
+        StateDiffTracer::Geth => {
+            let (bn, txs, diffs) =
+                source.geth_debug_trace_block_diffs(block_number, include_txs).await?;
+            Ok((bn, txs, diffs.into_iter().map(geth_diff_to_trace_results).collect()))
+        }
+    }
+}
+
+/// get state diffs of transaction from the tracer configured in query
+pub(crate) async fn extract_transaction_state_diffs(
+    transaction_hash: Vec<u8>,
+    source: &Source,
+    query: &Query,
+) -> R<BlockTxsTraces> {
+    match query.state_diff_tracer {
+        StateDiffTracer::Parity => source.trace_transaction_state_diffs(transaction_hash).await,
+        StateDiffTracer::Geth => {
+            let include_block_number =
+                query.schemas.values().any(|x| x.has_column("block_number"));
+            let (bn, txs, diffs) = source
+                .geth_debug_trace_transaction_diffs(transaction_hash, include_block_number)
+                .await?;
+            Ok((bn, txs, diffs.into_iter().map(geth_diff_to_trace_results).collect()))
+        }
+    }
+}
+
+/// convert geth prestate diff into parity state diff
+///
+/// geth omits unchanged fields from `post` and omits slots that were reset to zero from `post`
+pub(crate) fn geth_diff_to_trace_results(diff: DiffMode) -> TraceResults {
+    let DiffMode { pre, post } = diff;
+    let addresses: BTreeSet<Address> = pre.keys().chain(post.keys()).copied().collect();
+    let mut state_diff = BTreeMap::new();
+    for address in addresses.into_iter() {
+        let account_diff = match (pre.get(&address), post.get(&address)) {
+            (Some(pre), Some(post)) => AccountDiff {
+                balance: geth_delta(pre.balance, post.balance),
+                code: geth_delta(pre.code.clone(), post.code.clone()),
+                nonce: geth_delta(pre.nonce.map(U64::from), post.nonce.map(U64::from)),
+                storage: geth_storage_deltas(&pre.storage, &post.storage),
+            },
+            (None, Some(post)) => geth_account_delta(post, true),
+            (Some(pre), None) => geth_account_delta(pre, false),
+            (None, None) => continue,
+        };
+        state_diff.insert(address, account_diff);
+    }
+    TraceResults {
+        output: Bytes::new(),
+        state_diff: Some(StateDiff(state_diff)),
+        trace: vec![],
+        vm_trace: None,
+    }
+}
+
+fn geth_delta<T: PartialEq + Default>(pre: Option<T>, post: Option<T>) -> Delta<T> {
+    match (pre, post) {
+        (pre, Some(post)) if pre.as_ref() != Some(&post) => {
+            Delta::Changed(ChangedType { from: pre.unwrap_or_default(), to: post })
+        }
+        _ => Delta::Unchanged,
+    }
+}
+
+fn geth_storage_deltas(
+    pre: &BTreeMap<B256, B256>,
+    post: &BTreeMap<B256, B256>,
+) -> BTreeMap<B256, Delta<B256>> {
+    let slots: BTreeSet<&B256> = pre.keys().chain(post.keys()).collect();
+    slots
+        .into_iter()
+        .filter_map(|slot| {
+            let from = pre.get(slot).copied().unwrap_or_default();
+            let to = post.get(slot).copied().unwrap_or_default();
+            (from != to).then_some((*slot, Delta::Changed(ChangedType { from, to })))
+        })
+        .collect()
+}
+
+/// diff of an account that was created or destroyed within the transaction
+fn geth_account_delta(account: &AccountState, created: bool) -> AccountDiff {
+    fn delta<T>(value: T, created: bool) -> Delta<T> {
+        if created {
+            Delta::Added(value)
+        } else {
+            Delta::Removed(value)
+        }
+    }
+    AccountDiff {
+        balance: delta(account.balance.unwrap_or_default(), created),
+        code: delta(account.code.clone().unwrap_or_default(), created),
+        nonce: delta(U64::from(account.nonce.unwrap_or_default()), created),
+        storage: account
+            .storage
+            .iter()
+            .map(|(slot, value)| (*slot, delta(*value, created)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn test_geth_diff_to_trace_results() {
+        let existing = Address::repeat_byte(1);
+        let created = Address::repeat_byte(2);
+        let (slot_a, slot_b, slot_c) =
+            (B256::repeat_byte(1), B256::repeat_byte(2), B256::repeat_byte(3));
+        let pre_account = AccountState {
+            balance: Some(U256::from(10)),
+            nonce: Some(1),
+            code: Some(Bytes::from(vec![0x60])),
+            storage: BTreeMap::from([
+                (slot_a, B256::repeat_byte(9)),
+                (slot_b, B256::repeat_byte(8)),
+            ]),
+        };
+        let post_account = AccountState {
+            balance: Some(U256::from(7)),
+            nonce: None,
+            code: None,
+            storage: BTreeMap::from([
+                (slot_a, B256::repeat_byte(5)),
+                (slot_c, B256::repeat_byte(4)),
+            ]),
+        };
+        let created_account = AccountState { balance: Some(U256::from(3)), ..Default::default() };
+        let diff = DiffMode {
+            pre: BTreeMap::from([(existing, pre_account)]),
+            post: BTreeMap::from([(existing, post_account), (created, created_account)]),
+        };
+
+        let state_diff = geth_diff_to_trace_results(diff).state_diff.unwrap();
+        let account = &state_diff[&existing];
+        assert_eq!(
+            account.balance,
+            Delta::Changed(ChangedType { from: U256::from(10), to: U256::from(7) })
+        );
+        // fields omitted from post are unchanged
+        assert_eq!(account.nonce, Delta::Unchanged);
+        assert_eq!(account.code, Delta::Unchanged);
+        // slots omitted from post were reset to zero
+        assert_eq!(
+            account.storage[&slot_b],
+            Delta::Changed(ChangedType { from: B256::repeat_byte(8), to: B256::ZERO })
+        );
+        assert_eq!(
+            account.storage[&slot_c],
+            Delta::Changed(ChangedType { from: B256::ZERO, to: B256::repeat_byte(4) })
+        );
+        assert_eq!(account.storage.len(), 3);
+        assert_eq!(state_diff[&created].balance, Delta::Added(U256::from(3)));
+    }
+}
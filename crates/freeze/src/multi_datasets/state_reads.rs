@@ -59,7 +59,7 @@ impl CollectByBlock for StateReads {
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_reads(response, columns, &query.schemas)
+        process_state_reads(response, columns, query)
     }
 }
 
@@ -85,15 +85,16 @@ impl CollectByTransaction for StateReads {
     }
 
     fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
-        process_state_reads(response, columns, &query.schemas)
+        process_state_reads(response, columns, query)
     }
 }
 
 fn process_state_reads(
     response: BlockTxsTraces,
     columns: &mut StateReads,
-    schemas: &HashMap<Datatype, Table>,
+    query: &Query,
 ) -> R<()> {
+    let schemas = &query.schemas;
     let StateReads(balances, codes, nonces, storages) = columns;
     if schemas.get(&Datatype::BalanceReads).is_some() {
         balance_reads::process_balance_reads(&response, balances, schemas)?;
@@ -105,7 +106,7 @@ fn process_state_reads(
         nonce_reads::process_nonce_reads(&response, nonces, schemas)?;
     }
     if schemas.get(&Datatype::StorageReads).is_some() {
-        storage_reads::process_storage_reads(&response, storages, schemas)?;
+        storage_reads::process_storage_reads(&response, storages, schemas, &query.storage_slots)?;
     }
     Ok(())
 }
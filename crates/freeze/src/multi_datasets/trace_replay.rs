@@ -0,0 +1,427 @@
+use crate::*;
+use alloy::{
+    primitives::TxHash,
+    rpc::types::{
+        trace::parity::{
+            Action, LocalizedTransactionTrace, TraceResults, TraceResultsWithTransactionHash,
+            TraceType,
+        },
+        BlockNumberOrTag, BlockTransactionsKind,
+    },
+};
+use polars::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+};
+
+/// TraceReplay
+///
+/// state diffs, call traces, and vm traces replayed together with one parity `trace_replay*` call
+/// per block or transaction. it is only clustered with `--trace-replay`, so it is not a variant
+/// that can be requested by name
+#[derive(Default)]
+pub struct TraceReplay(
+    balance_diffs::BalanceDiffs,
+    code_diffs::CodeDiffs,
+    nonce_diffs::NonceDiffs,
+    storage_diffs::StorageDiffs,
+    contracts::Contracts,
+    native_transfers::NativeTransfers,
+    traces::Traces,
+    vm_traces::VmTraces,
+);
+
+/// block number, transaction hashes, replayed traces of each transaction, the call traces of
+/// the replays localized to their transactions, the gas of the transactions, and the
+/// transactions that are system transactions by type
+type BlockReplay = (
+    Option<u32>,
+    Vec<Option<Vec<u8>>>,
+    Vec<TraceResults>,
+    Vec<LocalizedTransactionTrace>,
+    traces::TransactionGas,
+    HashSet<TxHash>,
+);
+
+/// trace type that a datatype is replayed with, None if it is not collected from trace replays
+pub(crate) fn replay_trace_type(datatype: &Datatype) -> Option<TraceType> {
+    match datatype {
+        Datatype::BalanceDiffs |
+        Datatype::CodeDiffs |
+        Datatype::NonceDiffs |
+        Datatype::StorageDiffs => Some(TraceType::StateDiff),
+        Datatype::Contracts | Datatype::NativeTransfers | Datatype::Traces => {
+            Some(TraceType::Trace)
+        }
+        Datatype::VmTraces => Some(TraceType::VmTrace),
+        _ => None,
+    }
+}
+
+fn replay_trace_types(schemas: &Schemas) -> Vec<TraceType> {
+    [TraceType::Trace, TraceType::StateDiff, TraceType::VmTrace]
+        .into_iter()
+        .filter(|trace_type| {
+            schemas.keys().any(|datatype| replay_trace_type(datatype) == Some(*trace_type))
+        })
+        .collect()
+}
+
+const CALL_TRACE_DATATYPES: [Datatype; 3] =
+    [Datatype::Contracts, Datatype::NativeTransfers, Datatype::Traces];
+
+impl ToDataFrames for TraceReplay {
+    fn create_dfs(
+        self,
+        schemas: &HashMap<Datatype, Table>,
+        chain_id: u64,
+    ) -> R<HashMap<Datatype, DataFrame>> {
+        let TraceReplay(
+            balances,
+            codes,
+            nonces,
+            storages,
+            contracts,
+            native_transfers,
+            traces,
+            vm_traces,
+        ) = self;
+        let mut output = HashMap::new();
+        if schemas.contains_key(&Datatype::BalanceDiffs) {
+            output.extend(balances.create_dfs(schemas, chain_id)?);
+        }
+        if schemas.contains_key(&Datatype::CodeDiffs) {
+            output.extend(codes.create_dfs(schemas, chain_id)?);
+        }
+        if schemas.contains_key(&Datatype::NonceDiffs) {
+            output.extend(nonces.create_dfs(schemas, chain_id)?);
+        }
+        if schemas.contains_key(&Datatype::StorageDiffs) {
+            output.extend(storages.create_dfs(schemas, chain_id)?);
+        }
+        if schemas.contains_key(&Datatype::Contracts) {
+            output.extend(contracts.create_dfs(schemas, chain_id)?);
+        }
+        if schemas.contains_key(&Datatype::NativeTransfers) {
+            output.extend(native_transfers.create_dfs(schemas, chain_id)?);
+        }
+        if schemas.contains_key(&Datatype::Traces) {
+            output.extend(traces.create_dfs(schemas, chain_id)?);
+        }
+        if schemas.contains_key(&Datatype::VmTraces) {
+            output.extend(vm_traces.create_dfs(schemas, chain_id)?);
+        }
+        Ok(output)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByBlock for TraceReplay {
+    type Response = BlockReplay;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let block_number = request.block_number()?;
+        let block = BlockNumberOrTag::Number(block_number);
+        let target = format!("block {}", block_number);
+        let replay = |trace_types| source.trace_replay_block_transactions(block, trace_types);
+        let results = replay_trace_types_of(&query, &target, replay).await?;
+
+        // the block is only fetched for the block hash of call traces
+        let needs_block_hash = CALL_TRACE_DATATYPES.iter().any(|datatype| {
+            query.schemas.get(datatype).is_some_and(|schema| schema.has_column("block_hash"))
+        });
+        let block_hash = if needs_block_hash {
+            let block = source
+                .get_block(block_number, BlockTransactionsKind::Hashes)
+                .await?
+                .ok_or(CollectError::CollectError("could not find block".to_string()))?;
+            Some(block.header.hash)
+        } else {
+            None
+        };
+
+        let include_txs = query.schemas.values().any(|x| x.has_column("transaction_hash"));
+        let mut txs = Vec::with_capacity(results.len());
+        let mut trace_results = Vec::with_capacity(results.len());
+        let mut call_traces = Vec::new();
+        for (index, result) in results.into_iter().enumerate() {
+            let TraceResultsWithTransactionHash { mut full_trace, transaction_hash } = result;
+            for trace in std::mem::take(&mut full_trace.trace).into_iter() {
+                call_traces.push(LocalizedTransactionTrace {
+                    trace,
+                    block_hash,
+                    block_number: Some(block_number),
+                    transaction_hash: Some(transaction_hash),
+                    transaction_position: Some(index as u64),
+                });
+            }
+            txs.push(if include_txs { Some(transaction_hash.to_vec()) } else { None });
+            trace_results.push(full_trace);
+        }
+
+        // block rewards are not part of any transaction, so they are not replayed
+        if CALL_TRACE_DATATYPES.iter().any(|datatype| query.schemas.contains_key(datatype)) {
+            call_traces.extend(reward_traces(source.trace_block(block_number).await?));
+        }
+        let call_traces = traces::filter_traces_by_from_to_addresses(
+            call_traces,
+            &request.from_address,
+            &request.to_address,
+        );
+        let tx_gas = traces::block_transaction_gas(block_number, &source, &query).await?;
+        let system_txs = traces::system_tx_type_hashes(&call_traces, &source, &query).await?;
+        Ok((Some(block_number as u32), txs, trace_results, call_traces, tx_gas, system_txs))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_trace_replay(response, columns, query)
+    }
+}
+
+#[async_trait::async_trait]
+impl CollectByTransaction for TraceReplay {
+    type Response = BlockReplay;
+
+    async fn extract(request: Params, source: Arc<Source>, query: Arc<Query>) -> R<Self::Response> {
+        let tx_hash = request.ethers_transaction_hash()?;
+        let target = format!("transaction {}", tx_hash);
+        let source = &source;
+        let replay = move |trace_types| async move {
+            let full_trace = source.trace_replay_transaction(tx_hash, trace_types).await?;
+            Ok(vec![TraceResultsWithTransactionHash { full_trace, transaction_hash: tx_hash }])
+        };
+        let mut results = replay_trace_types_of(&query, &target, replay).await?;
+        let mut full_trace = results
+            .pop()
+            .ok_or(CollectError::CollectError("transaction not replayed".to_string()))?
+            .full_trace;
+
+        // the transaction is only fetched to localize call traces
+        let mut call_traces = Vec::new();
+        if CALL_TRACE_DATATYPES.iter().any(|datatype| query.schemas.contains_key(datatype)) {
+            let tx = source.get_transaction_by_hash(tx_hash).await?.ok_or_else(|| {
+                CollectError::CollectError("could not find transaction data".to_string())
+            })?;
+            if tx.block_number.is_none() {
+                return Err(CollectError::CollectError("transaction is pending".to_string()))
+            }
+            for trace in std::mem::take(&mut full_trace.trace).into_iter() {
+                call_traces.push(LocalizedTransactionTrace {
+                    trace,
+                    block_hash: tx.block_hash,
+                    block_number: tx.block_number,
+                    transaction_hash: Some(tx_hash),
+                    transaction_position: tx.transaction_index,
+                });
+            }
+        }
+        let call_traces = traces::filter_traces_by_from_to_addresses(
+            call_traces,
+            &request.from_address,
+            &request.to_address,
+        );
+        let tx_gas = traces::transaction_gas(tx_hash, source, &query).await?;
+        let system_txs = traces::system_tx_type_hashes(&call_traces, source, &query).await?;
+        let txs = vec![Some(tx_hash.to_vec())];
+        Ok((None, txs, vec![full_trace], call_traces, tx_gas, system_txs))
+    }
+
+    fn transform(response: Self::Response, columns: &mut Self, query: &Arc<Query>) -> R<()> {
+        process_trace_replay(response, columns, query)
+    }
+}
+
+fn reward_traces(traces: Vec<LocalizedTransactionTrace>) -> Vec<LocalizedTransactionTrace> {
+    traces.into_iter().filter(|trace| matches!(trace.trace.action, Action::Reward(_))).collect()
+}
+
+/// replay every trace type needed by query at once
+///
+/// some nodes return only part of the requested trace types, so trace types missing from the
+/// results are replayed again on their own, and are an error of the datatypes that need them if
+/// they are still missing
+async fn replay_trace_types_of<F, Fut>(
+    query: &Query,
+    target: &str,
+    replay: F,
+) -> R<Vec<TraceResultsWithTransactionHash>>
+where
+    F: Fn(Vec<TraceType>) -> Fut,
+    Fut: Future<Output = R<Vec<TraceResultsWithTransactionHash>>>,
+{
+    let trace_types = replay_trace_types(&query.schemas);
+    let mut results = replay(trace_types.clone()).await?;
+    let missing = missing_trace_types(&results, &trace_types);
+    if missing.is_empty() {
+        return Ok(results)
+    }
+
+    let fills = replay(missing.clone()).await?;
+    if fills.len() != results.len() {
+        let message = format!("replays of {} returned different numbers of transactions", target);
+        return Err(CollectError::CollectError(message))
+    }
+    for (result, fill) in results.iter_mut().zip(fills) {
+        fill_trace_types(&mut result.full_trace, fill.full_trace, &missing);
+    }
+    if let Some(trace_type) = missing_trace_types(&results, &missing).first() {
+        let datatypes: Vec<String> = query
+            .schemas
+            .keys()
+            .filter(|datatype| replay_trace_type(datatype) == Some(*trace_type))
+            .map(|datatype| datatype.name())
+            .collect();
+        let message = format!(
+            "node did not return {:?} traces of {}, which are needed by {}",
+            trace_type,
+            target,
+            datatypes.join(", ")
+        );
+        return Err(CollectError::CollectError(message))
+    }
+    Ok(results)
+}
+
+fn missing_trace_types(
+    results: &[TraceResultsWithTransactionHash],
+    trace_types: &[TraceType],
+) -> Vec<TraceType> {
+    trace_types
+        .iter()
+        .filter(|trace_type| {
+            !results.iter().all(|result| has_trace_type(&result.full_trace, trace_type))
+        })
+        .copied()
+        .collect()
+}
+
+/// every transaction has a top-level call trace, so empty call traces were not returned
+fn has_trace_type(result: &TraceResults, trace_type: &TraceType) -> bool {
+    match trace_type {
+        TraceType::Trace => !result.trace.is_empty(),
+        TraceType::StateDiff => result.state_diff.is_some(),
+        TraceType::VmTrace => result.vm_trace.is_some(),
+    }
+}
+
+fn fill_trace_types(result: &mut TraceResults, fill: TraceResults, trace_types: &[TraceType]) {
+    let TraceResults { trace, state_diff, vm_trace, .. } = fill;
+    if trace_types.contains(&TraceType::Trace) {
+        result.trace = trace;
+    }
+    if trace_types.contains(&TraceType::StateDiff) {
+        result.state_diff = state_diff;
+    }
+    if trace_types.contains(&TraceType::VmTrace) {
+        result.vm_trace = vm_trace;
+    }
+}
+
+fn process_trace_replay(response: BlockReplay, columns: &mut TraceReplay, query: &Query) -> R<()> {
+    let (block_number, txs, results, call_traces, tx_gas, system_txs) = response;
+    let schemas = &query.schemas;
+    let TraceReplay(
+        balances,
+        codes,
+        nonces,
+        storages,
+        contracts,
+        native_transfers,
+        traces,
+        vm_traces,
+    ) = columns;
+
+    let diffs = (block_number, txs, results);
+    if schemas.contains_key(&Datatype::BalanceDiffs) {
+        balance_diffs::process_balance_diffs(&diffs, balances, schemas)?;
+    }
+    if schemas.contains_key(&Datatype::CodeDiffs) {
+        code_diffs::process_code_diffs(&diffs, codes, schemas)?;
+    }
+    if schemas.contains_key(&Datatype::NonceDiffs) {
+        nonce_diffs::process_nonce_diffs(&diffs, nonces, schemas)?;
+    }
+    if schemas.contains_key(&Datatype::StorageDiffs) {
+        storage_diffs::process_storage_diffs(&diffs, storages, query)?;
+    }
+
+    if schemas.contains_key(&Datatype::Contracts) ||
+        schemas.contains_key(&Datatype::NativeTransfers)
+    {
+        let derived = if query.exclude_failed {
+            traces::filter_failed_traces(call_traces.clone())
+        } else {
+            call_traces.clone()
+        };
+        if schemas.contains_key(&Datatype::Contracts) {
+            contracts::process_contracts(&derived, contracts, schemas)?;
+        }
+        if schemas.contains_key(&Datatype::NativeTransfers) {
+            native_transfers::process_native_transfers(&derived, native_transfers, schemas)?;
+        }
+    }
+    if schemas.contains_key(&Datatype::Traces) {
+        let call_traces = traces::filter_query_traces(call_traces, query, &system_txs);
+        traces::process_traces(&call_traces, &tx_gas, traces, schemas)?;
+    }
+
+    // vm traces of blocks are not labeled with transaction hashes
+    if schemas.contains_key(&Datatype::VmTraces) {
+        let (block_number, txs, results) = diffs;
+        let tx = match query.time_dimension {
+            TimeDimension::Transactions => txs.into_iter().next().flatten(),
+            TimeDimension::Blocks => None,
+        };
+        vm_traces::process_vm_traces((block_number, tx, results), vm_traces, schemas)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        primitives::{Address, B256, U256},
+        rpc::types::trace::parity::{RewardAction, RewardType, StateDiff, TransactionTrace},
+    };
+
+    #[test]
+    fn test_fill_trace_types() {
+        let partial = TraceResults {
+            output: Default::default(),
+            state_diff: None,
+            trace: vec![TransactionTrace::default()],
+            vm_trace: None,
+        };
+        let result = |full_trace| TraceResultsWithTransactionHash {
+            full_trace,
+            transaction_hash: B256::ZERO,
+        };
+        let trace_types = [TraceType::Trace, TraceType::StateDiff];
+        let mut results = vec![result(partial.clone())];
+        assert_eq!(missing_trace_types(&results, &trace_types), vec![TraceType::StateDiff]);
+
+        let fill = TraceResults { state_diff: Some(StateDiff::default()), trace: vec![], ..partial };
+        fill_trace_types(&mut results[0].full_trace, fill, &[TraceType::StateDiff]);
+        assert!(missing_trace_types(&results, &trace_types).is_empty());
+        assert_eq!(results[0].full_trace.trace.len(), 1);
+    }
+
+    #[test]
+    fn test_reward_traces() {
+        let trace = |action| LocalizedTransactionTrace {
+            trace: TransactionTrace { action, ..Default::default() },
+            block_hash: None,
+            block_number: Some(1),
+            transaction_hash: None,
+            transaction_position: None,
+        };
+        let reward_type = RewardType::Block;
+        let reward = RewardAction { author: Address::ZERO, reward_type, value: U256::ZERO };
+        let traces = vec![trace(Action::Call(Default::default())), trace(Action::Reward(reward))];
+        let rewards = reward_traces(traces);
+        assert_eq!(rewards.len(), 1);
+        assert!(matches!(rewards[0].trace.action, Action::Reward(_)));
+    }
+}
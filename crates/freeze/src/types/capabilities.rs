@@ -0,0 +1,548 @@
+use crate::{
+    datasets::{
+        address_appearances::uses_appearance_source,
+        transactions::{needs_receipts, skips_block_body},
+    },
+    err,
+    multi_datasets::trace_replay::replay_trace_type,
+    CollectError, Datatype, Query, ReceiptsSource, Source, StateDiffTracer, TimeDimension,
+};
+use alloy::{
+    primitives::{Address, TxHash, B256, U256},
+    rpc::types::{trace::parity::TraceType, BlockTransactionsKind, Filter},
+};
+use std::collections::BTreeSet;
+
+/// rpc method used to collect datasets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RpcMethod {
+    /// `eth_getBlockByNumber`
+    GetBlockByNumber,
+    /// `eth_getBlockReceipts`
+    GetBlockReceipts,
+    /// `eth_getLogs`
+    GetLogs,
+    /// `eth_getUncleByBlockNumberAndIndex`
+    GetUncleByBlockNumberAndIndex,
+    /// `eth_getTransactionByHash`
+    GetTransactionByHash,
+    /// `eth_getTransactionReceipt`
+    GetTransactionReceipt,
+    /// `eth_getBalance`
+    GetBalance,
+    /// `eth_getCode`
+    GetCode,
+    /// `eth_getStorageAt`
+    GetStorageAt,
+    /// `eth_getTransactionCount`
+    GetTransactionCount,
+    /// `eth_call`
+    Call,
+    /// `trace_block`
+    TraceBlock,
+    /// `trace_transaction`
+    TraceTransaction,
+    /// `trace_replayBlockTransactions`
+    TraceReplayBlockTransactions,
+    /// `trace_replayTransaction`
+    TraceReplayTransaction,
+    /// `trace_call`
+    TraceCall,
+    /// `debug_traceBlockByNumber`
+    DebugTraceBlockByNumber,
+    /// `debug_traceTransaction`
+    DebugTraceTransaction,
+    /// `debug_accountRange`
+    DebugAccountRange,
+}
+
+impl RpcMethod {
+    /// name of rpc method
+    pub fn name(&self) -> &'static str {
+        match self {
+            RpcMethod::GetBlockByNumber => "eth_getBlockByNumber",
+            RpcMethod::GetBlockReceipts => "eth_getBlockReceipts",
+            RpcMethod::GetLogs => "eth_getLogs",
+            RpcMethod::GetUncleByBlockNumberAndIndex => "eth_getUncleByBlockNumberAndIndex",
+            RpcMethod::GetTransactionByHash => "eth_getTransactionByHash",
+            RpcMethod::GetTransactionReceipt => "eth_getTransactionReceipt",
+            RpcMethod::GetBalance => "eth_getBalance",
+            RpcMethod::GetCode => "eth_getCode",
+            RpcMethod::GetStorageAt => "eth_getStorageAt",
+            RpcMethod::GetTransactionCount => "eth_getTransactionCount",
+            RpcMethod::Call => "eth_call",
+            RpcMethod::TraceBlock => "trace_block",
+            RpcMethod::TraceTransaction => "trace_transaction",
+            RpcMethod::TraceReplayBlockTransactions => "trace_replayBlockTransactions",
+            RpcMethod::TraceReplayTransaction => "trace_replayTransaction",
+            RpcMethod::TraceCall => "trace_call",
+            RpcMethod::DebugTraceBlockByNumber => "debug_traceBlockByNumber",
+            RpcMethod::DebugTraceTransaction => "debug_traceTransaction",
+            RpcMethod::DebugAccountRange => "debug_accountRange",
+        }
+    }
+
+    /// whether method reads historical state, which requires an archive node for old blocks
+    pub fn reads_state(&self) -> bool {
+        matches!(
+            self,
+            RpcMethod::GetBalance |
+                RpcMethod::GetCode |
+                RpcMethod::GetStorageAt |
+                RpcMethod::GetTransactionCount |
+                RpcMethod::Call |
+                RpcMethod::TraceReplayBlockTransactions |
+                RpcMethod::TraceReplayTransaction |
+                RpcMethod::TraceCall |
+                RpcMethod::DebugTraceBlockByNumber |
+                RpcMethod::DebugTraceTransaction |
+                RpcMethod::DebugAccountRange
+        )
+    }
+
+    /// whether method is called with a transaction hash instead of a block number
+    pub fn takes_transaction(&self) -> bool {
+        matches!(
+            self,
+            RpcMethod::GetTransactionByHash |
+                RpcMethod::GetTransactionReceipt |
+                RpcMethod::TraceTransaction |
+                RpcMethod::TraceReplayTransaction |
+                RpcMethod::DebugTraceTransaction
+        )
+    }
+}
+
+impl Datatype {
+    /// rpc methods used to collect datatype
+    pub fn rpc_methods(&self, query: &Query, receipts_source: ReceiptsSource) -> Vec<RpcMethod> {
+        let by_tx = matches!(query.time_dimension, TimeDimension::Transactions);
+        if query.replays_traces() && replay_trace_type(self).is_some() {
+            return match (self, by_tx) {
+                (Datatype::Contracts | Datatype::NativeTransfers | Datatype::Traces, false) => {
+                    vec![RpcMethod::TraceReplayBlockTransactions, RpcMethod::GetBlockByNumber]
+                }
+                (Datatype::Contracts | Datatype::NativeTransfers | Datatype::Traces, true) => {
+                    vec![RpcMethod::TraceReplayTransaction, RpcMethod::GetTransactionByHash]
+                }
+                (_, false) => vec![RpcMethod::TraceReplayBlockTransactions],
+                (_, true) => vec![RpcMethod::TraceReplayTransaction],
+            }
+        }
+        let state_diffs = match (query.state_diff_tracer, by_tx) {
+            (StateDiffTracer::Parity, false) => RpcMethod::TraceReplayBlockTransactions,
+            (StateDiffTracer::Parity, true) => RpcMethod::TraceReplayTransaction,
+            (StateDiffTracer::Geth, false) => RpcMethod::DebugTraceBlockByNumber,
+            (StateDiffTracer::Geth, true) => RpcMethod::DebugTraceTransaction,
+        };
+        let (parity_traces, geth_traces, vm_traces) = if by_tx {
+            (
+                RpcMethod::TraceTransaction,
+                RpcMethod::DebugTraceTransaction,
+                RpcMethod::TraceReplayTransaction,
+            )
+        } else {
+            (
+                RpcMethod::TraceBlock,
+                RpcMethod::DebugTraceBlockByNumber,
+                RpcMethod::TraceReplayBlockTransactions,
+            )
+        };
+        match (self, by_tx) {
+            (Datatype::AddressAppearances, _) => {
+                let mut methods = if by_tx {
+                    vec![RpcMethod::GetTransactionByHash, RpcMethod::GetBlockByNumber]
+                } else {
+                    vec![RpcMethod::GetBlockByNumber]
+                };
+                if uses_appearance_source(query, "logs") {
+                    methods.push(if by_tx {
+                        RpcMethod::GetTransactionReceipt
+                    } else {
+                        RpcMethod::GetLogs
+                    });
+                }
+                if uses_appearance_source(query, "traces") {
+                    methods.push(parity_traces);
+                }
+                methods
+            }
+            (
+                Datatype::BalanceDiffs |
+                Datatype::CodeDiffs |
+                Datatype::NonceDiffs |
+                Datatype::StorageDiffs,
+                _,
+            ) => vec![state_diffs],
+            (
+                Datatype::BalanceReads |
+                Datatype::CodeReads |
+                Datatype::NonceReads |
+                Datatype::StorageReads |
+                Datatype::FourByteCounts |
+                Datatype::GethCalls |
+                Datatype::GethOpcodes |
+                Datatype::JavascriptTraces |
+                Datatype::GethBalanceDiffs |
+                Datatype::GethCodeDiffs |
+                Datatype::GethNonceDiffs |
+                Datatype::GethStorageDiffs,
+                _,
+            ) => vec![geth_traces],
+            (Datatype::Balances, _) => vec![RpcMethod::GetBalance],
+            (Datatype::Codes, _) => vec![RpcMethod::GetCode],
+            (Datatype::Nonces, _) => vec![RpcMethod::GetTransactionCount],
+            (Datatype::GenesisAlloc, _) => {
+                if query.partitions.iter().any(|partition| partition.addresses.is_some()) {
+                    vec![RpcMethod::GetBalance, RpcMethod::GetTransactionCount, RpcMethod::GetCode]
+                } else {
+                    vec![RpcMethod::DebugAccountRange]
+                }
+            }
+            (Datatype::Slots, _) => vec![RpcMethod::GetStorageAt],
+            (
+                Datatype::Erc20Balances |
+                Datatype::Erc20Metadata |
+                Datatype::Erc20Supplies |
+                Datatype::Erc721Metadata |
+                Datatype::EthCalls,
+                _,
+            ) => vec![RpcMethod::Call],
+            (Datatype::Simulations | Datatype::TraceCalls, _) => vec![RpcMethod::TraceCall],
+            (Datatype::Blobs | Datatype::Blocks | Datatype::TransactionAccessLists, false) => {
+                vec![RpcMethod::GetBlockByNumber]
+            }
+            (Datatype::Blobs | Datatype::Blocks | Datatype::TransactionAccessLists, true) => {
+                vec![RpcMethod::GetTransactionByHash]
+            }
+            (Datatype::Contracts | Datatype::NativeTransfers | Datatype::Traces, _) => {
+                vec![parity_traces]
+            }
+            (
+                Datatype::Erc20Approvals |
+                Datatype::Erc20Transfers |
+                Datatype::Erc721Transfers |
+                Datatype::Logs,
+                false,
+            ) => vec![RpcMethod::GetLogs],
+            (
+                Datatype::Erc20Approvals |
+                Datatype::Erc20Transfers |
+                Datatype::Erc721Transfers |
+                Datatype::Logs,
+                true,
+            ) => vec![RpcMethod::GetTransactionReceipt],
+            (Datatype::Transactions, false) if skips_block_body(query, receipts_source) => {
+                vec![RpcMethod::GetBlockReceipts]
+            }
+            (Datatype::Transactions, false) => {
+                let with_receipts = query
+                    .schemas
+                    .get(&Datatype::Transactions)
+                    .map(|schema| needs_receipts(schema) | schema.has_column("success"))
+                    .unwrap_or(false) ||
+                    query.min_priority_fee.is_some();
+                match (with_receipts, receipts_source) {
+                    (false, _) => vec![RpcMethod::GetBlockByNumber],
+                    (true, ReceiptsSource::Block) => {
+                        vec![RpcMethod::GetBlockByNumber, RpcMethod::GetBlockReceipts]
+                    }
+                    (true, _) => {
+                        vec![RpcMethod::GetBlockByNumber, RpcMethod::GetTransactionReceipt]
+                    }
+                }
+            }
+            (Datatype::Transactions, true) => {
+                vec![RpcMethod::GetTransactionByHash, RpcMethod::GetTransactionReceipt]
+            }
+            (Datatype::Uncles, _) => {
+                let with_headers = query.schemas.get(&Datatype::Uncles).is_some_and(|schema| {
+                    schema.has_column("uncle_number") || schema.has_column("uncle_miner")
+                });
+                if with_headers {
+                    vec![RpcMethod::GetBlockByNumber, RpcMethod::GetUncleByBlockNumberAndIndex]
+                } else {
+                    vec![RpcMethod::GetBlockByNumber]
+                }
+            }
+            (Datatype::VmTraces, _) => vec![vm_traces],
+        }
+    }
+}
+
+/// rpc method whose probe failed
+#[derive(Clone, Debug)]
+pub struct UnsupportedRpcMethod {
+    /// rpc method
+    pub method: RpcMethod,
+    /// block that was probed
+    pub block_number: u64,
+    /// whether the probe read historical state of the earliest block of the query
+    pub historical: bool,
+    /// error returned by the node
+    pub error: String,
+}
+
+/// blocks behind the chain tip of the recent probe block, so that nodes lagging the tip have it
+const PROBE_BLOCK_LAG: u64 = 8;
+
+/// most blocks searched backwards for a transaction to probe transaction methods with
+const MAX_PROBE_BLOCK_SEARCH: u64 = 16;
+
+/// issue a cheap call of each rpc method needed by query, returning the methods that failed
+///
+/// methods are probed at a recent block, and methods that read historical state are probed again
+/// at the earliest block of the query. methods that trace every transaction of a block are probed
+/// as called by the collection, and only if tracing the block fails without a response from the
+/// node, such as a timeout on a busy block, is a single transaction of the same namespace traced
+/// instead
+pub async fn probe_rpc_methods(
+    query: &Query,
+    source: &Source,
+) -> Result<Vec<UnsupportedRpcMethod>, CollectError> {
+    let source = Source { raw_response_dir: None, ..source.clone() };
+    let mut methods = BTreeSet::new();
+    for datatype in query.datatypes.iter().flat_map(|datatype| datatype.datatypes()) {
+        methods.extend(datatype.rpc_methods(query, source.receipts_source));
+    }
+    if methods.is_empty() {
+        return Ok(vec![])
+    }
+
+    let recent_block = source.get_block_number().await?.saturating_sub(PROBE_BLOCK_LAG);
+    let earliest_block = query
+        .partitions
+        .iter()
+        .filter_map(|partition| partition.stats().block_numbers?.min_value)
+        .min()
+        .filter(|block_number| *block_number < recent_block);
+    let requested_tx = query
+        .partitions
+        .iter()
+        .find_map(|partition| partition.stats().transactions?.min_value)
+        .map(|tx_hash| B256::from_slice(&tx_hash));
+    let needs_tx = methods.iter().any(|method| method.takes_transaction());
+
+    let mut probes = vec![(recent_block, false)];
+    if let Some(block_number) = earliest_block {
+        probes.push((block_number, true))
+    }
+    let mut unsupported = Vec::new();
+    for (block_number, historical) in probes.into_iter() {
+        let mut tx_hash = match requested_tx {
+            Some(tx_hash) => Some(tx_hash),
+            None if needs_tx => sample_transaction(&source, block_number).await,
+            None => None,
+        };
+        for method in methods.iter() {
+            if historical && !method.reads_state() {
+                continue
+            }
+            if method.takes_transaction() && tx_hash.is_none() {
+                continue
+            }
+            let mut result = probe_rpc_method(&source, *method, block_number, tx_hash).await;
+            if let (Err(e), Some(tx_method)) = (&result, transaction_counterpart(*method)) {
+                if !is_error_response(e) {
+                    if tx_hash.is_none() {
+                        tx_hash = sample_transaction(&source, block_number).await;
+                    }
+                    if tx_hash.is_some() {
+                        result = probe_rpc_method(&source, tx_method, block_number, tx_hash).await;
+                    }
+                }
+            }
+            if let Err(e) = result {
+                unsupported.push(UnsupportedRpcMethod {
+                    method: *method,
+                    block_number,
+                    historical,
+                    error: e.to_string(),
+                })
+            }
+        }
+    }
+    Ok(unsupported)
+}
+
+/// whether the node responded to a probe with an error, as opposed to the probe failing to get a
+/// response, in which case support of the method is unknown
+fn is_error_response(error: &CollectError) -> bool {
+    matches!(error, CollectError::ProviderError(alloy::transports::RpcError::ErrorResp(_)))
+}
+
+/// method that traces a single transaction, probed in place of a method that traces a whole block
+/// when the block could not be traced
+fn transaction_counterpart(method: RpcMethod) -> Option<RpcMethod> {
+    match method {
+        RpcMethod::TraceBlock => Some(RpcMethod::TraceTransaction),
+        RpcMethod::TraceReplayBlockTransactions => Some(RpcMethod::TraceReplayTransaction),
+        RpcMethod::DebugTraceBlockByNumber => Some(RpcMethod::DebugTraceTransaction),
+        _ => None,
+    }
+}
+
+/// hash of a transaction in block or in one of the blocks before it
+async fn sample_transaction(source: &Source, block_number: u64) -> Option<TxHash> {
+    let first_block = block_number.saturating_sub(MAX_PROBE_BLOCK_SEARCH);
+    for number in (first_block..=block_number).rev() {
+        if let Ok(Some(block)) = source.get_block(number, BlockTransactionsKind::Hashes).await {
+            if let Some(tx_hash) = block.transactions.hashes().next() {
+                return Some(tx_hash)
+            }
+        }
+    }
+    None
+}
+
+/// issue a cheap call of rpc method
+async fn probe_rpc_method(
+    source: &Source,
+    method: RpcMethod,
+    block_number: u64,
+    tx_hash: Option<TxHash>,
+) -> Result<(), CollectError> {
+    let address = Address::ZERO;
+    let tx_hash = || tx_hash.ok_or_else(|| err("no transaction to probe with"));
+    let not_found = |item: &str| err(&format!("{} {} not found", item, block_number));
+    match method {
+        RpcMethod::GetBlockByNumber => source
+            .get_block(block_number, BlockTransactionsKind::Hashes)
+            .await?
+            .map(drop)
+            .ok_or_else(|| not_found("block")),
+        RpcMethod::GetBlockReceipts => source
+            .get_block_receipts(block_number)
+            .await?
+            .map(drop)
+            .ok_or_else(|| not_found("receipts of block")),
+        RpcMethod::GetUncleByBlockNumberAndIndex => {
+            source.get_uncle(block_number, 0).await.map(drop)
+        }
+        RpcMethod::GetLogs => {
+            let filter = Filter::new().from_block(block_number).to_block(block_number);
+            source.get_logs(&filter).await.map(drop)
+        }
+        RpcMethod::GetTransactionByHash => source
+            .get_transaction_by_hash(tx_hash()?)
+            .await?
+            .map(drop)
+            .ok_or_else(|| err("transaction not found")),
+        RpcMethod::GetTransactionReceipt => source
+            .get_transaction_receipt(tx_hash()?)
+            .await?
+            .map(drop)
+            .ok_or_else(|| err("transaction receipt not found")),
+        RpcMethod::GetBalance => source.get_balance(address, block_number).await.map(drop),
+        RpcMethod::GetCode => source.get_code(address, block_number).await.map(drop),
+        RpcMethod::GetStorageAt => {
+            source.get_storage_at(address, U256::ZERO, block_number).await.map(drop)
+        }
+        RpcMethod::GetTransactionCount => {
+            source.get_transaction_count(address, block_number).await.map(drop)
+        }
+        RpcMethod::Call => source.call2(address, vec![], block_number).await.map(drop),
+        RpcMethod::TraceBlock => source.trace_block(block_number).await.map(drop),
+        RpcMethod::TraceTransaction => source.trace_transaction(tx_hash()?).await.map(drop),
+        RpcMethod::TraceReplayBlockTransactions => source
+            .trace_replay_block_transactions(block_number.into(), vec![TraceType::Trace])
+            .await
+            .map(drop),
+        RpcMethod::TraceReplayTransaction => source
+            .trace_replay_transaction(tx_hash()?, vec![TraceType::Trace])
+            .await
+            .map(drop),
+        RpcMethod::TraceCall => source
+            .trace_call2(address, vec![], vec![TraceType::Trace], Some(block_number))
+            .await
+            .map(drop),
+        RpcMethod::DebugTraceBlockByNumber => {
+            source.geth_debug_trace_block_calls(block_number as u32, false, false).await.map(drop)
+        }
+        RpcMethod::DebugTraceTransaction => source
+            .geth_debug_trace_transaction_calls(tx_hash()?.to_vec(), false, false)
+            .await
+            .map(drop),
+        RpcMethod::DebugAccountRange => {
+            source.debug_account_range(block_number, B256::ZERO, 1).await.map(drop)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColumnEncoding, Partition, U256Type};
+
+    fn query(datatype: Datatype) -> Query {
+        let u256_types = [U256Type::Binary];
+        let schema = datatype
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &None, None, None)
+            .unwrap();
+        Query::for_datatype(datatype, schema, vec![Partition::default()])
+    }
+
+    #[test]
+    fn test_rpc_methods() {
+        let methods = |query: &Query, datatype: Datatype| {
+            datatype.rpc_methods(query, ReceiptsSource::Auto)
+        };
+
+        // methods of each time dimension
+        let mut blocks = query(Datatype::Blocks);
+        assert_eq!(methods(&blocks, Datatype::Blocks), vec![RpcMethod::GetBlockByNumber]);
+        blocks.time_dimension = TimeDimension::Transactions;
+        assert_eq!(methods(&blocks, Datatype::Blocks), vec![RpcMethod::GetTransactionByHash]);
+        let logs = query(Datatype::Logs);
+        assert_eq!(methods(&logs, Datatype::Logs), vec![RpcMethod::GetLogs]);
+
+        // state diffs follow the tracer
+        let mut diffs = query(Datatype::StorageDiffs);
+        let expected = vec![RpcMethod::TraceReplayBlockTransactions];
+        assert_eq!(methods(&diffs, Datatype::StorageDiffs), expected);
+        diffs.state_diff_tracer = StateDiffTracer::Geth;
+        let expected = vec![RpcMethod::DebugTraceBlockByNumber];
+        assert_eq!(methods(&diffs, Datatype::StorageDiffs), expected);
+
+        // receipts follow the receipts source
+        let txs = query(Datatype::Transactions);
+        let expected = vec![RpcMethod::GetBlockByNumber, RpcMethod::GetBlockReceipts];
+        assert_eq!(Datatype::Transactions.rpc_methods(&txs, ReceiptsSource::Block), expected);
+        let expected = vec![RpcMethod::GetBlockByNumber, RpcMethod::GetTransactionReceipt];
+        assert_eq!(Datatype::Transactions.rpc_methods(&txs, ReceiptsSource::Auto), expected);
+
+        // calls
+        let calls = query(Datatype::EthCalls);
+        assert_eq!(methods(&calls, Datatype::EthCalls), vec![RpcMethod::Call]);
+        let traces = query(Datatype::TraceCalls);
+        assert_eq!(methods(&traces, Datatype::TraceCalls), vec![RpcMethod::TraceCall]);
+    }
+
+    #[test]
+    fn test_transaction_counterpart() {
+        let expected = Some(RpcMethod::TraceReplayTransaction);
+        assert_eq!(transaction_counterpart(RpcMethod::TraceReplayBlockTransactions), expected);
+        let expected = Some(RpcMethod::DebugTraceTransaction);
+        assert_eq!(transaction_counterpart(RpcMethod::DebugTraceBlockByNumber), expected);
+        assert_eq!(transaction_counterpart(RpcMethod::GetBlockByNumber), None);
+        for method in [RpcMethod::TraceBlock, RpcMethod::DebugTraceBlockByNumber] {
+            assert!(transaction_counterpart(method).unwrap().takes_transaction());
+        }
+    }
+
+    #[test]
+    fn test_is_error_response() {
+        let payload = alloy::rpc::json_rpc::ErrorPayload {
+            code: -32601,
+            message: "the method trace_block does not exist".into(),
+            data: None,
+        };
+        let error = CollectError::ProviderError(alloy::transports::RpcError::ErrorResp(payload));
+        assert!(is_error_response(&error));
+
+        // no response leaves the support of the method unknown
+        let error = CollectError::ProviderError(alloy::transports::RpcError::NullResp);
+        assert!(!is_error_response(&error));
+        assert!(!is_error_response(&CollectError::CollectError("timeout".to_string())));
+    }
+}
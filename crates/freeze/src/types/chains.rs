@@ -0,0 +1,220 @@
+use crate::Datatype;
+use alloy::primitives::{address, Address};
+
+/// chain-specific collector behavior
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainProfile {
+    /// family of chain, e.g. `ethereum`, `optimism`, or `arbitrum`
+    pub family: ChainFamily,
+    /// address ranges of precompiled contracts
+    pub precompile_ranges: Vec<(u64, u64)>,
+    /// senders of system transactions, which are issued by the protocol rather than by users
+    pub system_senders: Vec<Address>,
+    /// types of system transactions, which are not signed by a user
+    pub system_tx_types: Vec<u8>,
+    /// first block of the current client of the chain, earlier blocks were migrated from a
+    /// legacy client and cannot be traced by current nodes
+    pub first_traceable_block: Option<u64>,
+}
+
+/// family of chains that share collector behavior
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainFamily {
+    /// ethereum mainnet, testnets, and other chains without special semantics
+    Ethereum,
+    /// op stack chains, which include l1 deposit transactions
+    OpStack,
+    /// arbitrum chains, which include arbos internal transactions
+    Arbitrum,
+}
+
+/// sender of op stack l1 attributes deposit transactions
+const OP_STACK_DEPOSITOR: Address = address!("deaddeaddeaddeaddeaddeaddeaddeaddead0001");
+
+/// sender of arbos internal transactions
+const ARBOS: Address = address!("00000000000000000000000000000000000a4b05");
+
+impl ChainProfile {
+    /// get profile of chain, by chain id and then by network name
+    ///
+    /// unknown chains use ethereum mainnet behavior
+    pub fn new(chain_id: u64, network_name: &str) -> ChainProfile {
+        let family = match (chain_id, network_name) {
+            (10 | 420 | 8453 | 84531 | 7777777, _) => ChainFamily::OpStack,
+            (42161 | 42170, _) => ChainFamily::Arbitrum,
+            (_, "optimism" | "base" | "zora") => ChainFamily::OpStack,
+            (_, "arbitrum" | "arbitrum_nova") => ChainFamily::Arbitrum,
+            _ => ChainFamily::Ethereum,
+        };
+        let first_traceable_block = match chain_id {
+            // bedrock upgrade of optimism mainnet
+            10 => Some(105_235_063),
+            // nitro genesis of arbitrum one
+            42161 => Some(22_207_817),
+            _ => None,
+        };
+        ChainProfile { first_traceable_block, ..ChainProfile::from_family(family) }
+    }
+
+    /// get profile shared by a family of chains
+    pub fn from_family(family: ChainFamily) -> ChainProfile {
+        // ethereum precompiles through prague
+        let mut precompile_ranges = vec![(0x1, 0x11)];
+        let mut system_senders = Vec::new();
+        let mut system_tx_types = Vec::new();
+        match family {
+            ChainFamily::Ethereum => {}
+            ChainFamily::OpStack => {
+                // RIP-7212
+                precompile_ranges.push((0x100, 0x100));
+                system_senders.push(OP_STACK_DEPOSITOR);
+                // deposit transactions
+                system_tx_types.push(0x7e);
+            }
+            ChainFamily::Arbitrum => {
+                // arbitrum precompiles and RIP-7212
+                precompile_ranges.push((0x64, 0x100));
+                system_senders.push(ARBOS);
+                // deposit and internal transactions
+                system_tx_types.extend([0x64, 0x6a]);
+            }
+        }
+        ChainProfile {
+            family,
+            precompile_ranges,
+            system_senders,
+            system_tx_types,
+            first_traceable_block: None,
+        }
+    }
+
+    /// whether address sends system transactions on this chain
+    pub fn is_system_sender(&self, address: &Address) -> bool {
+        self.system_senders.contains(address)
+    }
+
+    /// whether transactions of this type are system transactions on this chain
+    pub fn is_system_tx_type(&self, tx_type: u8) -> bool {
+        self.system_tx_types.contains(&tx_type)
+    }
+
+    /// recommended default block range for datatypes, None if `0:latest` is recommended
+    pub fn default_blocks(&self, datatypes: &[Datatype]) -> Option<String> {
+        let first_block = self.first_traceable_block?;
+        datatypes.iter().any(is_traced).then(|| format!("{}:latest", first_block))
+    }
+
+    /// reasons that datatypes cannot be collected on this chain, starting from min_block
+    pub fn unsupported_datatypes(
+        &self,
+        datatypes: &[Datatype],
+        min_block: Option<u64>,
+    ) -> Vec<String> {
+        let mut reasons = Vec::new();
+        for datatype in datatypes.iter() {
+            if *datatype == Datatype::Blobs && self.family != ChainFamily::Ethereum {
+                reasons.push("blobs are only posted to ethereum l1 chains".to_string());
+            }
+            if let (Some(first_block), Some(min_block)) = (self.first_traceable_block, min_block) {
+                if is_traced(datatype) && min_block < first_block {
+                    reasons.push(format!(
+                        "{} cannot be traced before block {}, which this chain migrated from \
+                        a legacy client",
+                        datatype.name(),
+                        first_block
+                    ));
+                }
+            }
+        }
+        reasons
+    }
+}
+
+/// whether datatype is collected with trace_* or debug_trace* methods
+fn is_traced(datatype: &Datatype) -> bool {
+    matches!(
+        datatype,
+        Datatype::BalanceDiffs |
+            Datatype::BalanceReads |
+            Datatype::CodeDiffs |
+            Datatype::CodeReads |
+            Datatype::Contracts |
+            Datatype::FourByteCounts |
+            Datatype::GethCalls |
+            Datatype::GethCodeDiffs |
+            Datatype::GethBalanceDiffs |
+            Datatype::GethStorageDiffs |
+            Datatype::GethNonceDiffs |
+            Datatype::GethOpcodes |
+            Datatype::JavascriptTraces |
+            Datatype::NativeTransfers |
+            Datatype::NonceDiffs |
+            Datatype::NonceReads |
+            Datatype::StorageDiffs |
+            Datatype::StorageReads |
+            Datatype::Traces |
+            Datatype::VmTraces
+    )
+}
+
+impl Default for ChainProfile {
+    fn default() -> Self {
+        ChainProfile::from_family(ChainFamily::Ethereum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_profile() {
+        assert_eq!(ChainProfile::new(1, "ethereum").family, ChainFamily::Ethereum);
+        assert_eq!(ChainProfile::new(8453, "base").family, ChainFamily::OpStack);
+        assert_eq!(ChainProfile::new(42161, "network_42161").family, ChainFamily::Arbitrum);
+
+        // network name is used for chains with unknown ids
+        assert_eq!(ChainProfile::new(31337, "arbitrum").family, ChainFamily::Arbitrum);
+
+        // unknown chains default to ethereum mainnet behavior
+        let profile = ChainProfile::new(31337, "network_31337");
+        assert_eq!(profile, ChainProfile::default());
+        assert!(!profile.is_system_sender(&OP_STACK_DEPOSITOR));
+        assert!(ChainProfile::new(10, "optimism").is_system_sender(&OP_STACK_DEPOSITOR));
+    }
+
+    #[test]
+    fn test_system_tx_types() {
+        assert!(!ChainProfile::default().is_system_tx_type(0x7e));
+        assert!(ChainProfile::new(10, "optimism").is_system_tx_type(0x7e));
+        assert!(!ChainProfile::new(10, "optimism").is_system_tx_type(0x2));
+        assert!(ChainProfile::new(42161, "arbitrum").is_system_tx_type(0x6a));
+        assert!(!ChainProfile::new(42161, "arbitrum").is_system_tx_type(0x7e));
+    }
+
+    #[test]
+    fn test_precompile_ranges() {
+        assert_eq!(ChainProfile::new(1, "ethereum").precompile_ranges, vec![(0x1, 0x11)]);
+        let optimism = ChainProfile::new(10, "optimism").precompile_ranges;
+        assert_eq!(optimism, vec![(0x1, 0x11), (0x100, 0x100)]);
+        let arbitrum = ChainProfile::new(42161, "arbitrum").precompile_ranges;
+        assert_eq!(arbitrum, vec![(0x1, 0x11), (0x64, 0x100)]);
+    }
+
+    #[test]
+    fn test_chain_defaults() {
+        let optimism = ChainProfile::new(10, "optimism");
+        let traces = [Datatype::Blocks, Datatype::StorageDiffs];
+        assert_eq!(optimism.default_blocks(&traces), Some("105235063:latest".to_string()));
+        assert_eq!(optimism.default_blocks(&[Datatype::Blocks]), None);
+        assert_eq!(optimism.unsupported_datatypes(&traces, Some(100)).len(), 1);
+        assert!(optimism.unsupported_datatypes(&traces, Some(105_235_063)).is_empty());
+        assert_eq!(optimism.unsupported_datatypes(&[Datatype::Blobs], None).len(), 1);
+
+        // unknown chains keep the default range without warnings
+        let unknown = ChainProfile::new(31337, "network_31337");
+        assert_eq!(unknown.default_blocks(&traces), None);
+        let datatypes = [Datatype::Blobs, Datatype::StorageDiffs];
+        assert!(unknown.unsupported_datatypes(&datatypes, Some(0)).is_empty());
+    }
+}
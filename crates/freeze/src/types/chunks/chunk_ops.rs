@@ -38,18 +38,19 @@ pub trait ChunkData: Sized {
         file_output: &FileOutput,
         chunk_label: &Option<String>,
     ) -> Result<std::path::PathBuf, FileError> {
-        let network_name = file_output.prefix.clone();
+        let prefix = file_output.prefix.clone();
         let stub = match chunk_label {
             Some(chunk_label) => chunk_label.clone(),
             None => self.stub()?,
         };
-        let pieces: Vec<String> = match &file_output.suffix {
+        let mut pieces: Vec<String> = match &file_output.suffix {
             Some(suffix) => {
-                vec![network_name, datatype.name(), stub, suffix.clone()]
+                vec![prefix, datatype.name(), stub, suffix.clone()]
             }
-            None => vec![network_name, datatype.name(), stub],
+            None => vec![prefix, datatype.name(), stub],
         };
-        let filename = format!("{}.{}", pieces.join("__"), file_output.format.as_str());
+        pieces.retain(|piece| !piece.is_empty());
+        let filename = format!("{}.{}", pieces.join("__"), file_output.extension());
         Ok(file_output.output_dir.join(filename))
     }
 
@@ -60,7 +60,7 @@ impl NumberChunk {
                 })
                 .collect(),
             NumberChunk::Range(start_block, end_block) => {
-                let chunks = range_to_chunks(start_block, &(end_block + 1), log_request_size);
+                let chunks = range_to_chunks(start_block, end_block, log_request_size);
                 chunks
                     .iter()
                     .map(|(start, end)| FilterBlockOption::Range {
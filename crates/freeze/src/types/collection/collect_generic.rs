@@ -1,9 +1,16 @@
-use crate::*;
+use crate::{
+    dataframes::{sort_dfs, ChunkAppender},
+    *,
+};
+use alloy::rpc::types::BlockTransactionsKind;
 use futures::Future;
 use polars::prelude::*;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 use tokio::{sync::mpsc, task};
 
+/// most transactions of a block collected together when expanding blocks into transactions
+const TRANSACTION_BATCH_SIZE: usize = 1000;
+
 /// collect single partition
 pub async fn collect_partition(
     datatype: MetaDatatype,
@@ -11,12 +18,199 @@ pub async fn collect_partition(
     query: Arc<Query>,
     source: Arc<Source>,
 ) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
-    match query.time_dimension {
-        TimeDimension::Blocks => collect_by_block(datatype, partition, source, query).await,
+    collect_partition_appending(datatype, partition, query, source, None).await
+}
+
+/// collect single partition, appending the rows collected so far to files whenever appender is
+/// due between the chunks and transaction batches of the partition
+pub(crate) async fn collect_partition_appending(
+    datatype: MetaDatatype,
+    partition: Partition,
+    query: Arc<Query>,
+    source: Arc<Source>,
+    appender: Option<&mut ChunkAppender>,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    let arc_query = query.clone();
+    let arc_source = source.clone();
+    let log_counts_partition = query.block_log_counts.is_some().then(|| partition.clone());
+    let dfs = match query.time_dimension {
+        TimeDimension::Blocks => match query.collect_chunk_size {
+            Some(chunk_size) => {
+                collect_by_block_chunks(datatype, partition, source, query, chunk_size, appender)
+                    .await
+            }
+            None => collect_by_block(datatype, partition, source, query).await,
+        },
+        TimeDimension::Transactions if query.transactions_from_blocks => {
+            collect_by_transactions_of_blocks(datatype, partition, source, query, appender).await
+        }
         TimeDimension::Transactions => {
             collect_by_transaction(datatype, partition, source, query).await
         }
+    };
+
+    // counts of block logs are shared only by the chunks in flight
+    if let (Some(counts), Some(partition)) = (&arc_query.block_log_counts, log_counts_partition) {
+        counts.evict(&partition);
+    }
+    process_dfs(dfs?, &arc_query, &arc_source).await
+}
+
+/// process the dataframes of a partition, or the rows of a partition appended so far
+async fn process_dfs(
+    mut dfs: HashMap<Datatype, DataFrame>,
+    query: &Query,
+    source: &Source,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    // logs are collapsed after chunks and batches are stacked, so keys span the whole partition
+    if let Some(key) = &query.collapse_logs {
+        if let Some(df) = dfs.remove(&Datatype::Logs) {
+            let schema = query.schemas.get_schema(&Datatype::Logs)?;
+            dfs.insert(Datatype::Logs, logs::collapse_logs(df, key, schema)?);
+        }
+    }
+
+    reduce_first_seen(&mut dfs, query)?;
+
+    if let Some(resolver) = &query.ens_resolver {
+        for (datatype, df) in dfs.iter_mut() {
+            let schema = query.schemas.get_schema(datatype)?;
+            *df = resolver.add_ens_names(std::mem::take(df), schema, source).await?;
+        }
+    }
+    Ok(dfs)
+}
+
+/// reduce address appearances to the first appearance of each address, if the query keeps only
+/// those, so that chunks of a partition hold one row per address rather than every appearance
+fn reduce_first_seen(
+    dfs: &mut HashMap<Datatype, DataFrame>,
+    query: &Query,
+) -> Result<(), CollectError> {
+    if query.first_seen {
+        if let Some(df) = dfs.remove(&Datatype::AddressAppearances) {
+            let schema = query.schemas.get_schema(&Datatype::AddressAppearances)?;
+            let df = address_appearances::first_seen(df, schema)?;
+            dfs.insert(Datatype::AddressAppearances, df);
+        }
+    }
+    Ok(())
+}
+
+/// append the dataframes collected so far if appender is due, dropping them from memory
+async fn append_if_due(
+    dfs: &mut HashMap<Datatype, DataFrame>,
+    appender: Option<&mut ChunkAppender>,
+    query: &Query,
+    source: &Source,
+) -> Result<(), CollectError> {
+    if let Some(appender) = appender.filter(|appender| appender.is_due()) {
+        sort_dfs(dfs, query)?;
+        appender.append(process_dfs(std::mem::take(dfs), query, source).await?)?;
+    }
+    Ok(())
+}
+
+/// collect by block in consecutive chunks of at most chunk_size blocks
+///
+/// chunks are collected one after another, so a partition spanning many chunks holds no more
+/// than its own data, and the stacked chunks are sorted once collected
+async fn collect_by_block_chunks(
+    datatype: MetaDatatype,
+    partition: Partition,
+    source: Arc<Source>,
+    query: Arc<Query>,
+    chunk_size: u64,
+    mut appender: Option<&mut ChunkAppender>,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    let chunks = match &partition.block_numbers {
+        Some(block_numbers) if block_numbers.size() > chunk_size => {
+            block_numbers.subchunk_by_size(&chunk_size)
+        }
+        _ => return collect_by_block(datatype, partition, source, query).await,
+    };
+    let mut dfs: HashMap<Datatype, DataFrame> = HashMap::new();
+    for chunk in chunks.into_iter() {
+        let chunk_partition = Partition { block_numbers: Some(vec![chunk]), ..partition.clone() };
+        let chunk_dfs =
+            collect_by_block(datatype.clone(), chunk_partition, source.clone(), query.clone())
+                .await?;
+        stack_dfs(&mut dfs, chunk_dfs)?;
+        reduce_first_seen(&mut dfs, &query)?;
+        append_if_due(&mut dfs, appender.as_deref_mut(), &query, &source).await?;
+    }
+    sort_dfs(&mut dfs, &query)?;
+    Ok(dfs)
+}
+
+/// append the dataframes of a batch to the dataframes collected so far
+fn stack_dfs(
+    dfs: &mut HashMap<Datatype, DataFrame>,
+    batch_dfs: HashMap<Datatype, DataFrame>,
+) -> Result<(), CollectError> {
+    for (datatype, df) in batch_dfs.into_iter() {
+        match dfs.entry(datatype) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().vstack_mut(&df)?;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(df);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// collect by transaction over the transactions of each block of partition
+///
+/// blocks are expanded into transaction hashes one block at a time, so the hashes of the whole
+/// partition are never held at once, and large blocks are collected in batches
+async fn collect_by_transactions_of_blocks(
+    datatype: MetaDatatype,
+    partition: Partition,
+    source: Arc<Source>,
+    query: Arc<Query>,
+    mut appender: Option<&mut ChunkAppender>,
+) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
+    let block_numbers: Vec<u64> =
+        partition.block_numbers.iter().flatten().flat_map(|chunk| chunk.values()).collect();
+    let mut dfs: HashMap<Datatype, DataFrame> = HashMap::new();
+    for block_number in block_numbers.into_iter() {
+        let block = source
+            .get_block(block_number, BlockTransactionsKind::Hashes)
+            .await?
+            .ok_or_else(|| err(format!("could not find block {}", block_number).as_str()))?;
+        let hashes: Vec<Vec<u8>> = block.transactions.hashes().map(|hash| hash.to_vec()).collect();
+        for batch in hashes.chunks(TRANSACTION_BATCH_SIZE) {
+            let batch_partition = Partition {
+                block_numbers: None,
+                transactions: Some(vec![TransactionChunk::Values(batch.to_vec())]),
+                ..partition.clone()
+            };
+            let batch_dfs = collect_by_transaction(
+                datatype.clone(),
+                batch_partition,
+                source.clone(),
+                query.clone(),
+            )
+            .await?;
+            stack_dfs(&mut dfs, batch_dfs)?;
+            append_if_due(&mut dfs, appender.as_deref_mut(), &query, &source).await?;
+        }
     }
+
+    // partitions whose blocks have no transactions, or whose rows were all appended, still
+    // produce empty dataframes
+    if dfs.is_empty() {
+        let empty_partition = Partition {
+            block_numbers: None,
+            transactions: Some(vec![TransactionChunk::Values(vec![])]),
+            ..partition
+        };
+        return collect_by_transaction(datatype, empty_partition, source, query).await
+    }
+    sort_dfs(&mut dfs, &query)?;
+    Ok(dfs)
 }
 
 /// fetch data for a given partition
@@ -38,7 +232,11 @@ where
     T: Send + 'static,
 {
     let mut handles = Vec::new();
-    for rpc_params in partition.param_sets(inner_request_size)?.into_iter() {
+    let param_sets = match &query.trace_call_inputs {
+        Some(inputs) => inputs.param_sets(&partition),
+        None => partition.param_sets(inner_request_size, query.topic0_sets)?,
+    };
+    for rpc_params in param_sets.into_iter() {
         let sender = sender.clone();
         let source = source.clone();
         let query = query.clone();
@@ -55,6 +253,34 @@ where
     Ok(handles)
 }
 
+/// fetch every response of a partition, for datasets that process responses together
+pub(crate) async fn fetch_partition_responses<F, Fut, T>(
+    f_request: F,
+    partition: Partition,
+    source: Arc<Source>,
+    inner_request_size: Option<u64>,
+    query: Arc<Query>,
+) -> Result<Vec<T>, CollectError>
+where
+    F: Copy
+        + Send
+        + for<'a> Fn(Params, Arc<Source>, Arc<Query>) -> Fut
+        + std::marker::Sync
+        + 'static,
+    Fut: Future<Output = Result<T, CollectError>> + Send + 'static,
+    T: Send + 'static,
+{
+    let (sender, mut receiver) = mpsc::channel(1);
+    let handles =
+        fetch_partition(f_request, partition, source, inner_request_size, query, sender).await?;
+    let mut responses = Vec::new();
+    while let Some(message) = receiver.recv().await {
+        responses.push(message?);
+    }
+    join_partition_handles(handles).await?;
+    Ok(responses)
+}
+
 pub(crate) async fn join_partition_handles(
     handles: Vec<tokio::task::JoinHandle<Result<(), CollectError>>>,
 ) -> Result<(), CollectError> {
@@ -1,6 +1,6 @@
-use crate::CollectError;
+use crate::{CollectError, ParseError};
 /// conversion operations
-use alloy::primitives::{Bytes, I256, U256};
+use alloy::primitives::{Address, Bytes, I256, U256};
 use prefix_hex;
 
 /// convert Bytes to u32
@@ -62,22 +62,97 @@ pub trait ToVecHex {
     /// Output type
     type Output;
 
-    /// Convert to Vec of hex String
-    fn to_vec_hex(&self) -> Self::Output;
+    /// Convert to Vec of hex String, with or without `0x` prefix
+    fn to_vec_hex(&self, prefix: bool) -> Self::Output;
+
+    /// Convert to Vec of EIP-55 checksummed hex String, with or without `0x` prefix
+    fn to_vec_checksum_hex(&self, prefix: bool) -> Self::Output;
+}
+
+/// decode hex String into bytes, with or without `0x` prefix and in any letter case
+///
+/// odd-length hex is an error instead of being truncated
+pub fn decode_hex(value: &str) -> Result<Vec<u8>, ParseError> {
+    let raw = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+    if raw.len() % 2 == 1 {
+        return Err(ParseError::ParseError(format!("odd-length hex: {}", value)))
+    }
+    alloy::hex::decode(raw)
+        .map_err(|_| ParseError::ParseError(format!("could not parse data as hex: {}", value)))
+}
+
+/// encode bytes as hex String, with or without `0x` prefix
+pub fn encode_hex(value: &[u8], prefix: bool) -> String {
+    if prefix {
+        prefix_hex::encode(value)
+    } else {
+        alloy::hex::encode(value)
+    }
+}
+
+/// encode address as EIP-55 checksummed hex String, with or without `0x` prefix
+///
+/// values that are not 20 bytes are not addresses and are encoded as lowercase hex
+pub fn encode_checksum_hex(value: &[u8], prefix: bool) -> String {
+    if value.len() != 20 {
+        return encode_hex(value, prefix)
+    }
+    let checksummed = Address::from_slice(value).to_checksum(None);
+    if prefix {
+        checksummed
+    } else {
+        checksummed[2..].to_string()
+    }
 }
 
 impl ToVecHex for Vec<Vec<u8>> {
     type Output = Vec<String>;
 
-    fn to_vec_hex(&self) -> Self::Output {
-        self.iter().map(|v| prefix_hex::encode(v.clone())).collect()
+    fn to_vec_hex(&self, prefix: bool) -> Self::Output {
+        self.iter().map(|v| encode_hex(v, prefix)).collect()
+    }
+
+    fn to_vec_checksum_hex(&self, prefix: bool) -> Self::Output {
+        self.iter().map(|v| encode_checksum_hex(v, prefix)).collect()
     }
 }
 
 impl ToVecHex for Vec<Option<Vec<u8>>> {
     type Output = Vec<Option<String>>;
 
-    fn to_vec_hex(&self) -> Self::Output {
-        self.iter().map(|opt| opt.as_ref().map(|v| prefix_hex::encode(v.clone()))).collect()
+    fn to_vec_hex(&self, prefix: bool) -> Self::Output {
+        self.iter().map(|opt| opt.as_ref().map(|v| encode_hex(v, prefix))).collect()
+    }
+
+    fn to_vec_checksum_hex(&self, prefix: bool) -> Self::Output {
+        self.iter().map(|opt| opt.as_ref().map(|v| encode_checksum_hex(v, prefix))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_hex() {
+        let expected = vec![0xab, 0xcd];
+        assert_eq!(decode_hex("0xabcd").unwrap(), expected);
+        assert_eq!(decode_hex("0XABCD").unwrap(), expected);
+        assert_eq!(decode_hex("AbCd").unwrap(), expected);
+        assert_eq!(decode_hex("0x").unwrap(), Vec::<u8>::new());
+        assert!(decode_hex("0xabc").is_err());
+        assert!(decode_hex("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_encode_checksum_hex() {
+        let address = decode_hex("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        assert_eq!(encode_checksum_hex(&address, true), checksummed);
+        assert_eq!(encode_checksum_hex(&address, false), checksummed[2..]);
+
+        // values that are not addresses stay lowercase
+        let hash = vec![0xab; 32];
+        assert_eq!(encode_checksum_hex(&hash, true), encode_hex(&hash, true));
     }
 }
@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    io::{Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use polars::prelude::*;
+
+use super::{
+    decimals_to_strings, df_to_json_bytes, get_tmp_path, match_existing_columns,
+    metadata_to_sidecar, FileMetadata,
+};
+use crate::types::{
+    CollectError, Datatype, ExistingColumns, FileError, FileFormat, FileOutput, JsonOptions,
+};
+
+/// appends rows of a chunk to the temp files of its output files while the chunk is collected
+///
+/// temp files are renamed into place once the chunk is complete, so output files are never left
+/// partially written. only uncompressed csv and json files can be appended to
+///
+/// temp files show the progress of a chunk but are not resumed, a rerun collects the chunk again
+/// and replaces its temp files
+///
+/// rows are sorted within each appended batch only, batches are appended in the order that they
+/// are collected, so a file is only sorted as a whole when batches do not overlap in sort order
+pub(crate) struct ChunkAppender {
+    paths: HashMap<Datatype, PathBuf>,
+    file_output: FileOutput,
+    existing_columns: Arc<HashMap<Datatype, ExistingColumns>>,
+    interval: Duration,
+    last_flush: Instant,
+    n_rows: HashMap<Datatype, u64>,
+}
+
+impl ChunkAppender {
+    pub(crate) fn new(
+        paths: HashMap<Datatype, PathBuf>,
+        file_output: FileOutput,
+        existing_columns: Arc<HashMap<Datatype, ExistingColumns>>,
+        interval: Duration,
+    ) -> Self {
+        ChunkAppender {
+            paths,
+            file_output,
+            existing_columns,
+            interval,
+            last_flush: Instant::now(),
+            n_rows: HashMap::new(),
+        }
+    }
+
+    /// whether the flush interval has passed since the chunk started or was last flushed
+    pub(crate) fn is_due(&self) -> bool {
+        self.last_flush.elapsed() >= self.interval
+    }
+
+    /// number of rows appended for datatype, None if no rows were appended
+    pub(crate) fn n_rows(&self, datatype: &Datatype) -> Option<u64> {
+        self.n_rows.get(datatype).copied()
+    }
+
+    /// append dataframes to the temp files of their datatypes
+    pub(crate) fn append(&mut self, dfs: HashMap<Datatype, DataFrame>) -> Result<(), CollectError> {
+        for (datatype, mut df) in dfs.into_iter() {
+            if df.height() == 0 {
+                continue
+            }
+            let path = self.paths.get(&datatype).ok_or_else(|| {
+                CollectError::CollectError("could not get path for datatype".to_string())
+            })?;
+            if let Some(existing) = self.existing_columns.get(&datatype) {
+                let force = self.file_output.force_schema_change;
+                df = match_existing_columns(df, datatype, existing, force)?;
+            }
+            let is_first = !self.n_rows.contains_key(&datatype);
+            let tmp_path = get_tmp_path(path);
+            let (format, json) = (&self.file_output.format, self.file_output.json);
+            append_to_file(&mut df, &tmp_path, format, json, is_first)
+                .map_err(|_| CollectError::CollectError("error appending to file".to_string()))?;
+            *self.n_rows.entry(datatype).or_default() += df.height() as u64;
+        }
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// append the last rows of datatype and rename its temp file into place
+    ///
+    /// returns the size in bytes of the output file
+    pub(crate) fn finish(
+        &mut self,
+        datatype: Datatype,
+        df: &mut DataFrame,
+        metadata: Option<&FileMetadata>,
+    ) -> Result<u64, FileError> {
+        let path = self.paths.get(&datatype).ok_or(FileError::FileWriteError)?;
+        let tmp_path = get_tmp_path(path);
+        let is_first = self.n_rows.remove(&datatype).is_none();
+        let (format, json) = (&self.file_output.format, self.file_output.json);
+        append_to_file(df, &tmp_path, format, json, is_first)?;
+        std::fs::rename(tmp_path, path).map_err(|_e| FileError::FileWriteError)?;
+        let empty_path = self.file_output.get_empty_path(path);
+        if empty_path.exists() {
+            std::fs::remove_file(empty_path).map_err(|_e| FileError::FileWriteError)?;
+        }
+        if let Some(metadata) = metadata {
+            metadata_to_sidecar(metadata, &self.file_output.get_metadata_path(path))?;
+        }
+        Ok(std::fs::metadata(path).map_err(|_e| FileError::FileWriteError)?.len())
+    }
+
+    /// remove the temp files of rows appended so far, so that a retried chunk starts over
+    pub(crate) fn reset(&mut self) -> Result<(), FileError> {
+        for datatype in self.n_rows.keys() {
+            if let Some(path) = self.paths.get(datatype) {
+                let tmp_path = get_tmp_path(path);
+                if tmp_path.exists() {
+                    std::fs::remove_file(tmp_path).map_err(|_e| FileError::FileWriteError)?;
+                }
+            }
+        }
+        self.n_rows.clear();
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+}
+
+/// append rows of a dataframe to a csv or json file, creating the file if is_first
+///
+/// csv headers are only written when creating the file, and json rows are spliced into the
+/// array of the file before its closing bracket
+fn append_to_file(
+    df: &mut DataFrame,
+    filename: &Path,
+    format: &FileFormat,
+    json: JsonOptions,
+    is_first: bool,
+) -> Result<(), FileError> {
+    let mut df = decimals_to_strings(df)?;
+    if is_first {
+        if let Some(parent) = filename.parent() {
+            std::fs::create_dir_all(parent).map_err(|_e| FileError::FileWriteError)?;
+        }
+    }
+    match format {
+        FileFormat::Csv => {
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(!is_first)
+                .truncate(is_first)
+                .open(filename)
+                .map_err(|_e| FileError::FileWriteError)?;
+            CsvWriter::new(&mut file)
+                .include_header(is_first)
+                .finish(&mut df)
+                .map_err(|_e| FileError::FileWriteError)
+        }
+        FileFormat::Json => {
+            let rows = df_to_json_bytes(&df, json)?;
+            if is_first {
+                return std::fs::write(filename, rows).map_err(|_e| FileError::FileWriteError)
+            }
+            append_json_rows(filename, &rows, json.pretty)
+        }
+        FileFormat::Parquet => Err(FileError::FileWriteError),
+    }
+}
+
+/// splice a json array of rows into the json array of a file
+///
+/// pretty printed arrays that hold rows end with a newline before their closing bracket
+fn append_json_rows(filename: &Path, rows: &[u8], pretty: bool) -> Result<(), FileError> {
+    let Some(rows) = rows.strip_prefix(b"[").filter(|rows| *rows != b"]") else { return Ok(()) };
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(filename)
+        .map_err(|_e| FileError::FileWriteError)?;
+    let len = file.metadata().map_err(|_e| FileError::FileWriteError)?.len();
+    if len < 2 {
+        return Err(FileError::FileWriteError)
+    }
+
+    // overwrite the closing bracket of the file, separating rows unless the file has none
+    let (separator, n_closing): (&[u8], u64) = match (len, pretty) {
+        (2, _) => (b"", 1),
+        (_, false) => (b",", 1),
+        (_, true) => (b",", 2),
+    };
+    file.seek(SeekFrom::Start(len - n_closing)).map_err(|_e| FileError::FileWriteError)?;
+    file.write_all(separator).map_err(|_e| FileError::FileWriteError)?;
+    file.write_all(rows).map_err(|_e| FileError::FileWriteError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_to_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_append_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut first = df!("block_number" => [1u32, 2]).unwrap();
+        let mut second = df!("block_number" => [3u32]).unwrap();
+        let compact = JsonOptions::default();
+        let pretty = JsonOptions { pretty: true, ..Default::default() };
+
+        let path = dir.join("blocks.csv");
+        append_to_file(&mut first, &path, &FileFormat::Csv, compact, true).unwrap();
+        append_to_file(&mut second, &path, &FileFormat::Csv, compact, false).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "block_number\n1\n2\n3\n");
+
+        let path = dir.join("blocks.json");
+        let mut empty = first.head(Some(0));
+        append_to_file(&mut empty, &path, &FileFormat::Json, compact, true).unwrap();
+        append_to_file(&mut first, &path, &FileFormat::Json, compact, false).unwrap();
+        append_to_file(&mut empty, &path, &FileFormat::Json, compact, false).unwrap();
+        append_to_file(&mut second, &path, &FileFormat::Json, compact, false).unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let df = JsonReader::new(file).finish().unwrap();
+        let values: Vec<_> =
+            df.column("block_number").unwrap().i64().unwrap().into_iter().collect();
+        assert_eq!(values, vec![Some(1), Some(2), Some(3)]);
+
+        // rows appended to pretty printed files keep the layout of a file written at once
+        let path = dir.join("blocks_pretty.json");
+        append_to_file(&mut empty, &path, &FileFormat::Json, pretty, true).unwrap();
+        append_to_file(&mut first, &path, &FileFormat::Json, pretty, false).unwrap();
+        append_to_file(&mut second, &path, &FileFormat::Json, pretty, false).unwrap();
+        let all = first.vstack(&second).unwrap();
+        let expected = df_to_json_bytes(&all, pretty).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
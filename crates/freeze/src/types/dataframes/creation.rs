@@ -14,7 +14,12 @@ macro_rules! with_series_binary {
     ($all_series:expr, $name:expr, $value:expr, $schema:expr) => {
         if $schema.has_column($name) {
             if let Some(ColumnType::Hex) = $schema.column_type($name) {
-                $all_series.push(Series::new($name, $value.to_vec_hex()));
+                let prefix = $schema.binary_type.hex_prefix();
+                if $schema.checksums_column($name) {
+                    $all_series.push(Series::new($name, $value.to_vec_checksum_hex(prefix)));
+                } else {
+                    $all_series.push(Series::new($name, $value.to_vec_hex(prefix)));
+                }
             } else {
                 $all_series.push(Series::new($name, $value));
             }
@@ -33,8 +38,9 @@ macro_rules! with_series_u256 {
                 let name = name.as_str();
 
                 let converted: Vec<Vec<u8>> = $value.iter().map(|v| v.to_vec_u8()).collect();
-                if ColumnEncoding::Hex == $schema.binary_type {
-                    $all_series.push(Series::new(name, converted.to_vec_hex()));
+                if $schema.binary_type.is_hex() {
+                    let prefix = $schema.binary_type.hex_prefix();
+                    $all_series.push(Series::new(name, converted.to_vec_hex(prefix)));
                 } else {
                     $all_series.push(Series::new(name, converted));
                 }
@@ -89,7 +95,13 @@ macro_rules! with_series_u256 {
 
             // decimal128
             if $schema.u256_types.contains(&U256Type::Decimal128) {
-                return Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
+                let name = $name.to_string() + U256Type::Decimal128.suffix().as_str();
+                let name = name.as_str();
+
+                let converted: Vec<Option<U256>> = $value.iter().map(|v| Some(*v)).collect();
+                $all_series.push(to_decimal128_series(name, &converted, |v| {
+                    i128::try_from(v).ok()
+                })?);
             }
         }
     };
@@ -107,8 +119,9 @@ macro_rules! with_series_option_u256 {
 
                 let converted: Vec<Option<Vec<u8>>> =
                     $value.iter().map(|v| v.map(|x| x.to_vec_u8())).collect();
-                if ColumnEncoding::Hex == $schema.binary_type {
-                    $all_series.push(Series::new(name, converted.to_vec_hex()));
+                if $schema.binary_type.is_hex() {
+                    let prefix = $schema.binary_type.hex_prefix();
+                    $all_series.push(Series::new(name, converted.to_vec_hex(prefix)));
                 } else {
                     $all_series.push(Series::new(name, converted));
                 }
@@ -170,7 +183,10 @@ macro_rules! with_series_option_u256 {
 
             // decimal128
             if $schema.u256_types.contains(&U256Type::Decimal128) {
-                return Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
+                let name = $name.to_string() + U256Type::Decimal128.suffix().as_str();
+                let name = name.as_str();
+
+                $all_series.push(to_decimal128_series(name, &$value, |v| i128::try_from(v).ok())?);
             }
         }
     };
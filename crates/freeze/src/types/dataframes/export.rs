@@ -1,26 +1,187 @@
-use std::path::Path;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use polars::prelude::*;
+use polars::{
+    export::arrow::datatypes::{PhysicalType, PrimitiveType},
+    prelude::*,
+};
+use polars_parquet::write::{
+    transverse, Encoding, FileWriter, KeyValue, RowGroupIterator, Version, WriteOptions,
+};
 
-use crate::types::{FileError, FileOutput};
+use crate::types::{
+    EmptyFilePolicy, FileCompression, FileError, FileFormat, FileOutput, JsonOptions, Partition,
+};
 
-/// write polars dataframe to file
+/// path that a file is written to before being renamed into place
+///
+/// the temp file is kept in the same directory so that the rename is atomic
+pub(crate) fn get_tmp_path(filename: &Path) -> PathBuf {
+    let mut tmp_filename = filename.as_os_str().to_owned();
+    tmp_filename.push(".tmp");
+    PathBuf::from(tmp_filename)
+}
+
+/// remove temp files left behind by runs that crashed while writing these files
+pub(crate) fn remove_tmp_files<'a>(
+    filenames: impl Iterator<Item = &'a PathBuf>,
+) -> Result<(), FileError> {
+    for filename in filenames {
+        let tmp_filename = get_tmp_path(filename);
+        if tmp_filename.exists() {
+            std::fs::remove_file(tmp_filename).map_err(|_e| FileError::FileWriteError)?;
+        }
+    }
+    Ok(())
+}
+
+/// provenance of an output file, embedded in parquet metadata or written to a sidecar
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+pub(crate) struct FileMetadata {
+    pub(crate) cryo_version: String,
+    pub(crate) args_hash: Option<String>,
+    pub(crate) datatype: String,
+    pub(crate) schema_version: String,
+}
+
+impl FileMetadata {
+    /// parquet key-value metadata, with keys prefixed by `cryo_`
+    fn key_values(&self) -> Vec<KeyValue> {
+        let entries = [
+            ("cryo_version", Some(&self.cryo_version)),
+            ("cryo_args_hash", self.args_hash.as_ref()),
+            ("cryo_datatype", Some(&self.datatype)),
+            ("cryo_schema_version", Some(&self.schema_version)),
+        ];
+        entries
+            .into_iter()
+            .filter_map(|(key, value)| {
+                value.map(|value| KeyValue { key: key.to_string(), value: Some(value.clone()) })
+            })
+            .collect()
+    }
+}
+
+/// write polars dataframe to temp file, then rename it into place
+///
+/// a crash never leaves a readable but truncated file at `filename`. csv and json files cannot
+/// hold metadata, so their metadata is written to a sidecar
 pub(crate) fn df_to_file(
     df: &mut DataFrame,
     filename: &Path,
     file_output: &FileOutput,
+    metadata: Option<&FileMetadata>,
 ) -> Result<(), FileError> {
-    let tmp_filename = filename.with_extension("_tmp");
-    let result = match filename.extension().and_then(|ex| ex.to_str()) {
-        Some("parquet") => df_to_parquet(df, &tmp_filename, file_output),
-        Some("csv") => df_to_csv(df, &tmp_filename),
-        Some("json") => df_to_json(df, &tmp_filename),
-        _ => return Err(FileError::FileWriteError),
+    let tmp_filename = get_tmp_path(filename);
+    let result = match file_output.format {
+        FileFormat::Parquet => df_to_parquet(df, &tmp_filename, file_output, metadata),
+        FileFormat::Csv => df_to_csv(df, &tmp_filename, file_output.file_compression),
+        FileFormat::Json => {
+            df_to_json(df, &tmp_filename, file_output.file_compression, file_output.json)
+        }
     };
     match result {
         Ok(()) => std::fs::rename(tmp_filename, filename).map_err(|_e| FileError::FileWriteError),
-        Err(_e) => Err(FileError::FileWriteError),
+        Err(_e) => {
+            let _ = std::fs::remove_file(tmp_filename);
+            Err(FileError::FileWriteError)
+        }
+    }?;
+    match metadata {
+        Some(metadata) if file_output.format != FileFormat::Parquet => {
+            metadata_to_sidecar(metadata, &file_output.get_metadata_path(filename))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// write metadata of a csv or json output file to its sidecar
+pub(crate) fn metadata_to_sidecar(metadata: &FileMetadata, path: &Path) -> Result<(), FileError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|_e| FileError::FileWriteError)?;
+    }
+    let contents =
+        serde_json::to_string_pretty(metadata).map_err(|_e| FileError::FileWriteError)?;
+    let tmp_path = get_tmp_path(path);
+    std::fs::write(&tmp_path, contents).map_err(|_e| FileError::FileWriteError)?;
+    std::fs::rename(tmp_path, path).map_err(|_e| FileError::FileWriteError)
+}
+
+/// write dataframe of a chunk to its output file, following the policy for chunks without rows
+///
+/// returns the size in bytes of the output file, None if no output file exists afterwards
+pub(crate) fn chunk_to_file(
+    df: &mut DataFrame,
+    filename: &Path,
+    file_output: &FileOutput,
+    metadata: Option<&FileMetadata>,
+) -> Result<Option<u64>, FileError> {
+    let empty_path = file_output.get_empty_path(filename);
+    if df.height() > 0 || file_output.empty_file_policy == EmptyFilePolicy::Write {
+        df_to_file(df, filename, file_output, metadata)?;
+        if df.height() > 0 {
+            if empty_path.exists() {
+                std::fs::remove_file(empty_path).map_err(|_e| FileError::FileWriteError)?;
+            }
+        } else {
+            // record that the file was written without rows deliberately
+            record_empty_chunk(&empty_path)?;
+        }
+        let n_bytes = std::fs::metadata(filename).map_err(|_e| FileError::FileWriteError)?.len();
+        return Ok(Some(n_bytes))
+    }
+
+    // record that the chunk is complete even though no output file holds its rows
+    record_empty_chunk(&empty_path)?;
+    match file_output.empty_file_policy {
+        EmptyFilePolicy::Touch => {
+            std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
+            Ok(Some(0))
+        }
+        _ => {
+            if filename.exists() {
+                std::fs::remove_file(filename).map_err(|_e| FileError::FileWriteError)?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+fn record_empty_chunk(empty_path: &Path) -> Result<(), FileError> {
+    if let Some(parent) = empty_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|_e| FileError::FileWriteError)?;
+    }
+    std::fs::File::create(empty_path).map_err(|_e| FileError::FileWriteError)?;
+    Ok(())
+}
+
+/// write value counts of each dimension of a partition alongside the rows of its output file
+pub(crate) fn chunk_stats_to_file(
+    partition: &Partition,
+    n_rows: u64,
+    path: &Path,
+    file_output: &FileOutput,
+) -> Result<(), FileError> {
+    let counts = partition.stats().dim_counts();
+    let mut df = df!(
+        "dimension" => counts.iter().map(|c| c.0.to_string()).collect::<Vec<_>>(),
+        "min_value" => counts.iter().map(|c| c.1).collect::<Vec<_>>(),
+        "max_value" => counts.iter().map(|c| c.2).collect::<Vec<_>>(),
+        "n_values" => counts.iter().map(|c| c.3).collect::<Vec<_>>(),
+        "n_rows" => vec![n_rows; counts.len()],
+    )
+    .map_err(|_e| FileError::FileWriteError)?;
+
+    let stats_path = file_output.get_stats_path(path);
+    if let Some(parent) = stats_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|_e| FileError::FileWriteError)?;
     }
+    let tmp_path = get_tmp_path(&stats_path);
+    let file = std::fs::File::create(&tmp_path).map_err(|_e| FileError::FileWriteError)?;
+    ParquetWriter::new(file).finish(&mut df).map_err(|_e| FileError::FileWriteError)?;
+    std::fs::rename(tmp_path, stats_path).map_err(|_e| FileError::FileWriteError)
 }
 
 /// write polars dataframe to parquet file
@@ -28,35 +189,594 @@ fn df_to_parquet(
     df: &mut DataFrame,
     filename: &Path,
     file_output: &FileOutput,
+    metadata: Option<&FileMetadata>,
 ) -> Result<(), FileError> {
     let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
-    let result = ParquetWriter::new(file)
-        .with_statistics(file_output.parquet_statistics)
-        .with_compression(file_output.parquet_compression)
-        .with_row_group_size(file_output.row_group_size)
-        .finish(df);
+    let row_group_size = get_row_group_size(df, file_output);
+    let result = if file_output.parquet_dictionary && metadata.is_none() {
+        ParquetWriter::new(file)
+            .with_statistics(file_output.parquet_statistics)
+            .with_compression(file_output.parquet_compression)
+            .with_row_group_size(row_group_size)
+            .with_data_page_size(file_output.parquet_page_size)
+            .finish(df)
+    } else {
+        df_to_encoded_parquet(df, file, row_group_size, file_output, metadata)
+    };
     match result {
         Err(_e) => Err(FileError::FileWriteError),
         _ => Ok(()),
     }
 }
 
-/// write polars dataframe to csv file
-fn df_to_csv(df: &mut DataFrame, filename: &Path) -> Result<(), FileError> {
-    let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
-    let result = CsvWriter::new(file).finish(df);
-    match result {
-        Err(_e) => Err(FileError::FileWriteError),
-        _ => Ok(()),
+/// rows to sample when estimating bytes per row for a row group target size
+const ROW_GROUP_SAMPLE_ROWS: usize = 4096;
+
+/// rows per parquet row group, an explicit row count takes precedence over a target size
+///
+/// bytes per row are estimated from the in-memory size of the first rows of the dataframe
+fn get_row_group_size(df: &DataFrame, file_output: &FileOutput) -> Option<usize> {
+    match (file_output.row_group_size, file_output.row_group_target_bytes) {
+        (Some(row_group_size), _) => Some(row_group_size),
+        (None, Some(target_bytes)) if df.height() > 0 => {
+            let sample = df.slice(0, ROW_GROUP_SAMPLE_ROWS);
+            let bytes_per_row = sample.estimated_size().div_ceil(sample.height()).max(1);
+            Some((target_bytes / bytes_per_row).max(1))
+        }
+        _ => None,
+    }
+}
+
+/// write polars dataframe to parquet file, encoding row groups directly
+///
+/// polars' `ParquetWriter` always dictionary encodes non-float columns and cannot write
+/// key-value metadata, so this is used for plain encoded columns or files with metadata.
+/// dictionary encoding follows the choice of `ParquetWriter`
+fn df_to_encoded_parquet(
+    df: &mut DataFrame,
+    file: std::fs::File,
+    row_group_size: Option<usize>,
+    file_output: &FileOutput,
+    metadata: Option<&FileMetadata>,
+) -> PolarsResult<u64> {
+    let schema = df.schema().to_arrow(true);
+    let dictionary = file_output.parquet_dictionary;
+    let encodings = schema
+        .fields
+        .iter()
+        .map(|field| transverse(&field.data_type, |data_type| encoding(data_type, dictionary)))
+        .collect();
+    let options = WriteOptions {
+        write_statistics: file_output.parquet_statistics,
+        compression: file_output.parquet_compression.into(),
+        version: Version::V2,
+        data_pagesize_limit: file_output.parquet_page_size,
+    };
+
+    let row_group_size = row_group_size.unwrap_or(512 * 512).max(1);
+    let chunks = (0..df.height()).step_by(row_group_size).flat_map(|offset| {
+        let mut row_group = df.slice(offset as i64, row_group_size);
+        row_group.as_single_chunk();
+        row_group.iter_chunks(true).map(Ok).collect::<Vec<_>>()
+    });
+    let row_groups = RowGroupIterator::try_new(chunks, &schema, options, encodings)?;
+
+    let mut writer = FileWriter::try_new(file, schema, options)?;
+    for row_group in row_groups {
+        writer.write(row_group?)?;
+    }
+    writer.end(metadata.map(FileMetadata::key_values))
+}
+
+/// parquet encoding of a column, dictionary encoding all but float columns if enabled
+fn encoding(data_type: &ArrowDataType, dictionary: bool) -> Encoding {
+    match data_type.to_physical_type() {
+        _ if !dictionary => Encoding::Plain,
+        PhysicalType::Primitive(
+            PrimitiveType::Float16 | PrimitiveType::Float32 | PrimitiveType::Float64,
+        ) => Encoding::Plain,
+        PhysicalType::Primitive(_) |
+        PhysicalType::Dictionary(_) |
+        PhysicalType::LargeBinary |
+        PhysicalType::LargeUtf8 |
+        PhysicalType::Utf8View |
+        PhysicalType::BinaryView => Encoding::RleDictionary,
+        _ => Encoding::Plain,
     }
 }
 
+/// write polars dataframe to csv file
+fn df_to_csv(
+    df: &mut DataFrame,
+    filename: &Path,
+    compression: Option<FileCompression>,
+) -> Result<(), FileError> {
+    let mut df = decimals_to_strings(df)?;
+    write_compressed(filename, compression, |writer| CsvWriter::new(writer).finish(&mut df))
+}
+
 /// write polars dataframe to json file
-fn df_to_json(df: &mut DataFrame, filename: &Path) -> Result<(), FileError> {
-    let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
-    let result = JsonWriter::new(file).with_json_format(JsonFormat::Json).finish(df);
-    match result {
-        Err(_e) => Err(FileError::FileWriteError),
-        _ => Ok(()),
+fn df_to_json(
+    df: &mut DataFrame,
+    filename: &Path,
+    compression: Option<FileCompression>,
+    json: JsonOptions,
+) -> Result<(), FileError> {
+    let rows = df_to_json_bytes(df, json)?;
+    write_compressed(filename, compression, |writer| Ok(writer.write_all(&rows)?))
+}
+
+/// serialize polars dataframe as a json array of rows, on a single line unless pretty
+pub(crate) fn df_to_json_bytes(df: &DataFrame, json: JsonOptions) -> Result<Vec<u8>, FileError> {
+    let df = decimals_to_strings(df)?;
+    let mut df = if json.integers_as_numbers { df } else { integers_to_strings(&df)? };
+    let mut rows = Vec::new();
+    JsonWriter::new(&mut rows)
+        .with_json_format(JsonFormat::Json)
+        .finish(&mut df)
+        .map_err(|_e| FileError::FileWriteError)?;
+    Ok(if json.pretty { pretty_print_json(&rows) } else { rows })
+}
+
+/// indent compact json the way serde_json pretty prints it, keeping the order of keys
+///
+/// compact json has no whitespace outside of strings, so only structural bytes are expanded
+fn pretty_print_json(compact: &[u8]) -> Vec<u8> {
+    fn newline(output: &mut Vec<u8>, depth: usize) {
+        output.push(b'\n');
+        output.extend(std::iter::repeat_n(b' ', 2 * depth));
+    }
+    let mut output = Vec::with_capacity(2 * compact.len());
+    let (mut depth, mut in_string, mut escaped) = (0, false, false);
+    for (index, byte) in compact.iter().copied().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            output.push(byte);
+            continue
+        }
+        match byte {
+            b'"' => {
+                in_string = true;
+                output.push(byte);
+            }
+            // empty arrays and objects stay on one line
+            b'[' | b'{' if matches!(compact.get(index + 1), Some(b']' | b'}')) => output.push(byte),
+            b']' | b'}' if matches!(output.last(), Some(b'[' | b'{')) => output.push(byte),
+            b'[' | b'{' => {
+                depth += 1;
+                output.push(byte);
+                newline(&mut output, depth);
+            }
+            b']' | b'}' => {
+                depth -= 1;
+                newline(&mut output, depth);
+                output.push(byte);
+            }
+            b',' => {
+                output.push(byte);
+                newline(&mut output, depth);
+            }
+            b':' => output.extend_from_slice(b": "),
+            _ => output.push(byte),
+        }
+    }
+    output
+}
+
+/// create file and write to it, wrapping the file in a compressor if requested
+fn write_compressed<F>(
+    filename: &Path,
+    compression: Option<FileCompression>,
+    write: F,
+) -> Result<(), FileError>
+where
+    F: FnOnce(&mut dyn Write) -> PolarsResult<()>,
+{
+    let mut file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
+    match compression {
+        None => write(&mut file).map_err(|_e| FileError::FileWriteError),
+        Some(FileCompression::Gzip) => {
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            write(&mut encoder).map_err(|_e| FileError::FileWriteError)?;
+            encoder.finish().map(|_| ()).map_err(|_e| FileError::FileWriteError)
+        }
+        Some(FileCompression::Zstd) => {
+            let mut encoder =
+                zstd::Encoder::new(file, 0).map_err(|_e| FileError::FileWriteError)?;
+            write(&mut encoder).map_err(|_e| FileError::FileWriteError)?;
+            encoder.finish().map(|_| ()).map_err(|_e| FileError::FileWriteError)
+        }
+    }
+}
+
+/// csv and json writers cannot serialize decimals, so render them as exact decimal strings
+pub(crate) fn decimals_to_strings(df: &DataFrame) -> Result<DataFrame, FileError> {
+    let columns = df
+        .get_columns()
+        .iter()
+        .map(|column| match column.dtype() {
+            DataType::Decimal(_, _) => column.cast(&DataType::String),
+            _ => Ok(column.clone()),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_e| FileError::FileWriteError)?;
+    DataFrame::new(columns).map_err(|_e| FileError::FileWriteError)
+}
+
+/// json readers parse numbers as doubles, so render 64-bit integer columns as strings rather than
+/// let values beyond 2^53 be rounded
+///
+/// the type of a column depends only on its schema, so files of a datatype share their types
+fn integers_to_strings(df: &DataFrame) -> Result<DataFrame, FileError> {
+    let columns = df
+        .get_columns()
+        .iter()
+        .map(|column| match column.dtype() {
+            DataType::UInt64 | DataType::Int64 => column.cast(&DataType::String),
+            _ => Ok(column.clone()),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_e| FileError::FileWriteError)?;
+    DataFrame::new(columns).map_err(|_e| FileError::FileWriteError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AddressChunk, BlockChunk};
+    use std::io::Read;
+
+    #[test]
+    fn test_write_compressed_csv() {
+        let dir = std::env::temp_dir().join(format!("cryo_export_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut df = df!("block_number" => [1u32, 2, 3]).unwrap();
+
+        let path = dir.join("blocks.csv.gz");
+        df_to_csv(&mut df, &path, Some(FileCompression::Gzip)).unwrap();
+        let mut text = String::new();
+        let file = std::fs::File::open(&path).unwrap();
+        flate2::read::GzDecoder::new(file).read_to_string(&mut text).unwrap();
+        assert_eq!(text, "block_number\n1\n2\n3\n");
+
+        let path = dir.join("blocks.csv.zst");
+        df_to_csv(&mut df, &path, Some(FileCompression::Zstd)).unwrap();
+        let text = zstd::decode_all(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "block_number\n1\n2\n3\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_json() {
+        let dir = std::env::temp_dir().join(format!("cryo_export_json_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let big = (1u64 << 53) + 1;
+        let mut df = df!(
+            "block_number" => [1u32, 2],
+            "gas_used" => [1u64, big],
+            "value" => [-(big as i64), 3],
+        )
+        .unwrap();
+
+        // compact json is a single line, with every 64-bit integer written as a string
+        let path = dir.join("blocks.json");
+        df_to_json(&mut df, &path, None, JsonOptions::default()).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        let expected = serde_json::json!([
+            {"block_number": 1, "gas_used": "1", "value": "-9007199254740993"},
+            {"block_number": 2, "gas_used": big.to_string(), "value": "3"},
+        ]);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&text).unwrap(), expected);
+
+        // unless integers are written as numbers
+        let json = JsonOptions { integers_as_numbers: true, ..Default::default() };
+        df_to_json(&mut df, &path, None, json).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let numbers = serde_json::json!([
+            {"block_number": 1, "gas_used": 1, "value": -(big as i64)},
+            {"block_number": 2, "gas_used": big, "value": 3},
+        ]);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&text).unwrap(), numbers);
+
+        // pretty json is laid out like serde_json lays it out, keeping the column order
+        let json = JsonOptions { pretty: true, ..Default::default() };
+        df_to_json(&mut df, &path, None, json).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, String::from_utf8(serde_json::to_vec_pretty(&expected).unwrap()).unwrap());
+        let mut df = df.select(["value", "block_number"]).unwrap();
+        df_to_json(&mut df, &path, None, json).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.find("value").unwrap() < text.find("block_number").unwrap());
+
+        // strings holding structural characters, and empty arrays, are kept as they are
+        let compact = br#"[{"a":"x,\"[{:","b":[],"c":{}},{"a":"","b":[1,2],"c":{"d":null}}]"#;
+        let value: serde_json::Value = serde_json::from_slice(compact).unwrap();
+        assert_eq!(pretty_print_json(compact), serde_json::to_vec_pretty(&value).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_chunk_to_file_empty_policy() {
+        let dir = std::env::temp_dir().join(format!("cryo_empty_policy_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut sink = FileOutput {
+            output_dir: dir.clone(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: false,
+            force_schema_change: false,
+            format: FileFormat::Csv,
+            row_group_size: None,
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: EmptyFilePolicy::Skip,
+            schema_version: None,
+            flush_interval: None,
+            json: JsonOptions::default(),
+        };
+        let path = dir.join("logs.csv");
+        let mut empty = df!("block_number" => Vec::<u32>::new()).unwrap();
+
+        // skipped chunks write no file but are still complete
+        assert_eq!(chunk_to_file(&mut empty, &path, &sink, None).unwrap(), None);
+        assert!(!path.exists());
+        assert!(sink.is_complete(&path));
+
+        sink.empty_file_policy = EmptyFilePolicy::Touch;
+        assert_eq!(chunk_to_file(&mut empty, &path, &sink, None).unwrap(), Some(0));
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+
+        // written files without rows are also recorded
+        sink.empty_file_policy = EmptyFilePolicy::Write;
+        std::fs::remove_file(sink.get_empty_path(&path)).unwrap();
+        assert!(chunk_to_file(&mut empty, &path, &sink, None).unwrap().is_some());
+        assert!(sink.get_empty_path(&path).exists());
+
+        // chunks with rows replace the record of an empty chunk
+        let mut df = df!("block_number" => [1u32]).unwrap();
+        let n_bytes = chunk_to_file(&mut df, &path, &sink, None).unwrap();
+        assert_eq!(n_bytes, Some(std::fs::metadata(&path).unwrap().len()));
+        assert!(!sink.get_empty_path(&path).exists());
+        assert!(!get_tmp_path(&path).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_tmp_files() {
+        let dir = std::env::temp_dir().join(format!("cryo_tmp_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("logs.csv");
+        std::fs::write(&path, b"block_number\n1\n").unwrap();
+
+        // leftover temp files are removed, and the files themselves are kept
+        std::fs::write(get_tmp_path(&path), b"trunc").unwrap();
+        remove_tmp_files([path.clone()].iter()).unwrap();
+        assert!(!get_tmp_path(&path).exists());
+        assert!(path.exists());
+
+        // files without a temp file are left alone
+        remove_tmp_files([path.clone()].iter()).unwrap();
+        assert!(path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_parquet_without_dictionary() {
+        let dir = std::env::temp_dir().join(format!("cryo_plain_parquet_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_output = FileOutput {
+            output_dir: dir.clone(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: false,
+            force_schema_change: false,
+            format: FileFormat::Parquet,
+            row_group_size: Some(2),
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: Some(1024),
+            parquet_dictionary: false,
+            parquet_compression: ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            json: JsonOptions::default(),
+        };
+        let path = dir.join("logs.parquet");
+        let mut df = df!(
+            "block_number" => [1u32, 1, 1, 2, 2],
+            "address" => (0..5u8).map(|i| vec![i / 3; 20]).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        df_to_parquet(&mut df, &path, &file_output, None).unwrap();
+
+        let mut file = std::fs::File::open(&path).unwrap();
+        let metadata = polars_parquet::read::read_metadata(&mut file).unwrap();
+        assert_eq!(metadata.row_groups.len(), 3);
+        for column in metadata.row_groups.iter().flat_map(|row_group| row_group.columns()) {
+            for encoding in column.column_encoding() {
+                let encoding = Encoding::try_from(*encoding).unwrap();
+                assert!(!matches!(encoding, Encoding::RleDictionary | Encoding::PlainDictionary));
+            }
+        }
+        let written = ParquetReader::new(std::fs::File::open(&path).unwrap()).finish().unwrap();
+        assert!(written.equals(&df));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_file_metadata() {
+        let dir = std::env::temp_dir().join(format!("cryo_file_metadata_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut file_output = FileOutput {
+            output_dir: dir.clone(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: false,
+            force_schema_change: false,
+            format: FileFormat::Parquet,
+            row_group_size: None,
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: EmptyFilePolicy::Write,
+            schema_version: Some("2".to_string()),
+            flush_interval: None,
+            json: JsonOptions::default(),
+        };
+        let metadata = FileMetadata {
+            cryo_version: "0.3.2".to_string(),
+            args_hash: None,
+            datatype: "blocks".to_string(),
+            schema_version: "2".to_string(),
+        };
+        let mut df = df!("block_number" => [1u32, 2], "gas_used" => [3.0f64, 4.0]).unwrap();
+
+        // parquet files carry metadata as key-value metadata
+        let path = dir.join("blocks.parquet");
+        df_to_file(&mut df, &path, &file_output, Some(&metadata)).unwrap();
+        let mut file = std::fs::File::open(&path).unwrap();
+        let key_values = polars_parquet::read::read_metadata(&mut file).unwrap().key_value_metadata;
+        let key_values: Vec<_> = key_values
+            .unwrap()
+            .into_iter()
+            .filter(|key_value| key_value.key.starts_with("cryo_"))
+            .map(|key_value| (key_value.key, key_value.value.unwrap()))
+            .collect();
+        let expected =
+            [("cryo_version", "0.3.2"), ("cryo_datatype", "blocks"), ("cryo_schema_version", "2")];
+        assert!(key_values.iter().map(|(k, v)| (k.as_str(), v.as_str())).eq(expected));
+        let written = ParquetReader::new(std::fs::File::open(&path).unwrap()).finish().unwrap();
+        assert!(written.equals(&df));
+        assert!(!file_output.get_metadata_path(&path).exists());
+
+        // csv files get a sidecar instead
+        file_output.format = FileFormat::Csv;
+        let path = dir.join("blocks.csv");
+        df_to_file(&mut df, &path, &file_output, Some(&metadata)).unwrap();
+        let sidecar = std::fs::read_to_string(file_output.get_metadata_path(&path)).unwrap();
+        assert_eq!(serde_json::from_str::<FileMetadata>(&sidecar).unwrap(), metadata);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_row_group_target_bytes() {
+        let mut file_output = FileOutput {
+            output_dir: std::env::temp_dir(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: false,
+            force_schema_change: false,
+            format: FileFormat::Parquet,
+            row_group_size: None,
+            row_group_target_bytes: Some(80),
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            json: JsonOptions::default(),
+        };
+        let df = df!("block_number" => (0..100u64).collect::<Vec<_>>()).unwrap();
+        assert_eq!(get_row_group_size(&df, &file_output), Some(10));
+
+        // row groups always have at least one row
+        file_output.row_group_target_bytes = Some(1);
+        assert_eq!(get_row_group_size(&df, &file_output), Some(1));
+
+        // an explicit row count takes precedence
+        file_output.row_group_size = Some(25);
+        assert_eq!(get_row_group_size(&df, &file_output), Some(25));
+    }
+
+    #[test]
+    fn test_chunk_stats_to_file() {
+        let dir = std::env::temp_dir().join(format!("cryo_chunk_stats_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_output = FileOutput {
+            output_dir: dir.clone(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: false,
+            force_schema_change: false,
+            format: FileFormat::Parquet,
+            row_group_size: None,
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: true,
+            empty_file_policy: EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            json: JsonOptions::default(),
+        };
+        let partition = Partition {
+            block_numbers: Some(vec![BlockChunk::Range(10, 19)]),
+            addresses: Some(vec![AddressChunk::Values(vec![vec![1; 20], vec![2; 20]])]),
+            ..Default::default()
+        };
+        let path = dir.join("blocks").join("ethereum__blocks__00000010_to_00000019.parquet");
+        chunk_stats_to_file(&partition, 7, &path, &file_output).unwrap();
+
+        // each dimension of the partition has a row, with min and max values of block numbers
+        let stats_path = file_output.get_stats_path(&path);
+        assert!(!get_tmp_path(&stats_path).exists());
+        let stats = ParquetReader::new(std::fs::File::open(&stats_path).unwrap()).finish().unwrap();
+        let expected = df!(
+            "dimension" => ["block", "address"],
+            "min_value" => [Some(10u64), None],
+            "max_value" => [Some(19u64), None],
+            "n_values" => [10u64, 2],
+            "n_rows" => [7u64, 7],
+        )
+        .unwrap();
+        assert!(stats.equals_missing(&expected));
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,41 @@
+use polars::prelude::*;
+
+use crate::types::{CollectError, Table};
+
+pub(crate) trait FilterableDataFrame {
+    fn filter_by_schema(self, schema: &Table) -> Self;
+}
+
+impl FilterableDataFrame for Result<DataFrame, CollectError> {
+    fn filter_by_schema(self, schema: &Table) -> Self {
+        match (self, &schema.filter_expr) {
+            (Ok(df), Some(expr)) => {
+                df.lazy().filter(expr.clone()).collect().map_err(CollectError::PolarsError)
+            }
+            (df, _) => df,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColumnEncoding, Datatype, U256Type};
+
+    #[test]
+    fn test_filter_by_schema() {
+        let cols = Some(vec!["block_number", "value", "gas_used"]);
+        let cols = cols.map(|cols| cols.into_iter().map(String::from).collect());
+        let mut schema = Datatype::Transactions
+            .table_schema(&[U256Type::F64], &ColumnEncoding::Hex, &None, &None, &cols, None, None)
+            .unwrap();
+        schema.block_number_column = Some("block".to_string());
+        assert_eq!(schema.output_columns(), vec!["block", "value_f64", "gas_used"]);
+
+        schema.filter_expr = Some(col("gas_used").gt(lit(100u64)));
+        let df = df!("block" => [1u32, 2, 3], "gas_used" => [50u64, 150, 250]).unwrap();
+        let df = Ok(df).filter_by_schema(&schema).unwrap();
+        let blocks: Vec<_> = df.column("block").unwrap().u32().unwrap().into_iter().collect();
+        assert_eq!(blocks, vec![Some(2), Some(3)]);
+    }
+}
@@ -1,12 +1,21 @@
+mod append;
 mod export;
+mod filter;
 mod read;
+mod rename;
+mod schema_diff;
 mod sort;
 mod u256s;
 
 #[macro_use]
 mod creation;
 
+pub(crate) use append::ChunkAppender;
 pub(crate) use export::*;
+pub(crate) use filter::FilterableDataFrame;
 pub use read::*;
-pub(crate) use sort::SortableDataFrame;
+pub(crate) use rename::RenamableDataFrame;
+pub(crate) use schema_diff::{check_existing_columns, match_existing_columns, read_file_columns};
+pub use sort::pad_global_sort_labels;
+pub(crate) use sort::{ensure_global_sort, sort_dfs, SortableDataFrame};
 pub use u256s::*;
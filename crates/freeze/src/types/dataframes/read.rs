@@ -1,4 +1,4 @@
-use crate::ParseError;
+use crate::{decode_hex, ParseError};
 use polars::prelude::*;
 
 /// read single binary column of parquet file as Vec<u8>
@@ -17,6 +17,18 @@ pub fn read_binary_column(path: &str, column: &str) -> Result<Vec<Vec<u8>>, Pars
         .unique()
         .map_err(|_e| ParseError::ParseError("could not get column".to_string()))?;
 
+    // hex string columns are decoded, tolerating `0x` prefixes and either letter case
+    if let Ok(ca) = series.str() {
+        return ca
+            .into_iter()
+            .map(|value| {
+                let value = value
+                    .ok_or_else(|| ParseError::ParseError("transaction hash missing".to_string()))?;
+                decode_hex(value)
+            })
+            .collect()
+    }
+
     let ca = series
         .binary()
         .map_err(|_e| ParseError::ParseError("could not convert to binary column".to_string()))?;
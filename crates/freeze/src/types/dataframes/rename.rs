@@ -0,0 +1,37 @@
+use polars::prelude::*;
+
+use crate::types::{CollectError, Table};
+
+pub(crate) trait RenamableDataFrame {
+    fn rename_by_schema(self, schema: &Table) -> Self;
+}
+
+impl RenamableDataFrame for Result<DataFrame, CollectError> {
+    fn rename_by_schema(self, schema: &Table) -> Self {
+        match (self, &schema.block_number_column) {
+            (Ok(mut df), Some(name)) if schema.has_column("block_number") => {
+                df.rename("block_number", name).map_err(CollectError::PolarsError)?;
+                Ok(df)
+            }
+            (df, _) => df,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColumnEncoding, Datatype};
+
+    #[test]
+    fn test_rename_by_schema() {
+        let cols = Some(vec!["block_number".to_string(), "block_hash".to_string()]);
+        let mut schema = Datatype::Blocks
+            .table_schema(&[], &ColumnEncoding::Hex, &None, &None, &cols, None, None)
+            .unwrap();
+        schema.block_number_column = Some("block".to_string());
+        let df = df!("block_number" => [2u32, 1], "block_hash" => ["0x02", "0x01"]).unwrap();
+        let df = Ok(df).rename_by_schema(&schema).unwrap();
+        assert_eq!(df.get_column_names(), vec!["block", "block_hash"]);
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use polars::prelude::*;
+
+use crate::types::{
+    CollectError, Datatype, ExistingColumns, FileCompression, FileFormat, Query, SchemaFunctions,
+};
+
+/// read column names of an existing output file, None if the file cannot be read
+///
+/// json objects do not keep their key order, so json columns are returned sorted
+pub(crate) fn read_file_columns(
+    path: &Path,
+    format: &FileFormat,
+    compression: Option<FileCompression>,
+) -> Option<Vec<String>> {
+    let file = std::fs::File::open(path).ok()?;
+    match format {
+        FileFormat::Parquet => {
+            let schema = ParquetReader::new(file).schema().ok()?;
+            Some(schema.fields.iter().map(|field| field.name.clone()).collect())
+        }
+        FileFormat::Csv => {
+            let mut header = String::new();
+            BufReader::new(decompress(file, compression)?).read_line(&mut header).ok()?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                return None
+            }
+            Some(header.split(',').map(|column| column.trim_matches('"').to_string()).collect())
+        }
+        FileFormat::Json => {
+            // only the first row is parsed, files are arrays of rows
+            let mut reader = BufReader::new(decompress(file, compression)?);
+            let mut byte = [0u8];
+            while byte[0] != b'[' {
+                reader.read_exact(&mut byte).ok()?;
+                if byte[0] != b'[' && !byte[0].is_ascii_whitespace() {
+                    return None
+                }
+            }
+            let row: serde_json::Map<String, serde_json::Value> =
+                serde_json::Deserializer::from_reader(reader).into_iter().next()?.ok()?;
+            let mut columns: Vec<String> = row.keys().cloned().collect();
+            columns.sort();
+            Some(columns)
+        }
+    }
+}
+
+fn decompress(file: std::fs::File, compression: Option<FileCompression>) -> Option<Box<dyn Read>> {
+    match compression {
+        None => Some(Box::new(file)),
+        Some(FileCompression::Gzip) => Some(Box::new(flate2::read::GzDecoder::new(file))),
+        Some(FileCompression::Zstd) => Some(Box::new(zstd::Decoder::new(file).ok()?)),
+    }
+}
+
+/// check the output columns of each datatype against its existing files, before any collection
+///
+/// decoded columns depend on the decoded values, so they are only checked once collected
+pub(crate) fn check_existing_columns(
+    query: &Query,
+    existing_columns: &HashMap<Datatype, ExistingColumns>,
+    force: bool,
+) -> Result<(), CollectError> {
+    if force {
+        return Ok(())
+    }
+    for (datatype, existing) in existing_columns.iter() {
+        let schema = query.schemas.get_schema(datatype)?;
+        let columns = schema.output_columns();
+        let added: Vec<&String> =
+            columns.iter().filter(|column| !existing.columns.contains(column)).collect();
+        let removed: Vec<&String> = existing
+            .columns
+            .iter()
+            .filter(|column| !columns.contains(column) && !schema.decodes_column(column))
+            .collect();
+        if !added.is_empty() || !removed.is_empty() {
+            return Err(schema_change_error(*datatype, existing, &added, &removed))
+        }
+    }
+    Ok(())
+}
+
+/// match the columns of a dataframe to those of existing files of its datatype
+///
+/// columns that only differ in order are reordered to the existing order, other differences are
+/// an error unless `force` is set
+pub(crate) fn match_existing_columns(
+    df: DataFrame,
+    datatype: Datatype,
+    existing: &ExistingColumns,
+    force: bool,
+) -> Result<DataFrame, CollectError> {
+    let columns: Vec<String> = df.get_column_names().iter().map(|name| name.to_string()).collect();
+    let added: Vec<&String> =
+        columns.iter().filter(|column| !existing.columns.contains(column)).collect();
+    let removed: Vec<&String> =
+        existing.columns.iter().filter(|column| !columns.contains(column)).collect();
+    if added.is_empty() && removed.is_empty() {
+        if !existing.ordered || columns == existing.columns {
+            return Ok(df)
+        }
+        return df.select(&existing.columns).map_err(CollectError::PolarsError)
+    }
+    if force {
+        return Ok(df)
+    }
+    Err(schema_change_error(datatype, existing, &added, &removed))
+}
+
+fn schema_change_error(
+    datatype: Datatype,
+    existing: &ExistingColumns,
+    added: &[&String],
+    removed: &[&String],
+) -> CollectError {
+    let mut lines = vec![format!(
+        "columns of {} differ from existing file {}:",
+        datatype.name(),
+        existing.path.display()
+    )];
+    lines.extend(added.iter().map(|column| format!("+ {}", column)));
+    lines.extend(removed.iter().map(|column| format!("- {}", column)));
+    lines.push("use --force-schema-change to write files with the new columns".to_string());
+    CollectError::CollectError(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColumnEncoding, U256Type};
+
+    fn existing(columns: &[&str], ordered: bool) -> ExistingColumns {
+        ExistingColumns {
+            path: "blocks.parquet".into(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            ordered,
+        }
+    }
+
+    #[test]
+    fn test_match_existing_columns() {
+        let df = df!("block_number" => [1u32], "block_hash" => ["0x01"]).unwrap();
+
+        // order only differences are reconciled
+        let reordered = existing(&["block_hash", "block_number"], true);
+        let matched = match_existing_columns(df.clone(), Datatype::Blocks, &reordered, false);
+        assert_eq!(matched.unwrap().get_column_names(), vec!["block_hash", "block_number"]);
+        let unordered = existing(&["block_hash", "block_number"], false);
+        let matched = match_existing_columns(df.clone(), Datatype::Blocks, &unordered, false);
+        assert_eq!(matched.unwrap().get_column_names(), vec!["block_number", "block_hash"]);
+
+        // added and removed columns are an error unless forced
+        let changed = existing(&["block_number", "timestamp"], true);
+        let message = match match_existing_columns(df.clone(), Datatype::Blocks, &changed, false) {
+            Err(CollectError::CollectError(message)) => message,
+            _ => panic!("schema change should be an error"),
+        };
+        assert!(message.contains("+ block_hash") && message.contains("- timestamp"));
+        assert!(match_existing_columns(df, Datatype::Blocks, &changed, true).is_ok());
+    }
+
+    #[test]
+    fn test_check_existing_columns() {
+        let u256_types = [U256Type::Binary, U256Type::F64];
+        let columns = Some(vec!["block_number".to_string(), "value".to_string()]);
+        let schema = Datatype::Transactions
+            .table_schema(&u256_types, &ColumnEncoding::Hex, &None, &None, &columns, None, None)
+            .unwrap();
+        let query = Query::for_datatype(Datatype::Transactions, schema, vec![]);
+        let check = |columns: &[&str], force: bool| {
+            let existing = [(Datatype::Transactions, existing(columns, true))].into();
+            check_existing_columns(&query, &existing, force)
+        };
+
+        // columns are compared to the output columns of the schema, in any order
+        assert!(check(&["value_f64", "value_binary", "block_number"], false).is_ok());
+        assert!(check(&["block_number", "value_binary"], false).is_err());
+        assert!(check(&["block_number", "value_binary", "value_f64", "nonce"], false).is_err());
+        assert!(check(&["block_number", "value_binary"], true).is_ok());
+    }
+
+    #[test]
+    fn test_read_file_columns() {
+        let dir = std::env::temp_dir().join(format!("cryo_file_columns_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut df = df!("block_number" => [1u32], "block_hash" => ["0x01"]).unwrap();
+
+        let path = dir.join("blocks.parquet");
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        let columns = read_file_columns(&path, &FileFormat::Parquet, None).unwrap();
+        assert_eq!(columns, vec!["block_number", "block_hash"]);
+
+        let path = dir.join("blocks.csv");
+        CsvWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        let columns = read_file_columns(&path, &FileFormat::Csv, None).unwrap();
+        assert_eq!(columns, vec!["block_number", "block_hash"]);
+
+        let path = dir.join("blocks.json");
+        JsonWriter::new(std::fs::File::create(&path).unwrap())
+            .with_json_format(JsonFormat::Json)
+            .finish(&mut df)
+            .unwrap();
+        let columns = read_file_columns(&path, &FileFormat::Json, None).unwrap();
+        assert_eq!(columns, vec!["block_hash", "block_number"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,6 +1,10 @@
 use polars::prelude::*;
 
-use crate::types::{CollectError, Table};
+use crate::{
+    types::{CollectError, Table},
+    ChunkData, Datatype, Dim, Query, SchemaFunctions,
+};
+use std::collections::HashMap;
 
 pub(crate) trait SortableDataFrame {
     fn sort_by_schema(self, schema: &Table) -> Self;
@@ -10,9 +14,257 @@ impl SortableDataFrame for Result<DataFrame, CollectError> {
     fn sort_by_schema(self, schema: &Table) -> Self {
         match (self, &schema.sort_columns) {
             (Ok(df), Some(sort_columns)) => {
-                df.sort(sort_columns, false, false).map_err(CollectError::PolarsError)
+                let descending: Vec<bool> =
+                    (0..sort_columns.len()).map(|i| schema.is_sort_descending(i)).collect();
+                df.sort(sort_columns, descending, false).map_err(CollectError::PolarsError)
             }
             (df, _) => df,
         }
     }
 }
+
+/// sort dataframes stacked from several collections, each of which was sorted on its own
+pub(crate) fn sort_dfs(
+    dfs: &mut HashMap<Datatype, DataFrame>,
+    query: &Query,
+) -> Result<(), CollectError> {
+    for (datatype, df) in dfs.iter_mut() {
+        let schema = query.schemas.get_schema(datatype)?;
+        *df = Ok(std::mem::take(df)).sort_by_schema(schema)?;
+    }
+    Ok(())
+}
+
+/// label block partitions with a common zero-padded width, so filename order is block order
+pub fn pad_global_sort_labels(query: &mut Query) -> Result<(), CollectError> {
+    if query.partitioned_by != vec![Dim::BlockNumber] {
+        return Ok(())
+    }
+    let max_block = query
+        .partitions
+        .iter()
+        .filter_map(|partition| partition.block_numbers.as_ref()?.max_value())
+        .max()
+        .unwrap_or(0);
+    let width = max_block.to_string().len().max(8);
+    for partition in query.partitions.iter_mut() {
+        if partition.label.as_ref().is_some_and(|label| label.iter().any(|piece| piece.is_some())) {
+            continue
+        }
+        let chunks = partition
+            .block_numbers
+            .as_ref()
+            .ok_or(CollectError::CollectError("partition block numbers missing".to_string()))?;
+        if let (Some(min), Some(max)) = (chunks.min_value(), chunks.max_value()) {
+            let label = format!("{:0>width$}_to_{:0>width$}", min, max, width = width);
+            partition.label = Some(vec![Some(label)]);
+        }
+    }
+    Ok(())
+}
+
+/// check that concatenating output files in filename order yields globally sorted rows
+pub(crate) fn ensure_global_sort(query: &Query) -> Result<(), CollectError> {
+    // rows can only be ordered across files along the block dimension
+    if query.partitioned_by != vec![Dim::BlockNumber] {
+        let dims: Vec<_> = query.partitioned_by.iter().map(|dim| dim.to_string()).collect();
+        return Err(CollectError::CollectError(format!(
+            "global sort requires partitioning by block number only, got: {}",
+            dims.join(", ")
+        )))
+    }
+
+    // each file must be sorted by block number first
+    for (datatype, schema) in query.schemas.iter() {
+        match schema.sort_columns.as_deref() {
+            Some([first, ..]) if first == "block_number" && schema.is_sort_descending(0) => {
+                return Err(CollectError::CollectError(format!(
+                    "global sort requires {} to be sorted by ascending block_number",
+                    datatype.name()
+                )))
+            }
+            Some([first, ..]) if first == "block_number" => {}
+            None => {
+                return Err(CollectError::CollectError(format!(
+                    "global sort requires {} to be sorted, but its sorting is disabled",
+                    datatype.name()
+                )))
+            }
+            Some([first, ..]) => {
+                return Err(CollectError::CollectError(format!(
+                    "global sort requires {} to be sorted by block_number first, \
+                    `{}` cannot be ordered across block partitions",
+                    datatype.name(),
+                    first
+                )))
+            }
+            _ => {
+                return Err(CollectError::CollectError(format!(
+                    "global sort requires {} to be sorted by block_number",
+                    datatype.name()
+                )))
+            }
+        }
+    }
+
+    // filename order must match block order, and block ranges must not overlap
+    let mut ranges = Vec::new();
+    for partition in query.partitions.iter() {
+        let chunks = partition
+            .block_numbers
+            .as_ref()
+            .ok_or(CollectError::CollectError("partition block numbers missing".to_string()))?;
+        let label = partition.label(&query.partitioned_by)?;
+        match (chunks.min_value(), chunks.max_value()) {
+            (Some(min), Some(max)) => ranges.push((label, min, max)),
+            _ => return Err(CollectError::CollectError("partition has no blocks".to_string())),
+        }
+    }
+    ranges.sort();
+    for pair in ranges.windows(2) {
+        let ((previous_label, _, previous_max), (label, min, _)) = (&pair[0], &pair[1]);
+        if previous_max >= min {
+            return Err(CollectError::CollectError(format!(
+                "global sort requires non-overlapping block ranges in filename order, \
+                {} overlaps or sorts before {}",
+                previous_label, label
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BlockChunk, ColumnEncoding, Datatype, Partition, U256Type,
+    };
+
+    fn get_query(sort: &[&str], ranges: &[(u64, u64)]) -> Query {
+        let sort = Some(sort.iter().map(|s| s.to_string()).collect());
+        let schema = Datatype::Blocks
+            .table_schema(
+                &[U256Type::Binary],
+                &ColumnEncoding::Hex,
+                &None,
+                &None,
+                &None,
+                sort,
+                None,
+            )
+            .unwrap();
+        let partitions = ranges
+            .iter()
+            .map(|(start, end)| Partition {
+                block_numbers: Some(vec![BlockChunk::Range(*start, *end)]),
+                ..Default::default()
+            })
+            .collect();
+        Query::for_datatype(Datatype::Blocks, schema, partitions)
+    }
+
+    #[test]
+    fn test_ensure_global_sort() {
+        let query = get_query(&["block_number"], &[(1000, 1999), (0, 999)]);
+        assert!(ensure_global_sort(&query).is_ok());
+
+        // sort columns unrelated to partition dimension
+        let query = get_query(&["timestamp", "block_number"], &[(0, 999), (1000, 1999)]);
+        assert!(ensure_global_sort(&query).is_err());
+
+        // overlapping block ranges
+        let query = get_query(&["block_number"], &[(0, 1000), (1000, 1999)]);
+        assert!(ensure_global_sort(&query).is_err());
+
+        // filenames beyond padding width do not sort in block order until padded
+        let ranges = [(99_999_000, 99_999_999), (100_000_000, 100_000_999)];
+        let mut query = get_query(&["block_number"], &ranges);
+        assert!(ensure_global_sort(&query).is_err());
+        pad_global_sort_labels(&mut query).unwrap();
+        assert!(ensure_global_sort(&query).is_ok());
+
+        // descending block numbers are not in filename order
+        let mut query = get_query(&["block_number"], &[(0, 999)]);
+        query.schemas.values_mut().for_each(|schema| schema.sort_descending = vec![true]);
+        assert!(ensure_global_sort(&query).is_err());
+
+        // non-block partitioning
+        let mut query = get_query(&["block_number"], &[(0, 999)]);
+        query.partitioned_by = vec![Dim::BlockNumber, Dim::Address];
+        assert!(ensure_global_sort(&query).is_err());
+
+        // sorting disabled, rows keep their collected order
+        let mut query = get_query(&["block_number"], &[(0, 999)]);
+        query.schemas.values_mut().for_each(|schema| schema.sort_columns = None);
+        assert!(ensure_global_sort(&query).is_err());
+        let df = df!("block_number" => [2u32, 1]).unwrap();
+        let unsorted = Ok(df.clone()).sort_by_schema(&query.schemas[&Datatype::Blocks]).unwrap();
+        assert!(unsorted.equals(&df));
+    }
+
+    #[test]
+    fn test_pad_global_sort_labels() {
+        let ranges = [(100_000_000, 100_000_999), (0, 999), (99_999_000, 99_999_999)];
+        let mut query = get_query(&["block_number"], &ranges);
+        pad_global_sort_labels(&mut query).unwrap();
+        let labels: Vec<_> = query
+            .partitions
+            .iter()
+            .map(|partition| partition.label(&query.partitioned_by).unwrap())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "100000000_to_100000999",
+                "000000000_to_000000999",
+                "099999000_to_099999999",
+            ]
+        );
+        let mut sorted = labels.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![labels[1].clone(), labels[2].clone(), labels[0].clone()]);
+
+        // small block numbers keep the default width
+        let mut query = get_query(&["block_number"], &[(0, 999)]);
+        pad_global_sort_labels(&mut query).unwrap();
+        let label = query.partitions[0].label(&query.partitioned_by).unwrap();
+        assert_eq!(label, "00000000_to_00000999");
+    }
+
+    #[test]
+    fn test_sort_by_schema_descending() {
+        let query = get_query(&["block_number", "timestamp"], &[(0, 999)]);
+        let mut schema = query.schemas[&Datatype::Blocks].clone();
+        schema.sort_descending = vec![true];
+        let df = df!("block_number" => [1u32, 2, 2], "timestamp" => [5u32, 2, 1]).unwrap();
+
+        // block_number descending, timestamp ascending by default
+        let sorted = Ok(df).sort_by_schema(&schema).unwrap();
+        let timestamps = sorted.column("timestamp").unwrap().u32().unwrap();
+        assert_eq!(timestamps.into_no_null_iter().collect::<Vec<_>>(), vec![1, 2, 5]);
+    }
+
+    #[test]
+    fn test_sort_dfs() {
+        let mut query = get_query(&["timestamp", "block_number"], &[(0, 3)]);
+        let schema = query.schemas.get_mut(&Datatype::Blocks).unwrap();
+        schema.sort_descending = vec![true];
+
+        // each collection is sorted on its own before stacking
+        let mut df = df!("block_number" => [1u32, 0], "timestamp" => [20u32, 10]).unwrap();
+        df.vstack_mut(&df!("block_number" => [3u32, 2], "timestamp" => [40u32, 30]).unwrap())
+            .unwrap();
+        let mut dfs: HashMap<_, _> = [(Datatype::Blocks, df)].into_iter().collect();
+        sort_dfs(&mut dfs, &query).unwrap();
+        let blocks: Vec<_> = dfs[&Datatype::Blocks]
+            .column("block_number")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(blocks, vec![3, 2, 1, 0]);
+    }
+}
@@ -2,6 +2,38 @@ use crate::*;
 use alloy::primitives::{I256, U256};
 use polars::prelude::*;
 
+/// number of digits representable by a Decimal128 column
+pub const DECIMAL128_PRECISION: usize = 38;
+
+/// convert U256-like values into a Decimal128 Series
+///
+/// if any value exceeds the Decimal128 range, the whole batch is written as a String column of
+/// decimal strings instead, so that no value is truncated and the column keeps one type
+pub fn to_decimal128_series<T: ToString>(
+    name: &str,
+    values: &[Option<T>],
+    to_i128: impl Fn(&T) -> Option<i128>,
+) -> Result<Series, CollectError> {
+    let max_value = 10_u128.pow(DECIMAL128_PRECISION as u32);
+    let converted = values
+        .iter()
+        .map(|v| match v {
+            Some(v) => to_i128(v).filter(|x| x.unsigned_abs() < max_value).map(Some),
+            None => Some(None),
+        })
+        .collect::<Option<Vec<_>>>();
+    match converted {
+        Some(converted) => Ok(Int128Chunked::from_iter_options(name, converted.into_iter())
+            .into_decimal_unchecked(Some(DECIMAL128_PRECISION), 0)
+            .into_series()),
+        None => {
+            let strings: Vec<Option<String>> =
+                values.iter().map(|v| v.as_ref().map(|v| v.to_string())).collect();
+            Ok(Series::new(name, strings))
+        }
+    }
+}
+
 /// Converts a Vec of U256-like data into a polars Series
 pub trait ToU256Series {
     /// convert a Vec of U256-like data into a polars Series
@@ -27,7 +59,10 @@ impl ToU256Series for Vec<U256> {
             U256Type::Binary => {
                 let converted: Vec<Vec<u8>> = self.iter().map(|v| v.to_vec_u8()).collect();
                 match column_encoding {
-                    ColumnEncoding::Hex => Ok(Series::new(name, converted.to_vec_hex())),
+                    ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed => Ok(Series::new(
+                        name,
+                        converted.to_vec_hex(column_encoding.hex_prefix()),
+                    )),
                     ColumnEncoding::Binary => Ok(Series::new(name, converted)),
                 }
             }
@@ -54,7 +89,8 @@ impl ToU256Series for Vec<U256> {
                 Ok(Series::new(name, converted))
             }
             U256Type::Decimal128 => {
-                Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
+                let converted: Vec<Option<U256>> = self.iter().map(|v| Some(*v)).collect();
+                to_decimal128_series(name, &converted, |v| i128::try_from(v).ok())
             }
         }
     }
@@ -75,7 +111,10 @@ impl ToU256Series for Vec<Option<U256>> {
                 let converted: Vec<Option<Vec<u8>>> =
                     self.iter().map(|v| v.map(|x| x.to_vec_u8())).collect();
                 match column_encoding {
-                    ColumnEncoding::Hex => Ok(Series::new(name, converted.to_vec_hex())),
+                    ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed => Ok(Series::new(
+                        name,
+                        converted.to_vec_hex(column_encoding.hex_prefix()),
+                    )),
                     ColumnEncoding::Binary => Ok(Series::new(name, converted)),
                 }
             }
@@ -109,7 +148,7 @@ impl ToU256Series for Vec<Option<U256>> {
                 Ok(Series::new(name, converted))
             }
             U256Type::Decimal128 => {
-                Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
+                to_decimal128_series(name, self, |v| i128::try_from(v).ok())
             }
         }
     }
@@ -129,7 +168,10 @@ impl ToU256Series for Vec<I256> {
             U256Type::Binary => {
                 let converted: Vec<Vec<u8>> = self.iter().map(|v| v.to_vec_u8()).collect();
                 match column_encoding {
-                    ColumnEncoding::Hex => Ok(Series::new(name, converted.to_vec_hex())),
+                    ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed => Ok(Series::new(
+                        name,
+                        converted.to_vec_hex(column_encoding.hex_prefix()),
+                    )),
                     ColumnEncoding::Binary => Ok(Series::new(name, converted)),
                 }
             }
@@ -156,7 +198,8 @@ impl ToU256Series for Vec<I256> {
                 Ok(Series::new(name, converted))
             }
             U256Type::Decimal128 => {
-                Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
+                let converted: Vec<Option<I256>> = self.iter().map(|v| Some(*v)).collect();
+                to_decimal128_series(name, &converted, |v| i128::try_from(*v).ok())
             }
         }
     }
@@ -177,7 +220,10 @@ impl ToU256Series for Vec<Option<I256>> {
                 let converted: Vec<Option<Vec<u8>>> =
                     self.iter().map(|v| v.map(|x| x.to_vec_u8())).collect();
                 match column_encoding {
-                    ColumnEncoding::Hex => Ok(Series::new(name, converted.to_vec_hex())),
+                    ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed => Ok(Series::new(
+                        name,
+                        converted.to_vec_hex(column_encoding.hex_prefix()),
+                    )),
                     ColumnEncoding::Binary => Ok(Series::new(name, converted)),
                 }
             }
@@ -211,8 +257,36 @@ impl ToU256Series for Vec<Option<I256>> {
                 Ok(Series::new(name, converted))
             }
             U256Type::Decimal128 => {
-                Err(CollectError::CollectError("DECIMAL128 not implemented".to_string()))
+                to_decimal128_series(name, self, |v| i128::try_from(*v).ok())
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal128_series() {
+        let values = vec![Some(U256::from(1_000_000_000_000_000_000_u128)), None];
+        let series = to_decimal128_series("value", &values, |v| i128::try_from(v).ok()).unwrap();
+        assert_eq!(series.dtype(), &DataType::Decimal(Some(DECIMAL128_PRECISION), Some(0)));
+        assert_eq!(series.null_count(), 1);
+    }
+
+    #[test]
+    fn test_decimal128_series_overflow_falls_back_to_strings() {
+        let max = U256::from(10_u128.pow(DECIMAL128_PRECISION as u32) - 1);
+        let values = vec![Some(U256::from(1)), Some(max)];
+        let series = to_decimal128_series("value", &values, |v| i128::try_from(v).ok()).unwrap();
+        assert_eq!(series.dtype(), &DataType::Decimal(Some(DECIMAL128_PRECISION), Some(0)));
+
+        // one value out of range turns the whole batch into decimal strings
+        let values = vec![Some(U256::from(1)), None, Some(U256::MAX)];
+        let series = to_decimal128_series("value", &values, |v| i128::try_from(v).ok()).unwrap();
+        assert_eq!(series.dtype(), &DataType::String);
+        let strings: Vec<Option<&str>> = series.str().unwrap().into_iter().collect();
+        assert_eq!(strings, vec![Some("1"), None, Some(U256::MAX.to_string().as_str())]);
+    }
+}
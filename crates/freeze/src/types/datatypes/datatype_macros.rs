@@ -141,6 +141,9 @@ macro_rules! define_datatypes {
                     MultiDatatype::StateReads => {
                         StateReads::collect_by_block(partition, source, query, None)
                     },
+                    MultiDatatype::TraceReplay => {
+                        TraceReplay::collect_by_block(partition, source, query, None)
+                    },
                 },
             };
             task.await
@@ -184,6 +187,9 @@ macro_rules! define_datatypes {
                         MultiDatatype::StateReads => {
                             StateReads::collect_by_transaction(partition, source, query, inner_request_size)
                         },
+                        MultiDatatype::TraceReplay => {
+                            TraceReplay::collect_by_transaction(partition, source, query, inner_request_size)
+                        },
                     }
                 },
             };
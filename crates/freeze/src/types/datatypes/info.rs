@@ -0,0 +1,113 @@
+use super::{multi::MultiDatatype, scalar::Datatype};
+use crate::Dim;
+
+/// serializable metadata of a scalar or multi datatype
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DatatypeInfo {
+    /// Scalar datatype
+    Scalar(ScalarDatatypeInfo),
+    /// Multi datatype
+    Multi(MultiDatatypeInfo),
+}
+
+/// serializable metadata of a scalar datatype
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ScalarDatatypeInfo {
+    /// name of datatype
+    pub name: String,
+    /// aliases of datatype
+    pub aliases: Vec<String>,
+    /// whether datatype can be collected by block
+    pub can_collect_by_block: bool,
+    /// whether datatype can be collected by transaction
+    pub can_collect_by_transaction: bool,
+    /// required parameters of datatype
+    pub required_parameters: Vec<String>,
+    /// optional parameters of datatype
+    pub optional_parameters: Vec<String>,
+    /// parameter aliases of datatype, as (alias, target) pairs
+    pub parameter_aliases: Vec<(String, String)>,
+    /// default columns of datatype
+    pub default_columns: Vec<String>,
+    /// default sort columns of datatype
+    pub default_sort: Vec<String>,
+}
+
+/// serializable metadata of a multi datatype
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct MultiDatatypeInfo {
+    /// name of multi datatype
+    pub name: String,
+    /// names of the scalar datatypes collected together
+    pub datatypes: Vec<String>,
+}
+
+impl From<Datatype> for ScalarDatatypeInfo {
+    fn from(datatype: Datatype) -> ScalarDatatypeInfo {
+        let mut parameter_aliases: Vec<(String, String)> = datatype
+            .arg_aliases()
+            .iter()
+            .map(|(key, value)| (key.plural_name().to_string(), value.plural_name().to_string()))
+            .collect();
+        parameter_aliases.sort();
+        let to_strings = |dims: Vec<Dim>| dims.iter().map(|x| x.to_string()).collect();
+        ScalarDatatypeInfo {
+            name: datatype.name(),
+            aliases: datatype.aliases().into_iter().map(|x| x.to_string()).collect(),
+            can_collect_by_block: datatype.can_collect_by_block(),
+            can_collect_by_transaction: datatype.can_collect_by_transaction(),
+            required_parameters: to_strings(datatype.required_parameters()),
+            optional_parameters: to_strings(datatype.optional_parameters()),
+            parameter_aliases,
+            default_columns: datatype.default_columns().iter().map(|x| x.to_string()).collect(),
+            default_sort: datatype.default_sort(),
+        }
+    }
+}
+
+impl From<MultiDatatype> for MultiDatatypeInfo {
+    fn from(datatype: MultiDatatype) -> MultiDatatypeInfo {
+        MultiDatatypeInfo {
+            name: datatype.name(),
+            datatypes: datatype.datatypes().iter().map(|x| x.name()).collect(),
+        }
+    }
+}
+
+/// list metadata of all scalar datatypes followed by all multi datatypes
+pub fn list_datatypes() -> Vec<DatatypeInfo> {
+    let scalar = Datatype::all().into_iter().map(|x| DatatypeInfo::Scalar(x.into()));
+    let multi = MultiDatatype::variants().into_iter().map(|x| DatatypeInfo::Multi(x.into()));
+    scalar.chain(multi).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_datatypes() {
+        let infos = list_datatypes();
+        assert_eq!(infos.len(), Datatype::all().len() + MultiDatatype::variants().len());
+
+        let value = serde_json::to_value(&infos).unwrap();
+        let state_diffs = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|info| info["name"] == "state_diffs")
+            .unwrap();
+        assert_eq!(state_diffs["kind"], "multi");
+        assert_eq!(state_diffs["datatypes"][0], "balance_diffs");
+
+        let blocks = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|info| info["name"] == "blocks")
+            .unwrap();
+        assert_eq!(blocks["kind"], "scalar");
+        assert_eq!(blocks["can_collect_by_block"], true);
+    }
+}
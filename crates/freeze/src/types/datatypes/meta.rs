@@ -1,4 +1,6 @@
 use super::{multi::MultiDatatype, scalar::Datatype};
+use crate::multi_datasets::trace_replay::replay_trace_type;
+use std::collections::HashSet;
 
 /// datatype representing either a Datatype or MultiDatatype
 #[derive(Clone, Debug, serde::Serialize)]
@@ -37,3 +39,19 @@ pub fn cluster_datatypes(dts: Vec<Datatype>) -> Vec<MetaDatatype> {
     ]
     .concat()
 }
+
+/// cluster datatypes, replaying the state diffs, call traces, and vm traces of parity traces
+/// together in one pass
+///
+/// replays are only combined when they need at least two trace types, otherwise the datatypes are
+/// clustered as usual
+pub fn cluster_trace_replay_datatypes(dts: Vec<Datatype>) -> Vec<MetaDatatype> {
+    let replayed: Vec<Datatype> =
+        MultiDatatype::TraceReplay.datatypes().into_iter().filter(|dt| dts.contains(dt)).collect();
+    let trace_types: HashSet<_> = replayed.iter().filter_map(replay_trace_type).collect();
+    if trace_types.len() < 2 {
+        return cluster_datatypes(dts)
+    }
+    let other_dts: Vec<Datatype> = dts.into_iter().filter(|dt| !replayed.contains(dt)).collect();
+    [vec![MetaDatatype::Multi(MultiDatatype::TraceReplay)], cluster_datatypes(other_dts)].concat()
+}
@@ -1,9 +1,11 @@
 mod datatype_macros;
+mod info;
 /// meta datatypes
 pub mod meta;
 mod multi;
 mod scalar;
 
+pub use info::*;
 pub use meta::*;
 pub use multi::*;
 pub use scalar::*;
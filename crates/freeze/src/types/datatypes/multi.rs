@@ -18,6 +18,10 @@ pub enum MultiDatatype {
 
     /// balance reads, code reads, nonce reads, and storage reads
     StateReads,
+
+    /// state diffs, call traces, and vm traces replayed in one pass, only used with
+    /// `--trace-replay`
+    TraceReplay,
 }
 
 impl MultiDatatype {
@@ -46,10 +50,20 @@ impl MultiDatatype {
                 Datatype::NonceReads,
                 Datatype::StorageReads,
             ],
+            MultiDatatype::TraceReplay => vec![
+                Datatype::BalanceDiffs,
+                Datatype::CodeDiffs,
+                Datatype::NonceDiffs,
+                Datatype::StorageDiffs,
+                Datatype::Contracts,
+                Datatype::NativeTransfers,
+                Datatype::Traces,
+                Datatype::VmTraces,
+            ],
         }
     }
 
-    /// return all variants of multi datatype
+    /// return all variants of multi datatype that are clustered by default
     pub fn variants() -> Vec<MultiDatatype> {
         vec![
             MultiDatatype::BlocksAndTransactions,
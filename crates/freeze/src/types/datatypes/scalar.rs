@@ -7,6 +7,7 @@ define_datatypes!(
     BalanceDiffs,
     BalanceReads,
     Balances,
+    Blobs,
     Blocks,
     CodeDiffs,
     CodeReads,
@@ -21,6 +22,7 @@ define_datatypes!(
     Erc721Transfers,
     EthCalls,
     FourByteCounts,
+    GenesisAlloc,
     GethCalls,
     GethCodeDiffs,
     GethBalanceDiffs,
@@ -33,12 +35,15 @@ define_datatypes!(
     NonceDiffs,
     NonceReads,
     Nonces,
+    Simulations,
     Slots,
     StorageDiffs,
     StorageReads,
     Traces,
     TraceCalls,
+    TransactionAccessLists,
     Transactions,
+    Uncles,
     VmTraces,
 );
 
@@ -0,0 +1,361 @@
+use crate::{encode_hex, CollectError, ColumnEncoding, ToU256Series, ToVecHex, U256Type};
+use alloy::{
+    dyn_abi::{DynSolType, DynSolValue, JsonAbiExt, Specifier},
+    json_abi::{Function, JsonAbi},
+    primitives::{Selector, I256, U256},
+};
+use indexmap::IndexMap;
+use polars::prelude::*;
+use std::{collections::BTreeMap, path::Path};
+
+/// container for call decoding context
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallDecoder {
+    /// functions of the abis, by selector
+    pub functions: BTreeMap<Selector, Vec<Function>>,
+    /// argument columns and their types, `None` if argument types differ across functions
+    pub columns: IndexMap<String, Option<DynSolType>>,
+}
+
+/// call input decoded by a CallDecoder
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedCall {
+    /// signature of the matched function, ex: transfer(address,uint256)
+    pub signature: String,
+    /// named arguments of the call
+    pub args: Vec<(String, DynSolValue)>,
+}
+
+/// decoded call inputs of a table, one entry per row
+///
+/// rows without call input are `None`, rows that could not be decoded hold the reason
+#[derive(Clone, Debug, Default)]
+pub struct CallColumns {
+    rows: Vec<Option<Result<DecodedCall, String>>>,
+}
+
+impl CallColumns {
+    /// decode input of the next row, `None` for rows without call input
+    pub fn push(&mut self, decoder: &CallDecoder, input: Option<&[u8]>) {
+        self.rows.push(input.and_then(|input| decoder.decode_input(input).transpose()))
+    }
+}
+
+impl CallDecoder {
+    /// create a new CallDecoder from the functions of every json abi file in a directory
+    ///
+    /// files are read in name order, and a function with the same signature in several abis is
+    /// decoded using the argument names of the first abi
+    pub fn from_abi_dir(dir: &Path) -> Result<Self, String> {
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| format!("could not read abi dir {}: {}", dir.display(), e))?;
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect();
+        paths.sort();
+        let mut abis = Vec::new();
+        for path in paths.iter() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("could not read abi {}: {}", path.display(), e))?;
+            abis.push(parse_abi(&contents).map_err(|e| format!("{}: {}", path.display(), e))?);
+        }
+        Ok(Self::new(abis))
+    }
+
+    /// create a new CallDecoder from the functions of abis
+    pub fn new(abis: Vec<JsonAbi>) -> Self {
+        let mut functions: BTreeMap<Selector, Vec<Function>> = BTreeMap::new();
+        for function in abis.iter().flat_map(|abi| abi.functions()) {
+            let candidates = functions.entry(function.selector()).or_default();
+            if !candidates.iter().any(|f| f.signature() == function.signature()) {
+                candidates.push(function.clone());
+            }
+        }
+
+        let mut columns: IndexMap<String, Option<DynSolType>> = IndexMap::new();
+        for function in functions.values().flatten() {
+            for (i, param) in function.inputs.iter().enumerate() {
+                let ty = param.resolve().ok();
+                let name = arg_name(&param.name, i);
+                match columns.get(&name) {
+                    Some(existing) if *existing != ty => {
+                        columns.insert(name, None);
+                    }
+                    Some(_) => {}
+                    None => {
+                        columns.insert(name, ty);
+                    }
+                }
+            }
+        }
+
+        Self { functions, columns }
+    }
+
+    /// decode call input, `None` if input is empty
+    ///
+    /// fails if no function matches the input, or if several functions of the same selector do
+    pub fn decode_input(&self, input: &[u8]) -> Result<Option<DecodedCall>, String> {
+        if input.is_empty() {
+            return Ok(None)
+        } else if input.len() < 4 {
+            return Err("input shorter than selector".to_string())
+        }
+        let selector = Selector::from_slice(&input[..4]);
+        let candidates = match self.functions.get(&selector) {
+            Some(candidates) => candidates,
+            None => return Err(format!("unknown selector {}", selector)),
+        };
+        let mut matches = Vec::new();
+        for function in candidates.iter() {
+            if let Ok(values) = function.abi_decode_input(&input[4..], true) {
+                matches.push((function, values))
+            }
+        }
+        match matches.len() {
+            0 => {
+                let signatures: Vec<_> = candidates.iter().map(|f| f.signature()).collect();
+                Err(format!("input does not match {}", signatures.join(", ")))
+            }
+            1 => {
+                let (function, values) = matches.remove(0);
+                let args = function
+                    .inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, param)| arg_name(&param.name, i))
+                    .zip(values)
+                    .collect();
+                Ok(Some(DecodedCall { signature: function.signature(), args }))
+            }
+            _ => {
+                let signatures: Vec<_> = matches.iter().map(|(f, _)| f.signature()).collect();
+                Err(format!("ambiguous selector {}: {}", selector, signatures.join(", ")))
+            }
+        }
+    }
+
+    /// create `call__signature`, `call__error`, and one `call__<arg>` column per argument
+    pub fn make_series(
+        &self,
+        calls: CallColumns,
+        u256_types: &[U256Type],
+        column_encoding: &ColumnEncoding,
+        checksum_addresses: bool,
+    ) -> Result<Vec<Series>, CollectError> {
+        let mut signatures: Vec<Option<String>> = Vec::with_capacity(calls.rows.len());
+        let mut errors: Vec<Option<String>> = Vec::with_capacity(calls.rows.len());
+        let mut args: IndexMap<&String, Vec<Option<DynSolValue>>> =
+            self.columns.keys().map(|name| (name, Vec::with_capacity(calls.rows.len()))).collect();
+        for row in calls.rows.into_iter() {
+            let decoded = match row {
+                Some(Ok(decoded)) => {
+                    signatures.push(Some(decoded.signature));
+                    errors.push(None);
+                    decoded.args
+                }
+                Some(Err(e)) => {
+                    signatures.push(None);
+                    errors.push(Some(e));
+                    Vec::new()
+                }
+                None => {
+                    signatures.push(None);
+                    errors.push(None);
+                    Vec::new()
+                }
+            };
+            let mut decoded: BTreeMap<String, DynSolValue> = decoded.into_iter().collect();
+            for (name, values) in args.iter_mut() {
+                values.push(decoded.remove(*name))
+            }
+        }
+
+        let mut series = vec![
+            Series::new("call__signature", signatures),
+            Series::new("call__error", errors),
+        ];
+        for ((name, ty), values) in self.columns.iter().zip(args.into_values()) {
+            let name = format!("call__{}", name);
+            series.extend(make_arg_series(
+                name,
+                ty,
+                values,
+                u256_types,
+                column_encoding,
+                checksum_addresses,
+            )?);
+        }
+        Ok(series)
+    }
+}
+
+/// parse abi file, either a json abi or an object with an `abi` field such as build artifacts
+fn parse_abi(contents: &str) -> Result<JsonAbi, String> {
+    let value: serde_json::Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    let value = match value {
+        serde_json::Value::Object(mut object) if object.contains_key("abi") => {
+            object.remove("abi").unwrap_or_default()
+        }
+        value => value,
+    };
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+fn arg_name(name: &str, index: usize) -> String {
+    if name.is_empty() {
+        format!("arg{}", index)
+    } else {
+        name.to_string()
+    }
+}
+
+/// create the columns of decoded values of one argument, null where values are missing
+pub(crate) fn make_arg_series(
+    name: String,
+    ty: &Option<DynSolType>,
+    values: Vec<Option<DynSolValue>>,
+    u256_types: &[U256Type],
+    column_encoding: &ColumnEncoding,
+    checksum_addresses: bool,
+) -> Result<Vec<Series>, CollectError> {
+    let series = match ty {
+        Some(DynSolType::Address) | Some(DynSolType::Bytes) | Some(DynSolType::FixedBytes(_)) => {
+            let bytes: Vec<Option<Vec<u8>>> = values
+                .into_iter()
+                .map(|value| match value {
+                    Some(DynSolValue::Address(a)) => Some(a.to_vec()),
+                    Some(DynSolValue::Bytes(b)) => Some(b),
+                    Some(DynSolValue::FixedBytes(b, size)) => Some(b[..size].to_vec()),
+                    _ => None,
+                })
+                .collect();
+            let prefix = column_encoding.hex_prefix();
+            match column_encoding {
+                ColumnEncoding::Binary => Series::new(&name, bytes),
+                _ if checksum_addresses && ty == &Some(DynSolType::Address) => {
+                    Series::new(&name, bytes.to_vec_checksum_hex(prefix))
+                }
+                _ => Series::new(&name, bytes.to_vec_hex(prefix)),
+            }
+        }
+        Some(DynSolType::Uint(bits)) if *bits <= 64 => {
+            let uints: Vec<Option<u64>> = values
+                .into_iter()
+                .map(|value| value.and_then(|v| v.as_uint()).map(|(v, _)| v.wrapping_to::<u64>()))
+                .collect();
+            Series::new(&name, uints)
+        }
+        Some(DynSolType::Int(bits)) if *bits <= 64 => {
+            let ints: Vec<Option<i64>> = values
+                .into_iter()
+                .map(|value| value.and_then(|v| v.as_int()).map(|(v, _)| v.as_i64()))
+                .collect();
+            Series::new(&name, ints)
+        }
+        Some(DynSolType::Uint(_)) => {
+            let u256s: Vec<Option<U256>> =
+                values.into_iter().map(|value| value.and_then(|v| v.as_uint()).map(|v| v.0)).collect();
+            return u256_types
+                .iter()
+                .map(|u256_type| {
+                    u256s.to_u256_series(name.clone(), u256_type.clone(), column_encoding)
+                })
+                .collect()
+        }
+        Some(DynSolType::Int(_)) => {
+            let i256s: Vec<Option<I256>> =
+                values.into_iter().map(|value| value.and_then(|v| v.as_int()).map(|v| v.0)).collect();
+            return u256_types
+                .iter()
+                .map(|u256_type| {
+                    i256s.to_u256_series(name.clone(), u256_type.clone(), column_encoding)
+                })
+                .collect()
+        }
+        Some(DynSolType::Bool) => {
+            let bools: Vec<Option<bool>> =
+                values.into_iter().map(|value| value.and_then(|v| v.as_bool())).collect();
+            Series::new(&name, bools)
+        }
+        _ => {
+            let strings: Vec<Option<String>> =
+                values.iter().map(|value| value.as_ref().map(format_value)).collect();
+            Series::new(&name, strings)
+        }
+    };
+    Ok(vec![series])
+}
+
+/// format argument of a type without a dedicated column representation, such as arrays
+fn format_value(value: &DynSolValue) -> String {
+    let join = |values: &[DynSolValue]| {
+        values.iter().map(format_value).collect::<Vec<_>>().join(", ")
+    };
+    match value {
+        DynSolValue::Address(a) => a.to_string(),
+        DynSolValue::Bytes(b) => encode_hex(b, true),
+        DynSolValue::FixedBytes(b, size) => encode_hex(&b[..*size], true),
+        DynSolValue::Function(f) => encode_hex(f.as_slice(), true),
+        DynSolValue::Uint(v, _) => v.to_string(),
+        DynSolValue::Int(v, _) => v.to_string(),
+        DynSolValue::Bool(b) => b.to_string(),
+        DynSolValue::String(s) => s.clone(),
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+            format!("[{}]", join(values))
+        }
+        DynSolValue::Tuple(values) => format!("({})", join(values)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoder() -> CallDecoder {
+        let erc20 = r#"[{"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+            "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}],
+            "outputs": [{"name": "", "type": "bool"}]}]"#;
+        let weth = r#"{"abi": [{"type": "function", "name": "transfer",
+            "stateMutability": "nonpayable",
+            "inputs": [{"name": "dst", "type": "address"}, {"name": "wad", "type": "uint256"}],
+            "outputs": [{"name": "", "type": "bool"}]}]}"#;
+        CallDecoder::new(vec![parse_abi(erc20).unwrap(), parse_abi(weth).unwrap()])
+    }
+
+    #[test]
+    fn test_decode_input() {
+        let decoder = decoder();
+
+        // same signature across abis uses names of first abi
+        let columns: Vec<_> = decoder.columns.keys().cloned().collect();
+        assert_eq!(columns, vec!["to".to_string(), "amount".to_string()]);
+
+        let mut input = alloy::hex::decode("a9059cbb").unwrap();
+        input.extend([0; 12]);
+        input.extend([1; 20]);
+        input.extend(U256::from(5).to_be_bytes::<32>());
+        let decoded = decoder.decode_input(&input).unwrap().unwrap();
+        assert_eq!(decoded.signature, "transfer(address,uint256)");
+        assert_eq!(decoded.args[1], ("amount".to_string(), DynSolValue::Uint(U256::from(5), 256)));
+
+        // empty input has nothing to decode, mismatched input is an error
+        assert_eq!(decoder.decode_input(&[]), Ok(None));
+        assert!(decoder.decode_input(&input[..40]).is_err());
+        assert!(decoder.decode_input(&[0, 1, 2, 3]).is_err());
+
+        // undecoded rows keep their error and null arguments
+        let mut calls = CallColumns::default();
+        calls.push(&decoder, Some(&input));
+        calls.push(&decoder, Some(&[0, 1, 2, 3]));
+        calls.push(&decoder, None);
+        let series =
+            decoder.make_series(calls, &[U256Type::String], &ColumnEncoding::Binary, false);
+        let series = series.unwrap();
+        let names: Vec<_> = series.iter().map(|s| s.name().to_string()).collect();
+        assert_eq!(names, vec!["call__signature", "call__error", "call__to", "call__amount_string"]);
+        assert_eq!(series[1].null_count(), 2);
+        assert_eq!(series[3].null_count(), 2);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{make_arg_series, CollectError, ColumnEncoding, U256Type};
+use alloy::dyn_abi::{DynSolType, DynSolValue};
+use polars::prelude::*;
+
+/// container for decoding log data of a known layout, without the event abi
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataDecoder {
+    /// the raw layout string ex: uint256,address
+    pub raw: String,
+    /// types of the non-indexed values of log data
+    pub types: Vec<DynSolType>,
+}
+
+/// decoded log data of a table, one entry per row
+///
+/// rows whose data does not match the layout are `None`
+#[derive(Clone, Debug, Default)]
+pub struct DataColumns {
+    rows: Vec<Option<Vec<DynSolValue>>>,
+}
+
+impl DataColumns {
+    /// decode data of the next row
+    pub fn push(&mut self, decoder: &DataDecoder, data: &[u8]) {
+        self.rows.push(decoder.decode_data(data))
+    }
+}
+
+impl DataDecoder {
+    /// create a new DataDecoder from comma separated types
+    /// ex: DataDecoder::new("uint256,address")
+    pub fn new(layout: &str) -> Result<Self, String> {
+        let layout = layout.trim();
+        let tuple =
+            if layout.starts_with('(') { layout.to_string() } else { format!("({})", layout) };
+        match DynSolType::parse(&tuple) {
+            Ok(DynSolType::Tuple(types)) if !types.is_empty() => {
+                Ok(Self { raw: layout.to_string(), types })
+            }
+            Ok(_) => Err(format!("invalid log data layout: {}", layout)),
+            Err(e) => Err(format!(
+                "invalid log data layout {} (expect something like uint256,address) err: {}",
+                layout, e
+            )),
+        }
+    }
+
+    /// decode log data, `None` if data is not exactly an encoding of the layout
+    pub fn decode_data(&self, data: &[u8]) -> Option<Vec<DynSolValue>> {
+        let value = DynSolType::Tuple(self.types.clone()).abi_decode_sequence(data).ok()?;
+
+        // data with missing or trailing bytes can still decode, so compare encoded lengths
+        if value.abi_encode_sequence()?.len() != data.len() {
+            return None
+        }
+        match value {
+            DynSolValue::Tuple(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// create `data__decode_failed`, and one `data__arg<i>` column per type of the layout
+    pub fn make_series(
+        &self,
+        data: DataColumns,
+        u256_types: &[U256Type],
+        column_encoding: &ColumnEncoding,
+        checksum_addresses: bool,
+    ) -> Result<Vec<Series>, CollectError> {
+        let failed: Vec<bool> = data.rows.iter().map(|row| row.is_none()).collect();
+        let mut args: Vec<Vec<Option<DynSolValue>>> =
+            self.types.iter().map(|_| Vec::with_capacity(data.rows.len())).collect();
+        for row in data.rows.into_iter() {
+            match row {
+                Some(values) => {
+                    for (arg, value) in args.iter_mut().zip(values) {
+                        arg.push(Some(value))
+                    }
+                }
+                None => args.iter_mut().for_each(|arg| arg.push(None)),
+            }
+        }
+
+        let mut series = vec![Series::new("data__decode_failed", failed)];
+        for (i, (ty, values)) in self.types.iter().zip(args).enumerate() {
+            series.extend(make_arg_series(
+                format!("data__arg{}", i),
+                &Some(ty.clone()),
+                values,
+                u256_types,
+                column_encoding,
+                checksum_addresses,
+            )?);
+        }
+        Ok(series)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    #[test]
+    fn test_decode_data() {
+        let decoder = DataDecoder::new("uint256,address").unwrap();
+        assert_eq!(decoder.types, vec![DynSolType::Uint(256), DynSolType::Address]);
+        assert!(DataDecoder::new("uint256,notatype").is_err());
+
+        let mut data = U256::from(5).to_be_bytes::<32>().to_vec();
+        data.extend([0; 12]);
+        data.extend([1; 20]);
+        let expected = vec![
+            DynSolValue::Uint(U256::from(5), 256),
+            DynSolValue::Address(Address::repeat_byte(1)),
+        ];
+        assert_eq!(decoder.decode_data(&data), Some(expected));
+
+        // data longer or shorter than the layout is not decoded
+        assert_eq!(decoder.decode_data(&data[..32]), None);
+        assert_eq!(decoder.decode_data(&[data.clone(), vec![0; 32]].concat()), None);
+
+        // undecoded rows keep a failure flag and null values
+        let mut rows = DataColumns::default();
+        rows.push(&decoder, &data);
+        rows.push(&decoder, &data[..32]);
+        let series =
+            decoder.make_series(rows, &[U256Type::String], &ColumnEncoding::Binary, false).unwrap();
+        let names: Vec<_> = series.iter().map(|s| s.name().to_string()).collect();
+        assert_eq!(names, vec!["data__decode_failed", "data__arg0_string", "data__arg1"]);
+        let failed: Vec<_> = series[0].bool().unwrap().into_iter().collect();
+        assert_eq!(failed, vec![Some(false), Some(true)]);
+        assert_eq!(series[2].null_count(), 1);
+    }
+}
@@ -1,7 +1,8 @@
-use crate::{err, CollectError, ColumnEncoding, ToU256Series, U256Type};
+use crate::{
+    encode_checksum_hex, encode_hex, err, CollectError, ColumnEncoding, ToU256Series, U256Type,
+};
 use alloy::{
     dyn_abi::{DynSolValue, EventExt},
-    hex::ToHexExt,
     json_abi::Event,
     primitives::{I256, U256},
     rpc::types::Log,
@@ -86,6 +87,7 @@ impl LogDecoder {
         chunk_len: usize,
         u256_types: &[U256Type],
         column_encoding: &ColumnEncoding,
+        checksum_addresses: bool,
     ) -> Result<Vec<Series>, CollectError> {
         // This is a smooth brain way of doing this, but I can't think of a better way right now
         let mut ints: Vec<i64> = vec![];
@@ -102,15 +104,17 @@ impl LogDecoder {
             match token {
                 DynSolValue::Address(a) => match column_encoding {
                     ColumnEncoding::Binary => bytes.push(a.to_vec()),
-                    ColumnEncoding::Hex => hexes.push(format!("{:?}", a)),
+                    _ if checksum_addresses => hexes
+                        .push(encode_checksum_hex(a.as_slice(), column_encoding.hex_prefix())),
+                    _ => hexes.push(encode_hex(a.as_slice(), column_encoding.hex_prefix())),
                 },
                 DynSolValue::FixedBytes(b, _) => match column_encoding {
                     ColumnEncoding::Binary => bytes.push(b.to_vec()),
-                    ColumnEncoding::Hex => hexes.push(b.encode_hex()),
+                    _ => hexes.push(encode_hex(b.as_slice(), column_encoding.hex_prefix())),
                 },
                 DynSolValue::Bytes(b) => match column_encoding {
                     ColumnEncoding::Binary => bytes.push(b),
-                    ColumnEncoding::Hex => hexes.push(b.encode_hex()),
+                    _ => hexes.push(encode_hex(&b, column_encoding.hex_prefix())),
                 },
                 DynSolValue::Uint(i, size) => {
                     if size <= 64 {
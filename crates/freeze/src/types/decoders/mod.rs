@@ -1,3 +1,9 @@
+/// call decoder
+pub mod call_decoder;
+/// log data decoder
+pub mod data_decoder;
 /// log decoder
 pub mod log_decoder;
+pub use call_decoder::*;
+pub use data_decoder::*;
 pub use log_decoder::*;
@@ -0,0 +1,249 @@
+use crate::{
+    CollectError, ENSAddrResolver, ENSNameResolver, ENSRegistry, RateLimiter, Source, Table,
+};
+use alloy::{
+    hex,
+    primitives::{address, keccak256, Address, B256},
+    sol_types::SolCall,
+};
+use futures::future::join_all;
+use governor::Quota;
+use polars::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+};
+
+/// ENS registry, which has the same address on every chain that deploys ENS
+const ENS_REGISTRY: Address = address!("00000000000C2E074eC69A0bFb2997BA6C7d2e1e");
+
+/// reverse lookups per second across a run, each lookup is up to four `eth_call`s
+const ENS_LOOKUPS_PER_SECOND: u32 = 10;
+
+/// reverse ENS names of addresses, shared across the chunks of a run
+///
+/// addresses without a reverse record, or whose reverse name does not resolve back to them, are
+/// cached as None, failed lookups are not cached so that later chunks retry them
+#[derive(Clone)]
+pub struct EnsResolver {
+    block_number: u64,
+    names: Arc<Mutex<HashMap<Address, Option<String>>>>,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl EnsResolver {
+    /// create resolver that looks up reverse records at block_number
+    pub fn new(block_number: u64) -> Self {
+        let lookups_per_second = NonZeroU32::new(ENS_LOOKUPS_PER_SECOND).unwrap();
+        let quota = Quota::per_second(lookups_per_second).allow_burst(NonZeroU32::MIN);
+        EnsResolver {
+            block_number,
+            names: Arc::new(Mutex::new(HashMap::new())),
+            rate_limiter: Arc::new(RateLimiter::direct(quota)),
+        }
+    }
+
+    fn get(&self, address: &Address) -> Option<Option<String>> {
+        self.names.lock().ok().and_then(|names| names.get(address).cloned())
+    }
+
+    fn insert(&self, address: Address, name: Option<String>) {
+        if let Ok(mut names) = self.names.lock() {
+            names.insert(address, name);
+        }
+    }
+
+    /// reverse name of address, None if it has no reverse record or its lookup fails
+    async fn resolve(&self, address: Address, source: &Source) -> Option<String> {
+        if let Some(name) = self.get(&address) {
+            return name
+        }
+        match self.lookup(address, source).await {
+            Ok(name) => {
+                self.insert(address, name.clone());
+                name
+            }
+            Err(_) => None,
+        }
+    }
+
+    async fn lookup(
+        &self,
+        address: Address,
+        source: &Source,
+    ) -> Result<Option<String>, CollectError> {
+        self.rate_limiter.until_ready().await;
+        let node = namehash(&format!("{}.addr.reverse", hex::encode(address)));
+
+        // chains without an ENS registry return no data, which is the same as no record
+        let call_data = ENSRegistry::resolverCall { node }.abi_encode();
+        let output = source.call2(ENS_REGISTRY, call_data, self.block_number).await?;
+        if output.is_empty() {
+            return Ok(None)
+        }
+        let resolver = ENSRegistry::resolverCall::abi_decode_returns(&output, false)
+            .map_err(|e| CollectError::CollectError(format!("invalid ens resolver: {}", e)))?
+            ._0;
+        if resolver == Address::ZERO {
+            return Ok(None)
+        }
+
+        let call_data = ENSNameResolver::nameCall { node }.abi_encode();
+        let output = source.call2(resolver, call_data, self.block_number).await?;
+        if output.is_empty() {
+            return Ok(None)
+        }
+        let name = ENSNameResolver::nameCall::abi_decode_returns(&output, false)
+            .map_err(|e| CollectError::CollectError(format!("invalid ens name: {}", e)))?
+            ._0;
+        if name.is_empty() {
+            return Ok(None)
+        }
+
+        // anyone can set the reverse record of their address to any name, so the name is only
+        // kept if it resolves back to the address
+        match self.forward_lookup(&name, source).await? {
+            Some(resolved) if resolved == address => Ok(Some(name)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn forward_lookup(
+        &self,
+        name: &str,
+        source: &Source,
+    ) -> Result<Option<Address>, CollectError> {
+        let node = namehash(name);
+        let call_data = ENSRegistry::resolverCall { node }.abi_encode();
+        let output = source.call2(ENS_REGISTRY, call_data, self.block_number).await?;
+        if output.is_empty() {
+            return Ok(None)
+        }
+        let resolver = ENSRegistry::resolverCall::abi_decode_returns(&output, false)
+            .map_err(|e| CollectError::CollectError(format!("invalid ens resolver: {}", e)))?
+            ._0;
+        if resolver == Address::ZERO {
+            return Ok(None)
+        }
+
+        let call_data = ENSAddrResolver::addrCall { node }.abi_encode();
+        let output = source.call2(resolver, call_data, self.block_number).await?;
+        if output.is_empty() {
+            return Ok(None)
+        }
+        let address = ENSAddrResolver::addrCall::abi_decode_returns(&output, false)
+            .map_err(|e| CollectError::CollectError(format!("invalid ens address: {}", e)))?
+            ._0;
+        Ok(Some(address).filter(|address| *address != Address::ZERO))
+    }
+
+    /// add the ens name column of each address column of schema
+    pub(crate) async fn add_ens_names(
+        &self,
+        df: DataFrame,
+        schema: &Table,
+        source: &Source,
+    ) -> Result<DataFrame, CollectError> {
+        let columns = schema.ens_name_columns();
+        let mut unique: HashSet<Address> = HashSet::new();
+        for (address_column, _) in columns.iter() {
+            unique.extend(column_addresses(&df, address_column)?.into_iter().flatten());
+        }
+        let lookups = unique.into_iter().map(|address| async move {
+            (address, self.resolve(address, source).await)
+        });
+        let names: HashMap<Address, Option<String>> = join_all(lookups).await.into_iter().collect();
+        with_ens_names(df, &columns, &names)
+    }
+}
+
+/// addresses of an address column, None for nulls and for values that are not addresses
+fn column_addresses(df: &DataFrame, column: &str) -> Result<Vec<Option<Address>>, CollectError> {
+    let Ok(column) = df.column(column) else { return Ok(vec![None; df.height()]) };
+    let addresses = match column.dtype() {
+        DataType::Binary => column
+            .binary()?
+            .into_iter()
+            .map(|address| address.filter(|a| a.len() == 20).map(Address::from_slice))
+            .collect(),
+        DataType::String => column
+            .str()?
+            .into_iter()
+            .map(|address| address.and_then(|a| a.parse().ok()))
+            .collect(),
+        _ => vec![None; df.height()],
+    };
+    Ok(addresses)
+}
+
+/// add ens name columns of address columns, using the resolved names of their addresses
+fn with_ens_names(
+    mut df: DataFrame,
+    columns: &[(String, String)],
+    names: &HashMap<Address, Option<String>>,
+) -> Result<DataFrame, CollectError> {
+    for (address_column, ens_column) in columns.iter() {
+        let ens_names: Vec<Option<String>> = column_addresses(&df, address_column)?
+            .into_iter()
+            .map(|address| address.and_then(|address| names.get(&address).cloned().flatten()))
+            .collect();
+        df.with_column(Series::new(ens_column, ens_names))?;
+    }
+    Ok(df)
+}
+
+/// ENS namehash of a normalized name
+fn namehash(name: &str) -> B256 {
+    name.rsplit('.').fold(B256::ZERO, |node, label| {
+        keccak256([node.as_slice(), keccak256(label.as_bytes()).as_slice()].concat())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColumnEncoding, Datatype};
+
+    #[test]
+    fn test_namehash() {
+        let expected = "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae";
+        assert_eq!(namehash("eth").to_string(), expected);
+        let expected = "0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f";
+        assert_eq!(namehash("foo.eth").to_string(), expected);
+    }
+
+    #[test]
+    fn test_with_ens_names() {
+        let columns = Some(vec!["from_address".to_string(), "to_address".to_string()]);
+        let mut schema = Datatype::Transactions
+            .table_schema(&[], &ColumnEncoding::Hex, &None, &None, &columns, None, None)
+            .unwrap();
+        schema.add_ens_name_columns();
+        let ens_columns = schema.ens_name_columns();
+        assert_eq!(
+            ens_columns,
+            vec![
+                ("from_address".to_string(), "from_address_ens_name".to_string()),
+                ("to_address".to_string(), "to_address_ens_name".to_string()),
+            ]
+        );
+
+        let named = Address::repeat_byte(1);
+        let unnamed = Address::repeat_byte(2);
+        let df = df!(
+            "from_address" => [named.to_string(), unnamed.to_string()],
+            "to_address" => [Some(named.to_vec()), None],
+        )
+        .unwrap();
+        let names = HashMap::from([(named, Some("foo.eth".to_string())), (unnamed, None)]);
+        let df = with_ens_names(df, &ens_columns, &names).unwrap();
+
+        // every ens name column of the schema is added
+        assert_eq!(df.get_column_names(), schema.output_columns());
+        for (_, ens_column) in ens_columns.iter() {
+            let names: Vec<_> = df.column(ens_column).unwrap().str().unwrap().into_iter().collect();
+            assert_eq!(names, vec![Some("foo.eth"), None]);
+        }
+    }
+}
@@ -85,6 +85,23 @@ pub enum CollectError {
     /// Generic RPC Error
     #[error("RPC call error")]
     RPCError(String),
+
+    /// Error that persisted after retrying chunk
+    #[error("Failed after {0} retries: {1}")]
+    RetriesExhausted(u32, #[source] Box<CollectError>),
+}
+
+impl CollectError {
+    /// whether error may be transient and worth retrying
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            CollectError::ProviderError(_) |
+                CollectError::TaskFailed(_) |
+                CollectError::TooManyRequestsError |
+                CollectError::RPCError(_)
+        )
+    }
 }
 
 /// Error related to parsing
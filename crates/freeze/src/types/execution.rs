@@ -11,6 +11,10 @@ pub struct ExecutionEnv {
     pub verbose: u32,
     /// whether to generate report
     pub report: bool,
+    /// whether to keep the report updated during the run, so that aborted runs leave a report
+    pub report_on_error: bool,
+    /// whether to write a manifest of output files
+    pub manifest: bool,
     /// progress bar
     pub bar: Option<Arc<ProgressBar>>,
     /// cli command
@@ -25,6 +29,16 @@ pub struct ExecutionEnv {
     pub t_end: Option<SystemTime>,
     /// report directory
     pub report_dir: Option<PathBuf>,
+    /// number of times to retry a failed chunk
+    pub chunk_retries: u32,
+    /// number of rows after which no new chunks are started
+    pub max_rows_total: Option<u64>,
+    /// number of output bytes after which no chunks are started that could exceed it
+    pub max_output_bytes: Option<u64>,
+    /// target bytes per output file, sizing block chunks from the bytes per block collected
+    pub chunk_size_bytes: Option<u64>,
+    /// abort the run on the first chunk error
+    pub fail_fast: bool,
 }
 
 impl ExecutionEnv {
@@ -60,6 +74,8 @@ pub struct ExecutionEnvBuilder {
     dry: bool,
     verbose: u32,
     report: bool,
+    report_on_error: bool,
+    manifest: bool,
     bar: Option<Arc<ProgressBar>>,
     cli_command: Option<Vec<String>>,
     args: Option<String>,
@@ -67,6 +83,11 @@ pub struct ExecutionEnvBuilder {
     t_start: SystemTime,
     t_end: Option<SystemTime>,
     report_dir: Option<PathBuf>,
+    chunk_retries: u32,
+    max_rows_total: Option<u64>,
+    max_output_bytes: Option<u64>,
+    chunk_size_bytes: Option<u64>,
+    fail_fast: bool,
 }
 
 impl Default for ExecutionEnvBuilder {
@@ -75,6 +96,8 @@ impl Default for ExecutionEnvBuilder {
             dry: false,
             verbose: 1,
             report: true,
+            report_on_error: false,
+            manifest: false,
             bar: None,
             cli_command: Some(std::env::args().collect()),
             args: None,
@@ -82,6 +105,11 @@ impl Default for ExecutionEnvBuilder {
             t_start: SystemTime::now(),
             t_end: None,
             report_dir: None,
+            chunk_retries: 0,
+            max_rows_total: None,
+            max_output_bytes: None,
+            chunk_size_bytes: None,
+            fail_fast: false,
         }
     }
 }
@@ -110,12 +138,54 @@ impl ExecutionEnvBuilder {
         self
     }
 
+    /// keep report updated during the run
+    pub fn report_on_error(mut self, report_on_error: bool) -> Self {
+        self.report_on_error = report_on_error;
+        self
+    }
+
+    /// write manifest of output files
+    pub fn manifest(mut self, manifest: bool) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
     /// set report directory
     pub fn report_dir(mut self, report_dir: Option<PathBuf>) -> Self {
         self.report_dir = report_dir;
         self
     }
 
+    /// number of times to retry a failed chunk
+    pub fn chunk_retries(mut self, chunk_retries: u32) -> Self {
+        self.chunk_retries = chunk_retries;
+        self
+    }
+
+    /// number of rows after which no new chunks are started
+    pub fn max_rows_total(mut self, max_rows_total: Option<u64>) -> Self {
+        self.max_rows_total = max_rows_total;
+        self
+    }
+
+    /// number of output bytes after which no chunks are started that could exceed it
+    pub fn max_output_bytes(mut self, max_output_bytes: Option<u64>) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// target bytes per output file, sizing block chunks from the bytes per block collected
+    pub fn chunk_size_bytes(mut self, chunk_size_bytes: Option<u64>) -> Self {
+        self.chunk_size_bytes = chunk_size_bytes;
+        self
+    }
+
+    /// abort the run on the first chunk error
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
     /// progress bar size
     pub fn bar(mut self, n: u64) -> Result<Self, CollectError> {
         self.bar = Some(new_bar(n)?);
@@ -140,6 +210,8 @@ impl ExecutionEnvBuilder {
             dry: self.dry,
             verbose: self.verbose,
             report: self.report,
+            report_on_error: self.report_on_error,
+            manifest: self.manifest,
             bar: self.bar,
             cli_command: self.cli_command,
             args: self.args,
@@ -147,6 +219,11 @@ impl ExecutionEnvBuilder {
             t_start: self.t_start,
             t_end: self.t_end,
             report_dir: self.report_dir,
+            chunk_retries: self.chunk_retries,
+            max_rows_total: self.max_rows_total,
+            max_output_bytes: self.max_output_bytes,
+            chunk_size_bytes: self.chunk_size_bytes,
+            fail_fast: self.fail_fast,
         }
     }
 }
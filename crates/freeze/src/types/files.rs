@@ -1,37 +1,93 @@
-use crate::{CollectError, Datatype, MetaDatatype, ParseError, Partition, Query};
-use std::{collections::HashMap, path::PathBuf};
+use crate::{
+    dataframes::read_file_columns, CollectError, Datatype, MetaDatatype, ParseError, Partition,
+    Query,
+};
+use polars::prelude::{ParquetReader, SerReader};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 /// Options for file output
 #[derive(Clone, Debug)]
 pub struct FileOutput {
     /// Path of directory where to save files
     pub output_dir: std::path::PathBuf,
-    /// Prefix of file name
+    /// Prefix of file name, omitted from file names if empty
     pub prefix: String,
+    /// Name of network, used for network subdirectories
+    pub network: String,
     /// Suffix to use after datatype names
     pub suffix: Option<String>,
     /// subdirectories to use
     pub subdirs: Vec<SubDir>,
     /// Whether to overwrite existing files or skip them
     pub overwrite: bool,
+    /// Whether to overwrite existing files that fail validation
+    pub overwrite_partial: bool,
+    /// Whether to write files whose columns differ from existing files of their datatype
+    pub force_schema_change: bool,
     /// File format to use for output files
     pub format: FileFormat,
     /// Number of rows per parquet row group
     pub row_group_size: Option<usize>,
+    /// Target in-memory bytes per parquet row group, used when `row_group_size` is not set
+    pub row_group_target_bytes: Option<usize>,
     /// Parquet statistics recording flag
     pub parquet_statistics: bool,
+    /// Maximum size of parquet data pages in bytes
+    pub parquet_page_size: Option<usize>,
+    /// Whether to dictionary encode parquet columns
+    pub parquet_dictionary: bool,
     /// Parquet compression options
     pub parquet_compression: polars::prelude::ParquetCompression,
+    /// On-disk compression of csv and json files
+    pub file_compression: Option<FileCompression>,
+    /// Whether to write a stats parquet file for each output file
+    pub chunk_stats_file: bool,
+    /// What to write for chunks that produce zero rows
+    pub empty_file_policy: EmptyFilePolicy,
+    /// Schema version embedded with the cryo version, args hash, and datatype in the metadata of
+    /// output files, None to embed no metadata
+    pub schema_version: Option<String>,
+    /// Seconds after which rows collected so far are appended to temp files of csv and json
+    /// outputs, None to write each file at once
+    pub flush_interval: Option<u64>,
+    /// Layout of json files
+    pub json: JsonOptions,
 }
 
-/// Possible item to use as subdirectory
+/// Columns of an existing output file
 #[derive(Clone, Debug)]
+pub struct ExistingColumns {
+    /// path of existing file
+    pub path: PathBuf,
+    /// column names of existing file
+    pub columns: Vec<String>,
+    /// whether the file records column order, json files do not
+    pub ordered: bool,
+}
+
+/// What to write for output files of chunks that produce zero rows
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EmptyFilePolicy {
+    /// write a file with the schema and no rows
+    #[default]
+    Write,
+    /// write no file
+    Skip,
+    /// write a zero-byte marker file
+    Touch,
+}
+
+/// Possible item to use as subdirectory
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SubDir {
     /// datatype
     Datatype,
     /// network
     Network,
-    /// custom string
+    /// custom string, in which `{datatype}` is replaced by the datatype
     Custom(String),
 }
 
@@ -66,43 +122,202 @@ impl FileOutput {
         partition: &Partition,
         datatype: Datatype,
     ) -> Result<PathBuf, CollectError> {
-        let filename = if let Some(suffix) = self.suffix.clone() {
-            format!(
-                "{}__{}__{}__{}.{}",
-                self.prefix.clone(),
-                datatype.name(),
-                suffix,
-                partition.label(&query.partitioned_by)?,
-                self.format.as_str(),
-            )
-        } else {
-            format!(
-                "{}__{}__{}.{}",
-                self.prefix.clone(),
-                datatype.name(),
-                partition.label(&query.partitioned_by)?,
-                self.format.as_str(),
-            )
-        };
+        let filename = self.get_filename(datatype, partition.label(&query.partitioned_by)?);
         let filename = std::path::Path::new(&filename).to_path_buf();
+        let datatype_dir = match &self.suffix {
+            Some(suffix) => datatype.name() + "__" + suffix.as_str(),
+            None => datatype.name(),
+        };
         let mut output_dir = std::path::Path::new(&self.output_dir).to_path_buf();
         for subdir in self.subdirs.iter() {
             let subdir_str: String = match subdir {
-                SubDir::Network => self.prefix.clone(),
-                SubDir::Datatype => match &self.suffix {
-                    Some(suffix) => datatype.name() + "__" + suffix.as_str(),
-                    None => datatype.name(),
-                },
-                SubDir::Custom(subdir_str) => subdir_str.to_string(),
+                SubDir::Network => self.network.clone(),
+                SubDir::Datatype => datatype_dir.clone(),
+                SubDir::Custom(subdir_str) => subdir_str.replace("{datatype}", &datatype_dir),
             };
             output_dir = output_dir.join(std::path::Path::new(&subdir_str));
         }
 
-        std::fs::create_dir_all(output_dir.clone())
-            .map_err(|_| ParseError::ParseError("could not create dir".to_string()))?;
+        std::fs::create_dir_all(output_dir.clone()).map_err(|e| {
+            ParseError::ParseError(format!("could not create dir {}: {}", output_dir.display(), e))
+        })?;
 
         Ok(output_dir.join(filename))
     }
+
+    fn get_filename(&self, datatype: Datatype, partition_label: String) -> String {
+        format!("{}__{}.{}", self.get_filename_stem(datatype), partition_label, self.extension())
+    }
+
+    /// part of filenames of datatype that precedes the partition label
+    fn get_filename_stem(&self, datatype: Datatype) -> String {
+        let mut segments = Vec::new();
+        if !self.prefix.is_empty() {
+            segments.push(self.prefix.clone());
+        }
+        segments.push(datatype.name());
+        if let Some(suffix) = self.suffix.clone() {
+            segments.push(suffix);
+        }
+        segments.join("__")
+    }
+
+    /// columns of an existing output file of each datatype of query, for datatypes that have one
+    ///
+    /// files are looked up in the output directory of each datatype, including files of chunks
+    /// outside of the query
+    pub fn get_existing_columns(
+        &self,
+        query: &Query,
+    ) -> Result<HashMap<Datatype, ExistingColumns>, CollectError> {
+        let mut existing = HashMap::new();
+        for datatype in query.schemas.keys() {
+            let Some(dir) = self.get_datatype_dir(query, *datatype)? else { continue };
+            let Ok(entries) = std::fs::read_dir(dir) else { continue };
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| self.get_file_label(path, *datatype).is_some())
+                .collect();
+            paths.sort();
+            for path in paths.into_iter() {
+                let columns = read_file_columns(&path, &self.format, self.file_compression);
+                if let Some(columns) = columns {
+                    let ordered = self.format != FileFormat::Json;
+                    existing.insert(*datatype, ExistingColumns { path, columns, ordered });
+                    break
+                }
+            }
+        }
+        Ok(existing)
+    }
+
+    /// block ranges of complete output files of datatype already on disk, read from file names
+    pub fn get_existing_block_ranges(
+        &self,
+        query: &Query,
+        datatype: Datatype,
+    ) -> Result<Vec<(u64, u64)>, CollectError> {
+        let Some(dir) = self.get_datatype_dir(query, datatype)? else { return Ok(Vec::new()) };
+
+        // files of empty chunks may only be recorded under the empty directory
+        let mut filenames = HashSet::new();
+        for dir in [dir.clone(), self.get_empty_path(&dir)] {
+            let Ok(entries) = std::fs::read_dir(dir) else { continue };
+            filenames.extend(entries.filter_map(|entry| entry.ok().map(|entry| entry.file_name())));
+        }
+
+        let mut ranges = Vec::new();
+        for filename in filenames.into_iter() {
+            let path = dir.join(filename);
+            let Some(label) = self.get_file_label(&path, datatype) else { continue };
+            let Some((start, end)) = label.split_once("_to_") else { continue };
+            if let (Ok(start), Ok(end)) = (start.parse::<u64>(), end.parse::<u64>()) {
+                if start <= end && self.is_complete(&path) {
+                    ranges.push((start, end))
+                }
+            }
+        }
+        ranges.sort();
+        Ok(ranges)
+    }
+
+    /// directory of output files of datatype, None if no partitions
+    fn get_datatype_dir(
+        &self,
+        query: &Query,
+        datatype: Datatype,
+    ) -> Result<Option<PathBuf>, CollectError> {
+        let Some(partition) = query.partitions.first() else { return Ok(None) };
+        let path = self.get_path(query, partition, datatype)?;
+        Ok(path.parent().map(|dir| dir.to_path_buf()))
+    }
+
+    /// partition label in the name of an output file of datatype, None if not such a file
+    fn get_file_label(&self, path: &std::path::Path, datatype: Datatype) -> Option<String> {
+        let filename = path.file_name()?.to_string_lossy();
+        let stem = format!("{}__", self.get_filename_stem(datatype));
+        let extension = format!(".{}", self.extension());
+        let label = filename.strip_prefix(&stem)?.strip_suffix(&extension)?;
+        Some(label.to_string())
+    }
+
+    /// file extension, including the on-disk compression if any
+    pub fn extension(&self) -> String {
+        match &self.file_compression {
+            Some(compression) => format!("{}.{}", self.format.as_str(), compression.extension()),
+            None => self.format.as_str().to_string(),
+        }
+    }
+
+    /// path of stats file for an output file, stored under `{output_dir}/.cryo/stats`
+    pub fn get_stats_path(&self, path: &std::path::Path) -> PathBuf {
+        let relative = path.strip_prefix(&self.output_dir).unwrap_or(path);
+        let filename = relative.to_string_lossy();
+        let suffix = format!(".{}", self.extension());
+        let stem = filename.strip_suffix(suffix.as_str()).unwrap_or(&filename);
+        self.output_dir.join(".cryo/stats").join(format!("{}.parquet", stem))
+    }
+
+    /// path of metadata sidecar of a csv or json output file, stored under
+    /// `{output_dir}/.cryo/metadata`
+    pub fn get_metadata_path(&self, path: &std::path::Path) -> PathBuf {
+        let relative = path.strip_prefix(&self.output_dir).unwrap_or(path);
+        let mut filename = self.output_dir.join(".cryo/metadata").join(relative).into_os_string();
+        filename.push(".metadata.json");
+        PathBuf::from(filename)
+    }
+
+    /// path of record that an output file had no rows, stored under `{output_dir}/.cryo/empty`
+    ///
+    /// empty chunks are recorded so that later runs treat the missing, zero-byte, or zero-row
+    /// file as complete
+    pub fn get_empty_path(&self, path: &std::path::Path) -> PathBuf {
+        let relative = path.strip_prefix(&self.output_dir).unwrap_or(path);
+        self.output_dir.join(".cryo/empty").join(relative)
+    }
+
+    /// output of the partial files of partitions that are consolidated into one file once every
+    /// partition is collected, stored as parquet under `{output_dir}/.cryo/partials`
+    pub fn partials_output(&self) -> FileOutput {
+        FileOutput {
+            output_dir: self.output_dir.join(".cryo/partials"),
+            format: FileFormat::Parquet,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            ..self.clone()
+        }
+    }
+
+    /// whether existing output file is complete and can be skipped
+    pub fn is_complete(&self, path: &std::path::Path) -> bool {
+        if !path.exists() {
+            return self.get_empty_path(path).exists()
+        }
+        if !self.overwrite_partial {
+            return true
+        }
+        let is_empty = std::fs::metadata(path).map(|metadata| metadata.len() == 0).unwrap_or(false);
+        if is_empty && self.get_empty_path(path).exists() {
+            return true
+        }
+        match self.format {
+            // a readable footer means the write finished, files without rows must be recorded
+            FileFormat::Parquet => match std::fs::File::open(path) {
+                Ok(file) => match ParquetReader::new(file).num_rows() {
+                    Ok(n_rows) => n_rows > 0 || self.get_empty_path(path).exists(),
+                    Err(_) => false,
+                },
+                Err(_) => false,
+            },
+            // csv and json have no footer, fall back to a nonzero size check
+            FileFormat::Csv | FileFormat::Json => {
+                std::fs::metadata(path).map(|metadata| metadata.len() > 0).unwrap_or(false)
+            }
+        }
+    }
 }
 
 /// File format
@@ -127,13 +342,52 @@ impl FileFormat {
     }
 }
 
+/// On-disk compression of whole output files
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileCompression {
+    /// Gzip compression
+    Gzip,
+    /// Zstd compression
+    Zstd,
+}
+
+impl FileCompression {
+    /// convert FileCompression to str
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            FileCompression::Gzip => "gzip",
+            FileCompression::Zstd => "zstd",
+        }
+    }
+
+    /// file extension appended after the file format extension
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            FileCompression::Gzip => "gz",
+            FileCompression::Zstd => "zst",
+        }
+    }
+}
+
+/// Layout of json output files
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct JsonOptions {
+    /// Whether to pretty print json files rather than write them on a single line
+    pub pretty: bool,
+    /// Whether to write 64-bit integer columns as numbers rather than strings, which json
+    /// readers parsing numbers as doubles round beyond 2^53
+    pub integers_as_numbers: bool,
+}
+
 /// Encoding for binary data in a column
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub enum ColumnEncoding {
     /// Raw binary encoding
     Binary,
-    /// Hex binary encoding
+    /// Hex binary encoding, with `0x` prefix
     Hex,
+    /// Hex binary encoding, without `0x` prefix
+    HexUnprefixed,
 }
 
 impl ColumnEncoding {
@@ -142,6 +396,145 @@ impl ColumnEncoding {
         match *self {
             ColumnEncoding::Binary => "binary",
             ColumnEncoding::Hex => "hex",
+            ColumnEncoding::HexUnprefixed => "hex without 0x prefix",
         }
     }
+
+    /// whether binary data is encoded as hex strings
+    pub fn is_hex(&self) -> bool {
+        matches!(self, ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed)
+    }
+
+    /// whether hex strings are prefixed with `0x`
+    pub fn hex_prefix(&self) -> bool {
+        !matches!(self, ColumnEncoding::HexUnprefixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::*;
+
+    fn get_file_output(format: FileFormat) -> FileOutput {
+        FileOutput {
+            output_dir: std::env::temp_dir(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: true,
+            force_schema_change: false,
+            format,
+            row_group_size: None,
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            json: JsonOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_get_filename() {
+        let mut sink = get_file_output(FileFormat::Parquet);
+        let label = "00000000_to_00000999".to_string();
+        assert_eq!(
+            sink.get_filename(Datatype::Blocks, label.clone()),
+            "ethereum__blocks__00000000_to_00000999.parquet"
+        );
+
+        sink.prefix = "mainnet".to_string();
+        sink.suffix = Some("v2".to_string());
+        assert_eq!(
+            sink.get_filename(Datatype::Blocks, label.clone()),
+            "mainnet__blocks__v2__00000000_to_00000999.parquet"
+        );
+
+        // empty prefix starts file names with the datatype
+        sink.prefix = "".to_string();
+        assert_eq!(
+            sink.get_filename(Datatype::Blocks, label.clone()),
+            "blocks__v2__00000000_to_00000999.parquet"
+        );
+
+        // on-disk compression is appended after the format
+        sink.format = FileFormat::Csv;
+        sink.file_compression = Some(FileCompression::Gzip);
+        assert_eq!(
+            sink.get_filename(Datatype::Blocks, label),
+            "blocks__v2__00000000_to_00000999.csv.gz"
+        );
+    }
+
+    #[test]
+    fn test_get_stats_path() {
+        let mut sink = get_file_output(FileFormat::Csv);
+        sink.file_compression = Some(FileCompression::Zstd);
+        let path = sink.output_dir.join("blocks").join("ethereum__blocks__0_to_999.csv.zst");
+        assert_eq!(
+            sink.get_stats_path(&path),
+            sink.output_dir.join(".cryo/stats/blocks/ethereum__blocks__0_to_999.parquet")
+        );
+    }
+
+    #[test]
+    fn test_is_complete() {
+        let dir = std::env::temp_dir().join(format!("cryo_is_complete_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // complete parquet file
+        let sink = get_file_output(FileFormat::Parquet);
+        let path = dir.join("complete.parquet");
+        let mut df = df!("block_number" => [1u32, 2, 3]).unwrap();
+        ParquetWriter::new(std::fs::File::create(&path).unwrap()).finish(&mut df).unwrap();
+        assert!(sink.is_complete(&path));
+
+        // truncated parquet file
+        let bytes = std::fs::read(&path).unwrap();
+        let truncated = dir.join("truncated.parquet");
+        std::fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(!sink.is_complete(&truncated));
+        assert!(!sink.is_complete(&dir.join("missing.parquet")));
+
+        // parquet files without rows are complete only if recorded as empty
+        let sink = FileOutput { output_dir: dir.clone(), ..get_file_output(FileFormat::Parquet) };
+        let no_rows = dir.join("no_rows.parquet");
+        let mut df = df!("block_number" => Vec::<u32>::new()).unwrap();
+        ParquetWriter::new(std::fs::File::create(&no_rows).unwrap()).finish(&mut df).unwrap();
+        assert!(!sink.is_complete(&no_rows));
+        let empty_path = sink.get_empty_path(&no_rows);
+        std::fs::create_dir_all(empty_path.parent().unwrap()).unwrap();
+        std::fs::write(empty_path, b"").unwrap();
+        assert!(sink.is_complete(&no_rows));
+
+        // csv files can only be checked for size
+        let sink = get_file_output(FileFormat::Csv);
+        let empty = dir.join("empty.csv");
+        std::fs::write(&empty, b"").unwrap();
+        assert!(!sink.is_complete(&empty));
+
+        // empty chunks that were skipped or touched are recorded as complete
+        let sink = FileOutput { output_dir: dir.clone(), ..get_file_output(FileFormat::Parquet) };
+        let skipped = dir.join("skipped.parquet");
+        let touched = dir.join("touched.parquet");
+        std::fs::write(&touched, b"").unwrap();
+        assert!(!sink.is_complete(&skipped));
+        assert!(!sink.is_complete(&touched));
+        for path in [&skipped, &touched] {
+            let empty_path = sink.get_empty_path(path);
+            std::fs::create_dir_all(empty_path.parent().unwrap()).unwrap();
+            std::fs::write(empty_path, b"").unwrap();
+            assert!(sink.is_complete(path));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
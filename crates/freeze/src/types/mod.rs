@@ -1,4 +1,8 @@
 /// type specifications for cryo_freeze crate
+/// rpc methods needed by datasets
+pub mod capabilities;
+/// chain-specific collector behavior
+pub mod chains;
 /// type specifications for chunk types
 pub mod chunks;
 /// conversion operations
@@ -17,6 +21,8 @@ pub mod partitions;
 /// rpc_params
 pub mod rpc_params;
 
+pub use capabilities::{probe_rpc_methods, RpcMethod, UnsupportedRpcMethod};
+pub use chains::{ChainFamily, ChainProfile};
 pub use partitions::{Dim, Partition, PartitionLabels};
 pub use rpc_params::Params;
 
@@ -53,16 +59,31 @@ pub use chunks::{
     AddressChunk, BlockChunk, CallDataChunk, Chunk, ChunkData, ChunkStats, SlotChunk, Subchunk,
     TopicChunk, TransactionChunk,
 };
-pub use conversions::{bytes_to_u32, ToVecHex, ToVecU8};
+pub use conversions::{
+    bytes_to_u32, decode_hex, encode_checksum_hex, encode_hex, ToVecHex, ToVecU8,
+};
 pub use dataframes::*;
 pub use datatypes::*;
-pub use files::{ColumnEncoding, FileFormat, FileOutput, SubDir};
-pub use queries::{Query, QueryLabels, TimeDimension};
+pub use files::{
+    ColumnEncoding, EmptyFilePolicy, ExistingColumns, FileCompression, FileFormat, FileOutput,
+    JsonOptions, SubDir,
+};
+pub use queries::{
+    Query, QueryLabels, ReorgBuffer, RequestEstimate, StateDiffTracer, StorageSlots, TimeDimension,
+    TraceCall, TraceCallInputs,
+};
 pub use schemas::{ColumnType, SchemaFunctions, Schemas, Table, U256Type};
-pub use sources::{Fetcher, RateLimiter, Source, SourceLabels};
+pub use sources::{
+    check_chain_id, redact_rpc_url, rpc_url_has_credentials, AccountRange, AutoConcurrency,
+    AutoConcurrencyLayer, BackoffHintLayer, CallFrameResult, DumpAccount, Fetcher, RateLimiter,
+    RawResponseLayer, ReceiptsSource, RpcBatcher, RpcStats, RpcStatsLayer, Source, SourceLabels,
+};
 // pub(crate) use summaries::FreezeSummaryAgg;
 // pub use summaries::{FreezeChunkSummary, FreezeSummary};
-pub use summaries::{print_all_datasets, print_dataset_info, FreezeSummary};
+pub use summaries::{
+    dataset_dims, print_all_datasets, print_dataset_dims, print_dataset_info, print_preview,
+    FreezeSummary, OutputFile,
+};
 
 pub use errors::{err, ChunkError, CollectError, FileError, FreezeError, ParseError, R};
 
@@ -74,3 +95,7 @@ pub use signatures::*;
 /// decoders
 pub mod decoders;
 pub use decoders::*;
+
+/// reverse ENS resolution
+pub mod ens;
+pub use ens::EnsResolver;
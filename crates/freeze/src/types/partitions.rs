@@ -1,8 +1,50 @@
 use crate::{
-    err, types::chunks::Subchunk, AddressChunk, BlockChunk, CallDataChunk, ChunkData, ChunkStats,
-    CollectError, Params, SlotChunk, TopicChunk, TransactionChunk,
+    err, types::chunks::number_chunk::range_to_chunks, AddressChunk, BlockChunk, CallDataChunk,
+    ChunkData, ChunkStats, CollectError, Params, SlotChunk, TopicChunk, TransactionChunk,
 };
 
+/// block ranges of at most `inner_request_size` blocks that cover the block chunks
+///
+/// overlapping and adjacent chunks are merged first, so that no block is requested twice
+fn disjoint_block_ranges(block_numbers: &[BlockChunk], inner_request_size: u64) -> Vec<(u64, u64)> {
+    let inner_request_size = inner_request_size.max(1);
+    merge_block_intervals(block_numbers)
+        .iter()
+        .flat_map(|(start, end)| range_to_chunks(start, end, &inner_request_size))
+        .collect()
+}
+
+/// number of ranges of `disjoint_block_ranges`, without creating the ranges
+fn n_disjoint_block_ranges(block_numbers: &[BlockChunk], inner_request_size: u64) -> u64 {
+    let inner_request_size = inner_request_size.max(1);
+    merge_block_intervals(block_numbers)
+        .iter()
+        .map(|(start, end)| (end - start) / inner_request_size + 1)
+        .sum()
+}
+
+/// sorted inclusive intervals covering blocks, with overlapping and adjacent intervals merged
+fn merge_block_intervals(block_numbers: &[BlockChunk]) -> Vec<(u64, u64)> {
+    let mut intervals: Vec<(u64, u64)> = block_numbers
+        .iter()
+        .flat_map(|chunk| match chunk {
+            BlockChunk::Range(start, end) => vec![(*start, *end)],
+            BlockChunk::Numbers(numbers) => numbers.iter().map(|n| (*n, *n)).collect(),
+        })
+        .filter(|(start, end)| start <= end)
+        .collect();
+    intervals.sort_unstable();
+
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in intervals.into_iter() {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
 /// a dimension of chunking
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Serialize)]
 pub enum Dim {
@@ -337,7 +379,13 @@ impl Partition {
     }
 
     /// iterate through param sets of Partition
-    pub fn param_sets(&self, inner_request_size: Option<u64>) -> Result<Vec<Params>, CollectError> {
+    ///
+    /// with `topic0_sets`, each topic0 chunk becomes a single param set matching any of its topic0s
+    pub fn param_sets(
+        &self,
+        inner_request_size: Option<u64>,
+        topic0_sets: bool,
+    ) -> Result<Vec<Params>, CollectError> {
         let dims = self.dims();
         let include_block_ranges = inner_request_size.is_some() && dims.contains(&Dim::BlockNumber);
 
@@ -361,6 +409,15 @@ impl Partition {
                 Dim::ToAddress => parametrize!(outputs, new, self.to_addresses, to_address),
                 Dim::CallData => parametrize!(outputs, new, self.call_datas, call_data),
                 Dim::Slot => parametrize!(outputs, new, self.slots, slot),
+                Dim::Topic0 if topic0_sets => {
+                    let chunks = self.topic0s.as_ref().ok_or(err("missing topic0s"))?;
+                    for output in outputs.into_iter() {
+                        for chunk in chunks.iter() {
+                            let topic0s = Some(chunk.values());
+                            new.push(Params { topic0s, ..output.clone() })
+                        }
+                    }
+                }
                 Dim::Topic0 => parametrize!(outputs, new, self.topic0s, topic0),
                 Dim::Topic1 => parametrize!(outputs, new, self.topic1s, topic1),
                 Dim::Topic2 => parametrize!(outputs, new, self.topic2s, topic2),
@@ -373,22 +430,12 @@ impl Partition {
         let outputs = match (inner_request_size, self.block_numbers.clone(), include_block_ranges) {
             (_, _, false) => outputs,
             (Some(inner_request_size), Some(block_numbers), true) => {
-                let mut block_ranges = Vec::new();
-                for block_chunk in block_numbers.subchunk_by_size(&inner_request_size) {
-                    match block_chunk {
-                        BlockChunk::Range(start, end) => block_ranges.push(Some((start, end))),
-                        BlockChunk::Numbers(values) => {
-                            for value in values.iter() {
-                                block_ranges.push(Some((*value, *value)))
-                            }
-                        }
-                    }
-                }
-
+                let block_ranges = disjoint_block_ranges(&block_numbers, inner_request_size);
                 let mut new_outputs = Vec::new();
                 for output in outputs.iter() {
                     for block_range in block_ranges.iter() {
-                        new_outputs.push(Params { block_range: *block_range, ..output.clone() })
+                        new_outputs
+                            .push(Params { block_range: Some(*block_range), ..output.clone() })
                     }
                 }
                 new_outputs
@@ -403,6 +450,53 @@ impl Partition {
         Ok(outputs)
     }
 
+    /// number of param sets of Partition, each of which is requested separately
+    ///
+    /// computed as the product of the sizes of the dimensions, without creating the param sets
+    pub fn n_param_sets(
+        &self,
+        inner_request_size: Option<u64>,
+        topic0_sets: bool,
+    ) -> Result<u64, CollectError> {
+        fn size<T: ChunkData>(chunks: &Option<Vec<T>>) -> Result<u64, CollectError> {
+            let chunks = chunks.as_ref().ok_or(err("missing chunks"))?;
+            Ok(chunks.iter().map(|chunk| chunk.size()).sum())
+        }
+
+        let mut n_param_sets: u64 = 1;
+        for dimension in self.dims().iter() {
+            let n_values = match dimension {
+                Dim::BlockNumber => match (inner_request_size, &self.block_numbers) {
+                    (None, _) => size(&self.block_numbers)?,
+                    (Some(inner_request_size), Some(block_numbers)) => {
+                        n_disjoint_block_ranges(block_numbers, inner_request_size)
+                    }
+                    (Some(_), None) => {
+                        return Err(CollectError::CollectError(
+                            "insufficient block information present in partition".to_string(),
+                        ))
+                    }
+                },
+                Dim::TransactionHash => size(&self.transactions)?,
+                Dim::Address => size(&self.addresses)?,
+                Dim::Contract => size(&self.contracts)?,
+                Dim::FromAddress => size(&self.from_addresses)?,
+                Dim::ToAddress => size(&self.to_addresses)?,
+                Dim::CallData => size(&self.call_datas)?,
+                Dim::Slot => size(&self.slots)?,
+                Dim::Topic0 if topic0_sets => {
+                    self.topic0s.as_ref().ok_or(err("missing topic0s"))?.len() as u64
+                }
+                Dim::Topic0 => size(&self.topic0s)?,
+                Dim::Topic1 => size(&self.topic1s)?,
+                Dim::Topic2 => size(&self.topic2s)?,
+                Dim::Topic3 => size(&self.topic3s)?,
+            };
+            n_param_sets = n_param_sets.saturating_mul(n_values);
+        }
+        Ok(n_param_sets)
+    }
+
     /// return Vec of dimensions defined in partitions
     // NOTE: this function is not exhaustive
     pub fn dims(&self) -> Vec<Dim> {
@@ -464,6 +558,11 @@ impl Partition {
         }
     }
 
+    /// smallest block number of partition
+    pub fn min_block(&self) -> Option<u64> {
+        self.block_numbers.as_ref()?.iter().filter_map(|chunk| chunk.min_value()).min()
+    }
+
     /// get statistics for partition
     // NOTE: this function is not exhaustive
     pub fn stats(&self) -> PartitionStats {
@@ -535,6 +634,35 @@ fn fold<T: std::cmp::Ord + crate::types::chunks::chunk_ops::ValueToString>(
 }
 
 impl PartitionStats {
+    /// value count of each dimension present, with min and max for block numbers
+    ///
+    /// binary dimensions such as addresses and topics have no meaningful min or max
+    pub fn dim_counts(&self) -> Vec<(Dim, Option<u64>, Option<u64>, u64)> {
+        let binary_dims = [
+            (Dim::TransactionHash, &self.transactions),
+            (Dim::CallData, &self.call_datas),
+            (Dim::Address, &self.addresses),
+            (Dim::Contract, &self.contracts),
+            (Dim::FromAddress, &self.from_addresses),
+            (Dim::ToAddress, &self.to_addresses),
+            (Dim::Slot, &self.slots),
+            (Dim::Topic0, &self.topic0s),
+            (Dim::Topic1, &self.topic1s),
+            (Dim::Topic2, &self.topic2s),
+            (Dim::Topic3, &self.topic3s),
+        ];
+        let mut counts = Vec::new();
+        if let Some(stats) = &self.block_numbers {
+            counts.push((Dim::BlockNumber, stats.min_value, stats.max_value, stats.total_values));
+        }
+        for (dim, stats) in binary_dims.into_iter() {
+            if let Some(stats) = stats {
+                counts.push((dim, None, None, stats.total_values));
+            }
+        }
+        counts
+    }
+
     fn fold(self, other: PartitionStats) -> PartitionStats {
         PartitionStats {
             block_numbers: fold(self.block_numbers, other.block_numbers),
@@ -607,3 +735,58 @@ impl PartitionLabels {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disjoint_block_ranges() {
+        let chunks = vec![BlockChunk::Range(100, 119)];
+        let ranges = disjoint_block_ranges(&chunks, 7);
+        assert_eq!(ranges, vec![(100, 106), (107, 113), (114, 119)]);
+
+        // overlapping chunks and repeated numbers do not request any block twice
+        let chunks = vec![
+            BlockChunk::Range(100, 109),
+            BlockChunk::Range(105, 114),
+            BlockChunk::Numbers(vec![114, 115, 120, 120]),
+        ];
+        let ranges = disjoint_block_ranges(&chunks, 10);
+        assert_eq!(ranges, vec![(100, 109), (110, 115), (120, 120)]);
+        let blocks: Vec<u64> = ranges.iter().flat_map(|(start, end)| *start..=*end).collect();
+        let unique: std::collections::BTreeSet<_> = blocks.iter().collect();
+        assert_eq!(blocks.len(), unique.len());
+    }
+
+    #[test]
+    fn test_n_param_sets() {
+        let partition = Partition {
+            block_numbers: Some(vec![BlockChunk::Range(100, 119)]),
+            addresses: Some(vec![AddressChunk::Values(vec![vec![1; 20], vec![2; 20]])]),
+            ..Default::default()
+        };
+        assert_eq!(partition.n_param_sets(None, false).unwrap(), 40);
+        assert_eq!(partition.n_param_sets(Some(7), false).unwrap(), 6);
+
+        // counts match the param sets, including overlapping blocks and topic0 sets
+        let partition = Partition {
+            block_numbers: Some(vec![
+                BlockChunk::Range(100, 109),
+                BlockChunk::Numbers(vec![105, 110, 130]),
+            ]),
+            topic0s: Some(vec![
+                TopicChunk::Values(vec![vec![1; 32], vec![2; 32]]),
+                TopicChunk::Values(vec![vec![3; 32]]),
+            ]),
+            ..Default::default()
+        };
+        for (inner_request_size, topic0_sets) in
+            [(None, false), (None, true), (Some(4), false), (Some(4), true)]
+        {
+            let param_sets = partition.param_sets(inner_request_size, topic0_sets).unwrap();
+            let n_param_sets = partition.n_param_sets(inner_request_size, topic0_sets).unwrap();
+            assert_eq!(n_param_sets, param_sets.len() as u64);
+        }
+    }
+}
@@ -1,5 +1,10 @@
-use crate::{CollectError, Datatype, Dim, MetaDatatype, Partition, Table};
-use std::collections::{HashMap, HashSet};
+use crate::{
+    BlockChunk, BlockLogCounts, ChainProfile, ChunkData, CollectError, Datatype, Dim, EnsResolver,
+    Erc20MetadataCache, MetaDatatype, MultiDatatype, Params, Partition, ReceiptsSource, RpcMethod,
+    Table,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 /// Query
 #[derive(Clone)]
@@ -10,25 +15,219 @@ pub struct Query {
     pub schemas: HashMap<Datatype, Table>,
     /// Time dimension
     pub time_dimension: TimeDimension,
+    /// Collect by transaction over the transactions of each block of partitions
+    pub transactions_from_blocks: bool,
+    /// Request each topic0 chunk as one set of event signatures, instead of one topic0 at a time
+    pub topic0_sets: bool,
     /// MetaChunks
     pub partitions: Vec<Partition>,
     /// Partitioning
     pub partitioned_by: Vec<Dim>,
+    /// Blocks collected at a time, partitions spanning more blocks are collected in chunks
+    pub collect_chunk_size: Option<u64>,
     /// Exclude failed
     pub exclude_failed: bool,
+    /// Address ranges of precompiles to exclude from call traces
+    pub exclude_precompiles: Option<Vec<(u64, u64)>>,
+    /// Require output files to be globally sorted when concatenated in filename order
+    pub global_sort: bool,
+    /// Chain-specific collector behavior
+    pub chain_profile: ChainProfile,
+    /// Exclude system transactions of the chain from transactions and traces
+    pub exclude_system_transactions: bool,
+    /// Minimum priority fee per gas in wei of transactions, effective gas price minus base fee
+    pub min_priority_fee: Option<u64>,
+    /// EIP-2718 types of transactions to keep, transactions without a type are legacy (0)
+    pub tx_types: Option<Vec<u8>>,
+    /// Exclude addresses without code from codes
+    pub contracts_only: bool,
+    /// Collect transactions from block receipts alone if no column or filter needs block bodies
+    pub transactions_receipts_only: bool,
+    /// Exclude storage diffs of contracts created in the same transaction
+    pub exclude_creation_storage: bool,
+    /// Keep only blocks where the total supply of a token changed, in erc20_supplies, or where
+    /// the nonce of an address increased, in nonces
+    pub changes_only: bool,
+    /// Keep the first block of each token or address with changes_only, as a baseline
+    pub changes_baseline: bool,
+    /// Drop call traces nested deeper than this, the top-level call has depth 0
+    pub max_trace_depth: Option<u32>,
+    /// Record tracing errors as rows with an error column instead of failing the chunk
+    pub trace_error_as_column: bool,
+    /// Columns that logs are collapsed by, into one row per key with a count of its logs
+    pub collapse_logs: Option<Vec<String>>,
+    /// Fewest logs a block can have for its logs to be kept, counting every log of the block
+    pub min_block_logs: Option<u64>,
+    /// Most logs a block can have for its logs to be kept, counting every log of the block
+    pub max_block_logs: Option<u64>,
+    /// Cache of the number of logs of each block, used with `min_block_logs` or `max_block_logs`
+    pub block_log_counts: Option<BlockLogCounts>,
+    /// Sources of address appearances to collect, None for every source
+    pub appearance_sources: Option<Vec<String>>,
+    /// Keep only the first appearance of each address in address_appearances, at its lowest block
+    /// across all partitions, whose partial files are consolidated into one file
+    pub first_seen: bool,
+    /// (contract, slot) pairs of slot files, storage outside of these pairs is not collected
+    pub storage_slots: Option<StorageSlots>,
+    /// Calls of a trace calls input file, other contract and call data combinations are skipped
+    pub trace_call_inputs: Option<TraceCallInputs>,
+    /// Columns whose rpc failures are errors instead of nulls
+    pub required_columns: Vec<String>,
+    /// Cache of erc20 metadata, `None` to refetch metadata at every block
+    pub erc20_metadata_cache: Option<Erc20MetadataCache>,
+    /// Resolver of reverse ENS names of the address column, `None` to not resolve names
+    pub ens_resolver: Option<EnsResolver>,
     /// Javascript tracer
     pub js_tracer: Option<String>,
+    /// Tracer used to collect state diffs
+    pub state_diff_tracer: StateDiffTracer,
     /// Labels (these are non-functional)
     pub labels: QueryLabels,
 }
 
+/// estimated rpc requests of a query
+#[derive(Clone, Debug, Default)]
+pub struct RequestEstimate {
+    /// requests of each rpc method
+    pub requests: BTreeMap<RpcMethod, u64>,
+    /// whether some requests depend on data and are not counted
+    pub lower_bound: bool,
+}
+
+impl RequestEstimate {
+    /// total requests of all rpc methods
+    pub fn total(&self) -> u64 {
+        self.requests.values().sum()
+    }
+}
+
+/// (contract, slot) pairs of storage
+pub type StorageSlots = HashSet<(Vec<u8>, Vec<u8>)>;
+
+/// (contract, call data) pair of a call
+pub type TraceCall = (Vec<u8>, Vec<u8>);
+
+/// calls of a trace calls input file
+///
+/// each (contract, call data) pair maps to its block numbers, where `None` stands for every block
+/// of `--blocks`
+#[derive(Clone, Debug, Default)]
+pub struct TraceCallInputs {
+    /// block numbers of each (contract, call data) pair
+    pub calls: HashMap<TraceCall, HashSet<Option<u64>>>,
+    /// block numbers of the input file that are not blocks of `--blocks`
+    pub extra_blocks: HashSet<u64>,
+}
+
+impl TraceCallInputs {
+    /// param sets of the calls made at the blocks of the partition, one per call and block
+    pub fn param_sets(&self, partition: &Partition) -> Vec<Params> {
+        let partition_blocks = partition_block_set(partition);
+        let mut param_sets = Vec::new();
+        for ((contract, call_data), blocks) in self.calls.iter() {
+            for block in self.call_blocks(blocks, &partition_blocks) {
+                param_sets.push(Params {
+                    block_number: Some(block),
+                    contract: Some(contract.clone()),
+                    call_data: Some(call_data.clone()),
+                    ..Default::default()
+                });
+            }
+        }
+        param_sets
+    }
+
+    /// number of calls made at the blocks of the partition
+    pub fn n_param_sets(&self, partition: &Partition) -> u64 {
+        let partition_blocks = partition_block_set(partition);
+        let n_blocks = |blocks| self.call_blocks(blocks, &partition_blocks).len() as u64;
+        self.calls.values().map(n_blocks).sum()
+    }
+
+    /// blocks of the partition at which a call is made
+    ///
+    /// calls without a block number are made at every block that is not only an input file block
+    fn call_blocks(
+        &self,
+        blocks: &HashSet<Option<u64>>,
+        partition_blocks: &BTreeSet<u64>,
+    ) -> BTreeSet<u64> {
+        let in_partition = |block: &&u64| partition_blocks.contains(block);
+        let mut call_blocks: BTreeSet<u64> =
+            blocks.iter().flatten().filter(in_partition).copied().collect();
+        if blocks.contains(&None) {
+            let default_blocks = partition_blocks.iter().filter(|b| !self.extra_blocks.contains(b));
+            call_blocks.extend(default_blocks);
+        }
+        call_blocks
+    }
+}
+
+fn partition_block_set(partition: &Partition) -> BTreeSet<u64> {
+    partition.block_numbers.iter().flatten().flat_map(|chunk| chunk.values()).collect()
+}
+
+/// tracer used to collect state diffs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StateDiffTracer {
+    /// parity `trace_replayBlockTransactions` with `stateDiff`
+    #[default]
+    Parity,
+    /// geth `debug_traceBlockByNumber` with `prestateTracer` in diff mode
+    Geth,
+}
+
+/// how close to the chain tip blocks are collected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReorgBuffer {
+    /// only collect blocks at least this many blocks older than the latest block
+    Blocks(u64),
+    /// only collect blocks up to the finalized block
+    Finalized,
+}
+
+impl Default for ReorgBuffer {
+    fn default() -> ReorgBuffer {
+        ReorgBuffer::Blocks(0)
+    }
+}
+
+impl From<u64> for ReorgBuffer {
+    fn from(n_blocks: u64) -> ReorgBuffer {
+        ReorgBuffer::Blocks(n_blocks)
+    }
+}
+
+impl std::str::FromStr for ReorgBuffer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ReorgBuffer, String> {
+        match s {
+            "finalized" => Ok(ReorgBuffer::Finalized),
+            s => s
+                .parse::<u64>()
+                .map(ReorgBuffer::Blocks)
+                .map_err(|_| "must be a number of blocks or `finalized`".to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ReorgBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReorgBuffer::Blocks(n_blocks) => write!(f, "{}", n_blocks),
+            ReorgBuffer::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
 /// query labels (non-functional)
 #[derive(Clone)]
 pub struct QueryLabels {
     /// align
     pub align: bool,
     /// reorg buffer
-    pub reorg_buffer: u64,
+    pub reorg_buffer: ReorgBuffer,
 }
 
 impl Query {
@@ -42,6 +241,108 @@ impl Query {
         self.datatypes.iter().map(|x| x.datatypes().len()).sum::<usize>() * self.partitions.len()
     }
 
+    /// estimate the rpc requests of query, from the param sets of its partitions
+    ///
+    /// requests whose number depends on data, such as the receipts of each transaction of a block
+    /// or `eth_getLogs` requests that are split when they return too many logs, make the estimate a
+    /// lower bound
+    pub fn estimate_request_count(
+        &self,
+        inner_request_size: u64,
+        receipts_source: ReceiptsSource,
+    ) -> Result<RequestEstimate, CollectError> {
+        let by_tx = matches!(self.time_dimension, TimeDimension::Transactions);
+        let mut estimate = RequestEstimate::default();
+        for datatype in self.datatypes.iter() {
+            let block_ranges = match datatype {
+                MetaDatatype::Scalar(datatype) if datatype.use_block_ranges() => {
+                    Some(inner_request_size)
+                }
+                _ => None,
+            };
+            let mut n_params = 0;
+            for partition in self.partitions.iter() {
+                n_params += match &self.trace_call_inputs {
+                    Some(inputs) => inputs.n_param_sets(partition),
+                    None => partition.n_param_sets(block_ranges, self.topic0_sets)?,
+                };
+            }
+            let methods: BTreeSet<RpcMethod> = datatype
+                .datatypes()
+                .iter()
+                .flat_map(|datatype| datatype.rpc_methods(self, receipts_source))
+                .collect();
+            if self.transactions_from_blocks {
+                *estimate.requests.entry(RpcMethod::GetBlockByNumber).or_default() += n_params;
+            }
+            for method in methods.into_iter() {
+                let per_item = match method {
+                    RpcMethod::GetTransactionReceipt | RpcMethod::GetUncleByBlockNumberAndIndex => {
+                        !by_tx
+                    }
+                    _ => self.transactions_from_blocks && method.takes_transaction(),
+                };
+                if per_item {
+                    estimate.lower_bound = true;
+                    continue
+                }
+                if method == RpcMethod::GetLogs {
+                    estimate.lower_bound = true;
+                }
+                *estimate.requests.entry(method).or_default() += n_params;
+            }
+        }
+        Ok(estimate)
+    }
+
+    /// whether state diffs and traces are replayed together, see `--trace-replay`
+    pub fn replays_traces(&self) -> bool {
+        self.datatypes
+            .iter()
+            .any(|datatype| matches!(datatype, MetaDatatype::Multi(MultiDatatype::TraceReplay)))
+    }
+
+    /// value of an rpc result used to populate columns
+    ///
+    /// failures become nulls unless one of the columns is required, in which case the chunk fails
+    pub fn column_result<T>(
+        &self,
+        columns: &[&str],
+        result: Result<T, CollectError>,
+    ) -> Result<Option<T>, CollectError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => match columns.iter().find(|c| self.required_columns.iter().any(|r| r == *c)) {
+                Some(column) => Err(CollectError::CollectError(format!(
+                    "could not populate required column {}: {}",
+                    column, e
+                ))),
+                None => Ok(None),
+            },
+        }
+    }
+
+    /// largest block of the query below the first block of the partition, if any
+    ///
+    /// changes between sampled blocks continue from this block instead of restarting at each chunk
+    pub fn previous_chunk_block(&self, partition: &Partition) -> Option<u64> {
+        let first_block = partition.min_block()?;
+        let last_block_below = |chunk: &BlockChunk| match chunk {
+            BlockChunk::Numbers(numbers) => {
+                numbers.iter().filter(|n| **n < first_block).max().copied()
+            }
+            BlockChunk::Range(start, end) => {
+                (*start < first_block).then(|| (*end).min(first_block - 1))
+            }
+        };
+        self.partitions
+            .iter()
+            .filter_map(|partition| partition.block_numbers.as_ref())
+            .flatten()
+            .filter_map(last_block_below)
+            .max()
+    }
+
     /// check that query is valid
     pub fn is_valid(&self) -> Result<(), CollectError> {
         // check that required parameters are present
@@ -66,10 +367,65 @@ impl Query {
                 )))
             }
         }
+
+        // check that output files can be globally ordered
+        if self.global_sort {
+            crate::ensure_global_sort(self)?;
+        }
+
         Ok(())
     }
 }
 
+#[cfg(test)]
+impl Query {
+    /// query of a single datatype over block partitions
+    pub(crate) fn for_datatype(
+        datatype: Datatype,
+        schema: Table,
+        partitions: Vec<Partition>,
+    ) -> Query {
+        Query {
+            datatypes: vec![MetaDatatype::Scalar(datatype)],
+            schemas: [(datatype, schema)].into_iter().collect(),
+            time_dimension: TimeDimension::Blocks,
+            transactions_from_blocks: false,
+            topic0_sets: false,
+            partitions,
+            partitioned_by: vec![Dim::BlockNumber],
+            collect_chunk_size: None,
+            exclude_failed: false,
+            exclude_precompiles: None,
+            chain_profile: ChainProfile::default(),
+            exclude_system_transactions: false,
+            min_priority_fee: None,
+            tx_types: None,
+            contracts_only: false,
+            transactions_receipts_only: false,
+            exclude_creation_storage: false,
+            changes_only: false,
+            changes_baseline: true,
+            max_trace_depth: None,
+            trace_error_as_column: false,
+            collapse_logs: None,
+            min_block_logs: None,
+            max_block_logs: None,
+            appearance_sources: None,
+            first_seen: false,
+            storage_slots: None,
+            trace_call_inputs: None,
+            required_columns: vec![],
+            block_log_counts: None,
+            erc20_metadata_cache: None,
+            ens_resolver: None,
+            state_diff_tracer: StateDiffTracer::Parity,
+            global_sort: true,
+            js_tracer: None,
+            labels: QueryLabels { align: false, reorg_buffer: ReorgBuffer::default() },
+        }
+    }
+}
+
 /// Time dimension for queries
 #[derive(Clone)]
 pub enum TimeDimension {
@@ -78,3 +434,100 @@ pub enum TimeDimension {
     /// Transactions
     Transactions,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockChunk, ColumnEncoding, U256Type};
+
+    #[test]
+    fn test_column_result() {
+        let schema = Datatype::Erc20Balances
+            .table_schema(
+                &[U256Type::Binary],
+                &ColumnEncoding::Hex,
+                &None,
+                &None,
+                &None,
+                None,
+                None,
+            )
+            .unwrap();
+        let mut query = Query::for_datatype(Datatype::Erc20Balances, schema, vec![]);
+        let failed = || Err::<u32, _>(CollectError::RPCError("missing trie node".to_string()));
+
+        // failures become nulls when no column is required
+        assert_eq!(query.column_result(&["balance"], failed()).unwrap(), None);
+        assert_eq!(query.column_result(&["balance"], Ok(1)).unwrap(), Some(1));
+
+        // failures only fail the chunk for required columns
+        query.required_columns = vec!["balance".to_string()];
+        assert!(query.column_result(&["balance"], failed()).is_err());
+        assert!(query.column_result(&["output_data", "balance"], failed()).is_err());
+        assert_eq!(query.column_result(&["symbol"], failed()).unwrap(), None);
+        assert_eq!(query.column_result(&["balance"], Ok(1)).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_previous_chunk_block() {
+        let schema = Datatype::Nonces
+            .table_schema(
+                &[U256Type::Binary],
+                &ColumnEncoding::Hex,
+                &None,
+                &None,
+                &None,
+                None,
+                None,
+            )
+            .unwrap();
+        let partition = |chunks: Vec<BlockChunk>| Partition {
+            block_numbers: Some(chunks),
+            ..Default::default()
+        };
+        let partitions = vec![
+            partition(vec![BlockChunk::Range(100, 199)]),
+            partition(vec![BlockChunk::Range(200, 299)]),
+            partition(vec![BlockChunk::Numbers(vec![350, 320])]),
+            partition(vec![BlockChunk::Numbers(vec![500, 410, 340])]),
+        ];
+        let query = Query::for_datatype(Datatype::Nonces, schema, partitions.clone());
+        assert_eq!(query.previous_chunk_block(&partitions[0]), None);
+        assert_eq!(query.previous_chunk_block(&partitions[1]), Some(199));
+
+        // the previous block is a block of the query, not the block before the partition
+        assert_eq!(query.previous_chunk_block(&partitions[2]), Some(299));
+        assert_eq!(query.previous_chunk_block(&partitions[3]), Some(320));
+        assert_eq!(query.previous_chunk_block(&Partition::default()), None);
+    }
+
+    #[test]
+    fn test_trace_call_param_sets() {
+        let call = |n: u8| (vec![n; 20], vec![n; 4]);
+        let blocks = |blocks: &[Option<u64>]| blocks.iter().copied().collect::<HashSet<_>>();
+        let inputs = TraceCallInputs {
+            calls: [
+                (call(1), blocks(&[Some(5), None])),
+                (call(2), blocks(&[Some(20)])),
+                (call(3), blocks(&[Some(99)])),
+            ]
+            .into_iter()
+            .collect(),
+            extra_blocks: [20, 99].into_iter().collect(),
+        };
+        let partition = Partition {
+            block_numbers: Some(vec![BlockChunk::Range(1, 2), BlockChunk::Numbers(vec![5, 20])]),
+            ..Default::default()
+        };
+
+        // one param set per call and block, calls without a block skip input file blocks
+        let mut param_sets: Vec<_> = inputs
+            .param_sets(&partition)
+            .into_iter()
+            .map(|params| (params.contract.unwrap()[0], params.block_number.unwrap()))
+            .collect();
+        param_sets.sort();
+        assert_eq!(param_sets, vec![(1, 1), (1, 2), (1, 5), (2, 20)]);
+        assert_eq!(inputs.n_param_sets(&partition), 4);
+    }
+}
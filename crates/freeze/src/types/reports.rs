@@ -1,6 +1,7 @@
-use crate::{err, CollectError, ExecutionEnv, FileOutput, FreezeSummary, Query};
+use crate::{dataframes, err, CollectError, ExecutionEnv, FileOutput, FreezeSummary, Query};
 use chrono::{DateTime, Local};
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::Write,
     path::{Path, PathBuf},
@@ -11,8 +12,12 @@ struct FreezeReport {
     cryo_version: String,
     // node_client: String,
     cli_command: Option<Vec<String>>,
+    label: Option<String>,
+    prefix: String,
     results: Option<SerializedFreezeSummary>,
     args: Option<String>,
+    args_hash: Option<String>,
+    schema_version: Option<String>,
 }
 
 #[derive(serde::Serialize, Debug)]
@@ -53,6 +58,53 @@ pub(crate) fn write_report(
     query: &Query,
     sink: &FileOutput,
     freeze_summary: Option<&FreezeSummary>,
+) -> Result<PathBuf, CollectError> {
+    let path = write_report_file(env, query, sink, freeze_summary, freeze_summary.is_some())?;
+
+    // delete initial report
+    if freeze_summary.is_some() {
+        let incomplete_path = get_report_path(env, sink, false)?;
+        std::fs::remove_file(incomplete_path)
+            .map_err(|_| err("could not delete initial report file"))?;
+    }
+
+    Ok(path)
+}
+
+/// rewrites the incomplete report of a run with the chunks completed or errored so far
+///
+/// reports are written to a temp file and renamed into place after every chunk result, so a run
+/// that aborts, is cancelled, or panics leaves a partial report of every finished chunk rather
+/// than a partially written file
+pub(crate) struct PartialReport<'a> {
+    env: &'a ExecutionEnv,
+    query: &'a Query,
+    sink: &'a FileOutput,
+}
+
+impl<'a> PartialReport<'a> {
+    /// partial report of a run, None unless reports are kept updated on error
+    pub(crate) fn new(
+        env: &'a ExecutionEnv,
+        query: &'a Query,
+        sink: &'a FileOutput,
+    ) -> Option<Self> {
+        (env.report && env.report_on_error).then_some(PartialReport { env, query, sink })
+    }
+
+    /// rewrite the report with the result of a chunk
+    pub(crate) fn update(&mut self, summary: &FreezeSummary) -> Result<(), CollectError> {
+        write_report_file(self.env, self.query, self.sink, Some(summary), false)?;
+        Ok(())
+    }
+}
+
+fn write_report_file(
+    env: &ExecutionEnv,
+    query: &Query,
+    sink: &FileOutput,
+    freeze_summary: Option<&FreezeSummary>,
+    is_complete: bool,
 ) -> Result<PathBuf, CollectError> {
     // determine version
     let cryo_version = CRYO_VERSION.to_string();
@@ -63,27 +115,26 @@ pub(crate) fn write_report(
     let report = FreezeReport {
         cryo_version,
         cli_command: env.cli_command.clone(),
+        label: sink.suffix.clone(),
+        prefix: sink.prefix.clone(),
         args: env.args.clone(),
+        args_hash: args_hash(env),
+        schema_version: sink.schema_version.clone(),
         results: serialized_summary,
     };
     let serialized = serde_json::to_string(&report)
         .map_err(|_| CollectError::CollectError("could not serialize report".to_string()))?;
 
     // create path
-    let path = get_report_path(env, sink, freeze_summary.is_some())?;
+    let path = get_report_path(env, sink, is_complete)?;
 
-    // save to file
-    let mut file = File::create(&path)
+    // save to temp file, then rename into place so the report is never partially written
+    let tmp_path = dataframes::get_tmp_path(&path);
+    let mut file = File::create(&tmp_path)
         .map_err(|_| CollectError::CollectError("could not create report file".to_string()))?;
     file.write_all(serialized.as_bytes())
         .map_err(|_| CollectError::CollectError("could not write report data".to_string()))?;
-
-    // delete initial report
-    if freeze_summary.is_some() {
-        let incomplete_path = get_report_path(env, sink, false)?;
-        std::fs::remove_file(incomplete_path)
-            .map_err(|_| err("could not delete initial report file"))?;
-    }
+    std::fs::rename(&tmp_path, &path).map_err(|_| err("could not move report file into place"))?;
 
     Ok(path)
 }
@@ -126,5 +177,153 @@ fn serialize_summary(
     })
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct Manifest {
+    cryo_version: String,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+struct ManifestFile {
+    path: PathBuf,
+    datatype: String,
+    start_block: Option<u64>,
+    end_block: Option<u64>,
+    n_rows: u64,
+    n_bytes: u64,
+}
+
+/// write `_manifest.json` to the output dir, listing each file written by the freeze
+///
+/// entries of a prior manifest are kept unless a file was rewritten, so that resumed runs
+/// produce a manifest covering both the earlier and the new files
+pub(crate) fn write_manifest(
+    sink: &FileOutput,
+    summary: &FreezeSummary,
+) -> Result<PathBuf, CollectError> {
+    let mut files = Vec::new();
+    for (path, file) in summary.files.iter() {
+        let n_bytes =
+            std::fs::metadata(path).map_err(|_| err("could not get size of output file"))?.len();
+        files.push(ManifestFile {
+            path: path.clone(),
+            datatype: file.datatype.name(),
+            start_block: file.start_block,
+            end_block: file.end_block,
+            n_rows: file.n_rows,
+            n_bytes,
+        })
+    }
+
+    let path = sink.output_dir.join("_manifest.json");
+    let prior = match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str::<Manifest>(&contents)
+            .map_err(|_| err("could not parse existing manifest"))?
+            .files,
+        Err(_) => Vec::new(),
+    };
+    let manifest =
+        Manifest { cryo_version: CRYO_VERSION.to_string(), files: merge_manifest(prior, files) };
+    let serialized = serde_json::to_string_pretty(&manifest)
+        .map_err(|_| err("could not serialize manifest"))?;
+    std::fs::write(&path, serialized).map_err(|_| err("could not write manifest"))?;
+    Ok(path)
+}
+
+/// merge new manifest entries into prior entries, sorted by path
+fn merge_manifest(prior: Vec<ManifestFile>, new: Vec<ManifestFile>) -> Vec<ManifestFile> {
+    let mut files: BTreeMap<PathBuf, ManifestFile> =
+        prior.into_iter().map(|file| (file.path.clone(), file)).collect();
+    files.extend(new.into_iter().map(|file| (file.path.clone(), file)));
+    files.into_values().collect()
+}
+
+/// hash of the serialized args of a run, embedded in output file metadata to identify the run
+pub(crate) fn args_hash(env: &ExecutionEnv) -> Option<String> {
+    env.args.as_ref().map(|args| alloy::primitives::keccak256(args.as_bytes()).to_string())
+}
+
 /// cryo version
 pub const CRYO_VERSION: &str = env!("GIT_DESCRIPTION");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockChunk, Datatype, Partition};
+
+    fn file(path: &str, n_rows: u64) -> ManifestFile {
+        ManifestFile {
+            path: PathBuf::from(path),
+            datatype: "blocks".to_string(),
+            start_block: Some(0),
+            end_block: Some(999),
+            n_rows,
+            n_bytes: 100,
+        }
+    }
+
+    #[test]
+    fn test_merge_manifest() {
+        let prior = vec![file("b.parquet", 1), file("a.parquet", 1)];
+        let new = vec![file("c.parquet", 2), file("a.parquet", 2)];
+        let merged = merge_manifest(prior, new);
+        assert_eq!(merged, vec![file("a.parquet", 2), file("b.parquet", 1), file("c.parquet", 2)]);
+    }
+
+    #[test]
+    fn test_partial_report_after_failing_chunk() {
+        let dir = std::env::temp_dir().join(format!("cryo_partial_report_{}", std::process::id()));
+        let env = ExecutionEnv {
+            report: true,
+            report_on_error: true,
+            report_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let sink = FileOutput {
+            output_dir: dir.clone(),
+            prefix: "ethereum".to_string(),
+            network: "ethereum".to_string(),
+            suffix: None,
+            subdirs: vec![],
+            overwrite: false,
+            overwrite_partial: false,
+            force_schema_change: false,
+            format: crate::FileFormat::Parquet,
+            row_group_size: None,
+            row_group_target_bytes: None,
+            parquet_statistics: true,
+            parquet_page_size: None,
+            parquet_dictionary: true,
+            parquet_compression: polars::prelude::ParquetCompression::Uncompressed,
+            file_compression: None,
+            chunk_stats_file: false,
+            empty_file_policy: crate::EmptyFilePolicy::Write,
+            schema_version: None,
+            flush_interval: None,
+            json: Default::default(),
+        };
+        let schema = Datatype::Blocks
+            .table_schema(&[], &crate::ColumnEncoding::Hex, &None, &None, &None, None, None)
+            .unwrap();
+        let partition = Partition {
+            block_numbers: Some(vec![BlockChunk::Range(0, 9)]),
+            ..Default::default()
+        };
+        let query = Query::for_datatype(Datatype::Blocks, schema, vec![partition.clone()]);
+
+        // the first failing chunk is in the report without waiting for later chunks
+        let mut report = PartialReport::new(&env, &query, &sink).unwrap();
+        let mut summary = FreezeSummary::default();
+        summary.errored.push((Some(partition), err("failed")));
+        report.update(&summary).unwrap();
+        let path = get_report_path(&env, &sink, false).unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let errored = report["results"]["errored_paths"].as_array().unwrap();
+        assert_eq!(errored.len(), 1);
+        let filename = "ethereum__blocks__00000000_to_00000009.parquet";
+        assert!(errored[0].as_str().unwrap().ends_with(filename));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{err, CollectError};
 use alloy::{
     primitives::{Address, BlockNumber, B256},
-    rpc::types::{Filter, FilterBlockOption},
+    rpc::types::{Filter, FilterBlockOption, ValueOrArray},
 };
 
 /// represents parameters for a single rpc call
@@ -27,6 +27,8 @@ pub struct Params {
     pub slot: Option<Vec<u8>>,
     /// topic0
     pub topic0: Option<Vec<u8>>,
+    /// topic0 set, logs matching any of these are included
+    pub topic0s: Option<Vec<Vec<u8>>>,
     /// topic1
     pub topic1: Option<Vec<u8>>,
     /// topic2
@@ -118,6 +120,10 @@ impl Params {
         if self.topic0.is_some() {
             filter = filter.event_signature(B256::from_slice(&self.topic0.clone().unwrap()));
         }
+        if let Some(topic0s) = &self.topic0s {
+            let topic0s = topic0s.iter().map(|x| B256::from_slice(x)).collect();
+            filter = filter.event_signature(ValueOrArray::Array(topic0s));
+        }
         if self.topic1.is_some() {
             filter = filter.topic1(B256::from_slice(&self.topic1.clone().unwrap()));
         }
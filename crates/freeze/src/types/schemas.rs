@@ -1,8 +1,9 @@
 /// types and functions related to schemas
 use std::collections::HashMap;
 
-use crate::{err, CollectError, ColumnEncoding, Datatype, LogDecoder};
+use crate::{err, CallDecoder, CollectError, ColumnEncoding, DataDecoder, Datatype, LogDecoder};
 use indexmap::{IndexMap, IndexSet};
+use polars::prelude::Expr;
 use thiserror::Error;
 
 /// collection of schemas
@@ -31,6 +32,9 @@ pub struct Table {
     /// sort order for rows
     pub sort_columns: Option<Vec<String>>,
 
+    /// whether each sort column is sorted descending, columns without an entry are ascending
+    pub sort_descending: Vec<bool>,
+
     /// representations to use for u256 columns
     pub u256_types: Vec<U256Type>,
 
@@ -39,8 +43,44 @@ pub struct Table {
 
     /// log decoder for table
     pub log_decoder: Option<LogDecoder>,
+
+    /// decoder of call inputs for table
+    pub call_decoder: Option<CallDecoder>,
+
+    /// decoder of log data of a known layout for table
+    pub data_decoder: Option<DataDecoder>,
+
+    /// output name of block_number column, columns keep their internal name until output
+    pub block_number_column: Option<String>,
+
+    /// whether hex strings of address columns are EIP-55 checksummed
+    pub checksum_addresses: bool,
+
+    /// expression that rows must satisfy, evaluated on the output columns of each chunk
+    pub filter_expr: Option<Expr>,
 }
 
+/// suffix of the column of ENS names of an address column
+pub const ENS_NAME_SUFFIX: &str = "_ens_name";
+
+/// columns whose values are 20-byte addresses
+const ADDRESS_COLUMNS: &[&str] = &[
+    "action_from",
+    "action_to",
+    "address",
+    "author",
+    "contract_address",
+    "deployer",
+    "erc20",
+    "erc721",
+    "factory",
+    "from_address",
+    "result_address",
+    "to_address",
+    "tx_to_address",
+    "uncle_miner",
+];
+
 impl Table {
     /// return whether schema has a column
     pub fn has_column(&self, column: &str) -> bool {
@@ -56,6 +96,77 @@ impl Table {
     pub fn columns(&self) -> Vec<&str> {
         self.columns.keys().map(|x| x.as_str()).collect()
     }
+
+    /// add a column of the reverse ENS names of each address column
+    pub fn add_ens_name_columns(&mut self) {
+        let ens_columns: Vec<String> = self
+            .columns
+            .keys()
+            .filter(|column| ADDRESS_COLUMNS.contains(&column.as_str()))
+            .map(|column| column.clone() + ENS_NAME_SUFFIX)
+            .collect();
+        for column in ens_columns.into_iter() {
+            self.columns.insert(column, ColumnType::String);
+        }
+    }
+
+    /// address columns that have a column of ENS names, paired with their ENS name column
+    pub fn ens_name_columns(&self) -> Vec<(String, String)> {
+        self.columns
+            .keys()
+            .filter(|column| ADDRESS_COLUMNS.contains(&column.as_str()))
+            .map(|column| (column.clone(), column.clone() + ENS_NAME_SUFFIX))
+            .filter(|(_, ens_column)| self.columns.contains_key(ens_column))
+            .collect()
+    }
+
+    /// whether hex strings of column are EIP-55 checksummed, only address columns are
+    pub fn checksums_column(&self, column: &str) -> bool {
+        self.checksum_addresses && ADDRESS_COLUMNS.contains(&column)
+    }
+
+    /// get output names of columns, with u256 columns expanded to one column per u256 type
+    /// and block_number renamed to block_number_column
+    pub fn output_columns(&self) -> Vec<String> {
+        let mut output = Vec::new();
+        for (column, ctype) in self.columns.iter() {
+            match (ctype, column.as_str(), &self.block_number_column) {
+                (ColumnType::UInt256, _, _) => {
+                    for u256_type in self.u256_types.iter() {
+                        output.push(column.clone() + u256_type.suffix().as_str())
+                    }
+                }
+                (_, "block_number", Some(name)) => output.push(name.clone()),
+                _ => output.push(column.clone()),
+            }
+        }
+        output
+    }
+
+    /// whether column could be created by a decoder of the table, the names of decoded
+    /// columns depend on the decoded values so they are only known once decoded
+    pub fn decodes_column(&self, column: &str) -> bool {
+        (self.log_decoder.is_some() && column.starts_with("event__")) ||
+            (self.call_decoder.is_some() && column.starts_with("call__")) ||
+            (self.data_decoder.is_some() && column.starts_with("data__"))
+    }
+
+    /// keep only the key columns that rows are collapsed by and chain_id, plus a count column
+    ///
+    /// decoded columns differ across the rows of a key, so decoders are dropped as well
+    pub fn collapse_columns(&mut self, key: &[String]) {
+        self.columns.retain(|column, _| key.contains(column) || column == "chain_id");
+        self.columns.insert("count".to_string(), ColumnType::UInt64);
+        self.sort_columns = Some(key.to_vec());
+        self.sort_descending = vec![];
+        self.log_decoder = None;
+        self.data_decoder = None;
+    }
+
+    /// whether sort column at index is sorted descending
+    pub fn is_sort_descending(&self, index: usize) -> bool {
+        self.sort_descending.get(index).copied().unwrap_or(false)
+    }
 }
 
 /// representation of a U256 datum
@@ -188,7 +299,7 @@ impl Datatype {
         let mut columns = IndexMap::new();
         for column in used_columns {
             let mut ctype = column_types.get(column.as_str()).ok_or(SchemaError::InvalidColumn)?;
-            if (*binary_column_format == ColumnEncoding::Hex) & (ctype == &ColumnType::Binary) {
+            if binary_column_format.is_hex() & (ctype == &ColumnType::Binary) {
                 ctype = &ColumnType::Hex;
             }
             columns.insert((*column.clone()).to_string(), *ctype);
@@ -197,10 +308,16 @@ impl Datatype {
         let schema = Table {
             datatype: *self,
             sort_columns: sort,
+            sort_descending: Vec::new(),
             columns,
             u256_types: u256_types.to_owned(),
             binary_type: binary_column_format.clone(),
             log_decoder,
+            call_decoder: None,
+            data_decoder: None,
+            block_number_column: None,
+            checksum_addresses: false,
+            filter_expr: None,
         };
         Ok(schema)
     }
@@ -257,7 +374,7 @@ mod tests {
         let table = Datatype::Blocks
             .table_schema(&get_u256_types(), &ColumnEncoding::Hex, &None, &None, &cols, None, None)
             .unwrap();
-        assert_eq!(21, table.columns().len());
+        assert_eq!(23, table.columns().len());
         assert!(table.columns().contains(&"block_hash"));
         assert!(table.columns().contains(&"transactions_root"));
     }
@@ -308,7 +425,7 @@ mod tests {
                 None,
             )
             .unwrap();
-        assert_eq!(21, table.columns().len());
+        assert_eq!(23, table.columns().len());
         assert!(table.columns().contains(&"block_hash"));
         assert!(table.columns().contains(&"transactions_root"));
     }
@@ -24,3 +24,21 @@ sol! {
         function tokenURI(uint256 tokenId) external view returns (string memory);
     }
 }
+
+sol! {
+    contract ENSRegistry {
+        function resolver(bytes32 node) external view returns (address);
+    }
+}
+
+sol! {
+    contract ENSNameResolver {
+        function name(bytes32 node) external view returns (string memory);
+    }
+}
+
+sol! {
+    contract ENSAddrResolver {
+        function addr(bytes32 node) external view returns (address);
+    }
+}
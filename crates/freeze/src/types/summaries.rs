@@ -2,11 +2,13 @@ use std::collections::HashMap;
 
 use chrono::{DateTime, Local};
 use colored::Colorize;
+use polars::prelude::*;
 use thousands::Separable;
 
 use crate::{
     chunks::chunk_ops::ValueToString, ChunkData, ChunkStats, CollectError, ColumnType, Datatype,
-    Dim, ExecutionEnv, FileOutput, MetaDatatype, MultiDatatype, Partition, Query, Source, Table,
+    Dim, ExecutionEnv, FileOutput, MetaDatatype, MultiDatatype, Partition, Query, ReorgBuffer,
+    RpcStats, Source, Table,
 };
 use std::path::PathBuf;
 
@@ -28,6 +30,47 @@ pub struct FreezeSummary {
     pub errored: Vec<(Option<Partition>, CollectError)>,
     /// rows written
     pub n_rows: u64,
+    /// retries used by each completed partition
+    pub n_retries: Vec<u32>,
+    /// output files written
+    pub files: HashMap<PathBuf, OutputFile>,
+}
+
+/// output file written by a freeze
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputFile {
+    /// datatype of the file
+    pub datatype: Datatype,
+    /// first block of the file
+    pub start_block: Option<u64>,
+    /// last block of the file
+    pub end_block: Option<u64>,
+    /// rows written to the file
+    pub n_rows: u64,
+}
+
+impl OutputFile {
+    /// output file of a datatype covering the blocks of a partition
+    pub fn new(datatype: Datatype, partition: &Partition, n_rows: u64) -> Self {
+        let block_numbers = partition.stats().block_numbers;
+        OutputFile {
+            datatype,
+            start_block: block_numbers.as_ref().and_then(|stats| stats.min_value),
+            end_block: block_numbers.as_ref().and_then(|stats| stats.max_value),
+            n_rows,
+        }
+    }
+}
+
+impl FreezeSummary {
+    /// number of completed chunks per retry count, excluding chunks that needed no retries
+    pub fn retry_histogram(&self) -> std::collections::BTreeMap<u32, usize> {
+        let mut histogram = std::collections::BTreeMap::new();
+        for n_retries in self.n_retries.iter().filter(|n_retries| **n_retries > 0) {
+            *histogram.entry(*n_retries).or_insert(0) += 1;
+        }
+        histogram
+    }
 }
 
 /// print all datasets
@@ -110,6 +153,102 @@ pub fn print_dataset_info(datatype: Datatype, schema: &Table) {
     print_schema(&datatype, schema);
 }
 
+/// print partition dims of each datatype, as one json object per line
+pub fn print_dataset_dims(datatypes: &[Datatype]) {
+    for datatype in datatypes.iter() {
+        println!("{}", dataset_dims(datatype));
+    }
+}
+
+/// partition dims of datatype for each mode that it can be collected in
+///
+/// the dim that a mode iterates over is required in that mode, in addition to the required dims
+/// of the datatype
+pub fn dataset_dims(datatype: &Datatype) -> serde_json::Value {
+    let names = |dims: &[Dim]| dims.iter().map(|dim| dim.to_string()).collect::<Vec<_>>();
+    let required = datatype.required_parameters();
+    let optional = datatype.optional_parameters();
+    let modes = [
+        (Dim::BlockNumber, datatype.can_collect_by_block()),
+        (Dim::TransactionHash, datatype.can_collect_by_transaction()),
+    ];
+    let mut by_mode = serde_json::Map::new();
+    for (mode_dim, can_collect) in modes.into_iter() {
+        if !can_collect {
+            continue
+        }
+        let mode_required: Vec<Dim> = std::iter::once(mode_dim).chain(required.clone()).collect();
+        let dims = serde_json::json!({
+            "required": names(&mode_required),
+            "optional": names(&optional),
+        });
+        by_mode.insert(mode_dim.to_string(), dims);
+    }
+    serde_json::json!({
+        "datatype": datatype.name(),
+        "aliases": datatype.aliases(),
+        "modes": by_mode,
+    })
+}
+
+/// print preview rows of each datatype, binary columns are shown as hex
+pub fn print_preview(
+    previews: HashMap<Datatype, DataFrame>,
+    n_rows: usize,
+) -> Result<(), CollectError> {
+    let mut previews: Vec<_> = previews.into_iter().collect();
+    previews.sort_by_key(|(datatype, _)| datatype.name());
+    for (i, (datatype, df)) in previews.into_iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let columns = df
+            .get_columns()
+            .iter()
+            .map(|series| match series.dtype() {
+                DataType::Binary => {
+                    let hexes: Vec<Option<String>> = series
+                        .binary()?
+                        .into_iter()
+                        .map(|value| value.map(prefix_hex::encode))
+                        .collect();
+                    Ok(Series::new(series.name(), hexes))
+                }
+                _ => Ok(series.clone()),
+            })
+            .collect::<Result<Vec<_>, PolarsError>>()
+            .map_err(CollectError::PolarsError)?;
+        let df = DataFrame::new(columns).map_err(CollectError::PolarsError)?;
+        print_header(format!("{} preview", datatype.name()));
+        println!("{}", preview_table(&df.head(Some(n_rows)))?);
+    }
+    Ok(())
+}
+
+/// table of every row of dataframe, unlike its display which elides rows beyond a limit
+fn preview_table(df: &DataFrame) -> Result<comfy_table::Table, CollectError> {
+    let header: Vec<String> = df
+        .get_columns()
+        .iter()
+        .map(|series| format!("{}\n---\n{}", series.name(), series.dtype()))
+        .collect();
+    let mut table = comfy_table::Table::new();
+    table
+        .load_preset(comfy_table::presets::UTF8_FULL_CONDENSED)
+        .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+        .set_header(header);
+    for i in 0..df.height() {
+        let row = df
+            .get_columns()
+            .iter()
+            .map(|series| series.str_value(i).map(|value| value.to_string()))
+            .collect::<Result<Vec<_>, PolarsError>>()
+            .map_err(CollectError::PolarsError)?;
+        table.add_row(row);
+    }
+    Ok(table)
+}
+
 pub(crate) fn print_header<A: AsRef<str>>(header: A) {
     let header_str = header.as_ref().white().bold();
     let underline = "─".repeat(header_str.len()).truecolor(TITLE_R, TITLE_G, TITLE_B);
@@ -174,9 +313,14 @@ pub(crate) fn print_cryo_intro(
     if env.verbose > 1 {
         print_bullet_indent("exclude failed items", query.exclude_failed.to_string(), 4);
     }
+    if let Some(ranges) = &query.exclude_precompiles {
+        let ranges: Vec<_> =
+            ranges.iter().map(|(start, end)| format!("{:#x}:{:#x}", start, end)).collect();
+        print_bullet_indent("exclude precompiles", ranges.join(", "), 4);
+    }
 
     print_bullet("source", "");
-    print_bullet_indent("network", &sink.prefix, 4);
+    print_bullet_indent("network", &sink.network, 4);
     print_bullet_indent("rpc url", &source.rpc_url, 4);
     match source.labels.max_requests_per_second {
         Some(max_requests_per_second) => print_bullet_indent(
@@ -186,13 +330,18 @@ pub(crate) fn print_cryo_intro(
         ),
         None => print_bullet_indent("max requests per second", "unlimited", 4),
     };
-    match source.labels.max_concurrent_requests {
-        Some(max_concurrent_requests) => print_bullet_indent(
+    match (source.labels.min_concurrent_requests, source.labels.max_concurrent_requests) {
+        (Some(min), Some(max)) => print_bullet_indent(
+            "max concurrent requests",
+            format!("auto, {} to {}", min.separate_with_commas(), max.separate_with_commas()),
+            4,
+        ),
+        (_, Some(max_concurrent_requests)) => print_bullet_indent(
             "max concurrent requests",
             max_concurrent_requests.separate_with_commas(),
             4,
         ),
-        None => print_bullet_indent("max concurrent requests", "unlimited", 4),
+        (_, None) => print_bullet_indent("max concurrent requests", "unlimited", 4),
     };
     match source.max_concurrent_chunks {
         Some(max_concurrent_chunks) => print_bullet_indent(
@@ -221,6 +370,16 @@ pub(crate) fn print_cryo_intro(
     if query.schemas.contains_key(&Datatype::Logs) {
         print_bullet_indent("inner request size", source.inner_request_size.to_string(), 4);
     };
+    if let Ok(estimate) =
+        query.estimate_request_count(source.inner_request_size, source.receipts_source)
+    {
+        let estimate_text = if estimate.lower_bound {
+            format!("at least {}", estimate.total().separate_with_commas())
+        } else {
+            estimate.total().separate_with_commas()
+        };
+        print_bullet_indent("estimated rpc requests", estimate_text, 4);
+    }
 
     print_bullet("output", "");
     if let Some(partition) = query.partitions.first() {
@@ -243,6 +402,16 @@ pub(crate) fn print_cryo_intro(
     );
     print_bullet_indent("chunks to collect", chunk_text, 4);
     print_bullet_indent("output format", sink.format.as_str(), 4);
+    if let Some(file_compression) = &sink.file_compression {
+        print_bullet_indent("file compression", file_compression.as_str(), 4);
+    }
+    if sink.prefix != sink.network {
+        let prefix = if sink.prefix.is_empty() { "[none]" } else { sink.prefix.as_str() };
+        print_bullet_indent("output prefix", prefix, 4);
+    }
+    if let Some(schema) = query.schemas.values().next() {
+        print_bullet_indent("binary encoding", schema.binary_type.as_str(), 4);
+    }
     print_bullet_indent("output dir", sink.output_dir.clone().to_string_lossy(), 4);
 
     // print report path
@@ -280,7 +449,7 @@ pub(crate) fn print_cryo_intro(
     Ok(())
 }
 
-fn print_chunks(chunks: &[Partition], align: Option<bool>, reorg_buffer: Option<u64>) {
+fn print_chunks(chunks: &[Partition], align: Option<bool>, reorg_buffer: Option<ReorgBuffer>) {
     let stats = crate::types::partitions::meta_chunks_stats(chunks);
     for (dim, dim_stats) in [(Dim::BlockNumber, stats.block_numbers)].iter() {
         if let Some(dim_stats) = dim_stats {
@@ -289,7 +458,7 @@ fn print_chunks(chunks: &[Partition], align: Option<bool>, reorg_buffer: Option<
     }
 
     // NOTE: this branch is not exhaustive
-    for (dim, dim_stats) in vec![
+    for (dim, dim_stats) in [
         (Dim::TransactionHash, stats.transactions),
         (Dim::CallData, stats.call_datas),
         (Dim::Address, stats.addresses),
@@ -314,7 +483,7 @@ fn print_chunk<T: Ord + ValueToString>(
     dim: &Dim,
     dim_stats: &ChunkStats<T>,
     align: Option<bool>,
-    reorg_buffer: Option<u64>,
+    reorg_buffer: Option<ReorgBuffer>,
 ) {
     if dim_stats.total_values == 1 {
         print_bullet_indent(
@@ -386,6 +555,11 @@ fn print_schema(name: &Datatype, schema: &Table) {
     }
     println!();
     if let Some(sort_cols) = schema.sort_columns.clone() {
+        let sort_cols: Vec<_> = sort_cols
+            .into_iter()
+            .enumerate()
+            .map(|(i, col)| if schema.is_sort_descending(i) { col + " desc" } else { col })
+            .collect();
         println!("sorting {} by: {}", name.name(), sort_cols.join(", "));
     } else {
         println!("sorting disabled for {}", name.name());
@@ -400,6 +574,7 @@ fn print_schema(name: &Datatype, schema: &Table) {
 pub(crate) fn print_cryo_conclusion(
     freeze_summary: &FreezeSummary,
     query: &Query,
+    source: &Source,
     env: &ExecutionEnv,
 ) {
     let new_env = match env.t_end {
@@ -491,6 +666,14 @@ pub(crate) fn print_cryo_conclusion(
         4,
     );
 
+    for (n_retries, n_chunks) in freeze_summary.retry_histogram() {
+        let label = match n_retries {
+            1 => "chunks needing 1 retry".to_string(),
+            n_retries => format!("chunks needing {} retries", n_retries),
+        };
+        print_bullet_indent(label, n_chunks.separate_with_commas(), 4);
+    }
+
     print_chunks_speeds(
         freeze_summary.completed.clone(),
         &query.partitioned_by,
@@ -498,6 +681,26 @@ pub(crate) fn print_cryo_conclusion(
         query.datatypes.len() as u64,
     );
     print_bullet_indent("rows written", freeze_summary.n_rows.separate_with_commas(), 0);
+
+    if env.verbose > 1 {
+        print_rpc_stats(&source.rpc_stats);
+    }
+}
+
+fn print_rpc_stats(rpc_stats: &RpcStats) {
+    let summary = rpc_stats.summary();
+    if summary.is_empty() {
+        return
+    }
+    print_bullet("rpc calls", "");
+    for (method, n_calls, n_bytes) in summary.into_iter() {
+        let value = format!(
+            "{} calls, {} bytes received",
+            n_calls.separate_with_commas(),
+            n_bytes.separate_with_commas()
+        );
+        print_bullet_indent(method, value, 4);
+    }
 }
 
 macro_rules! print_dim_speed {
@@ -584,3 +787,49 @@ fn format_float(number: f64) -> String {
 
     format!("{}.{}", int_part.separate_with_commas(), frac_str)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_histogram() {
+        let summary = FreezeSummary {
+            completed: vec![Partition::default(); 5],
+            n_retries: vec![0, 1, 2, 1, 0],
+            ..Default::default()
+        };
+        let histogram = summary.retry_histogram();
+        assert_eq!(histogram.into_iter().collect::<Vec<_>>(), vec![(1, 2), (2, 1)]);
+        assert_eq!(
+            summary.retry_histogram().values().sum::<usize>() +
+                summary.n_retries.iter().filter(|n| **n == 0).count(),
+            summary.completed.len()
+        );
+    }
+
+    #[test]
+    fn test_dataset_dims() {
+        let dims = dataset_dims(&Datatype::Balances);
+        assert_eq!(dims["datatype"], "balances");
+        assert_eq!(dims["modes"]["block"]["required"], serde_json::json!(["block", "address"]));
+        assert!(dims["modes"].get("transaction").is_none());
+
+        let dims = dataset_dims(&Datatype::Logs);
+        assert_eq!(dims["modes"]["transaction"]["required"], serde_json::json!(["transaction"]));
+        assert_eq!(dims["modes"]["block"]["optional"], dims["modes"]["transaction"]["optional"]);
+    }
+
+    #[test]
+    fn test_preview_table() {
+        let numbers: Vec<u32> = (0..25).collect();
+        let df = df!("block_number" => &numbers).unwrap();
+        let table = preview_table(&df).unwrap();
+
+        // every row is shown, without reading or setting polars format variables
+        assert_eq!(table.row_iter().count(), 25);
+        assert!(table.to_string().contains("block_number"));
+        assert!(table.to_string().contains("24"));
+        assert!(std::env::var("POLARS_FMT_MAX_ROWS").is_err());
+    }
+}
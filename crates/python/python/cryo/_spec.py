@@ -24,7 +24,7 @@ if typing.TYPE_CHECKING:
         datatypes: typing.Sequence[Datatype]
         blocks: typing.Sequence[str] | None
         align: bool
-        reorg_buffer: int
+        reorg_buffer: int | Literal['finalized']
         include_columns: typing.Sequence[str] | None
         exclude_columns: typing.Sequence[str] | None
         columns: typing.Sequence[str] | None
@@ -2,9 +2,11 @@ use polars::prelude::*;
 use pyo3::{exceptions::PyTypeError, prelude::*};
 use pyo3_polars::PyDataFrame;
 
-use cryo_cli::{parse_args, Args};
+use cryo_cli::{check_rpc_methods, parse_args, Args};
 use cryo_freeze::collect;
 
+use crate::freeze_adapter::ReorgBufferArg;
+
 #[pyfunction(
     signature = (
         datatype = None,
@@ -14,40 +16,95 @@ use cryo_freeze::collect;
         command = None,
         timestamps = None,
         txs = None,
+        transactions_from_block_file = None,
+        collect_by = None,
         align = false,
-        reorg_buffer = 0,
+        reorg_buffer = ReorgBufferArg::Blocks(0),
+        min_block = None,
+        max_block = None,
+        skip_chunks_file = None,
         include_columns = None,
         exclude_columns = None,
+        exclude_input = false,
+        require_columns = None,
         columns = None,
         u256_types = None,
+        value_as_decimal = false,
         hex = false,
+        hex_prefix = false,
+        no_hex_prefix = false,
+        address_checksum = false,
         sort = None,
+        block_number_column_name = None,
+        global_sort = false,
+        filter_expr = None,
         exclude_failed = false,
+        exclude_system_txs = false,
+        min_priority_fee = None,
+        tx_type = None,
+        resolve_ens = false,
+        no_precompiles = false,
+        precompile_range = None,
+        max_trace_depth = None,
+        trace_error_as_column = false,
+        include_trace_gas = false,
         rpc = None,
         network_name = None,
+        assert_chain_id = None,
+        rpc_header = vec![],
         requests_per_second = None,
         max_concurrent_requests = None,
         max_concurrent_chunks = None,
+        concurrency_auto = false,
+        rpc_batch_size = None,
+        trace_replay = false,
+        max_rows_total = None,
+        max_output_bytes = None,
+        fail_fast = false,
         chunk_order = None,
         max_retries = 10,
+        chunk_retries = 0,
         initial_backoff = 500,
         compute_units_per_second = 50,
         dry = false,
+        preview = None,
+        dump_raw_responses = None,
+        receipts_source = None,
+        no_probe = false,
         chunk_size = 1000,
+        blocks_per_file = None,
+        chunk_size_bytes = None,
         n_chunks = None,
         partition_by = None,
         output_dir = ".".to_string(),
         subdirs = vec![],
+        output_prefix = None,
         label = None,
         overwrite = false,
+        overwrite_partial = false,
+        force_schema_change = false,
+        skip_existing_blocks_from = None,
         csv = false,
         json = false,
+        json_pretty = false,
+        json_compact = false,
+        json_integers_as_numbers = false,
         row_group_size = None,
         n_row_groups = None,
+        row_group_target_bytes = None,
         no_stats = false,
+        parquet_page_size = None,
+        parquet_no_dictionary = false,
         compression = vec!["lz4".to_string()],
+        compression_for = None,
         report_dir = None,
         no_report = false,
+        report_on_error = false,
+        include_chunk_stats_file = false,
+        manifest = false,
+        empty_file_policy = None,
+        output_schema_version = None,
+        flush_interval = None,
         address = None,
         to_address = None,
         from_address = None,
@@ -55,16 +112,37 @@ use cryo_freeze::collect;
         function = None,
         inputs = None,
         slot = None,
+        slot_file = None,
+        trace_calls_input_file = None,
         contract = None,
         topic0 = None,
+        topic0_file = None,
         topic1 = None,
         topic2 = None,
         topic3 = None,
+        lenient_address = false,
+        abi_dir = None,
+        decode_calls = false,
+        decode_data = None,
+        contracts_only = false,
+        transactions_receipts_only = false,
+        exclude_creation_storage = false,
+        include_creation_storage = false,
+        changes_only = false,
+        no_baseline = false,
+        refetch_metadata = false,
+        erc20_transfers_decode_value_as_decimal = false,
         inner_request_size = 1,
         js_tracer = None,
+        tracer = "parity".to_string(),
         verbose = false,
         no_verbose = false,
         event_signature = None,
+        collapse_logs = None,
+        min_block_logs = None,
+        max_block_logs = None,
+        appearance_sources = None,
+        first_seen = false,
     )
 )]
 #[allow(clippy::too_many_arguments)]
@@ -76,40 +154,95 @@ pub fn _collect(
     command: Option<String>,
     timestamps: Option<Vec<String>>,
     txs: Option<Vec<String>>,
+    transactions_from_block_file: Option<Vec<String>>,
+    collect_by: Option<String>,
     align: bool,
-    reorg_buffer: u64,
+    reorg_buffer: ReorgBufferArg,
+    min_block: Option<u64>,
+    max_block: Option<u64>,
+    skip_chunks_file: Option<String>,
     include_columns: Option<Vec<String>>,
     exclude_columns: Option<Vec<String>>,
+    exclude_input: bool,
+    require_columns: Option<Vec<String>>,
     columns: Option<Vec<String>>,
     u256_types: Option<Vec<String>>,
+    value_as_decimal: bool,
     hex: bool,
+    hex_prefix: bool,
+    no_hex_prefix: bool,
+    address_checksum: bool,
     sort: Option<Vec<String>>,
+    block_number_column_name: Option<String>,
+    global_sort: bool,
+    filter_expr: Option<String>,
     exclude_failed: bool,
+    exclude_system_txs: bool,
+    min_priority_fee: Option<f64>,
+    tx_type: Option<Vec<String>>,
+    resolve_ens: bool,
+    no_precompiles: bool,
+    precompile_range: Option<Vec<String>>,
+    max_trace_depth: Option<u32>,
+    trace_error_as_column: bool,
+    include_trace_gas: bool,
     rpc: Option<String>,
     network_name: Option<String>,
+    assert_chain_id: Option<u64>,
+    rpc_header: Vec<String>,
     requests_per_second: Option<u32>,
     max_concurrent_requests: Option<u64>,
     max_concurrent_chunks: Option<u64>,
+    concurrency_auto: bool,
+    rpc_batch_size: Option<u64>,
+    trace_replay: bool,
+    max_rows_total: Option<u64>,
+    max_output_bytes: Option<u64>,
+    fail_fast: bool,
     chunk_order: Option<String>,
     max_retries: u32,
+    chunk_retries: u32,
     initial_backoff: u64,
     compute_units_per_second: u64,
     dry: bool,
+    preview: Option<usize>,
+    dump_raw_responses: Option<String>,
+    receipts_source: Option<String>,
+    no_probe: bool,
     chunk_size: u64,
+    blocks_per_file: Option<u64>,
+    chunk_size_bytes: Option<u64>,
     n_chunks: Option<u64>,
     partition_by: Option<Vec<String>>,
     output_dir: String,
     subdirs: Vec<String>,
+    output_prefix: Option<String>,
     label: Option<String>,
     overwrite: bool,
+    overwrite_partial: bool,
+    force_schema_change: bool,
+    skip_existing_blocks_from: Option<String>,
     csv: bool,
     json: bool,
+    json_pretty: bool,
+    json_compact: bool,
+    json_integers_as_numbers: bool,
     row_group_size: Option<usize>,
     n_row_groups: Option<usize>,
+    row_group_target_bytes: Option<usize>,
     no_stats: bool,
+    parquet_page_size: Option<usize>,
+    parquet_no_dictionary: bool,
     compression: Vec<String>,
+    compression_for: Option<Vec<String>>,
     report_dir: Option<String>,
     no_report: bool,
+    report_on_error: bool,
+    include_chunk_stats_file: bool,
+    manifest: bool,
+    empty_file_policy: Option<String>,
+    output_schema_version: Option<String>,
+    flush_interval: Option<u64>,
     address: Option<Vec<String>>,
     to_address: Option<Vec<String>>,
     from_address: Option<Vec<String>>,
@@ -117,16 +250,37 @@ pub fn _collect(
     function: Option<Vec<String>>,
     inputs: Option<Vec<String>>,
     slot: Option<Vec<String>>,
+    slot_file: Option<String>,
+    trace_calls_input_file: Option<String>,
     contract: Option<Vec<String>>,
     topic0: Option<Vec<String>>,
+    topic0_file: Option<Vec<String>>,
     topic1: Option<Vec<String>>,
     topic2: Option<Vec<String>>,
     topic3: Option<Vec<String>>,
+    lenient_address: bool,
+    abi_dir: Option<String>,
+    decode_calls: bool,
+    decode_data: Option<String>,
+    contracts_only: bool,
+    transactions_receipts_only: bool,
+    exclude_creation_storage: bool,
+    include_creation_storage: bool,
+    changes_only: bool,
+    no_baseline: bool,
+    refetch_metadata: bool,
+    erc20_transfers_decode_value_as_decimal: bool,
     inner_request_size: u64,
     js_tracer: Option<String>,
+    tracer: String,
     verbose: bool,
     no_verbose: bool,
     event_signature: Option<String>,
+    collapse_logs: Option<String>,
+    min_block_logs: Option<u64>,
+    max_block_logs: Option<u64>,
+    appearance_sources: Option<Vec<String>>,
+    first_seen: bool,
 ) -> PyResult<&PyAny> {
     if let Some(command) = command {
         pyo3_asyncio::tokio::future_into_py(py, async move {
@@ -142,40 +296,95 @@ pub fn _collect(
             remember,
             timestamps,
             txs,
+            transactions_from_block_file,
+            collect_by,
             align,
-            reorg_buffer,
+            reorg_buffer: reorg_buffer.parse()?,
+            min_block,
+            max_block,
+            skip_chunks_file,
             include_columns,
             exclude_columns,
+            exclude_input,
+            require_columns,
             columns,
             u256_types,
+            value_as_decimal,
             hex,
+            hex_prefix,
+            no_hex_prefix,
+            address_checksum,
             sort,
+            block_number_column_name,
+            global_sort,
+            filter_expr,
             exclude_failed,
+            exclude_system_txs,
+            min_priority_fee,
+            tx_type,
+            resolve_ens,
+            no_precompiles,
+            precompile_range,
+            max_trace_depth,
+            trace_error_as_column,
+            include_trace_gas,
             rpc,
             network_name,
+            assert_chain_id,
+            rpc_header,
             requests_per_second,
             max_concurrent_requests,
             max_concurrent_chunks,
+            concurrency_auto,
+            rpc_batch_size,
+            trace_replay,
+            max_rows_total,
+            max_output_bytes,
+            fail_fast,
             chunk_order,
             max_retries,
+            chunk_retries,
             initial_backoff,
             compute_units_per_second,
             dry,
+            preview,
+            dump_raw_responses,
+            receipts_source,
+            no_probe,
             chunk_size,
+            blocks_per_file,
+            chunk_size_bytes,
             n_chunks,
             partition_by,
             output_dir,
             subdirs,
+            output_prefix,
             label,
             overwrite,
+            overwrite_partial,
+            force_schema_change,
+            skip_existing_blocks_from,
             csv,
             json,
+            json_pretty,
+            json_compact,
+            json_integers_as_numbers,
             row_group_size,
             n_row_groups,
+            row_group_target_bytes,
             no_stats,
+            parquet_page_size,
+            parquet_no_dictionary,
             compression,
+            compression_for,
             report_dir: report_dir.map(std::path::PathBuf::from),
             no_report,
+            report_on_error,
+            include_chunk_stats_file,
+            manifest,
+            empty_file_policy,
+            output_schema_version,
+            flush_interval,
             address,
             to_address,
             from_address,
@@ -183,16 +392,37 @@ pub fn _collect(
             function,
             inputs,
             slot,
+            slot_file,
+            trace_calls_input_file,
             contract,
             topic0,
+            topic0_file,
             topic1,
             topic2,
             topic3,
+            lenient_address,
+            abi_dir,
+            decode_calls,
+            decode_data,
+            contracts_only,
+            transactions_receipts_only,
+            exclude_creation_storage,
+            include_creation_storage,
+            changes_only,
+            no_baseline,
+            refetch_metadata,
+            erc20_transfers_decode_value_as_decimal,
             inner_request_size,
             js_tracer,
+            tracer,
             verbose,
             no_verbose,
             event_signature,
+            collapse_logs,
+            min_block_logs,
+            max_block_logs,
+            appearance_sources,
+            first_seen,
         };
         pyo3_asyncio::tokio::future_into_py(py, async move {
             match run_collect(args).await {
@@ -202,7 +432,7 @@ pub fn _collect(
             }
         })
     } else {
-        return Err(PyErr::new::<PyTypeError, _>("must specify datatype or command"))
+        Err(PyErr::new::<PyTypeError, _>("must specify datatype or command"))
     }
 }
 
@@ -211,6 +441,11 @@ async fn run_collect(args: Args) -> PolarsResult<DataFrame> {
         Ok(opts) => opts,
         Err(e) => panic!("error parsing opts {:?}", e),
     };
+    if !args.no_probe {
+        if let Err(e) = check_rpc_methods(&query, &source).await {
+            panic!("error probing rpc methods {:?}", e)
+        }
+    }
     match collect(query.into(), source.into()).await {
         Ok(df) => Ok(df),
         Err(e) => panic!("error collecting {:?}", e),
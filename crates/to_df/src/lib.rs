@@ -83,7 +83,7 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
                                     ColumnEncoding::Binary => {
                                         cols.push(Series::new(full_name, Vec::<Vec<u8>>::new()))
                                     },
-                                    ColumnEncoding::Hex => {
+                                    ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed => {
                                         cols.push(Series::new(full_name, Vec::<String>::new()))
                                     },
                                 }
@@ -93,7 +93,11 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
                             U256Type::F64 => cols.push(Series::new(full_name, Vec::<f64>::new())),
                             U256Type::U32 => cols.push(Series::new(full_name, Vec::<u32>::new())),
                             U256Type::U64 => cols.push(Series::new(full_name, Vec::<u64>::new())),
-                            U256Type::Decimal128 => cols.push(Series::new(full_name, Vec::<Vec<u8>>::new())),
+                            U256Type::Decimal128 => cols.push(
+                                Int128Chunked::from_vec(full_name, Vec::new())
+                                    .into_decimal_unchecked(Some(DECIMAL128_PRECISION), 0)
+                                    .into_series()
+                            ),
                         }
                     }
                 }
@@ -109,13 +113,13 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
                             DynSolType::Address => {
                                 match schema.binary_type {
                                     ColumnEncoding::Binary => cols.push(Series::new(name, Vec::<Vec<u8>>::new())),
-                                    ColumnEncoding::Hex => cols.push(Series::new(name, Vec::<String>::new())),
+                                    ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed => cols.push(Series::new(name, Vec::<String>::new())),
                                 }
                             },
                             DynSolType::Bytes => {
                                 match schema.binary_type {
                                     ColumnEncoding::Binary => cols.push(Series::new(name, Vec::<Vec<u8>>::new())),
-                                    ColumnEncoding::Hex => cols.push(Series::new(name, Vec::<String>::new())),
+                                    ColumnEncoding::Hex | ColumnEncoding::HexUnprefixed => cols.push(Series::new(name, Vec::<String>::new())),
                                 }
                             },
                             DynSolType::Int(bits) => {
@@ -150,6 +154,7 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
                             chunk_len as usize,
                             &u256_types,
                             &schema.binary_type,
+                            schema.checksum_addresses,
                         );
                         match series_vec {
                             Ok(s) => {
@@ -171,6 +176,40 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
         quote! {}
     };
 
+    let has_call_cols = field_names_and_types.iter().any(|(name, _)| name == "call_cols");
+    let call_code = if has_call_cols {
+        quote! {
+            if let Some(decoder) = &schema.call_decoder {
+                let u256_types: Vec<_> = schema.u256_types.clone().into_iter().collect();
+                cols.extend(decoder.make_series(
+                    self.call_cols,
+                    &u256_types,
+                    &schema.binary_type,
+                    schema.checksum_addresses,
+                )?);
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let has_data_cols = field_names_and_types.iter().any(|(name, _)| name == "data_cols");
+    let data_code = if has_data_cols {
+        quote! {
+            if let Some(decoder) = &schema.data_decoder {
+                let u256_types: Vec<_> = schema.u256_types.clone().into_iter().collect();
+                cols.extend(decoder.make_series(
+                    self.data_cols,
+                    &u256_types,
+                    &schema.binary_type,
+                    schema.checksum_addresses,
+                )?);
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     fn map_type_to_column_type(ty: &syn::Type) -> Option<proc_macro2::TokenStream> {
         match quote!(#ty).to_string().as_str() {
             "Vec < bool >" => Some(quote! { ColumnType::Boolean }),
@@ -208,7 +247,11 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
         if let Some(column_type) = map_type_to_column_type(ty) {
             let field_name_str = format!("{}", quote!(#name));
             column_types.push(quote! { (#field_name_str, #column_type) });
-        } else if name != "n_rows" && name != "event_cols" {
+        } else if name != "n_rows" &&
+            name != "event_cols" &&
+            name != "call_cols" &&
+            name != "data_cols"
+        {
             println!("invalid column type for {name} in table {}", datatype_str);
         }
     }
@@ -242,7 +285,15 @@ pub fn to_df(attrs: TokenStream, input: TokenStream) -> TokenStream {
 
                 #event_code
 
-                let df = DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)?;
+                #call_code
+
+                #data_code
+
+                let df = DataFrame::new(cols)
+                    .map_err(CollectError::PolarsError)
+                    .sort_by_schema(schema)
+                    .rename_by_schema(schema)
+                    .filter_by_schema(schema)?;
                 let mut output = std::collections::HashMap::new();
                 output.insert(datatype, df);
                 Ok(output)